@@ -8,8 +8,8 @@ function App() {
   const [isLoggedIn, setIsLoggedIn] = useState(false)
 
   useEffect(() => {
-    // 检查是否已经有保存的 API Key
-    if (storage.getApiKey()) {
+    // 检查是否已经有未过期的会话 Token
+    if (storage.getSession()) {
       setIsLoggedIn(true)
     }
   }, [])
@@ -6,8 +6,10 @@ import type {
   SuccessResponse,
   SetDisabledRequest,
   SetPriorityRequest,
+  UpdateCredentialRequest,
   AddCredentialRequest,
   AddCredentialResponse,
+  SessionResponse,
 } from '@/types/api'
 
 // 创建 axios 实例
@@ -18,15 +20,64 @@ const api = axios.create({
   },
 })
 
-// 请求拦截器添加 API Key
-api.interceptors.request.use((config) => {
-  const apiKey = storage.getApiKey()
-  if (apiKey) {
-    config.headers['x-api-key'] = apiKey
+// 会话 Token 剩余有效期低于该值时自动续期
+const SESSION_REFRESH_MARGIN_MS = 5 * 60 * 1000
+
+let refreshing: Promise<void> | null = null
+
+// 续期会话 Token（并发请求共用同一次续期）
+function refreshSessionIfNeeded(): Promise<void> {
+  const session = storage.getSession()
+  if (!session || new Date(session.expiresAt).getTime() - Date.now() > SESSION_REFRESH_MARGIN_MS) {
+    return Promise.resolve()
+  }
+  refreshing ??= axios
+    .post<SessionResponse>('/api/admin/session/refresh', null, {
+      headers: { Authorization: `Bearer ${session.token}` },
+    })
+    .then(({ data }) => storage.setSession({ token: data.token, expiresAt: data.expiresAt }))
+    .catch(() => undefined)
+    .finally(() => {
+      refreshing = null
+    })
+  return refreshing
+}
+
+// 请求拦截器添加会话 Token
+api.interceptors.request.use(async (config) => {
+  await refreshSessionIfNeeded()
+  const session = storage.getSession()
+  if (session) {
+    config.headers['Authorization'] = `Bearer ${session.token}`
   }
   return config
 })
 
+// 会话失效（过期、注销或账号被删除）时回到登录页
+api.interceptors.response.use(undefined, (error) => {
+  if (axios.isAxiosError(error) && error.response?.status === 401) {
+    storage.removeSession()
+    window.location.reload()
+  }
+  return Promise.reject(error)
+})
+
+// 用 Admin API Key 登录，换取会话 Token
+export async function login(key: string): Promise<SessionResponse> {
+  const { data } = await axios.post<SessionResponse>('/api/admin/login', { key })
+  storage.setSession({ token: data.token, expiresAt: data.expiresAt })
+  return data
+}
+
+// 注销当前会话
+export async function logout(): Promise<void> {
+  try {
+    await api.post('/logout')
+  } finally {
+    storage.removeSession()
+  }
+}
+
 // 获取所有凭据状态
 export async function getCredentials(): Promise<CredentialsStatusResponse> {
   const { data } = await api.get<CredentialsStatusResponse>('/credentials')
@@ -57,6 +108,15 @@ export async function setCredentialPriority(
   return data
 }
 
+// 更新凭据备注、负责人联系方式与来源
+export async function updateCredential(
+  id: number,
+  req: UpdateCredentialRequest
+): Promise<SuccessResponse> {
+  const { data } = await api.patch<SuccessResponse>(`/credentials/${id}`, req)
+  return data
+}
+
 // 重置失败计数
 export async function resetCredentialFailure(
   id: number
@@ -86,13 +146,15 @@ export async function deleteCredential(id: number): Promise<SuccessResponse> {
 }
 
 // 获取负载均衡模式
-export async function getLoadBalancingMode(): Promise<{ mode: 'priority' | 'balanced' }> {
-  const { data } = await api.get<{ mode: 'priority' | 'balanced' }>('/config/load-balancing')
+export type LoadBalancingMode = 'priority' | 'balanced' | 'weighted' | 'least-usage' | 'sticky'
+
+export async function getLoadBalancingMode(): Promise<{ mode: LoadBalancingMode }> {
+  const { data } = await api.get<{ mode: LoadBalancingMode }>('/config/load-balancing')
   return data
 }
 
 // 设置负载均衡模式
-export async function setLoadBalancingMode(mode: 'priority' | 'balanced'): Promise<{ mode: 'priority' | 'balanced' }> {
-  const { data } = await api.put<{ mode: 'priority' | 'balanced' }>('/config/load-balancing', { mode })
+export async function setLoadBalancingMode(mode: LoadBalancingMode): Promise<{ mode: LoadBalancingMode }> {
+  const { data } = await api.put<{ mode: LoadBalancingMode }>('/config/load-balancing', { mode })
   return data
 }
@@ -80,6 +80,9 @@ export function AddCredentialDialog({ open, onOpenChange }: AddCredentialDialogP
       {
         onSuccess: (data) => {
           toast.success(data.message)
+          if (data.warning) {
+            toast.warning(data.warning)
+          }
           onOpenChange(false)
           resetForm()
         },
@@ -1,6 +1,6 @@
 import { useState } from 'react'
 import { toast } from 'sonner'
-import { RefreshCw, ChevronUp, ChevronDown, Wallet, Trash2, Loader2 } from 'lucide-react'
+import { RefreshCw, ChevronUp, ChevronDown, Wallet, Trash2, Loader2, NotebookPen } from 'lucide-react'
 import { Card, CardContent, CardHeader, CardTitle } from '@/components/ui/card'
 import { Button } from '@/components/ui/button'
 import { Badge } from '@/components/ui/badge'
@@ -19,6 +19,7 @@ import type { CredentialStatusItem, BalanceResponse } from '@/types/api'
 import {
   useSetDisabled,
   useSetPriority,
+  useUpdateCredential,
   useResetFailure,
   useDeleteCredential,
 } from '@/hooks/use-credentials'
@@ -59,9 +60,12 @@ export function CredentialCard({
   const [editingPriority, setEditingPriority] = useState(false)
   const [priorityValue, setPriorityValue] = useState(String(credential.priority))
   const [showDeleteDialog, setShowDeleteDialog] = useState(false)
+  const [showNotesDialog, setShowNotesDialog] = useState(false)
+  const [notesForm, setNotesForm] = useState({ notes: '', ownerContact: '', source: '' })
 
   const setDisabled = useSetDisabled()
   const setPriority = useSetPriority()
+  const updateCredential = useUpdateCredential()
   const resetFailure = useResetFailure()
   const deleteCredential = useDeleteCredential()
 
@@ -99,6 +103,30 @@ export function CredentialCard({
     )
   }
 
+  const openNotesDialog = () => {
+    setNotesForm({
+      notes: credential.notes ?? '',
+      ownerContact: credential.ownerContact ?? '',
+      source: credential.source ?? '',
+    })
+    setShowNotesDialog(true)
+  }
+
+  const handleSaveNotes = () => {
+    updateCredential.mutate(
+      { id: credential.id, ...notesForm },
+      {
+        onSuccess: (res) => {
+          toast.success(res.message)
+          setShowNotesDialog(false)
+        },
+        onError: (err) => {
+          toast.error('保存失败: ' + (err as Error).message)
+        },
+      }
+    )
+  }
+
   const handleReset = () => {
     resetFailure.mutate(credential.id, {
       onSuccess: (res) => {
@@ -225,6 +253,14 @@ export function CredentialCard({
               <span className="text-muted-foreground">最后调用：</span>
               <span className="font-medium">{formatLastUsed(credential.lastUsedAt)}</span>
             </div>
+            {credential.exhaustedUntil && (
+              <div className="col-span-2">
+                <span className="text-muted-foreground">额度用尽，恢复时间：</span>
+                <span className="font-medium text-red-500">
+                  {new Date(credential.exhaustedUntil).toLocaleString()}
+                </span>
+              </div>
+            )}
             <div className="col-span-2">
               <span className="text-muted-foreground">剩余用量：</span>
               {loadingBalance ? (
@@ -248,6 +284,24 @@ export function CredentialCard({
                 <span className="font-medium">{credential.proxyUrl}</span>
               </div>
             )}
+            {credential.ownerContact && (
+              <div className="col-span-2">
+                <span className="text-muted-foreground">负责人：</span>
+                <span className="font-medium">{credential.ownerContact}</span>
+              </div>
+            )}
+            {credential.source && (
+              <div className="col-span-2">
+                <span className="text-muted-foreground">来源：</span>
+                <span className="font-medium">{credential.source}</span>
+              </div>
+            )}
+            {credential.notes && (
+              <div className="col-span-2">
+                <span className="text-muted-foreground">备注：</span>
+                <span className="font-medium whitespace-pre-wrap">{credential.notes}</span>
+              </div>
+            )}
             {credential.hasProfileArn && (
               <div className="col-span-2">
                 <Badge variant="secondary">有 Profile ARN</Badge>
@@ -310,6 +364,10 @@ export function CredentialCard({
               <Wallet className="h-4 w-4 mr-1" />
               查看余额
             </Button>
+            <Button size="sm" variant="outline" onClick={openNotesDialog}>
+              <NotebookPen className="h-4 w-4 mr-1" />
+              备注
+            </Button>
             <Button
               size="sm"
               variant="destructive"
@@ -324,6 +382,54 @@ export function CredentialCard({
         </CardContent>
       </Card>
 
+      {/* 备注信息对话框 */}
+      <Dialog open={showNotesDialog} onOpenChange={setShowNotesDialog}>
+        <DialogContent>
+          <DialogHeader>
+            <DialogTitle>凭据 #{credential.id} 备注信息</DialogTitle>
+            <DialogDescription>仅用于管理，留空表示清除</DialogDescription>
+          </DialogHeader>
+          <div className="space-y-3">
+            <div className="space-y-1">
+              <label className="text-sm font-medium">负责人联系方式</label>
+              <Input
+                value={notesForm.ownerContact}
+                onChange={(e) => setNotesForm({ ...notesForm, ownerContact: e.target.value })}
+                placeholder="邮箱、IM 账号等"
+              />
+            </div>
+            <div className="space-y-1">
+              <label className="text-sm font-medium">来源</label>
+              <Input
+                value={notesForm.source}
+                onChange={(e) => setNotesForm({ ...notesForm, source: e.target.value })}
+                placeholder="采购渠道、批次等"
+              />
+            </div>
+            <div className="space-y-1">
+              <label className="text-sm font-medium">备注</label>
+              <textarea
+                value={notesForm.notes}
+                onChange={(e) => setNotesForm({ ...notesForm, notes: e.target.value })}
+                className="flex min-h-[80px] w-full rounded-md border border-input bg-background px-3 py-2 text-sm ring-offset-background placeholder:text-muted-foreground focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2"
+              />
+            </div>
+          </div>
+          <DialogFooter>
+            <Button
+              variant="outline"
+              onClick={() => setShowNotesDialog(false)}
+              disabled={updateCredential.isPending}
+            >
+              取消
+            </Button>
+            <Button onClick={handleSaveNotes} disabled={updateCredential.isPending}>
+              保存
+            </Button>
+          </DialogFooter>
+        </DialogContent>
+      </Dialog>
+
       {/* 删除确认对话框 */}
       <Dialog open={showDeleteDialog} onOpenChange={setShowDeleteDialog}>
         <DialogContent>
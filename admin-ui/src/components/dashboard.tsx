@@ -2,7 +2,6 @@ import { useState, useEffect, useRef } from 'react'
 import { RefreshCw, LogOut, Moon, Sun, Server, Plus, Upload, FileUp, Trash2, RotateCcw, CheckCircle2 } from 'lucide-react'
 import { useQueryClient } from '@tanstack/react-query'
 import { toast } from 'sonner'
-import { storage } from '@/lib/storage'
 import { Card, CardContent, CardHeader, CardTitle } from '@/components/ui/card'
 import { Button } from '@/components/ui/button'
 import { Badge } from '@/components/ui/badge'
@@ -13,7 +12,7 @@ import { BatchImportDialog } from '@/components/batch-import-dialog'
 import { KamImportDialog } from '@/components/kam-import-dialog'
 import { BatchVerifyDialog, type VerifyResult } from '@/components/batch-verify-dialog'
 import { useCredentials, useDeleteCredential, useResetFailure, useLoadBalancingMode, useSetLoadBalancingMode } from '@/hooks/use-credentials'
-import { getCredentialBalance } from '@/api/credentials'
+import { getCredentialBalance, logout, type LoadBalancingMode } from '@/api/credentials'
 import { extractErrorMessage } from '@/lib/utils'
 import type { BalanceResponse } from '@/types/api'
 
@@ -21,6 +20,23 @@ interface DashboardProps {
   onLogout: () => void
 }
 
+const LOAD_BALANCING_MODES: LoadBalancingMode[] = ['priority', 'balanced', 'weighted', 'least-usage', 'sticky']
+
+function loadBalancingModeName(mode: LoadBalancingMode) {
+  switch (mode) {
+    case 'priority':
+      return '优先级模式'
+    case 'balanced':
+      return '均衡负载'
+    case 'weighted':
+      return '加权随机'
+    case 'least-usage':
+      return '最久未使用'
+    case 'sticky':
+      return '会话粘滞'
+  }
+}
+
 export function Dashboard({ onLogout }: DashboardProps) {
   const [selectedCredentialId, setSelectedCredentialId] = useState<number | null>(null)
   const [balanceDialogOpen, setBalanceDialogOpen] = useState(false)
@@ -119,9 +135,10 @@ export function Dashboard({ onLogout }: DashboardProps) {
   }
 
   const handleLogout = () => {
-    storage.removeApiKey()
-    queryClient.clear()
-    onLogout()
+    logout().finally(() => {
+      queryClient.clear()
+      onLogout()
+    })
   }
 
   // 选择管理
@@ -447,11 +464,11 @@ export function Dashboard({ onLogout }: DashboardProps) {
   // 切换负载均衡模式
   const handleToggleLoadBalancing = () => {
     const currentMode = loadBalancingData?.mode || 'priority'
-    const newMode = currentMode === 'priority' ? 'balanced' : 'priority'
+    const newMode = LOAD_BALANCING_MODES[(LOAD_BALANCING_MODES.indexOf(currentMode) + 1) % LOAD_BALANCING_MODES.length]
 
     setLoadBalancingMode(newMode, {
       onSuccess: () => {
-        const modeName = newMode === 'priority' ? '优先级模式' : '均衡负载模式'
+        const modeName = loadBalancingModeName(newMode)
         toast.success(`已切换到${modeName}`)
       },
       onError: (error) => {
@@ -505,7 +522,7 @@ export function Dashboard({ onLogout }: DashboardProps) {
               disabled={isLoadingMode || isSettingMode}
               title="切换负载均衡模式"
             >
-              {isLoadingMode ? '加载中...' : (loadBalancingData?.mode === 'priority' ? '优先级模式' : '均衡负载')}
+              {isLoadingMode ? '加载中...' : loadBalancingModeName(loadBalancingData?.mode || 'priority')}
             </Button>
             <Button variant="ghost" size="icon" onClick={toggleDarkMode}>
               {darkMode ? <Sun className="h-5 w-5" /> : <Moon className="h-5 w-5" />}
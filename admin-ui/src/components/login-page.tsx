@@ -1,30 +1,31 @@
-import { useState, useEffect } from 'react'
+import { useState } from 'react'
 import { KeyRound } from 'lucide-react'
-import { storage } from '@/lib/storage'
+import { toast } from 'sonner'
+import { login } from '@/api/credentials'
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from '@/components/ui/card'
 import { Input } from '@/components/ui/input'
 import { Button } from '@/components/ui/button'
 
 interface LoginPageProps {
-  onLogin: (apiKey: string) => void
+  onLogin: () => void
 }
 
 export function LoginPage({ onLogin }: LoginPageProps) {
   const [apiKey, setApiKey] = useState('')
+  const [submitting, setSubmitting] = useState(false)
 
-  useEffect(() => {
-    // 从 storage 读取保存的 API Key
-    const savedKey = storage.getApiKey()
-    if (savedKey) {
-      setApiKey(savedKey)
-    }
-  }, [])
-
-  const handleSubmit = (e: React.FormEvent) => {
+  const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault()
-    if (apiKey.trim()) {
-      storage.setApiKey(apiKey.trim())
-      onLogin(apiKey.trim())
+    if (!apiKey.trim()) return
+    setSubmitting(true)
+    try {
+      // 用 Admin API Key 换取短期会话 Token，Key 本身不保存
+      await login(apiKey.trim())
+      onLogin()
+    } catch {
+      toast.error('Admin API Key 无效')
+    } finally {
+      setSubmitting(false)
     }
   }
 
@@ -51,7 +52,7 @@ export function LoginPage({ onLogin }: LoginPageProps) {
                 className="text-center"
               />
             </div>
-            <Button type="submit" className="w-full" disabled={!apiKey.trim()}>
+            <Button type="submit" className="w-full" disabled={!apiKey.trim() || submitting}>
               登录
             </Button>
           </form>
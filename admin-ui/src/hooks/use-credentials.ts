@@ -3,6 +3,7 @@ import {
   getCredentials,
   setCredentialDisabled,
   setCredentialPriority,
+  updateCredential,
   resetCredentialFailure,
   getCredentialBalance,
   addCredential,
@@ -10,7 +11,7 @@ import {
   getLoadBalancingMode,
   setLoadBalancingMode,
 } from '@/api/credentials'
-import type { AddCredentialRequest } from '@/types/api'
+import type { AddCredentialRequest, UpdateCredentialRequest } from '@/types/api'
 
 // 查询凭据列表
 export function useCredentials() {
@@ -55,6 +56,18 @@ export function useSetPriority() {
   })
 }
 
+// 更新凭据备注信息
+export function useUpdateCredential() {
+  const queryClient = useQueryClient()
+  return useMutation({
+    mutationFn: ({ id, ...req }: { id: number } & UpdateCredentialRequest) =>
+      updateCredential(id, req),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ['credentials'] })
+    },
+  })
+}
+
 // 重置失败计数
 export function useResetFailure() {
   const queryClient = useQueryClient()
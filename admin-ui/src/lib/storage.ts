@@ -1,7 +1,23 @@
-const API_KEY_STORAGE_KEY = 'adminApiKey'
+const SESSION_STORAGE_KEY = 'adminSession'
 
+export interface StoredSession {
+  token: string
+  expiresAt: string
+}
+
+// 只保存短期有效的会话 Token，不保存原始 Admin API Key
 export const storage = {
-  getApiKey: () => localStorage.getItem(API_KEY_STORAGE_KEY),
-  setApiKey: (key: string) => localStorage.setItem(API_KEY_STORAGE_KEY, key),
-  removeApiKey: () => localStorage.removeItem(API_KEY_STORAGE_KEY),
+  getSession: (): StoredSession | null => {
+    const raw = localStorage.getItem(SESSION_STORAGE_KEY)
+    if (!raw) return null
+    try {
+      const session = JSON.parse(raw) as StoredSession
+      return new Date(session.expiresAt).getTime() > Date.now() ? session : null
+    } catch {
+      return null
+    }
+  },
+  setSession: (session: StoredSession) =>
+    localStorage.setItem(SESSION_STORAGE_KEY, JSON.stringify(session)),
+  removeSession: () => localStorage.removeItem(SESSION_STORAGE_KEY),
 }
@@ -22,6 +22,25 @@ export interface CredentialStatusItem {
   lastUsedAt: string | null
   hasProxy: boolean
   proxyUrl?: string
+  exhaustedUntil?: string
+  stats: CredentialStats
+  notes?: string
+  ownerContact?: string
+  source?: string
+}
+
+// 凭据长期调用质量统计
+export interface CredentialStats {
+  failures: {
+    auth: number
+    quota: number
+    transient: number
+    network: number
+    client: number
+  }
+  avgLatencyMs?: number
+  lastError?: string
+  lastErrorAt?: string
 }
 
 // 余额响应
@@ -58,6 +77,13 @@ export interface SetPriorityRequest {
   priority: number
 }
 
+// 更新凭据备注信息请求（未提供的字段保持不变，空字符串表示清除）
+export interface UpdateCredentialRequest {
+  notes?: string
+  ownerContact?: string
+  source?: string
+}
+
 // 添加凭据请求
 export interface AddCredentialRequest {
   refreshToken: string
@@ -79,4 +105,14 @@ export interface AddCredentialResponse {
   message: string
   credentialId: number
   email?: string
+  duplicateOf?: number[]
+  warning?: string
+}
+
+// 登录 / 续期响应
+export interface SessionResponse {
+  token: string
+  expiresAt: string
+  name: string
+  role: 'admin' | 'readonly'
 }
@@ -0,0 +1,21 @@
+fn main() {
+    // gRPC 管理接口（`grpc` feature）由 proto 生成代码
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    // 未指定 PROTOC 时使用内置的 protoc，构建环境无需安装 protobuf 编译器
+    if std::env::var_os("PROTOC").is_none()
+        && let Ok(protoc) = protoc_bin_vendored::protoc_bin_path()
+    {
+        // SAFETY: 构建脚本是单线程的，设置环境变量时没有其他线程读取
+        unsafe { std::env::set_var("PROTOC", protoc) };
+    }
+    println!("cargo:rerun-if-changed=proto/admin.proto");
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/admin.proto"], &["proto"])
+        .expect("编译 proto/admin.proto 失败");
+}
@@ -5,6 +5,7 @@ use std::fmt;
 use axum::http::StatusCode;
 
 use super::types::AdminErrorResponse;
+use crate::common::i18n::Message;
 
 /// Admin 服务错误类型
 #[derive(Debug)]
@@ -48,17 +49,25 @@ impl AdminServiceError {
         }
     }
 
+    /// 按当前请求语言（见 `common::i18n`）生成的错误消息
+    fn localized_message(&self) -> String {
+        let (message, detail) = match self {
+            AdminServiceError::NotFound { id } => (Message::CredentialNotFound, id.to_string()),
+            AdminServiceError::UpstreamError(msg) => (Message::UpstreamError, msg.clone()),
+            AdminServiceError::InternalError(msg) => (Message::InternalError, msg.clone()),
+            AdminServiceError::InvalidCredential(msg) => (Message::InvalidCredential, msg.clone()),
+        };
+        format!("{}: {}", message.localized(), detail)
+    }
+
     /// 转换为 API 错误响应
     pub fn into_response(self) -> AdminErrorResponse {
+        let message = self.localized_message();
         match &self {
-            AdminServiceError::NotFound { .. } => AdminErrorResponse::not_found(self.to_string()),
-            AdminServiceError::UpstreamError(_) => AdminErrorResponse::api_error(self.to_string()),
-            AdminServiceError::InternalError(_) => {
-                AdminErrorResponse::internal_error(self.to_string())
-            }
-            AdminServiceError::InvalidCredential(_) => {
-                AdminErrorResponse::invalid_request(self.to_string())
-            }
+            AdminServiceError::NotFound { .. } => AdminErrorResponse::not_found(message),
+            AdminServiceError::UpstreamError(_) => AdminErrorResponse::api_error(message),
+            AdminServiceError::InternalError(_) => AdminErrorResponse::internal_error(message),
+            AdminServiceError::InvalidCredential(_) => AdminErrorResponse::invalid_request(message),
         }
     }
 }
@@ -0,0 +1,378 @@
+//! gRPC 管理接口（`proto/admin.proto`）
+//!
+//! 与 HTTP Admin API 共用 `AdminService` 与 Admin 账号：metadata 中的 `x-api-key` 或
+//! `authorization: Bearer` 按同样的规则认证，只读账号只能调用查询类方法，
+//! 修改类方法以账号的名义执行并写入审计日志。配置 `ipAccess.admin` 时按对端地址过滤。
+
+// tonic 的方法签名约定以 `Status` 作为错误类型
+#![allow(clippy::result_large_err)]
+
+use std::future::Future;
+use std::net::SocketAddr;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use tonic::{Request, Response, Status};
+
+use super::error::AdminServiceError;
+use super::middleware::{AdminState, authenticate};
+use super::types::{self, CostSummary};
+use crate::events;
+use crate::model::config::{AdminAccount, AdminRole};
+
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("kiro.admin.v1");
+}
+
+use proto::admin_service_server::{AdminService, AdminServiceServer};
+
+impl From<AdminServiceError> for Status {
+    fn from(e: AdminServiceError) -> Self {
+        let message = e.to_string();
+        match e {
+            AdminServiceError::NotFound { .. } => Status::not_found(message),
+            AdminServiceError::UpstreamError(_) => Status::unavailable(message),
+            AdminServiceError::InternalError(_) => Status::internal(message),
+            AdminServiceError::InvalidCredential(_) => Status::invalid_argument(message),
+        }
+    }
+}
+
+impl From<CostSummary> for proto::CostSummary {
+    fn from(s: CostSummary) -> Self {
+        Self {
+            requests: s.requests,
+            input_tokens: s.input_tokens,
+            output_tokens: s.output_tokens,
+            cache_creation_input_tokens: s.cache_creation_input_tokens,
+            cache_read_input_tokens: s.cache_read_input_tokens,
+            cost_usd: s.cost_usd,
+            unpriced_requests: s.unpriced_requests,
+        }
+    }
+}
+
+fn parse_date(field: &str, value: Option<String>) -> Result<Option<NaiveDate>, Status> {
+    value
+        .map(|v| {
+            v.parse()
+                .map_err(|_| Status::invalid_argument(format!("{} 不是有效的日期: {}", field, v)))
+        })
+        .transpose()
+}
+
+fn parse_time(field: &str, value: Option<String>) -> Result<Option<DateTime<Utc>>, Status> {
+    value
+        .map(|v| {
+            DateTime::parse_from_rfc3339(&v)
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|_| {
+                    Status::invalid_argument(format!("{} 不是有效的 RFC3339 时间: {}", field, v))
+                })
+        })
+        .transpose()
+}
+
+/// gRPC 管理服务
+struct GrpcAdmin {
+    state: AdminState,
+}
+
+impl GrpcAdmin {
+    /// 认证调用者；`write` 为修改类方法，只读账号调用时返回 PermissionDenied
+    fn authorize<T>(&self, request: &Request<T>, write: bool) -> Result<AdminAccount, Status> {
+        if let Some(access) = self.state.service.ip_access() {
+            let ip = request.remote_addr().map(|addr| addr.ip());
+            if !access.admin.permits(ip) {
+                tracing::warn!(client_ip = ?ip, "IP 访问控制拒绝 gRPC 管理请求");
+                return Err(Status::permission_denied("IP 地址不在允许范围内"));
+            }
+        }
+
+        let metadata = request.metadata();
+        let presented = metadata
+            .get("x-api-key")
+            .and_then(|v| v.to_str().ok())
+            .or_else(|| {
+                metadata
+                    .get("authorization")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.strip_prefix("Bearer "))
+            })
+            .ok_or_else(|| Status::unauthenticated("缺少 Admin API Key"))?;
+        let (account, _) = authenticate(&self.state, presented)
+            .ok_or_else(|| Status::unauthenticated("Admin API Key 无效"))?;
+        if write && account.role == AdminRole::Readonly {
+            return Err(Status::permission_denied("只读账号不能执行修改操作"));
+        }
+        Ok(account)
+    }
+
+    /// 以 `account` 的名义执行修改操作并写入审计日志
+    async fn audited<T>(
+        &self,
+        account: AdminAccount,
+        method: &str,
+        operation: impl Future<Output = Result<T, Status>>,
+    ) -> Result<Response<T>, Status> {
+        let result = events::with_actor(account.name.clone(), operation).await;
+        tracing::info!(
+            target: "audit",
+            admin = %account.name,
+            method = "gRPC",
+            path = %format_args!("/kiro.admin.v1.AdminService/{}", method),
+            status = ?result.as_ref().err().map(|e| e.code()),
+            "Admin API 操作"
+        );
+        result.map(Response::new)
+    }
+}
+
+#[tonic::async_trait]
+impl AdminService for GrpcAdmin {
+    async fn list_credentials(
+        &self,
+        request: Request<proto::ListCredentialsRequest>,
+    ) -> Result<Response<proto::ListCredentialsResponse>, Status> {
+        self.authorize(&request, false)?;
+        let status = self.state.service.get_all_credentials();
+        let credentials = status
+            .credentials
+            .into_iter()
+            .map(|c| proto::Credential {
+                id: c.id,
+                priority: c.priority,
+                disabled: c.disabled,
+                failure_count: c.failure_count,
+                is_current: c.is_current,
+                expires_at: c.expires_at,
+                auth_method: c.auth_method,
+                has_profile_arn: c.has_profile_arn,
+                email: c.email,
+                success_count: c.success_count,
+                last_used_at: c.last_used_at,
+                has_proxy: c.has_proxy,
+                proxy_url: c.proxy_url,
+                proxy_fallback_urls: c.proxy_fallback_urls,
+                exhausted_until: c.exhausted_until,
+                failures: Some(proto::FailureCounts {
+                    auth: c.stats.failures.auth,
+                    quota: c.stats.failures.quota,
+                    transient: c.stats.failures.transient,
+                    network: c.stats.failures.network,
+                    client: c.stats.failures.client,
+                }),
+                avg_latency_ms: c.stats.avg_latency_ms,
+                last_error: c.stats.last_error,
+                last_error_at: c.stats.last_error_at,
+                notes: c.notes,
+                owner_contact: c.owner_contact,
+                source: c.source,
+            })
+            .collect();
+        Ok(Response::new(proto::ListCredentialsResponse {
+            total: status.total as u64,
+            available: status.available as u64,
+            current_id: status.current_id,
+            credentials,
+        }))
+    }
+
+    async fn add_credential(
+        &self,
+        request: Request<proto::AddCredentialRequest>,
+    ) -> Result<Response<proto::AddCredentialResponse>, Status> {
+        let account = self.authorize(&request, true)?;
+        let req = request.into_inner();
+        let req = types::AddCredentialRequest {
+            refresh_token: req.refresh_token,
+            auth_method: req.auth_method.unwrap_or_else(|| "social".to_string()),
+            client_id: req.client_id,
+            client_secret: req.client_secret,
+            priority: req.priority,
+            region: req.region,
+            auth_region: req.auth_region,
+            api_region: req.api_region,
+            machine_id: req.machine_id,
+            email: req.email,
+            proxy_url: req.proxy_url,
+            proxy_username: req.proxy_username,
+            proxy_password: req.proxy_password,
+            proxy_fallback_urls: req.proxy_fallback_urls,
+        };
+        self.audited(account, "AddCredential", async {
+            let response = self.state.service.add_credential(req).await?;
+            Ok(proto::AddCredentialResponse {
+                message: response.message,
+                credential_id: response.credential_id,
+                email: response.email,
+                duplicate_of: response.duplicate_of,
+                warning: response.warning,
+            })
+        })
+        .await
+    }
+
+    async fn delete_credential(
+        &self,
+        request: Request<proto::CredentialRequest>,
+    ) -> Result<Response<proto::SuccessResponse>, Status> {
+        let account = self.authorize(&request, true)?;
+        let id = request.into_inner().id;
+        self.audited(account, "DeleteCredential", async {
+            self.state.service.delete_credential(id)?;
+            Ok(proto::SuccessResponse {
+                message: format!("凭据 #{} 已删除", id),
+            })
+        })
+        .await
+    }
+
+    async fn set_credential_disabled(
+        &self,
+        request: Request<proto::SetCredentialDisabledRequest>,
+    ) -> Result<Response<proto::SuccessResponse>, Status> {
+        let account = self.authorize(&request, true)?;
+        let req = request.into_inner();
+        self.audited(account, "SetCredentialDisabled", async {
+            self.state.service.set_disabled(req.id, req.disabled)?;
+            let action = if req.disabled { "禁用" } else { "启用" };
+            Ok(proto::SuccessResponse {
+                message: format!("凭据 #{} 已{}", req.id, action),
+            })
+        })
+        .await
+    }
+
+    async fn set_credential_priority(
+        &self,
+        request: Request<proto::SetCredentialPriorityRequest>,
+    ) -> Result<Response<proto::SuccessResponse>, Status> {
+        let account = self.authorize(&request, true)?;
+        let req = request.into_inner();
+        self.audited(account, "SetCredentialPriority", async {
+            self.state.service.set_priority(req.id, req.priority)?;
+            Ok(proto::SuccessResponse {
+                message: format!("凭据 #{} 优先级已设置为 {}", req.id, req.priority),
+            })
+        })
+        .await
+    }
+
+    async fn reset_credential(
+        &self,
+        request: Request<proto::CredentialRequest>,
+    ) -> Result<Response<proto::SuccessResponse>, Status> {
+        let account = self.authorize(&request, true)?;
+        let id = request.into_inner().id;
+        self.audited(account, "ResetCredential", async {
+            self.state.service.reset_and_enable(id)?;
+            Ok(proto::SuccessResponse {
+                message: format!("凭据 #{} 失败计数已重置并重新启用", id),
+            })
+        })
+        .await
+    }
+
+    async fn get_credential_balance(
+        &self,
+        request: Request<proto::CredentialRequest>,
+    ) -> Result<Response<proto::Balance>, Status> {
+        self.authorize(&request, false)?;
+        let balance = self
+            .state
+            .service
+            .get_balance(request.into_inner().id)
+            .await?;
+        Ok(Response::new(proto::Balance {
+            id: balance.id,
+            subscription_title: balance.subscription_title,
+            current_usage: balance.current_usage,
+            usage_limit: balance.usage_limit,
+            remaining: balance.remaining,
+            usage_percentage: balance.usage_percentage,
+            next_reset_at: balance.next_reset_at,
+        }))
+    }
+
+    async fn get_costs(
+        &self,
+        request: Request<proto::GetCostsRequest>,
+    ) -> Result<Response<proto::GetCostsResponse>, Status> {
+        self.authorize(&request, false)?;
+        let req = request.into_inner();
+        let query = types::CostsQuery {
+            since: parse_date("since", req.since)?,
+            until: parse_date("until", req.until)?,
+            api_key_id: req.api_key_id,
+            credential_id: req.credential_id,
+        };
+        let costs = self.state.service.get_costs(&query);
+        let convert = |map: std::collections::BTreeMap<String, CostSummary>| {
+            map.into_iter().map(|(k, v)| (k, v.into())).collect()
+        };
+        Ok(Response::new(proto::GetCostsResponse {
+            since: costs.since,
+            currency: costs.currency.to_string(),
+            total: Some(costs.total.into()),
+            by_api_key: convert(costs.by_api_key),
+            by_credential: convert(costs.by_credential),
+            by_day: convert(costs.by_day),
+            by_model: convert(costs.by_model),
+        }))
+    }
+
+    async fn list_request_records(
+        &self,
+        request: Request<proto::ListRequestRecordsRequest>,
+    ) -> Result<Response<proto::ListRequestRecordsResponse>, Status> {
+        self.authorize(&request, false)?;
+        let req = request.into_inner();
+        let query = types::RequestRecordsQuery {
+            offset: req.offset.map(|v| v as usize),
+            limit: req.limit.map(|v| v as usize),
+            since: parse_time("since", req.since)?,
+            until: parse_time("until", req.until)?,
+            model: req.model,
+            credential_id: req.credential_id,
+            api_key_id: req.api_key_id,
+        };
+        let response = self.state.service.get_request_records(&query);
+        let records = response
+            .records
+            .into_iter()
+            .map(|r| proto::RequestRecord {
+                timestamp: r.timestamp.to_rfc3339(),
+                api_key_id: r.api_key_id,
+                credential_id: r.credential_id,
+                model: r.model,
+                stream: r.stream,
+                status: r.status.map(u32::from),
+                attempts: r.attempts as u64,
+                duration_ms: r.duration_ms,
+                input_tokens: r.input_tokens,
+                key_id: r.key_id,
+                signature: r.signature,
+            })
+            .collect();
+        Ok(Response::new(proto::ListRequestRecordsResponse {
+            total: response.total as u64,
+            offset: response.offset as u64,
+            limit: response.limit as u64,
+            records,
+        }))
+    }
+}
+
+/// 在 `addr` 上启动 gRPC 管理服务（后台任务，随进程退出）
+pub fn spawn_grpc_server(state: AdminState, addr: SocketAddr) {
+    tokio::spawn(async move {
+        let result = tonic::transport::Server::builder()
+            .add_service(AdminServiceServer::new(GrpcAdmin { state }))
+            .serve(addr)
+            .await;
+        if let Err(e) = result {
+            tracing::error!("gRPC 管理服务异常退出: {}", e);
+        }
+    });
+}
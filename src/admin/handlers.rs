@@ -1,21 +1,65 @@
 //! Admin API HTTP 处理器
 
 use axum::{
-    Json,
-    extract::{Path, State},
-    response::IntoResponse,
+    Extension, Json,
+    extract::{
+        Path, Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{HeaderMap, StatusCode, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
 };
+use futures::stream::{self, Stream};
+use tokio::sync::broadcast;
 
 use super::{
     middleware::AdminState,
+    session::SessionClaims,
     types::{
-        AddCredentialRequest, SetDisabledRequest, SetLoadBalancingModeRequest, SetPriorityRequest,
-        SuccessResponse,
+        AddCredentialRequest, AddCredentialResponse, AddTenantKeyRequest, AddTenantKeyResponse,
+        AdminErrorResponse, AdminIdentity, BalanceResponse, CostsQuery, CostsResponse,
+        CredentialEndpointsResponse, CredentialsBundle, CredentialsStatusResponse, DryRunQuery,
+        DuplicateCredentialsResponse, ForecastResponse, ImportCredentialsResponse,
+        LoadBalancingModeResponse, LoginRequest, LogsQuery, LogsResponse, ModelRouteItem,
+        ModelRoutesResponse, ProxiesResponse, RefreshAccountResponse, ReloadConfigResponse,
+        RequestRecordsQuery, RequestRecordsResponse, SessionResponse, SetDisabledRequest,
+        SetLoadBalancingModeRequest, SetPriorityRequest, SetTenantRequest, SigningKeyResponse,
+        StreamStatsResponse, SuccessResponse, TenantsResponse, TestProxyRequest, TestProxyResponse,
+        UpdateCredentialRequest,
     },
 };
+use crate::cluster::ClusterStatus;
+use crate::common::{auth, i18n};
+use crate::events;
+use crate::kiro::schema_drift::UnknownEventsReport;
+use crate::logging::{self, LogEntry, LogFilter};
+use crate::model::config::AdminAccount;
+use crate::recovery::RecoveryReport;
+
+/// `/logs` 默认返回条数
+const DEFAULT_LOG_LIMIT: usize = 200;
+
+/// 导入/导出加解密口令所在的请求头
+const PASSPHRASE_HEADER: &str = "x-passphrase";
+
+fn passphrase(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(PASSPHRASE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|s| !s.is_empty())
+}
 
 /// GET /api/admin/credentials
 /// 获取所有凭据状态
+#[utoipa::path(
+    get,
+    path = "/api/admin/credentials",
+    tag = "admin",
+    responses((status = 200, body = CredentialsStatusResponse))
+)]
 pub async fn get_all_credentials(State(state): State<AdminState>) -> impl IntoResponse {
     let response = state.service.get_all_credentials();
     Json(response)
@@ -23,6 +67,14 @@ pub async fn get_all_credentials(State(state): State<AdminState>) -> impl IntoRe
 
 /// POST /api/admin/credentials/:id/disabled
 /// 设置凭据禁用状态
+#[utoipa::path(
+    post,
+    path = "/api/admin/credentials/{id}/disabled",
+    tag = "admin",
+    params(("id" = u64, Path, description = "凭据 ID")),
+    request_body = SetDisabledRequest,
+    responses((status = 200, body = SuccessResponse), (status = 404, description = "凭据不存在", body = AdminErrorResponse))
+)]
 pub async fn set_credential_disabled(
     State(state): State<AdminState>,
     Path(id): Path<u64>,
@@ -39,6 +91,14 @@ pub async fn set_credential_disabled(
 
 /// POST /api/admin/credentials/:id/priority
 /// 设置凭据优先级
+#[utoipa::path(
+    post,
+    path = "/api/admin/credentials/{id}/priority",
+    tag = "admin",
+    params(("id" = u64, Path, description = "凭据 ID")),
+    request_body = SetPriorityRequest,
+    responses((status = 200, body = SuccessResponse), (status = 404, description = "凭据不存在", body = AdminErrorResponse))
+)]
 pub async fn set_credential_priority(
     State(state): State<AdminState>,
     Path(id): Path<u64>,
@@ -54,8 +114,36 @@ pub async fn set_credential_priority(
     }
 }
 
+/// PATCH /api/admin/credentials/:id
+/// 更新凭据备注、负责人联系方式与来源
+#[utoipa::path(
+    patch,
+    path = "/api/admin/credentials/{id}",
+    tag = "admin",
+    params(("id" = u64, Path, description = "凭据 ID")),
+    request_body = UpdateCredentialRequest,
+    responses((status = 200, body = SuccessResponse), (status = 404, description = "凭据不存在", body = AdminErrorResponse))
+)]
+pub async fn update_credential(
+    State(state): State<AdminState>,
+    Path(id): Path<u64>,
+    Json(payload): Json<UpdateCredentialRequest>,
+) -> impl IntoResponse {
+    match state.service.update_credential(id, payload) {
+        Ok(_) => Json(SuccessResponse::new(format!("凭据 #{} 信息已更新", id))).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
 /// POST /api/admin/credentials/:id/reset
 /// 重置失败计数并重新启用
+#[utoipa::path(
+    post,
+    path = "/api/admin/credentials/{id}/reset",
+    tag = "admin",
+    params(("id" = u64, Path, description = "凭据 ID")),
+    responses((status = 200, body = SuccessResponse), (status = 404, description = "凭据不存在", body = AdminErrorResponse))
+)]
 pub async fn reset_failure_count(
     State(state): State<AdminState>,
     Path(id): Path<u64>,
@@ -72,6 +160,13 @@ pub async fn reset_failure_count(
 
 /// GET /api/admin/credentials/:id/balance
 /// 获取指定凭据的余额
+#[utoipa::path(
+    get,
+    path = "/api/admin/credentials/{id}/balance",
+    tag = "admin",
+    params(("id" = u64, Path, description = "凭据 ID")),
+    responses((status = 200, body = BalanceResponse), (status = 404, description = "凭据不存在", body = AdminErrorResponse))
+)]
 pub async fn get_credential_balance(
     State(state): State<AdminState>,
     Path(id): Path<u64>,
@@ -82,8 +177,89 @@ pub async fn get_credential_balance(
     }
 }
 
+/// POST /api/admin/credentials/:id/refresh-account
+/// 重新查询账号信息，更新邮箱并检测账号是否被暂停
+#[utoipa::path(
+    post,
+    path = "/api/admin/credentials/{id}/refresh-account",
+    tag = "admin",
+    params(("id" = u64, Path, description = "凭据 ID")),
+    responses((status = 200, body = RefreshAccountResponse), (status = 404, description = "凭据不存在", body = AdminErrorResponse))
+)]
+pub async fn refresh_account(
+    State(state): State<AdminState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    match state.service.refresh_account(id).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// GET /api/admin/credentials/proxies
+/// 获取凭据代理的健康状态
+#[utoipa::path(
+    get,
+    path = "/api/admin/credentials/proxies",
+    tag = "admin",
+    responses((status = 200, body = ProxiesResponse))
+)]
+pub async fn get_credential_proxies(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_proxies())
+}
+
+/// GET /api/admin/credentials/duplicates
+/// 列出疑似重复（同一账号）的凭据分组
+#[utoipa::path(
+    get,
+    path = "/api/admin/credentials/duplicates",
+    tag = "admin",
+    responses((status = 200, body = DuplicateCredentialsResponse))
+)]
+pub async fn get_duplicate_credentials(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_duplicate_credentials())
+}
+
+/// GET /api/admin/credentials/endpoints
+/// 列出每个凭据的生效端点
+#[utoipa::path(
+    get,
+    path = "/api/admin/credentials/endpoints",
+    tag = "admin",
+    responses((status = 200, body = CredentialEndpointsResponse))
+)]
+pub async fn get_credential_endpoints(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_credential_endpoints())
+}
+
+/// GET /api/admin/credentials/:id/forecast
+/// 按当前消耗速度预测额度耗尽时间
+#[utoipa::path(
+    get,
+    path = "/api/admin/credentials/{id}/forecast",
+    tag = "admin",
+    params(("id" = u64, Path, description = "凭据 ID")),
+    responses((status = 200, body = ForecastResponse), (status = 404, description = "凭据不存在", body = AdminErrorResponse))
+)]
+pub async fn get_credential_forecast(
+    State(state): State<AdminState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    match state.service.get_forecast(id).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
 /// POST /api/admin/credentials
 /// 添加新凭据
+#[utoipa::path(
+    post,
+    path = "/api/admin/credentials",
+    tag = "admin",
+    request_body = AddCredentialRequest,
+    responses((status = 200, body = AddCredentialResponse), (status = 400, body = AdminErrorResponse))
+)]
 pub async fn add_credential(
     State(state): State<AdminState>,
     Json(payload): Json<AddCredentialRequest>,
@@ -96,6 +272,13 @@ pub async fn add_credential(
 
 /// DELETE /api/admin/credentials/:id
 /// 删除凭据
+#[utoipa::path(
+    delete,
+    path = "/api/admin/credentials/{id}",
+    tag = "admin",
+    params(("id" = u64, Path, description = "凭据 ID")),
+    responses((status = 200, body = SuccessResponse), (status = 404, description = "凭据不存在", body = AdminErrorResponse))
+)]
 pub async fn delete_credential(
     State(state): State<AdminState>,
     Path(id): Path<u64>,
@@ -106,21 +289,688 @@ pub async fn delete_credential(
     }
 }
 
+/// GET /api/admin/me
+/// 获取当前 Admin 账号的名称和角色
+#[utoipa::path(
+    get,
+    path = "/api/admin/me",
+    tag = "admin",
+    responses((status = 200, body = AdminIdentity))
+)]
+pub async fn get_me(Extension(identity): Extension<AdminIdentity>) -> impl IntoResponse {
+    Json(identity)
+}
+
+/// POST /api/admin/login
+/// 用 Admin 账号的 Key（或账号名 + Key）换取短期有效的会话 Token
+#[utoipa::path(
+    post,
+    path = "/api/admin/login",
+    tag = "admin",
+    request_body = LoginRequest,
+    responses((status = 200, body = SessionResponse), (status = 401, body = AdminErrorResponse)),
+    security(())
+)]
+pub async fn login(
+    State(state): State<AdminState>,
+    Json(payload): Json<LoginRequest>,
+) -> impl IntoResponse {
+    let account = match (&payload.key, &payload.username, &payload.password) {
+        (Some(key), _, _) => state.service.find_admin_account(key),
+        (None, Some(username), Some(password)) => state
+            .service
+            .find_admin_account_by_name(username)
+            .filter(|account| auth::verify_api_key(password, &account.key)),
+        _ => None,
+    };
+    let Some(account) = account else {
+        let error = AdminErrorResponse::authentication_error();
+        return (StatusCode::UNAUTHORIZED, Json(error)).into_response();
+    };
+
+    tracing::info!(target: "audit", admin = %account.name, "Admin 登录");
+    Json(issue_session(&state, &account)).into_response()
+}
+
+/// POST /api/admin/session/refresh
+/// 换发新的会话 Token 并注销当前 Token（需使用会话 Token 认证）
+#[utoipa::path(
+    post,
+    path = "/api/admin/session/refresh",
+    tag = "admin",
+    responses((status = 200, body = SessionResponse), (status = 400, description = "未使用会话 Token 认证", body = AdminErrorResponse))
+)]
+pub async fn refresh_session(
+    State(state): State<AdminState>,
+    claims: Option<Extension<SessionClaims>>,
+) -> impl IntoResponse {
+    let Some(Extension(claims)) = claims else {
+        let error =
+            AdminErrorResponse::invalid_request(i18n::Message::SessionTokenRequired.localized());
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    };
+    let Some(account) = state.service.find_admin_account_by_name(&claims.sub) else {
+        let error = AdminErrorResponse::authentication_error();
+        return (StatusCode::UNAUTHORIZED, Json(error)).into_response();
+    };
+
+    state.sessions.revoke(&claims);
+    Json(issue_session(&state, &account)).into_response()
+}
+
+/// POST /api/admin/logout
+/// 注销当前会话 Token（使用 Admin API Key 认证时无操作）
+#[utoipa::path(
+    post,
+    path = "/api/admin/logout",
+    tag = "admin",
+    responses((status = 200, body = SuccessResponse))
+)]
+pub async fn logout(
+    State(state): State<AdminState>,
+    claims: Option<Extension<SessionClaims>>,
+) -> impl IntoResponse {
+    if let Some(Extension(claims)) = claims {
+        state.sessions.revoke(&claims);
+        tracing::info!(target: "audit", admin = %claims.sub, "Admin 注销");
+    }
+    Json(SuccessResponse::new("已注销"))
+}
+
+fn issue_session(state: &AdminState, account: &AdminAccount) -> SessionResponse {
+    let ttl = state.service.admin_session_ttl_minutes();
+    let (token, claims) = state.sessions.issue(account, ttl);
+    let expires_at = chrono::DateTime::from_timestamp(claims.exp, 0).unwrap_or_default();
+    SessionResponse {
+        token,
+        expires_at: expires_at.to_rfc3339(),
+        name: account.name.clone(),
+        role: account.role,
+    }
+}
+
 /// GET /api/admin/config/load-balancing
 /// 获取负载均衡模式
+#[utoipa::path(
+    get,
+    path = "/api/admin/config/load-balancing",
+    tag = "admin",
+    responses((status = 200, body = LoadBalancingModeResponse))
+)]
 pub async fn get_load_balancing_mode(State(state): State<AdminState>) -> impl IntoResponse {
     let response = state.service.get_load_balancing_mode();
     Json(response)
 }
 
 /// PUT /api/admin/config/load-balancing
-/// 设置负载均衡模式
+/// 设置负载均衡模式（`?dry_run=true` 时只返回变更预览）
+#[utoipa::path(
+    put,
+    path = "/api/admin/config/load-balancing",
+    tag = "admin",
+    params(DryRunQuery),
+    request_body = SetLoadBalancingModeRequest,
+    responses((status = 200, description = "`dry_run=true` 时为 ConfigDiffResponse", body = LoadBalancingModeResponse), (status = 400, body = AdminErrorResponse))
+)]
 pub async fn set_load_balancing_mode(
     State(state): State<AdminState>,
+    Query(query): Query<DryRunQuery>,
     Json(payload): Json<SetLoadBalancingModeRequest>,
 ) -> impl IntoResponse {
+    if query.dry_run {
+        return match state.service.preview_load_balancing_mode(payload) {
+            Ok(response) => Json(response).into_response(),
+            Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+        };
+    }
     match state.service.set_load_balancing_mode(payload) {
         Ok(response) => Json(response).into_response(),
         Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
     }
 }
+
+/// GET /api/admin/stats/streams
+/// 获取流式响应结束统计（正常结束 / 上游出错 / 客户端断开）
+#[utoipa::path(
+    get,
+    path = "/api/admin/stats/streams",
+    tag = "admin",
+    responses((status = 200, body = StreamStatsResponse))
+)]
+pub async fn get_stream_stats(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_stream_stats())
+}
+
+/// GET /api/admin/recovery
+/// 获取启动恢复报告（上次是否未正常退出、估计丢失的统计与中断的流、隔离的损坏文件）
+#[utoipa::path(
+    get,
+    path = "/api/admin/recovery",
+    tag = "admin",
+    responses((status = 200, body = RecoveryReport))
+)]
+pub async fn get_recovery_report(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_recovery_report())
+}
+
+/// GET /api/admin/diagnostics/unknown-events
+/// 获取上游事件流中未识别的消息类型、事件类型与字段（用于发现上游协议变更）
+#[utoipa::path(
+    get,
+    path = "/api/admin/diagnostics/unknown-events",
+    tag = "admin",
+    responses((status = 200, body = UnknownEventsReport))
+)]
+pub async fn get_unknown_events(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_unknown_events())
+}
+
+/// GET /api/admin/cluster
+/// 获取集群状态：本实例标识、leader、最近有心跳的实例与最近 7 天的集群合计用量
+#[utoipa::path(
+    get,
+    path = "/api/admin/cluster",
+    tag = "admin",
+    responses(
+        (status = 200, body = ClusterStatus),
+        (status = 500, body = AdminErrorResponse)
+    )
+)]
+pub async fn get_cluster_status(State(state): State<AdminState>) -> impl IntoResponse {
+    match state.service.get_cluster_status().await {
+        Ok(status) => Json(status).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// POST /api/admin/support-bundle
+/// 生成脱敏的诊断包（zip），包含版本信息、配置、最近日志、凭据健康概况与最近错误，
+/// 可直接附在 issue 中
+#[utoipa::path(
+    post,
+    path = "/api/admin/support-bundle",
+    tag = "admin",
+    responses(
+        (status = 200, description = "诊断包", content_type = "application/zip", body = [u8]),
+        (status = 500, body = AdminErrorResponse)
+    )
+)]
+pub async fn create_support_bundle(State(state): State<AdminState>) -> impl IntoResponse {
+    match state.service.support_bundle() {
+        Ok(content) => {
+            let filename = format!(
+                "kiro-rs-support-{}.zip",
+                chrono::Utc::now().format("%Y%m%d%H%M%S")
+            );
+            (
+                [
+                    (header::CONTENT_TYPE, "application/zip".to_string()),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        format!("attachment; filename=\"{}\"", filename),
+                    ),
+                ],
+                content,
+            )
+                .into_response()
+        }
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// GET /api/admin/token-usage/requests
+/// 分页查询最近的请求记录，支持时间范围、模型、凭据与 API Key 过滤
+#[utoipa::path(
+    get,
+    path = "/api/admin/token-usage/requests",
+    tag = "admin",
+    params(RequestRecordsQuery),
+    responses((status = 200, body = RequestRecordsResponse))
+)]
+pub async fn get_request_records(
+    State(state): State<AdminState>,
+    Query(query): Query<RequestRecordsQuery>,
+) -> impl IntoResponse {
+    Json(state.service.get_request_records(&query))
+}
+
+/// GET /api/admin/token-usage/signing-key
+/// 获取校验请求记录签名所需的公钥与签名格式
+#[utoipa::path(
+    get,
+    path = "/api/admin/token-usage/signing-key",
+    tag = "admin",
+    responses((status = 200, body = SigningKeyResponse), (status = 404, description = "未配置 usageSigningKey", body = AdminErrorResponse))
+)]
+pub async fn get_signing_key(State(state): State<AdminState>) -> impl IntoResponse {
+    match state.service.get_signing_key() {
+        Some(response) => Json(response).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(AdminErrorResponse::not_found(
+                i18n::Message::SigningKeyNotConfigured.localized(),
+            )),
+        )
+            .into_response(),
+    }
+}
+
+/// GET /api/admin/costs
+/// 按 API Key、凭据、日期与模型汇总估算费用，支持日期范围、API Key 与凭据过滤
+#[utoipa::path(
+    get,
+    path = "/api/admin/costs",
+    tag = "admin",
+    params(CostsQuery),
+    responses((status = 200, body = CostsResponse))
+)]
+pub async fn get_costs(
+    State(state): State<AdminState>,
+    Query(query): Query<CostsQuery>,
+) -> impl IntoResponse {
+    Json(state.service.get_costs(&query))
+}
+
+/// GET /api/admin/config/model-routes
+/// 获取模型路由表
+#[utoipa::path(
+    get,
+    path = "/api/admin/config/model-routes",
+    tag = "admin",
+    responses((status = 200, body = ModelRoutesResponse))
+)]
+pub async fn get_model_routes(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_model_routes())
+}
+
+/// PUT /api/admin/config/model-routes/:alias
+/// 新增或替换模型路由（`?dry_run=true` 时只返回变更预览）
+#[utoipa::path(
+    put,
+    path = "/api/admin/config/model-routes/{alias}",
+    tag = "admin",
+    params(("alias" = String, Path, description = "客户端模型名"), DryRunQuery),
+    request_body = ModelRouteItem,
+    responses((status = 200, description = "`dry_run=true` 时为 ConfigDiffResponse", body = ModelRoutesResponse), (status = 400, body = AdminErrorResponse))
+)]
+pub async fn set_model_route(
+    State(state): State<AdminState>,
+    Path(alias): Path<String>,
+    Query(query): Query<DryRunQuery>,
+    Json(payload): Json<ModelRouteItem>,
+) -> impl IntoResponse {
+    if query.dry_run {
+        return match state.service.preview_model_route(alias, payload) {
+            Ok(response) => Json(response).into_response(),
+            Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+        };
+    }
+    match state.service.set_model_route(alias, payload) {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// DELETE /api/admin/config/model-routes/:alias
+/// 删除模型路由
+#[utoipa::path(
+    delete,
+    path = "/api/admin/config/model-routes/{alias}",
+    tag = "admin",
+    params(("alias" = String, Path, description = "客户端模型名")),
+    responses((status = 200, body = SuccessResponse), (status = 404, description = "模型路由不存在", body = AdminErrorResponse))
+)]
+pub async fn delete_model_route(
+    State(state): State<AdminState>,
+    Path(alias): Path<String>,
+) -> impl IntoResponse {
+    match state.service.delete_model_route(&alias) {
+        Ok(true) => {
+            Json(SuccessResponse::new(format!("模型路由 {} 已删除", alias))).into_response()
+        }
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(AdminErrorResponse::not_found(format!(
+                "{}: {}",
+                i18n::Message::ModelRouteNotFound.localized(),
+                alias
+            ))),
+        )
+            .into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// 租户不存在的 404 响应
+fn tenant_not_found(name: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(AdminErrorResponse::not_found(format!(
+            "{}: {}",
+            i18n::Message::TenantNotFound.localized(),
+            name
+        ))),
+    )
+        .into_response()
+}
+
+/// GET /api/admin/tenants
+/// 获取租户列表（含本月用量）
+#[utoipa::path(
+    get,
+    path = "/api/admin/tenants",
+    tag = "admin",
+    responses((status = 200, body = TenantsResponse))
+)]
+pub async fn get_tenants(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_tenants().await)
+}
+
+/// PUT /api/admin/tenants/:name
+/// 新增或修改租户（保留已有的 API Key）
+#[utoipa::path(
+    put,
+    path = "/api/admin/tenants/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "租户名")),
+    request_body = SetTenantRequest,
+    responses((status = 200, body = TenantsResponse), (status = 400, body = AdminErrorResponse))
+)]
+pub async fn set_tenant(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(payload): Json<SetTenantRequest>,
+) -> impl IntoResponse {
+    match state.service.set_tenant(name, payload).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// DELETE /api/admin/tenants/:name
+/// 删除租户（其 API Key 随之失效）
+#[utoipa::path(
+    delete,
+    path = "/api/admin/tenants/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "租户名")),
+    responses((status = 200, body = SuccessResponse), (status = 404, description = "租户不存在", body = AdminErrorResponse))
+)]
+pub async fn delete_tenant(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.service.delete_tenant(&name) {
+        Ok(true) => Json(SuccessResponse::new(format!("租户 {} 已删除", name))).into_response(),
+        Ok(false) => tenant_not_found(&name),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// POST /api/admin/tenants/:name/keys
+/// 为租户添加 API Key（未指定 `key` 时自动生成，完整 Key 只在响应中返回一次）
+#[utoipa::path(
+    post,
+    path = "/api/admin/tenants/{name}/keys",
+    tag = "admin",
+    params(("name" = String, Path, description = "租户名")),
+    request_body = AddTenantKeyRequest,
+    responses((status = 200, body = AddTenantKeyResponse), (status = 400, body = AdminErrorResponse), (status = 404, description = "租户不存在", body = AdminErrorResponse))
+)]
+pub async fn add_tenant_key(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(payload): Json<AddTenantKeyRequest>,
+) -> impl IntoResponse {
+    match state.service.add_tenant_key(&name, payload) {
+        Ok(Some(response)) => Json(response).into_response(),
+        Ok(None) => tenant_not_found(&name),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// DELETE /api/admin/tenants/:name/keys/:key_id
+/// 移除租户的 API Key
+#[utoipa::path(
+    delete,
+    path = "/api/admin/tenants/{name}/keys/{key_id}",
+    tag = "admin",
+    params(
+        ("name" = String, Path, description = "租户名"),
+        ("key_id" = String, Path, description = "API Key 标识（见 `GET /api/admin/tenants` 的 `apiKeyIds`）")
+    ),
+    responses((status = 200, body = SuccessResponse), (status = 404, description = "租户或 API Key 不存在", body = AdminErrorResponse))
+)]
+pub async fn remove_tenant_key(
+    State(state): State<AdminState>,
+    Path((name, key_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.service.remove_tenant_key(&name, &key_id) {
+        Ok(true) => Json(SuccessResponse::new(format!(
+            "已移除租户 {} 的 API Key {}",
+            name, key_id
+        )))
+        .into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(AdminErrorResponse::not_found(format!(
+                "{}: {}/{}",
+                i18n::Message::TenantKeyNotFound.localized(),
+                name,
+                key_id
+            ))),
+        )
+            .into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// GET /api/admin/credentials/export
+/// 导出所有凭据（携带 `x-passphrase` 头时返回加密信封）
+#[utoipa::path(
+    get,
+    path = "/api/admin/credentials/export",
+    tag = "admin",
+    params(("x-passphrase" = Option<String>, Header, description = "加密口令")),
+    responses((status = 200, body = CredentialsBundle))
+)]
+pub async fn export_credentials(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    match state.service.export_credentials(passphrase(&headers)) {
+        Ok(bundle) => Json(bundle).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// POST /api/admin/credentials/import
+/// 批量导入凭据（加密信封需携带 `x-passphrase` 头）
+#[utoipa::path(
+    post,
+    path = "/api/admin/credentials/import",
+    tag = "admin",
+    params(("x-passphrase" = Option<String>, Header, description = "解密口令")),
+    request_body = CredentialsBundle,
+    responses((status = 200, body = ImportCredentialsResponse), (status = 400, body = AdminErrorResponse))
+)]
+pub async fn import_credentials(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Json(payload): Json<CredentialsBundle>,
+) -> impl IntoResponse {
+    match state
+        .service
+        .import_credentials(payload, passphrase(&headers))
+        .await
+    {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// POST /api/admin/config/proxy/test
+/// 经候选代理发起一次真实的出站请求，报告出口 IP 与耗时（用于保存配置前验证代理）
+#[utoipa::path(
+    post,
+    path = "/api/admin/config/proxy/test",
+    tag = "admin",
+    request_body = TestProxyRequest,
+    responses((status = 200, body = TestProxyResponse), (status = 400, description = "代理地址或测试目标无效", body = AdminErrorResponse))
+)]
+pub async fn test_proxy(
+    State(state): State<AdminState>,
+    Json(payload): Json<TestProxyRequest>,
+) -> impl IntoResponse {
+    match state.service.test_proxy(payload).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// POST /api/admin/config/reload
+/// 重新读取 config.json 并热更新可在运行时生效的配置
+/// （`?dry_run=true` 时只校验并返回与运行中配置的差异）
+#[utoipa::path(
+    post,
+    path = "/api/admin/config/reload",
+    tag = "admin",
+    params(DryRunQuery),
+    responses((status = 200, description = "`dry_run=true` 时为 ConfigDiffResponse", body = ReloadConfigResponse), (status = 500, body = AdminErrorResponse))
+)]
+pub async fn reload_config(
+    State(state): State<AdminState>,
+    Query(query): Query<DryRunQuery>,
+) -> impl IntoResponse {
+    if query.dry_run {
+        return match state.service.preview_reload_config() {
+            Ok(response) => Json(response).into_response(),
+            Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+        };
+    }
+    match state.service.reload_config() {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+    }
+}
+
+/// GET /api/admin/logs
+/// 获取内存缓冲中的最近日志
+#[utoipa::path(
+    get,
+    path = "/api/admin/logs",
+    tag = "admin",
+    params(LogsQuery),
+    responses((status = 200, body = LogsResponse), (status = 400, body = AdminErrorResponse))
+)]
+pub async fn get_logs(Query(query): Query<LogsQuery>) -> impl IntoResponse {
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(AdminErrorResponse::invalid_request(e)),
+            )
+                .into_response();
+        }
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_LOG_LIMIT);
+    let logs = logging::buffer()
+        .map(|buffer| buffer.recent(&filter, limit))
+        .unwrap_or_default();
+    Json(LogsResponse { logs }).into_response()
+}
+
+/// GET /api/admin/logs/stream
+/// 通过 WebSocket 实时推送新产生的日志（每条一个 JSON 文本帧）
+#[utoipa::path(
+    get,
+    path = "/api/admin/logs/stream",
+    tag = "admin",
+    params(LogsQuery),
+    responses((status = 101, description = "WebSocket，每条日志为一个 JSON 文本帧（LogEntry）"))
+)]
+pub async fn stream_logs(
+    ws: WebSocketUpgrade,
+    Query(query): Query<LogsQuery>,
+) -> impl IntoResponse {
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(AdminErrorResponse::invalid_request(e)),
+            )
+                .into_response();
+        }
+    };
+    let Some(buffer) = logging::buffer() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(AdminErrorResponse::internal_error(
+                i18n::Message::LogBufferDisabled.localized(),
+            )),
+        )
+            .into_response();
+    };
+    let receiver = buffer.subscribe();
+    ws.on_upgrade(move |socket| forward_logs(socket, receiver, filter))
+}
+
+/// 将日志转发到 WebSocket，直到客户端断开
+///
+/// 这里不能输出日志，否则每条转发都会产生新的日志事件
+async fn forward_logs(
+    mut socket: WebSocket,
+    mut receiver: broadcast::Receiver<LogEntry>,
+    filter: LogFilter,
+) {
+    loop {
+        tokio::select! {
+            entry = receiver.recv() => match entry {
+                Ok(entry) if filter.matches(&entry) => {
+                    let Ok(text) = serde_json::to_string(&entry) else {
+                        continue;
+                    };
+                    if socket.send(Message::Text(text.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                // 客户端处理过慢，跳过被覆盖的事件
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// GET /api/admin/events/stream
+/// 以 SSE 推送进程内事件（请求完成、凭据启用/禁用、增删凭据、配置重载）
+#[utoipa::path(
+    get,
+    path = "/api/admin/events/stream",
+    tag = "admin",
+    responses((status = 200, description = "SSE 事件流，每条 data 为一个 JSON 事件", content_type = "text/event-stream"))
+)]
+pub async fn stream_events() -> Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>> {
+    let receiver = events::subscribe();
+    let stream = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(envelope) => {
+                    let event = Event::default().json_data(&envelope).ok()?;
+                    return Some((Ok(event), receiver));
+                }
+                // 客户端处理过慢，跳过被覆盖的事件
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
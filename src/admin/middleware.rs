@@ -5,46 +5,167 @@ use std::sync::Arc;
 use axum::{
     body::Body,
     extract::State,
-    http::{Request, StatusCode},
+    http::{Method, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Json, Response},
 };
 
 use super::service::AdminService;
-use super::types::AdminErrorResponse;
-use crate::common::auth;
+use super::session::{AdminSessions, SessionClaims};
+use super::types::{AdminErrorResponse, AdminIdentity};
+use crate::common::i18n::{self, Locale};
+use crate::common::{auth, ip_access};
+use crate::events;
+use crate::model::config::{AdminAccount, AdminRole};
 
 /// Admin API 共享状态
 #[derive(Clone)]
 pub struct AdminState {
-    /// Admin API 密钥
-    pub admin_api_key: String,
     /// Admin 服务
     pub service: Arc<AdminService>,
+    /// 会话 Token
+    pub sessions: Arc<AdminSessions>,
 }
 
 impl AdminState {
-    pub fn new(admin_api_key: impl Into<String>, service: AdminService) -> Self {
+    pub fn new(service: AdminService) -> Self {
         Self {
-            admin_api_key: admin_api_key.into(),
             service: Arc::new(service),
+            sessions: Arc::new(AdminSessions::default()),
         }
     }
 }
 
+/// 只读账号可以调用的 POST 接口：无副作用的连通性测试，续期与注销只影响调用者自己的会话
+const READONLY_POST_PATHS: &[&str] = &["/config/proxy/test", "/session/refresh", "/logout"];
+
+/// 只读账号允许的请求：查询类（GET / HEAD）与 `READONLY_POST_PATHS` 中的 POST，
+/// 导出凭据会暴露 refreshToken，不允许
+fn is_readonly_request(method: &Method, path: &str) -> bool {
+    match *method {
+        Method::GET | Method::HEAD => !path.ends_with("/credentials/export"),
+        Method::POST => READONLY_POST_PATHS.iter().any(|safe| path.ends_with(safe)),
+        _ => false,
+    }
+}
+
+/// 认证请求：会话 Token 对应的账号须仍存在且 Key 未更换，否则按 Admin API Key 查找账号
+pub(super) fn authenticate(
+    state: &AdminState,
+    presented: &str,
+) -> Option<(AdminAccount, Option<SessionClaims>)> {
+    if let Some(claims) = state.sessions.verify(presented) {
+        let account = state
+            .service
+            .find_admin_account_by_name(&claims.sub)
+            .filter(|account| claims.matches(account))?;
+        return Some((account, Some(claims)));
+    }
+    state
+        .service
+        .find_admin_account(presented)
+        .map(|account| (account, None))
+}
+
+/// 语言协商中间件：按 `Accept-Language` 选择本次请求的错误消息语言（见 `common::i18n`），
+/// 同时用于 Admin API 与 Admin UI
+pub async fn locale_middleware(request: Request<Body>, next: Next) -> Response {
+    let locale = Locale::from_headers(request.headers());
+    i18n::with_locale(locale, next.run(request)).await
+}
+
+/// IP 访问控制中间件（规则见配置 `ipAccess.admin`），同时用于 Admin API 与 Admin UI
+pub async fn ip_access_middleware(
+    State(state): State<AdminState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if let Some(access) = state.service.ip_access() {
+        let ip = ip_access::client_ip(&request, access.trust_forwarded_for);
+        if !access.admin.permits(ip) {
+            tracing::warn!(client_ip = ?ip, path = %request.uri().path(), "IP 访问控制拒绝 Admin 请求");
+            let error = AdminErrorResponse::ip_denied();
+            return (StatusCode::FORBIDDEN, Json(error)).into_response();
+        }
+    }
+    next.run(request).await
+}
+
 /// Admin API 认证中间件
+///
+/// 按会话 Token 或 Key 查找 Admin 账号并校验角色，通过后将 `AdminIdentity`
+/// （以及会话 Token 的 `SessionClaims`）写入请求扩展；
+/// 修改类请求以该账号的名义执行（期间发布的事件带上账号名），并写入审计日志
 pub async fn admin_auth_middleware(
     State(state): State<AdminState>,
-    request: Request<Body>,
+    mut request: Request<Body>,
     next: Next,
 ) -> Response {
-    let api_key = auth::extract_api_key(&request);
+    let authenticated = auth::extract_api_key(&request).and_then(|key| authenticate(&state, &key));
+    let Some((account, session)) = authenticated else {
+        let error = AdminErrorResponse::authentication_error();
+        return (StatusCode::UNAUTHORIZED, Json(error)).into_response();
+    };
 
-    match api_key {
-        Some(key) if auth::constant_time_eq(&key, &state.admin_api_key) => next.run(request).await,
-        _ => {
-            let error = AdminErrorResponse::authentication_error();
-            (StatusCode::UNAUTHORIZED, Json(error)).into_response()
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let readonly = is_readonly_request(&method, &path);
+    if account.role == AdminRole::Readonly && !readonly {
+        let error = AdminErrorResponse::permission_error();
+        return (StatusCode::FORBIDDEN, Json(error)).into_response();
+    }
+
+    request.extensions_mut().insert(AdminIdentity {
+        name: account.name.clone(),
+        role: account.role,
+    });
+    if let Some(claims) = session {
+        request.extensions_mut().insert(claims);
+    }
+    if readonly {
+        return next.run(request).await;
+    }
+
+    let response = events::with_actor(account.name.clone(), next.run(request)).await;
+    tracing::info!(
+        target: "audit",
+        admin = %account.name,
+        method = %method,
+        path = %path,
+        status = response.status().as_u16(),
+        "Admin API 操作"
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_readonly_request() {
+        for path in READONLY_POST_PATHS {
+            let path = format!("/api/admin{}", path);
+            assert!(is_readonly_request(&Method::POST, &path), "{}", path);
+            assert!(!is_readonly_request(&Method::DELETE, &path), "{}", path);
+        }
+        assert!(is_readonly_request(&Method::GET, "/api/admin/credentials"));
+        assert!(is_readonly_request(&Method::HEAD, "/api/admin/config"));
+        assert!(!is_readonly_request(
+            &Method::GET,
+            "/api/admin/credentials/export"
+        ));
+        for path in [
+            "/api/admin/credentials",
+            "/api/admin/config/reload",
+            "/api/admin/credentials/1/reset",
+            "/api/admin/support-bundle",
+        ] {
+            assert!(!is_readonly_request(&Method::POST, path), "{}", path);
         }
+        assert!(!is_readonly_request(
+            &Method::PUT,
+            "/api/admin/config/proxy"
+        ));
     }
 }
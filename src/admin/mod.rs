@@ -12,17 +12,25 @@
 //! # 使用
 //! ```ignore
 //! let admin_service = AdminService::new(token_manager.clone());
-//! let admin_state = AdminState::new(admin_api_key, admin_service);
+//! let admin_state = AdminState::new(admin_service);
 //! let admin_router = create_admin_router(admin_state);
 //! ```
 
 mod error;
+#[cfg(feature = "grpc")]
+mod grpc;
 mod handlers;
 mod middleware;
+mod openapi;
 mod router;
 mod service;
+mod session;
+mod support_bundle;
 pub mod types;
 
-pub use middleware::AdminState;
+#[cfg(feature = "grpc")]
+pub use grpc::spawn_grpc_server;
+pub use middleware::{AdminState, ip_access_middleware, locale_middleware};
+pub use openapi::AdminApiDoc;
 pub use router::create_admin_router;
 pub use service::AdminService;
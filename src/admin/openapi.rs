@@ -0,0 +1,53 @@
+//! Admin API 的 OpenAPI 描述
+
+use utoipa::OpenApi;
+
+use super::handlers;
+
+/// Admin API（`/api/admin`）
+#[derive(OpenApi)]
+#[openapi(paths(
+    handlers::get_all_credentials,
+    handlers::add_credential,
+    handlers::export_credentials,
+    handlers::import_credentials,
+    handlers::get_credential_endpoints,
+    handlers::get_duplicate_credentials,
+    handlers::get_credential_proxies,
+    handlers::update_credential,
+    handlers::delete_credential,
+    handlers::set_credential_disabled,
+    handlers::set_credential_priority,
+    handlers::reset_failure_count,
+    handlers::get_credential_balance,
+    handlers::get_credential_forecast,
+    handlers::refresh_account,
+    handlers::get_load_balancing_mode,
+    handlers::set_load_balancing_mode,
+    handlers::get_model_routes,
+    handlers::set_model_route,
+    handlers::delete_model_route,
+    handlers::test_proxy,
+    handlers::reload_config,
+    handlers::get_tenants,
+    handlers::set_tenant,
+    handlers::delete_tenant,
+    handlers::add_tenant_key,
+    handlers::remove_tenant_key,
+    handlers::get_logs,
+    handlers::stream_logs,
+    handlers::stream_events,
+    handlers::get_stream_stats,
+    handlers::get_recovery_report,
+    handlers::get_cluster_status,
+    handlers::get_unknown_events,
+    handlers::create_support_bundle,
+    handlers::get_request_records,
+    handlers::get_signing_key,
+    handlers::get_costs,
+    handlers::get_me,
+    handlers::login,
+    handlers::refresh_session,
+    handlers::logout,
+))]
+pub struct AdminApiDoc;
@@ -2,16 +2,27 @@
 
 use axum::{
     Router, middleware,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
+};
+use tower_http::compression::{
+    CompressionLayer,
+    predicate::{DefaultPredicate, NotForContentType, Predicate},
 };
 
 use super::{
     handlers::{
-        add_credential, delete_credential, get_all_credentials, get_credential_balance,
-        get_load_balancing_mode, reset_failure_count, set_credential_disabled,
-        set_credential_priority, set_load_balancing_mode,
+        add_credential, add_tenant_key, create_support_bundle, delete_credential,
+        delete_model_route, delete_tenant, export_credentials, get_all_credentials,
+        get_cluster_status, get_costs, get_credential_balance, get_credential_endpoints,
+        get_credential_forecast, get_credential_proxies, get_duplicate_credentials,
+        get_load_balancing_mode, get_logs, get_me, get_model_routes, get_recovery_report,
+        get_request_records, get_signing_key, get_stream_stats, get_tenants, get_unknown_events,
+        import_credentials, login, logout, refresh_account, refresh_session, reload_config,
+        remove_tenant_key, reset_failure_count, set_credential_disabled, set_credential_priority,
+        set_load_balancing_mode, set_model_route, set_tenant, stream_events, stream_logs,
+        test_proxy, update_credential,
     },
-    middleware::{AdminState, admin_auth_middleware},
+    middleware::{AdminState, admin_auth_middleware, ip_access_middleware, locale_middleware},
 };
 
 /// 创建 Admin API 路由
@@ -20,35 +31,131 @@ use super::{
 /// - `GET /credentials` - 获取所有凭据状态
 /// - `POST /credentials` - 添加新凭据
 /// - `DELETE /credentials/:id` - 删除凭据
+/// - `PATCH /credentials/:id` - 更新凭据备注、负责人联系方式与来源
 /// - `POST /credentials/:id/disabled` - 设置凭据禁用状态
 /// - `POST /credentials/:id/priority` - 设置凭据优先级
 /// - `POST /credentials/:id/reset` - 重置失败计数
 /// - `GET /credentials/:id/balance` - 获取凭据余额
+/// - `GET /credentials/:id/forecast` - 预测额度耗尽时间
+/// - `POST /credentials/:id/refresh-account` - 刷新账号信息（邮箱、暂停状态）
+/// - `GET /credentials/export` - 批量导出凭据
+/// - `POST /credentials/import` - 批量导入凭据
+/// - `GET /credentials/endpoints` - 列出每个凭据的生效端点
+/// - `GET /credentials/duplicates` - 列出疑似重复的凭据
+/// - `GET /credentials/proxies` - 获取凭据代理的健康状态
 /// - `GET /config/load-balancing` - 获取负载均衡模式
 /// - `PUT /config/load-balancing` - 设置负载均衡模式
+/// - `GET /config/model-routes` - 获取模型路由表
+/// - `PUT /config/model-routes/:alias` - 新增或替换模型路由
+/// - `DELETE /config/model-routes/:alias` - 删除模型路由
+/// - `POST /config/proxy/test` - 经候选代理测试出站连接（出口 IP 与延迟）
+/// - `POST /config/reload` - 重新加载配置文件
+/// - `GET /tenants` - 获取租户列表（含本月用量）
+/// - `PUT /tenants/:name` - 新增或修改租户
+/// - `DELETE /tenants/:name` - 删除租户
+/// - `POST /tenants/:name/keys` - 为租户添加 API Key
+/// - `DELETE /tenants/:name/keys/:key_id` - 移除租户的 API Key
+/// - `GET /logs` - 获取最近日志
+/// - `GET /logs/stream` - WebSocket 实时推送日志
+/// - `GET /events/stream` - SSE 推送进程内事件
+/// - `GET /stats/streams` - 流式响应结束统计
+/// - `GET /recovery` - 启动恢复报告
+/// - `GET /cluster` - 集群状态（实例、leader 与集群合计用量）
+/// - `GET /diagnostics/unknown-events` - 上游事件流中未识别的事件类型与字段
+/// - `POST /support-bundle` - 生成脱敏的诊断包（zip）
+/// - `GET /token-usage/requests` - 分页查询最近的请求记录
+/// - `GET /token-usage/signing-key` - 请求记录签名的公钥与签名格式
+/// - `GET /costs` - 按 API Key / 凭据 / 日期汇总估算费用
+/// - `GET /me` - 当前 Admin 账号的名称和角色
+/// - `POST /login` - 用 Admin API Key 换取短期会话 Token（无需认证）
+/// - `POST /session/refresh` - 换发会话 Token 并注销当前 Token
+/// - `POST /logout` - 注销当前会话 Token
 ///
 /// # 认证
-/// 需要 Admin API Key 认证，支持：
+/// 需要 Admin API Key 或会话 Token 认证，支持：
 /// - `x-api-key` header
 /// - `Authorization: Bearer <token>` header
+///
+/// Key 对应 `adminAccounts`（及 `adminApiKey` / `adminReadonlyApiKey`）中的账号，
+/// 只读账号仅允许 GET 请求（导出凭据除外），其余返回 403
+///
+/// 配置了 `ipAccess.admin` 时，所有端点（含登录）先按客户端 IP 过滤
+///
+/// 修改配置的 `PUT /config/*` 与 `POST /config/reload` 带 `?dry_run=true` 时只返回变更预览
 pub fn create_admin_router(state: AdminState) -> Router {
-    Router::new()
+    let router = Router::new()
         .route(
             "/credentials",
             get(get_all_credentials).post(add_credential),
         )
-        .route("/credentials/{id}", delete(delete_credential))
+        .route("/credentials/export", get(export_credentials))
+        .route("/credentials/import", post(import_credentials))
+        .route("/credentials/endpoints", get(get_credential_endpoints))
+        .route("/credentials/duplicates", get(get_duplicate_credentials))
+        .route("/credentials/proxies", get(get_credential_proxies))
+        .route(
+            "/credentials/{id}",
+            delete(delete_credential).patch(update_credential),
+        )
         .route("/credentials/{id}/disabled", post(set_credential_disabled))
         .route("/credentials/{id}/priority", post(set_credential_priority))
         .route("/credentials/{id}/reset", post(reset_failure_count))
         .route("/credentials/{id}/balance", get(get_credential_balance))
+        .route("/credentials/{id}/forecast", get(get_credential_forecast))
+        .route("/credentials/{id}/refresh-account", post(refresh_account))
         .route(
             "/config/load-balancing",
             get(get_load_balancing_mode).put(set_load_balancing_mode),
         )
+        .route("/config/model-routes", get(get_model_routes))
+        .route(
+            "/config/model-routes/{alias}",
+            put(set_model_route).delete(delete_model_route),
+        )
+        .route("/config/proxy/test", post(test_proxy))
+        .route("/config/reload", post(reload_config))
+        .route("/tenants", get(get_tenants))
+        .route("/tenants/{name}", put(set_tenant).delete(delete_tenant))
+        .route("/tenants/{name}/keys", post(add_tenant_key))
+        .route("/tenants/{name}/keys/{key_id}", delete(remove_tenant_key))
+        .route("/logs", get(get_logs))
+        .route("/logs/stream", get(stream_logs))
+        .route("/events/stream", get(stream_events))
+        .route("/stats/streams", get(get_stream_stats))
+        .route("/recovery", get(get_recovery_report))
+        .route("/cluster", get(get_cluster_status))
+        .route("/diagnostics/unknown-events", get(get_unknown_events))
+        .route("/support-bundle", post(create_support_bundle))
+        .route("/token-usage/requests", get(get_request_records))
+        .route("/token-usage/signing-key", get(get_signing_key))
+        .route("/costs", get(get_costs))
+        .route("/me", get(get_me))
+        .route("/session/refresh", post(refresh_session))
+        .route("/logout", post(logout))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             admin_auth_middleware,
         ))
-        .with_state(state)
+        // 登录接口自行校验 Key，不经过认证中间件
+        .route("/login", post(login))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            ip_access_middleware,
+        ))
+        .layer(middleware::from_fn(locale_middleware))
+        .layer(compression_layer());
+    // 预检请求不携带认证信息，CORS 放在最外层
+    match state.service.cors() {
+        Some(cors) => router.layer(cors.layer()),
+        None => router,
+    }
+    .with_state(state)
+}
+
+/// 按 `Accept-Encoding` 以 gzip / br 压缩响应
+///
+/// 不压缩 SSE 流（日志、事件推送）、过小的响应，以及本身已压缩的诊断包
+fn compression_layer() -> CompressionLayer<impl Predicate> {
+    let predicate = DefaultPredicate::new().and(NotForContentType::const_new("application/zip"));
+    CompressionLayer::new().compress_when(predicate)
 }
@@ -1,21 +1,55 @@
 //! Admin API 业务逻辑服务
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use chrono::Utc;
+use chrono::{DateTime, NaiveDate, Utc};
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
 
+use crate::common::cors::CorsPolicy;
+use crate::common::{auth, crypto, usage_signing};
+use crate::events::{self, AppEvent, EventEnvelope, TokenUsage};
+use crate::http_client::ProxyConfig;
 use crate::kiro::model::credentials::KiroCredentials;
-use crate::kiro::token_manager::MultiTokenManager;
+use crate::kiro::regions::{ApiEndpoints, AuthEndpoints};
+use crate::kiro::token_manager::{DisabledReason, MultiTokenManager, uses_idc_refresh};
+use crate::proxy_health;
 
 use super::error::AdminServiceError;
+use super::support_bundle::{self, SupportBundle};
 use super::types::{
-    AddCredentialRequest, AddCredentialResponse, BalanceResponse, CredentialStatusItem,
-    CredentialsStatusResponse, LoadBalancingModeResponse, SetLoadBalancingModeRequest,
+    AddCredentialRequest, AddCredentialResponse, AddTenantKeyRequest, AddTenantKeyResponse,
+    BalanceResponse, ConfigChange, ConfigDiffResponse, CostSummary, CostsQuery, CostsResponse,
+    CredentialEndpoints, CredentialEndpointsResponse, CredentialStatusItem, CredentialsBundle,
+    CredentialsStatusResponse, DuplicateCredentialsResponse, ForecastResponse,
+    ImportCredentialResult, ImportCredentialsResponse, LoadBalancingModeResponse, ModelRouteItem,
+    ModelRoutesResponse, ProxiesResponse, ProxyStatusItem, RefreshAccountResponse,
+    ReloadConfigResponse, RequestRecord, RequestRecordsQuery, RequestRecordsResponse,
+    SIGNED_PAYLOAD_VERSION, SIGNED_RECORD_FIELDS, SetLoadBalancingModeRequest, SetTenantRequest,
+    SigningKeyResponse, StreamOutcomeCounts, StreamStatsResponse, TenantItem, TenantsResponse,
+    TestProxyRequest, TestProxyResponse, UpdateCredentialRequest,
 };
+use crate::model::config::{AdminAccount, Config, IpAccessConfig, TenantConfig};
+
+/// 配置变更预览中不显示取值的配置项
+const SECRET_CONFIG_FIELDS: &[&str] = &[
+    "apiKey",
+    "countTokensApiKey",
+    "proxyPassword",
+    "adminApiKey",
+    "adminReadonlyApiKey",
+    "adminAccounts",
+    "moderationApiKey",
+    "embeddingsApiKey",
+    "upstreamExtraHeaders",
+    "usageSigningKey",
+    "alerts",
+    "cluster",
+    "tenants",
+];
 
 /// 余额缓存过期时间（秒），5 分钟
 const BALANCE_CACHE_TTL_SECS: i64 = 300;
@@ -29,6 +63,45 @@ struct CachedBalance {
     data: BalanceResponse,
 }
 
+/// 每个凭据保留的余额采样数
+const MAX_USAGE_SAMPLES: usize = 288;
+
+/// 使用最近采样估算速度所需的最短时间跨度（秒）
+const MIN_SAMPLE_SPAN_SECS: f64 = 600.0;
+
+/// 额度重置周期（秒），按月重置，用于推算本周期开始时间
+const RESET_PERIOD_SECS: f64 = 30.0 * 24.0 * 3600.0;
+
+/// 保留的最近请求记录数
+const MAX_REQUEST_RECORDS: usize = 10_000;
+
+/// 请求记录默认 / 最大分页大小
+const DEFAULT_REQUEST_RECORDS_LIMIT: usize = 50;
+const MAX_REQUEST_RECORDS_LIMIT: usize = 1000;
+
+/// 费用统计保留的天数
+const COST_RETENTION_DAYS: i64 = 400;
+
+/// 费用统计的最小汇总单元
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct CostKey {
+    /// UTC 日期（排在首位，便于按日期清理）
+    day: NaiveDate,
+    api_key_id: Option<String>,
+    credential_id: Option<u64>,
+    model: String,
+}
+
+/// 余额采样（用于估算消耗速度）
+#[derive(Debug, Clone, Copy)]
+struct UsageSample {
+    /// 采样时间（Unix 秒）
+    at: f64,
+    usage: f64,
+    /// 采样时的下次重置时间，用于区分重置周期
+    next_reset_at: Option<f64>,
+}
+
 /// Admin 服务
 ///
 /// 封装所有 Admin API 的业务逻辑
@@ -36,6 +109,16 @@ pub struct AdminService {
     token_manager: Arc<MultiTokenManager>,
     balance_cache: Mutex<HashMap<u64, CachedBalance>>,
     cache_path: Option<PathBuf>,
+    /// 余额采样历史（仅内存）
+    usage_samples: Mutex<HashMap<u64, VecDeque<UsageSample>>>,
+    /// 流式响应结束统计（仅内存）
+    stream_stats: Mutex<StreamStatsResponse>,
+    /// 最近的请求记录（仅内存，最新的在末尾）
+    request_records: Mutex<VecDeque<RequestRecord>>,
+    /// 按日期 / API Key / 凭据 / 模型汇总的用量与估算费用（仅内存）
+    costs: Mutex<BTreeMap<CostKey, CostSummary>>,
+    /// 费用统计开始时间（RFC3339 格式）
+    costs_since: String,
 }
 
 impl AdminService {
@@ -50,9 +133,51 @@ impl AdminService {
             token_manager,
             balance_cache: Mutex::new(balance_cache),
             cache_path,
+            usage_samples: Mutex::new(HashMap::new()),
+            stream_stats: Mutex::new(StreamStatsResponse {
+                since: Utc::now().to_rfc3339(),
+                total: StreamOutcomeCounts::default(),
+                by_api_key: BTreeMap::new(),
+            }),
+            request_records: Mutex::new(VecDeque::new()),
+            costs: Mutex::new(BTreeMap::new()),
+            costs_since: Utc::now().to_rfc3339(),
         }
     }
 
+    /// 按 Admin API Key 查找账号（每次读取最新配置，账号变更热重载后立即生效）
+    pub fn find_admin_account(&self, key: &str) -> Option<AdminAccount> {
+        self.token_manager.config().find_admin_account(key)
+    }
+
+    /// 按账号名查找 Admin 账号
+    pub fn find_admin_account_by_name(&self, name: &str) -> Option<AdminAccount> {
+        self.token_manager
+            .config()
+            .all_admin_accounts()
+            .into_iter()
+            .find(|account| account.name == name)
+    }
+
+    /// IP 访问控制配置
+    pub fn ip_access(&self) -> Option<IpAccessConfig> {
+        self.token_manager.config().ip_access.clone()
+    }
+
+    /// Admin API 的 CORS 规则（`cors.admin`）
+    pub fn cors(&self) -> Option<CorsPolicy> {
+        self.token_manager
+            .config()
+            .cors
+            .as_ref()
+            .and_then(|cors| cors.admin.clone())
+    }
+
+    /// 会话 Token 有效期（分钟）
+    pub fn admin_session_ttl_minutes(&self) -> u64 {
+        self.token_manager.config().admin_session_ttl_minutes
+    }
+
     /// 获取所有凭据状态
     pub fn get_all_credentials(&self) -> CredentialsStatusResponse {
         let snapshot = self.token_manager.snapshot();
@@ -75,6 +200,12 @@ impl AdminService {
                 last_used_at: entry.last_used_at.clone(),
                 has_proxy: entry.has_proxy,
                 proxy_url: entry.proxy_url,
+                proxy_fallback_urls: entry.proxy_fallback_urls,
+                exhausted_until: entry.exhausted_until,
+                stats: entry.stats,
+                notes: entry.notes,
+                owner_contact: entry.owner_contact,
+                source: entry.source,
             })
             .collect();
 
@@ -113,6 +244,17 @@ impl AdminService {
             .map_err(|e| self.classify_error(e, id))
     }
 
+    /// 更新凭据备注、负责人联系方式与来源
+    pub fn update_credential(
+        &self,
+        id: u64,
+        req: UpdateCredentialRequest,
+    ) -> Result<(), AdminServiceError> {
+        self.token_manager
+            .update_notes(id, req.notes, req.owner_contact, req.source)
+            .map_err(|e| self.classify_error(e, id))
+    }
+
     /// 重置失败计数并重新启用
     pub fn reset_and_enable(&self, id: u64) -> Result<(), AdminServiceError> {
         self.token_manager
@@ -136,6 +278,7 @@ impl AdminService {
 
         // 缓存未命中或已过期，从上游获取
         let balance = self.fetch_balance(id).await?;
+        self.record_usage_sample(&balance);
 
         // 更新缓存
         {
@@ -181,6 +324,75 @@ impl AdminService {
         })
     }
 
+    /// 记录余额采样
+    fn record_usage_sample(&self, balance: &BalanceResponse) {
+        let mut samples = self.usage_samples.lock();
+        let samples = samples.entry(balance.id).or_default();
+        if samples.len() >= MAX_USAGE_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(UsageSample {
+            at: Utc::now().timestamp() as f64,
+            usage: balance.current_usage,
+            next_reset_at: balance.next_reset_at,
+        });
+    }
+
+    /// 预测凭据额度耗尽时间
+    ///
+    /// 优先使用本周期内最早的余额采样计算近期消耗速度；采样不足时按本周期平均速度估算
+    pub async fn get_forecast(&self, id: u64) -> Result<ForecastResponse, AdminServiceError> {
+        let balance = self.get_balance(id).await?;
+        let now = Utc::now().timestamp() as f64;
+
+        let recent_rate = {
+            let samples = self.usage_samples.lock();
+            samples.get(&id).and_then(|samples| {
+                samples
+                    .iter()
+                    .find(|s| {
+                        s.next_reset_at == balance.next_reset_at
+                            && now - s.at >= MIN_SAMPLE_SPAN_SECS
+                            && s.usage <= balance.current_usage
+                    })
+                    .map(|s| (balance.current_usage - s.usage) / (now - s.at) * 3600.0)
+            })
+        };
+        let period_rate = balance.next_reset_at.and_then(|reset_at| {
+            let elapsed = now - (reset_at - RESET_PERIOD_SECS);
+            (elapsed > 0.0).then(|| balance.current_usage / elapsed * 3600.0)
+        });
+
+        let (usage_per_hour, basis) = match (recent_rate, period_rate) {
+            (Some(rate), _) => (Some(rate), "recent"),
+            (None, Some(rate)) => (Some(rate), "periodAverage"),
+            (None, None) => (None, "none"),
+        };
+
+        let projected_exhaustion_at = match usage_per_hour {
+            _ if balance.remaining <= 0.0 => Some(now),
+            Some(rate) if rate > 0.0 => Some(now + balance.remaining / rate * 3600.0),
+            _ => None,
+        };
+        let exhausts_before_reset = match (projected_exhaustion_at, balance.next_reset_at) {
+            (Some(at), Some(reset_at)) => at < reset_at,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+
+        Ok(ForecastResponse {
+            id,
+            current_usage: balance.current_usage,
+            usage_limit: balance.usage_limit,
+            remaining: balance.remaining,
+            next_reset_at: balance.next_reset_at,
+            usage_per_hour,
+            basis,
+            projected_exhaustion_at,
+            exhausts_before_reset,
+        })
+    }
+
     /// 添加新凭据
     pub async fn add_credential(
         &self,
@@ -204,12 +416,41 @@ impl AdminService {
             machine_id: req.machine_id,
             email: req.email,
             subscription_title: None, // 将在首次获取使用额度时自动更新
+            weight: None,
+            account_id: None,
             proxy_url: req.proxy_url,
             proxy_username: req.proxy_username,
             proxy_password: req.proxy_password,
+            proxy_fallback_urls: req.proxy_fallback_urls,
             disabled: false, // 新添加的凭据默认启用
+            notes: None,
+            owner_contact: None,
+            source: None,
         };
 
+        let credential_id = self.add_and_probe(new_cred).await?;
+
+        // 账号 ID / 邮箱在探测额度时获取，refreshToken 不同的同一账号只能在此时发现
+        let duplicate_of = self.token_manager.find_duplicates_of(credential_id);
+        let warning = (!duplicate_of.is_empty()).then(|| {
+            let ids: Vec<String> = duplicate_of.iter().map(|id| format!("#{}", id)).collect();
+            let warning = format!("该凭据疑似与已有凭据 {} 属于同一账号", ids.join(", "));
+            tracing::warn!("凭据 #{} {}", credential_id, warning);
+            warning
+        });
+
+        Ok(AddCredentialResponse {
+            success: true,
+            message: format!("凭据添加成功，ID: {}", credential_id),
+            credential_id,
+            email,
+            duplicate_of,
+            warning,
+        })
+    }
+
+    /// 添加凭据并获取订阅等级
+    async fn add_and_probe(&self, new_cred: KiroCredentials) -> Result<u64, AdminServiceError> {
         // 调用 token_manager 添加凭据
         let credential_id = self
             .token_manager
@@ -222,28 +463,252 @@ impl AdminService {
             tracing::warn!("添加凭据后获取订阅等级失败（不影响凭据添加）: {}", e);
         }
 
-        Ok(AddCredentialResponse {
-            success: true,
-            message: format!("凭据添加成功，ID: {}", credential_id),
-            credential_id,
-            email,
+        Ok(credential_id)
+    }
+
+    /// 导出所有凭据，提供口令时加密
+    pub fn export_credentials(
+        &self,
+        passphrase: Option<&str>,
+    ) -> Result<CredentialsBundle, AdminServiceError> {
+        let credentials = self.token_manager.export_credentials();
+        let Some(passphrase) = passphrase else {
+            return Ok(CredentialsBundle::Plain(credentials));
+        };
+
+        let plaintext = serde_json::to_vec(&credentials)
+            .map_err(|e| AdminServiceError::InternalError(format!("序列化凭据失败: {}", e)))?;
+        let envelope = crypto::encrypt(&plaintext, passphrase)
+            .map_err(|e| AdminServiceError::InternalError(e.to_string()))?;
+        Ok(CredentialsBundle::Encrypted(envelope))
+    }
+
+    /// 批量导入凭据
+    ///
+    /// 逐条验证并添加（与 `add_credential` 相同），单条失败不影响其余凭据
+    pub async fn import_credentials(
+        &self,
+        bundle: CredentialsBundle,
+        passphrase: Option<&str>,
+    ) -> Result<ImportCredentialsResponse, AdminServiceError> {
+        let credentials = match bundle {
+            CredentialsBundle::Plain(credentials) => credentials,
+            CredentialsBundle::Encrypted(envelope) => {
+                let passphrase = passphrase.ok_or_else(|| {
+                    AdminServiceError::InvalidCredential("加密数据需要提供口令".to_string())
+                })?;
+                let plaintext = crypto::decrypt(&envelope, passphrase)
+                    .map_err(|e| AdminServiceError::InvalidCredential(e.to_string()))?;
+                serde_json::from_slice(&plaintext).map_err(|e| {
+                    AdminServiceError::InvalidCredential(format!("解密后的数据格式错误: {}", e))
+                })?
+            }
+        };
+
+        let mut results = Vec::with_capacity(credentials.len());
+        for (index, mut cred) in credentials.into_iter().enumerate() {
+            // ID 与访问令牌由本实例重新分配和刷新
+            cred.id = None;
+            cred.access_token = None;
+            cred.expires_at = None;
+
+            let result = match self.add_and_probe(cred).await {
+                Ok(id) => ImportCredentialResult {
+                    index,
+                    credential_id: Some(id),
+                    error: None,
+                },
+                Err(e) => {
+                    tracing::warn!("导入第 {} 条凭据失败: {}", index, e);
+                    ImportCredentialResult {
+                        index,
+                        credential_id: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            };
+            results.push(result);
+        }
+
+        let imported = results.iter().filter(|r| r.credential_id.is_some()).count();
+        let failed = results.len() - imported;
+        Ok(ImportCredentialsResponse {
+            success: failed == 0,
+            imported,
+            failed,
+            results,
         })
     }
 
     /// 删除凭据
     pub fn delete_credential(&self, id: u64) -> Result<(), AdminServiceError> {
+        // 余额缓存由事件消费者在收到 CredentialDeleted 后清理
         self.token_manager
             .delete_credential(id)
-            .map_err(|e| self.classify_delete_error(e, id))?;
+            .map_err(|e| self.classify_delete_error(e, id))
+    }
 
-        // 清理已删除凭据的余额缓存
-        {
-            let mut cache = self.balance_cache.lock();
-            cache.remove(&id);
+    /// 订阅进程内事件，维护余额缓存与采样
+    ///
+    /// - 凭据删除：清理其余额缓存与采样
+    /// - 因额度用尽被禁用、或重新启用：额度状态已变化，使缓存的余额失效
+    pub fn spawn_event_consumer(self: &Arc<Self>) {
+        let service = self.clone();
+        let mut receiver = events::subscribe();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(envelope) => service.handle_event(&envelope),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("余额缓存事件处理过慢，跳过了 {} 个事件", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    fn handle_event(&self, envelope: &EventEnvelope) {
+        let timestamp = DateTime::parse_from_rfc3339(&envelope.timestamp)
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+        let id = match &envelope.event {
+            AppEvent::RequestCompleted {
+                api_key_id,
+                input_tokens,
+                credential_id,
+                model,
+                stream,
+                status,
+                attempts,
+                duration_ms,
+            } => {
+                let mut record = RequestRecord {
+                    timestamp,
+                    api_key_id: api_key_id.clone(),
+                    credential_id: *credential_id,
+                    model: model.clone(),
+                    stream: *stream,
+                    status: *status,
+                    attempts: *attempts,
+                    duration_ms: *duration_ms,
+                    input_tokens: *input_tokens,
+                    output_tokens: None,
+                    cache_read_input_tokens: None,
+                    cache_creation_input_tokens: None,
+                    key_id: None,
+                    signature_version: None,
+                    signature: None,
+                };
+                self.sign_record(&mut record);
+
+                let mut records = self.request_records.lock();
+                if records.len() >= MAX_REQUEST_RECORDS {
+                    records.pop_front();
+                }
+                records.push_back(record);
+                return;
+            }
+            AppEvent::UsageRecorded {
+                api_key_id,
+                credential_id,
+                model,
+                usage,
+            } => {
+                self.settle_request_record(api_key_id, *credential_id, usage);
+                let cost_usd = self.token_manager.config().pricing_for(model).map(|p| {
+                    p.cost(
+                        usage.input_tokens,
+                        usage.output_tokens,
+                        usage.cache_creation_input_tokens,
+                        usage.cache_read_input_tokens,
+                    )
+                });
+                let day = timestamp.date_naive();
+                let mut costs = self.costs.lock();
+                costs
+                    .entry(CostKey {
+                        day,
+                        api_key_id: api_key_id.clone(),
+                        credential_id: *credential_id,
+                        model: model.clone(),
+                    })
+                    .or_default()
+                    .record(usage, cost_usd);
+                let cutoff = day - chrono::Duration::days(COST_RETENTION_DAYS);
+                while costs
+                    .first_key_value()
+                    .is_some_and(|(key, _)| key.day < cutoff)
+                {
+                    costs.pop_first();
+                }
+                return;
+            }
+            AppEvent::StreamEnded {
+                api_key_id,
+                outcome,
+                ..
+            } => {
+                let mut stats = self.stream_stats.lock();
+                stats.total.record(*outcome);
+                stats
+                    .by_api_key
+                    .entry(api_key_id.clone().unwrap_or_else(|| "unknown".to_string()))
+                    .or_default()
+                    .record(*outcome);
+                return;
+            }
+            AppEvent::CredentialDeleted { credential_id } => {
+                self.usage_samples.lock().remove(credential_id);
+                credential_id
+            }
+            AppEvent::CredentialDisabled {
+                credential_id,
+                reason: DisabledReason::QuotaExceeded,
+            }
+            | AppEvent::CredentialEnabled { credential_id } => credential_id,
+            _ => return,
+        };
+
+        let removed = self.balance_cache.lock().remove(id).is_some();
+        if removed {
+            self.save_balance_cache();
         }
-        self.save_balance_cache();
+    }
 
-        Ok(())
+    /// 配置了 `usageSigningKey` 时为请求记录签名
+    fn sign_record(&self, record: &mut RequestRecord) {
+        if let Some(key) = &self.token_manager.config().usage_signing_key {
+            record.signature = Some(key.sign(&record.signed_payload()));
+            record.key_id = Some(key.key_id());
+            record.signature_version = Some(SIGNED_PAYLOAD_VERSION);
+        }
+    }
+
+    /// 把响应的最终用量填入对应的请求记录并重新签名
+    ///
+    /// 事件不携带请求标识，按 API Key 与凭据匹配最近一条尚未填入用量的成功记录
+    fn settle_request_record(
+        &self,
+        api_key_id: &Option<String>,
+        credential_id: Option<u64>,
+        usage: &TokenUsage,
+    ) {
+        let mut records = self.request_records.lock();
+        let Some(record) = records.iter_mut().rev().find(|record| {
+            record.output_tokens.is_none()
+                && record
+                    .status
+                    .is_some_and(|status| (200..300).contains(&status))
+                && record.api_key_id == *api_key_id
+                && record.credential_id == credential_id
+        }) else {
+            return;
+        };
+        record.output_tokens = Some(usage.output_tokens);
+        record.cache_read_input_tokens = Some(usage.cache_read_input_tokens);
+        record.cache_creation_input_tokens = Some(usage.cache_creation_input_tokens);
+        self.sign_record(record);
     }
 
     /// 获取负载均衡模式
@@ -259,11 +724,9 @@ impl AdminService {
         req: SetLoadBalancingModeRequest,
     ) -> Result<LoadBalancingModeResponse, AdminServiceError> {
         // 验证模式值
-        if req.mode != "priority" && req.mode != "balanced" {
-            return Err(AdminServiceError::InvalidCredential(
-                "mode 必须是 'priority' 或 'balanced'".to_string(),
-            ));
-        }
+        self.token_manager
+            .validate_load_balancing_mode(&req.mode)
+            .map_err(|e| AdminServiceError::InvalidCredential(e.to_string()))?;
 
         self.token_manager
             .set_load_balancing_mode(req.mode.clone())
@@ -272,6 +735,336 @@ impl AdminService {
         Ok(LoadBalancingModeResponse { mode: req.mode })
     }
 
+    /// 获取流式响应结束统计
+    pub fn get_stream_stats(&self) -> StreamStatsResponse {
+        self.stream_stats.lock().clone()
+    }
+
+    /// 获取上游事件流中未识别的消息类型、事件类型与字段
+    pub fn get_unknown_events(&self) -> crate::kiro::schema_drift::UnknownEventsReport {
+        crate::kiro::schema_drift::report()
+    }
+
+    /// 获取启动恢复报告
+    pub fn get_recovery_report(&self) -> crate::recovery::RecoveryReport {
+        crate::recovery::report()
+    }
+
+    /// 获取集群状态（未启用集群模式时 `enabled` 为 false）
+    pub async fn get_cluster_status(
+        &self,
+    ) -> Result<crate::cluster::ClusterStatus, AdminServiceError> {
+        crate::cluster::status()
+            .await
+            .map_err(|e| AdminServiceError::InternalError(format!("读取集群状态失败: {}", e)))
+    }
+
+    /// 分页查询最近的请求记录（按时间倒序）
+    pub fn get_request_records(&self, query: &RequestRecordsQuery) -> RequestRecordsResponse {
+        let offset = query.offset.unwrap_or(0);
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_REQUEST_RECORDS_LIMIT)
+            .clamp(1, MAX_REQUEST_RECORDS_LIMIT);
+
+        let records = self.request_records.lock();
+        let mut total = 0;
+        let mut page = Vec::new();
+        for record in records.iter().rev().filter(|r| query.matches(r)) {
+            if total >= offset && page.len() < limit {
+                page.push(record.clone());
+            }
+            total += 1;
+        }
+        RequestRecordsResponse {
+            total,
+            offset,
+            limit,
+            records: page,
+        }
+    }
+
+    /// 请求记录签名的校验信息，未配置 `usageSigningKey` 时返回 None
+    pub fn get_signing_key(&self) -> Option<SigningKeyResponse> {
+        let config = self.token_manager.config();
+        let key = config.usage_signing_key.as_ref()?;
+        Some(SigningKeyResponse {
+            algorithm: usage_signing::ALGORITHM,
+            public_key: key.public_key(),
+            key_id: key.key_id(),
+            version: SIGNED_PAYLOAD_VERSION,
+            signed_fields: SIGNED_RECORD_FIELDS,
+        })
+    }
+
+    /// 按 API Key、凭据、日期与模型汇总估算费用
+    pub fn get_costs(&self, query: &CostsQuery) -> CostsResponse {
+        let mut response = CostsResponse {
+            since: self.costs_since.clone(),
+            currency: "USD",
+            total: CostSummary::default(),
+            by_api_key: BTreeMap::new(),
+            by_credential: BTreeMap::new(),
+            by_day: BTreeMap::new(),
+            by_model: BTreeMap::new(),
+        };
+        let unknown = || "unknown".to_string();
+
+        let costs = self.costs.lock();
+        let matching = costs.iter().filter(|(key, _)| {
+            query.since.is_none_or(|since| key.day >= since)
+                && query.until.is_none_or(|until| key.day <= until)
+                && query
+                    .api_key_id
+                    .as_ref()
+                    .is_none_or(|id| key.api_key_id.as_ref() == Some(id))
+                && query
+                    .credential_id
+                    .is_none_or(|id| key.credential_id == Some(id))
+        });
+        for (key, summary) in matching {
+            response.total.merge(summary);
+            response
+                .by_api_key
+                .entry(key.api_key_id.clone().unwrap_or_else(unknown))
+                .or_default()
+                .merge(summary);
+            response
+                .by_credential
+                .entry(key.credential_id.map_or_else(unknown, |id| id.to_string()))
+                .or_default()
+                .merge(summary);
+            response
+                .by_day
+                .entry(key.day.to_string())
+                .or_default()
+                .merge(summary);
+            response
+                .by_model
+                .entry(key.model.clone())
+                .or_default()
+                .merge(summary);
+        }
+        response
+    }
+
+    /// 获取凭据代理的健康状态（按代理地址汇总各凭据的候选代理）
+    pub fn get_proxies(&self) -> ProxiesResponse {
+        let health = proxy_health::snapshot();
+        let mut proxies: BTreeMap<String, ProxyStatusItem> = BTreeMap::new();
+        for (id, candidates) in self.token_manager.proxy_candidates() {
+            let active = proxy_health::select(&candidates);
+            for proxy in candidates.into_iter().flatten() {
+                let item = proxies.entry(proxy.url.clone()).or_insert_with(|| {
+                    let health = health.get(&proxy.url);
+                    ProxyStatusItem {
+                        url: proxy_health::display_url(&proxy.url),
+                        healthy: health.is_none_or(|h| h.healthy),
+                        checked_at: health.map(|h| h.checked_at.clone()),
+                        last_error: health.and_then(|h| h.last_error.clone()),
+                        credential_ids: Vec::new(),
+                        active_credential_ids: Vec::new(),
+                    }
+                });
+                item.credential_ids.push(id);
+                if active.as_ref() == Some(&proxy) {
+                    item.active_credential_ids.push(id);
+                }
+            }
+        }
+        ProxiesResponse {
+            proxies: proxies.into_values().collect(),
+        }
+    }
+
+    /// 经候选代理发起一次真实的出站请求，报告出口 IP 与耗时（不保存配置、不影响健康状态）
+    pub async fn test_proxy(
+        &self,
+        req: TestProxyRequest,
+    ) -> Result<TestProxyResponse, AdminServiceError> {
+        let mut proxy = ProxyConfig::new(req.url.trim());
+        if let (Some(username), Some(password)) = (req.username, req.password) {
+            proxy = proxy.with_auth(username, password);
+        }
+        proxy
+            .validate()
+            .map_err(|e| AdminServiceError::InvalidCredential(e.to_string()))?;
+
+        let target_url = req
+            .target_url
+            .unwrap_or_else(|| proxy_health::EGRESS_TEST_URL.to_string());
+        let valid_target = reqwest::Url::parse(&target_url)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+        if !valid_target {
+            return Err(AdminServiceError::InvalidCredential(format!(
+                "无效的测试目标: {}",
+                target_url
+            )));
+        }
+
+        let tls_backend = self.token_manager.config().tls_backend;
+        let mut response = TestProxyResponse {
+            success: false,
+            url: proxy_health::display_url(&proxy.url),
+            remote_dns: proxy.remote_dns(),
+            target_url,
+            status_code: None,
+            exit_ip: None,
+            latency_ms: None,
+            error: None,
+        };
+        match proxy_health::test_egress(&proxy, &response.target_url, tls_backend).await {
+            Ok(report) => {
+                response.success = (200..300).contains(&report.status);
+                if !response.success {
+                    response.error = Some(format!("目标返回 HTTP {}", report.status));
+                }
+                response.status_code = Some(report.status);
+                response.exit_ip = report.exit_ip;
+                response.latency_ms = Some(report.latency_ms);
+            }
+            Err(e) => response.error = Some(format!("{:#}", e)),
+        }
+        Ok(response)
+    }
+
+    /// 获取模型路由表
+    pub fn get_model_routes(&self) -> ModelRoutesResponse {
+        ModelRoutesResponse {
+            routes: self
+                .token_manager
+                .model_routes()
+                .into_iter()
+                .map(|(alias, route)| (alias, route.into()))
+                .collect(),
+        }
+    }
+
+    /// 新增或替换模型路由
+    pub fn set_model_route(
+        &self,
+        alias: String,
+        item: ModelRouteItem,
+    ) -> Result<ModelRoutesResponse, AdminServiceError> {
+        if let Some(id) = item.credential_id
+            && !self
+                .token_manager
+                .snapshot()
+                .entries
+                .iter()
+                .any(|e| e.id == id)
+        {
+            return Err(AdminServiceError::NotFound { id });
+        }
+
+        self.token_manager
+            .set_model_route(alias, item.into())
+            .map_err(|e| AdminServiceError::InvalidCredential(e.to_string()))?;
+        Ok(self.get_model_routes())
+    }
+
+    /// 删除模型路由，返回路由是否存在
+    pub fn delete_model_route(&self, alias: &str) -> Result<bool, AdminServiceError> {
+        self.token_manager
+            .remove_model_route(alias)
+            .map_err(|e| AdminServiceError::InternalError(e.to_string()))
+    }
+
+    /// 获取租户列表（含本月用量）
+    pub async fn get_tenants(&self) -> TenantsResponse {
+        let mut tenants = BTreeMap::new();
+        for (name, tenant) in self.token_manager.tenants() {
+            let item = TenantItem {
+                api_key_ids: tenant
+                    .api_keys
+                    .iter()
+                    .map(|key| auth::configured_api_key_id(key))
+                    .collect(),
+                usage: crate::tenants::usage(&name).await,
+                allowed_models: tenant.allowed_models,
+                credential_ids: tenant.credential_ids,
+                credential_fallback: tenant.credential_fallback,
+                monthly_token_quota: tenant.monthly_token_quota,
+                monthly_request_quota: tenant.monthly_request_quota,
+            };
+            tenants.insert(name, item);
+        }
+        TenantsResponse { tenants }
+    }
+
+    /// 新增或修改租户（保留已有的 API Key）
+    pub async fn set_tenant(
+        &self,
+        name: String,
+        req: SetTenantRequest,
+    ) -> Result<TenantsResponse, AdminServiceError> {
+        let api_keys = self
+            .token_manager
+            .tenants()
+            .remove(&name)
+            .map(|tenant| tenant.api_keys)
+            .unwrap_or_default();
+        let tenant = TenantConfig {
+            api_keys,
+            allowed_models: req.allowed_models,
+            credential_ids: req.credential_ids,
+            credential_fallback: req.credential_fallback,
+            monthly_token_quota: req.monthly_token_quota,
+            monthly_request_quota: req.monthly_request_quota,
+        };
+        self.token_manager
+            .set_tenant(name, tenant)
+            .map_err(|e| AdminServiceError::InvalidCredential(e.to_string()))?;
+        Ok(self.get_tenants().await)
+    }
+
+    /// 删除租户，返回租户是否存在
+    pub fn delete_tenant(&self, name: &str) -> Result<bool, AdminServiceError> {
+        self.token_manager
+            .remove_tenant(name)
+            .map_err(|e| AdminServiceError::InternalError(e.to_string()))
+    }
+
+    /// 为租户添加 API Key（配置中保存哈希），租户不存在时返回 None
+    pub fn add_tenant_key(
+        &self,
+        name: &str,
+        req: AddTenantKeyRequest,
+    ) -> Result<Option<AddTenantKeyResponse>, AdminServiceError> {
+        let Some(mut tenant) = self.token_manager.tenants().remove(name) else {
+            return Ok(None);
+        };
+        let key = req
+            .key
+            .unwrap_or_else(|| format!("sk-kiro-{}", uuid::Uuid::new_v4().simple()));
+        tenant.api_keys.push(auth::hash_api_key(&key));
+        self.token_manager
+            .set_tenant(name.to_string(), tenant)
+            .map_err(|e| AdminServiceError::InvalidCredential(e.to_string()))?;
+        Ok(Some(AddTenantKeyResponse {
+            key_id: auth::api_key_id(&key),
+            key,
+        }))
+    }
+
+    /// 按 API Key 标识移除租户的 Key，返回 Key 是否存在（租户不存在时也返回 false）
+    pub fn remove_tenant_key(&self, name: &str, key_id: &str) -> Result<bool, AdminServiceError> {
+        let Some(mut tenant) = self.token_manager.tenants().remove(name) else {
+            return Ok(false);
+        };
+        let before = tenant.api_keys.len();
+        tenant
+            .api_keys
+            .retain(|key| auth::configured_api_key_id(key) != key_id);
+        if tenant.api_keys.len() == before {
+            return Ok(false);
+        }
+        self.token_manager
+            .set_tenant(name.to_string(), tenant)
+            .map_err(|e| AdminServiceError::InternalError(e.to_string()))?;
+        Ok(true)
+    }
+
     // ============ 余额缓存持久化 ============
 
     fn load_balance_cache_from(cache_path: &Option<PathBuf>) -> HashMap<u64, CachedBalance> {
@@ -290,6 +1083,7 @@ impl AdminService {
             Ok(m) => m,
             Err(e) => {
                 tracing::warn!("解析余额缓存失败，将忽略: {}", e);
+                crate::recovery::quarantine(path, e);
                 return HashMap::new();
             }
         };
@@ -313,6 +1107,10 @@ impl AdminService {
             Some(p) => p,
             None => return,
         };
+        if !crate::disk_monitor::optional_writes_allowed() {
+            tracing::debug!("磁盘空间不足，跳过保存余额缓存");
+            return;
+        }
 
         // 持有锁期间完成序列化和写入，防止并发损坏
         let cache = self.balance_cache.lock();
@@ -355,6 +1153,7 @@ impl AdminService {
             // HTTP 响应错误（来自 refresh_*_token 的错误消息）
             msg.contains("凭证已过期或无效") ||
             msg.contains("权限不足") ||
+            msg.contains("账号已被暂停") ||
             msg.contains("已被限流") ||
             msg.contains("服务器错误") ||
             msg.contains("Token 刷新失败") ||
@@ -374,6 +1173,338 @@ impl AdminService {
         }
     }
 
+    /// 重新读取配置文件并热更新
+    pub fn reload_config(&self) -> Result<ReloadConfigResponse, AdminServiceError> {
+        let config_path = self
+            .token_manager
+            .config()
+            .config_path()
+            .map(|p| p.to_path_buf())
+            .ok_or_else(|| AdminServiceError::InternalError("配置文件路径未知".to_string()))?;
+
+        let config = Config::load(&config_path).map_err(|e| {
+            AdminServiceError::InvalidCredential(format!(
+                "加载配置失败 {}: {}",
+                config_path.display(),
+                e
+            ))
+        })?;
+        let requires_restart = self
+            .token_manager
+            .reload_config(config)
+            .map_err(|e| AdminServiceError::InvalidCredential(e.to_string()))?;
+
+        let message = if requires_restart.is_empty() {
+            "配置已重新加载".to_string()
+        } else {
+            format!(
+                "配置已重新加载，以下配置项需重启后生效: {}",
+                requires_restart.join(", ")
+            )
+        };
+        Ok(ReloadConfigResponse {
+            success: true,
+            message,
+            requires_restart: requires_restart.into_iter().map(String::from).collect(),
+        })
+    }
+
+    /// 预览重新加载配置：校验 `config.json` 并返回与运行中配置的差异，不应用
+    pub fn preview_reload_config(&self) -> Result<ConfigDiffResponse, AdminServiceError> {
+        let current = self.token_manager.config();
+        let config_path = current
+            .config_path()
+            .ok_or_else(|| AdminServiceError::InternalError("配置文件路径未知".to_string()))?;
+
+        let mut config = Config::load(config_path).map_err(|e| {
+            AdminServiceError::InvalidCredential(format!(
+                "加载配置失败 {}: {}",
+                config_path.display(),
+                e
+            ))
+        })?;
+        self.token_manager
+            .validate_load_balancing_mode(&config.load_balancing_mode)
+            .map_err(|e| AdminServiceError::InvalidCredential(e.to_string()))?;
+        config.data_dir_override = current.data_dir_override.clone();
+        Ok(self.config_diff(config, false))
+    }
+
+    /// 预览设置负载均衡模式
+    pub fn preview_load_balancing_mode(
+        &self,
+        req: SetLoadBalancingModeRequest,
+    ) -> Result<ConfigDiffResponse, AdminServiceError> {
+        self.token_manager
+            .validate_load_balancing_mode(&req.mode)
+            .map_err(|e| AdminServiceError::InvalidCredential(e.to_string()))?;
+
+        let mut config = (*self.token_manager.config()).clone();
+        config.load_balancing_mode = req.mode;
+        Ok(self.config_diff(config, true))
+    }
+
+    /// 预览新增或替换模型路由
+    pub fn preview_model_route(
+        &self,
+        alias: String,
+        item: ModelRouteItem,
+    ) -> Result<ConfigDiffResponse, AdminServiceError> {
+        let route = item.into();
+        self.token_manager
+            .validate_model_route(&alias, &route)
+            .map_err(|e| AdminServiceError::InvalidCredential(e.to_string()))?;
+
+        let mut config = (*self.token_manager.config()).clone();
+        config.model_aliases.insert(alias, route);
+        Ok(self.config_diff(config, true))
+    }
+
+    /// 比较运行中的配置与 `next`，返回按字段列出的差异
+    fn config_diff(&self, next: Config, persists_to_file: bool) -> ConfigDiffResponse {
+        let mut current = (*self.token_manager.config()).clone();
+        // 负载均衡模式可通过 Admin API 单独修改，以运行中的值为准
+        current.load_balancing_mode = self.token_manager.get_load_balancing_mode();
+        let restart_required = current.restart_required_changes(&next);
+
+        let to_map = |config: &Config| match serde_json::to_value(config) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        let before = to_map(&current);
+        let after = to_map(&next);
+        let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+        fields.sort();
+        fields.dedup();
+
+        let changes = fields
+            .into_iter()
+            .filter_map(|field| {
+                let old = before.get(field).cloned().unwrap_or_default();
+                let new = after.get(field).cloned().unwrap_or_default();
+                if old == new {
+                    return None;
+                }
+                let redact = |value: serde_json::Value| {
+                    if SECRET_CONFIG_FIELDS.contains(&field.as_str()) && !value.is_null() {
+                        serde_json::Value::from("[REDACTED]")
+                    } else {
+                        value
+                    }
+                };
+                Some(ConfigChange {
+                    field: field.clone(),
+                    before: redact(old),
+                    after: redact(new),
+                    requires_restart: restart_required.contains(&field.as_str()),
+                })
+            })
+            .collect();
+
+        ConfigDiffResponse {
+            dry_run: true,
+            persists_to_file: persists_to_file && current.config_path().is_some(),
+            changes,
+        }
+    }
+
+    /// 列出疑似重复的凭据
+    pub fn get_duplicate_credentials(&self) -> DuplicateCredentialsResponse {
+        DuplicateCredentialsResponse {
+            groups: self.token_manager.duplicate_groups(),
+        }
+    }
+
+    /// 刷新指定凭据的账号信息（邮箱、订阅等级、暂停状态）
+    pub async fn refresh_account(
+        &self,
+        id: u64,
+    ) -> Result<RefreshAccountResponse, AdminServiceError> {
+        let status = self
+            .token_manager
+            .refresh_account(id)
+            .await
+            .map_err(|e| self.classify_balance_error(e, id))?;
+
+        let message = if status.suspended {
+            format!("凭据 #{} 账号已被暂停，已自动禁用", id)
+        } else {
+            format!("凭据 #{} 账号信息已刷新", id)
+        };
+        Ok(RefreshAccountResponse {
+            success: true,
+            message,
+            email: status.email,
+            user_id: status.user_id,
+            suspended: status.suspended,
+        })
+    }
+
+    /// 列出每个凭据实际使用的端点（已应用 Region 优先级与 `regionEndpoints` 覆盖）
+    pub fn get_credential_endpoints(&self) -> CredentialEndpointsResponse {
+        let config = self.token_manager.config();
+        let credentials = self
+            .token_manager
+            .export_credentials()
+            .into_iter()
+            .filter_map(|cred| {
+                let auth_region = cred.effective_auth_region(&config);
+                let api_region = cred.effective_api_region(&config);
+                let auth = AuthEndpoints::for_region(auth_region, &config);
+                let api = ApiEndpoints::for_region(api_region, &config);
+                let is_idc = uses_idc_refresh(&cred);
+                Some(CredentialEndpoints {
+                    id: cred.id?,
+                    auth_method: if is_idc { "idc" } else { "social" }.to_string(),
+                    auth_region: auth_region.to_string(),
+                    api_region: api_region.to_string(),
+                    refresh_url: if is_idc {
+                        auth.idc_token_url()
+                    } else {
+                        auth.social_refresh_url()
+                    },
+                    api_url: api.generate_url(),
+                    mcp_url: api.mcp_url(),
+                })
+            })
+            .collect();
+        CredentialEndpointsResponse { credentials }
+    }
+
+    /// 生成脱敏的诊断包（zip）：版本信息、配置、最近日志、凭据健康概况与最近错误
+    ///
+    /// 配置中的密钥类配置项只保留是否设置；凭据 Token、密钥类配置项与代理密码的原文
+    /// 出现在任何内容（包括日志）中都会被替换为 `[REDACTED]`。凭据的邮箱、备注等个人信息不导出。
+    pub fn support_bundle(&self) -> Result<Vec<u8>, AdminServiceError> {
+        let config = self.token_manager.config();
+        let credentials = self.token_manager.export_credentials();
+
+        let mut config_value = match serde_json::to_value(&*config) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        let mut secrets = Vec::new();
+        for field in SECRET_CONFIG_FIELDS {
+            if let Some(value) = config_value.get_mut(*field)
+                && !value.is_null()
+            {
+                support_bundle::collect_secrets(value, &mut secrets);
+                *value = serde_json::Value::from(support_bundle::REDACTED);
+            }
+        }
+        let proxy_urls = credentials
+            .iter()
+            .flat_map(|c| c.proxy_url.iter().chain(&c.proxy_fallback_urls))
+            .map(String::as_str)
+            .chain(config.proxy_url.as_deref())
+            .chain(config.cluster.as_ref().map(|c| c.redis_url.as_str()));
+        for url in proxy_urls {
+            if let Ok(parsed) = reqwest::Url::parse(url)
+                && let Some(password) = parsed.password()
+            {
+                // URL 中的密码是百分号编码的，日志里可能出现任一形式
+                secrets.push(password.to_string());
+                if let Ok(decoded) = urlencoding::decode(password) {
+                    secrets.push(decoded.into_owned());
+                }
+            }
+        }
+        for cred in &credentials {
+            secrets.extend(
+                [
+                    &cred.access_token,
+                    &cred.refresh_token,
+                    &cred.client_secret,
+                    &cred.proxy_password,
+                ]
+                .into_iter()
+                .flatten()
+                .cloned(),
+            );
+        }
+        let mut bundle = SupportBundle::new(secrets);
+
+        bundle.add_json(
+            "version.json",
+            &serde_json::json!({
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+                "os": std::env::consts::OS,
+                "arch": std::env::consts::ARCH,
+                "generatedAt": Utc::now().to_rfc3339(),
+            }),
+        );
+        bundle.add_json("config.json", &config_value);
+
+        let status = self.get_all_credentials();
+        let health: Vec<serde_json::Value> = status
+            .credentials
+            .iter()
+            .map(|c| {
+                serde_json::json!({
+                    "id": c.id,
+                    "priority": c.priority,
+                    "disabled": c.disabled,
+                    "isCurrent": c.is_current,
+                    "failureCount": c.failure_count,
+                    "successCount": c.success_count,
+                    "authMethod": c.auth_method,
+                    "expiresAt": c.expires_at,
+                    "lastUsedAt": c.last_used_at,
+                    "exhaustedUntil": c.exhausted_until,
+                    "hasProfileArn": c.has_profile_arn,
+                    "hasProxy": c.has_proxy,
+                    "stats": c.stats,
+                })
+            })
+            .collect();
+        bundle.add_json(
+            "credentials.json",
+            &serde_json::json!({
+                "total": status.total,
+                "available": status.available,
+                "currentId": status.current_id,
+                "credentials": health,
+            }),
+        );
+        bundle.add_json("proxies.json", &self.get_proxies());
+
+        let logs = crate::logging::buffer()
+            .map(|buffer| buffer.recent(&crate::logging::LogFilter::default(), usize::MAX))
+            .unwrap_or_default();
+        let warnings: Vec<&crate::logging::LogEntry> = logs
+            .iter()
+            .filter(|e| e.level <= tracing::Level::WARN)
+            .collect();
+        let credential_errors: Vec<serde_json::Value> = status
+            .credentials
+            .iter()
+            .filter(|c| c.stats.last_error.is_some())
+            .map(|c| {
+                serde_json::json!({
+                    "id": c.id,
+                    "lastError": c.stats.last_error,
+                    "lastErrorAt": c.stats.last_error_at,
+                })
+            })
+            .collect();
+        bundle.add_json(
+            "errors.json",
+            &serde_json::json!({
+                "credentials": credential_errors,
+                "logs": warnings,
+                "streams": self.get_stream_stats(),
+                "recovery": self.get_recovery_report(),
+                "unknownEvents": self.get_unknown_events(),
+            }),
+        );
+        bundle.add_jsonl("logs.jsonl", &logs);
+
+        bundle
+            .finish()
+            .map_err(|e| AdminServiceError::InternalError(format!("生成诊断包失败: {}", e)))
+    }
+
     /// 分类添加凭据错误
     fn classify_add_error(&self, e: anyhow::Error) -> AdminServiceError {
         let msg = e.to_string();
@@ -405,7 +1536,8 @@ impl AdminService {
         let msg = e.to_string();
         if msg.contains("不存在") {
             AdminServiceError::NotFound { id }
-        } else if msg.contains("只能删除已禁用的凭据") || msg.contains("请先禁用凭据") {
+        } else if msg.contains("只能删除已禁用的凭据") || msg.contains("请先禁用凭据")
+        {
             AdminServiceError::InvalidCredential(msg)
         } else {
             AdminServiceError::InternalError(msg)
@@ -0,0 +1,89 @@
+//! Admin 会话
+//!
+//! `POST /login` 用 Admin 账号的 Key 换取短期有效的 JWT，管理面板只保存该 Token，
+//! 不必长期保存原始 Key。签名密钥在进程启动时随机生成，重启后所有会话失效；
+//! 注销的 Token 记录在内存中直到过期。Token 绑定账号名与 Key 指纹，
+//! 账号被删除或更换 Key 后已签发的 Token 随之失效。
+
+use std::collections::HashMap;
+
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use chrono::{Duration, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::common::{auth, jwt};
+use crate::model::config::AdminAccount;
+
+/// 会话 Token 的 claims
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionClaims {
+    /// 账号名
+    pub sub: String,
+    /// 签发时账号 Key 的指纹
+    pub kid: String,
+    /// Token ID（注销时使用）
+    pub jti: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl SessionClaims {
+    /// Token 是否仍属于该账号（账号未被删除、Key 未更换）
+    pub fn matches(&self, account: &AdminAccount) -> bool {
+        self.sub == account.name && self.kid == auth::api_key_id(&account.key)
+    }
+}
+
+/// 会话 Token 签发与校验
+pub struct AdminSessions {
+    secret: [u8; 32],
+    /// 已注销的 Token ID -> 过期时间（UNIX 秒）
+    revoked: Mutex<HashMap<String, i64>>,
+}
+
+impl Default for AdminSessions {
+    /// 使用随机生成的签名密钥
+    fn default() -> Self {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        Self {
+            secret,
+            revoked: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl AdminSessions {
+    /// 为账号签发会话 Token
+    pub fn issue(&self, account: &AdminAccount, ttl_minutes: u64) -> (String, SessionClaims) {
+        let now = Utc::now();
+        let ttl = Duration::minutes(ttl_minutes.max(1) as i64);
+        let claims = SessionClaims {
+            sub: account.name.clone(),
+            kid: auth::api_key_id(&account.key),
+            jti: uuid::Uuid::new_v4().to_string(),
+            iat: now.timestamp(),
+            exp: (now + ttl).timestamp(),
+        };
+        (jwt::encode(&claims, &self.secret), claims)
+    }
+
+    /// 校验会话 Token：签名有效、未过期且未注销
+    pub fn verify(&self, token: &str) -> Option<SessionClaims> {
+        let claims: SessionClaims = jwt::decode(token, &self.secret)?;
+        if claims.exp <= Utc::now().timestamp() || self.revoked.lock().contains_key(&claims.jti) {
+            return None;
+        }
+        Some(claims)
+    }
+
+    /// 注销会话 Token
+    pub fn revoke(&self, claims: &SessionClaims) {
+        let now = Utc::now().timestamp();
+        let mut revoked = self.revoked.lock();
+        revoked.retain(|_, exp| *exp > now);
+        revoked.insert(claims.jti.clone(), claims.exp);
+    }
+}
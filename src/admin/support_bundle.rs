@@ -0,0 +1,108 @@
+//! 诊断包（support bundle）打包
+//!
+//! 将多份 JSON 诊断数据打包为一个 zip，写入前把所有字符串中出现的密钥替换为 `[REDACTED]`，
+//! 便于用户在提交 issue 时直接附上。
+
+use std::io::{Cursor, Write};
+
+use serde::Serialize;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// 替换密钥后的占位符
+pub const REDACTED: &str = "[REDACTED]";
+
+/// 短于此长度的字符串不作为密钥替换（避免误伤普通文本）
+const MIN_SECRET_LEN: usize = 6;
+
+/// 诊断包构建器
+pub struct SupportBundle {
+    secrets: Vec<String>,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl SupportBundle {
+    /// `secrets` 为需要从所有内容中抹去的密钥原文
+    pub fn new(secrets: impl IntoIterator<Item = String>) -> Self {
+        let mut secrets: Vec<String> = secrets
+            .into_iter()
+            .filter(|s| s.len() >= MIN_SECRET_LEN)
+            .collect();
+        // 先替换较长的密钥，避免其中包含的较短密钥先被替换后留下残片
+        secrets.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        secrets.dedup();
+        Self {
+            secrets,
+            files: Vec::new(),
+        }
+    }
+
+    /// 添加一个 JSON 文件
+    pub fn add_json(&mut self, name: &str, value: &impl Serialize) {
+        let value = self.redacted(value);
+        let content = serde_json::to_vec_pretty(&value).unwrap_or_default();
+        self.files.push((name.to_string(), content));
+    }
+
+    /// 添加一个 JSON Lines 文件（每个元素一行）
+    pub fn add_jsonl<T: Serialize>(&mut self, name: &str, items: &[T]) {
+        let mut content = Vec::new();
+        for item in items {
+            let value = self.redacted(item);
+            if serde_json::to_writer(&mut content, &value).is_ok() {
+                content.push(b'\n');
+            }
+        }
+        self.files.push((name.to_string(), content));
+    }
+
+    fn redacted(&self, value: &impl Serialize) -> serde_json::Value {
+        let mut value = serde_json::to_value(value).unwrap_or_default();
+        self.redact(&mut value);
+        value
+    }
+
+    fn redact(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(s) => {
+                for secret in &self.secrets {
+                    if s.contains(secret.as_str()) {
+                        *s = s.replace(secret.as_str(), REDACTED);
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|v| self.redact(v)),
+            serde_json::Value::Object(map) => map.values_mut().for_each(|v| self.redact(v)),
+            _ => {}
+        }
+    }
+
+    /// 生成 zip 内容
+    pub fn finish(self) -> zip::result::ZipResult<Vec<u8>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (name, content) in self.files {
+            writer.start_file(name, options)?;
+            writer.write_all(&content)?;
+        }
+        Ok(writer.finish()?.into_inner())
+    }
+}
+
+/// 收集 JSON 值中的所有字符串叶子（账号名、角色、键前缀等标识字段除外），作为需要抹去的密钥
+pub fn collect_secrets(value: &serde_json::Value, out: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(s) => out.push(s.clone()),
+        serde_json::Value::Array(items) => items.iter().for_each(|v| collect_secrets(v, out)),
+        serde_json::Value::Object(map) => map
+            .iter()
+            .filter(|(key, _)| {
+                !matches!(
+                    key.as_str(),
+                    "name" | "role" | "keyPrefix" | "allowedModels"
+                )
+            })
+            .for_each(|(_, v)| collect_secrets(v, out)),
+        _ => {}
+    }
+}
@@ -1,11 +1,25 @@
 //! Admin API 类型定义
 
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use tracing::Level;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::common::crypto::EncryptedEnvelope;
+use crate::common::i18n::Message;
+use crate::events::{StreamOutcome, TokenUsage};
+use crate::kiro::model::credentials::KiroCredentials;
+use crate::kiro::token_manager::{CredentialStats, DuplicateGroup};
+use crate::logging::{LogEntry, LogFilter};
+use crate::model::config::{AdminRole, CredentialFallback, ModelRoute};
+use crate::tenants::TenantUsage;
 
 // ============ 凭据状态 ============
 
 /// 所有凭据状态响应
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CredentialsStatusResponse {
     /// 凭据总数
@@ -19,7 +33,7 @@ pub struct CredentialsStatusResponse {
 }
 
 /// 单个凭据的状态信息
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CredentialStatusItem {
     /// 凭据唯一 ID
@@ -51,12 +65,93 @@ pub struct CredentialStatusItem {
     /// 代理 URL（用于前端展示）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub proxy_url: Option<String>,
+    /// 备用代理 URL（按顺序使用）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub proxy_fallback_urls: Vec<String>,
+    /// 额度用尽后的恢复时间（RFC3339 格式，到达后自动重新启用）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exhausted_until: Option<String>,
+    /// 长期调用质量统计（按类别的失败次数、平均延迟、最近错误，重启后保留）
+    pub stats: CredentialStats,
+    /// 备注
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// 账号负责人的联系方式
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner_contact: Option<String>,
+    /// 账号来源
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// 凭据代理的健康状态
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyStatusItem {
+    /// 代理地址（密码已隐藏）
+    pub url: String,
+    /// 是否可用（尚未检查过时为 true）
+    pub healthy: bool,
+    /// 最近一次检查（或连接失败）的时间（RFC3339 格式）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<String>,
+    /// 不可用的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// 以该代理为候选代理的凭据
+    pub credential_ids: Vec<u64>,
+    /// 当前经该代理连接的凭据
+    pub active_credential_ids: Vec<u64>,
+}
+
+/// 凭据代理健康状态响应
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxiesResponse {
+    pub proxies: Vec<ProxyStatusItem>,
+}
+
+/// 代理测试请求（测试尚未保存的代理配置）
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TestProxyRequest {
+    /// 代理地址（http/https/socks5/socks5h）
+    pub url: String,
+    /// 代理认证用户名
+    pub username: Option<String>,
+    /// 代理认证密码
+    pub password: Option<String>,
+    /// 测试请求的目标（默认 https://checkip.amazonaws.com）
+    pub target_url: Option<String>,
+}
+
+/// 代理测试结果
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TestProxyResponse {
+    /// 是否经代理成功访问目标（目标返回 2xx）
+    pub success: bool,
+    /// 代理地址（密码已隐藏）
+    pub url: String,
+    /// 目标域名是否由代理服务器解析
+    pub remote_dns: bool,
+    pub target_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+    /// 出口 IP（无法从响应中识别时不返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_ip: Option<String>,
+    /// 从发出请求到收到响应头的耗时（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 // ============ 操作请求 ============
 
 /// 启用/禁用凭据请求
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SetDisabledRequest {
     /// 是否禁用
@@ -64,15 +159,24 @@ pub struct SetDisabledRequest {
 }
 
 /// 修改优先级请求
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SetPriorityRequest {
     /// 新优先级值
     pub priority: u32,
 }
 
+/// 更新凭据备注信息请求（未提供的字段保持不变，空字符串表示清除）
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCredentialRequest {
+    pub notes: Option<String>,
+    pub owner_contact: Option<String>,
+    pub source: Option<String>,
+}
+
 /// 添加凭据请求
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AddCredentialRequest {
     /// 刷新令牌（必填）
@@ -117,6 +221,10 @@ pub struct AddCredentialRequest {
 
     /// 凭据级代理认证密码（可选）
     pub proxy_password: Option<String>,
+
+    /// 备用代理 URL（可选，按顺序使用，认证信息写在 URL 中，特殊值 "direct" 表示直连）
+    #[serde(default)]
+    pub proxy_fallback_urls: Vec<String>,
 }
 
 fn default_auth_method() -> String {
@@ -124,7 +232,7 @@ fn default_auth_method() -> String {
 }
 
 /// 添加凭据成功响应
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AddCredentialResponse {
     pub success: bool,
@@ -134,12 +242,58 @@ pub struct AddCredentialResponse {
     /// 用户邮箱（如果获取成功）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
+    /// 疑似同一账号的已有凭据 ID（按账号 ID / 邮箱匹配）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub duplicate_of: Vec<u64>,
+    /// 重复提示
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+// ============ 批量导入导出 ============
+
+/// 批量导入/导出的凭据数据
+///
+/// 明文为凭据数组（与凭据文件的多凭据格式一致），指定口令时为加密信封
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum CredentialsBundle {
+    Plain(Vec<KiroCredentials>),
+    Encrypted(EncryptedEnvelope),
+}
+
+/// 批量导入响应
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportCredentialsResponse {
+    /// 是否全部导入成功
+    pub success: bool,
+    /// 成功导入的数量
+    pub imported: usize,
+    /// 导入失败的数量
+    pub failed: usize,
+    /// 每条凭据的导入结果（顺序与请求一致）
+    pub results: Vec<ImportCredentialResult>,
+}
+
+/// 单条凭据的导入结果
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportCredentialResult {
+    /// 在导入数组中的下标
+    pub index: usize,
+    /// 新凭据 ID（导入成功时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_id: Option<u64>,
+    /// 失败原因（导入失败时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 // ============ 余额查询 ============
 
 /// 余额查询响应
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BalanceResponse {
     /// 凭据 ID
@@ -158,28 +312,565 @@ pub struct BalanceResponse {
     pub next_reset_at: Option<f64>,
 }
 
+/// 额度耗尽预测响应
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ForecastResponse {
+    /// 凭据 ID
+    pub id: u64,
+    /// 当前使用量
+    pub current_usage: f64,
+    /// 使用限额
+    pub usage_limit: f64,
+    /// 剩余额度
+    pub remaining: f64,
+    /// 下次重置时间（Unix 时间戳）
+    pub next_reset_at: Option<f64>,
+    /// 估算的消耗速度（每小时）
+    pub usage_per_hour: Option<f64>,
+    /// 速度估算依据：recent（最近的余额采样）/ periodAverage（本周期平均）/ none（数据不足）
+    pub basis: &'static str,
+    /// 按当前速度预计耗尽的时间（Unix 时间戳），无消耗或数据不足时为空
+    pub projected_exhaustion_at: Option<f64>,
+    /// 是否会在下次重置前耗尽
+    pub exhausts_before_reset: bool,
+}
+
 // ============ 负载均衡配置 ============
 
 /// 负载均衡模式响应
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct LoadBalancingModeResponse {
-    /// 当前模式（"priority" 或 "balanced"）
+    /// 当前模式（"priority"、"balanced"、"weighted"、"least-usage" 或 "sticky"）
     pub mode: String,
 }
 
 /// 设置负载均衡模式请求
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SetLoadBalancingModeRequest {
-    /// 模式（"priority" 或 "balanced"）
+    /// 模式（"priority"、"balanced"、"weighted"、"least-usage" 或 "sticky"）
     pub mode: String,
 }
 
+// ============ 模型路由 ============
+
+/// 单条模型路由
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelRouteItem {
+    /// 实际使用的模型名
+    pub model: String,
+    /// 客户端未指定 max_tokens 时使用的默认值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<i32>,
+    /// 固定使用的凭据 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_id: Option<u64>,
+}
+
+impl From<ModelRoute> for ModelRouteItem {
+    fn from(route: ModelRoute) -> Self {
+        Self {
+            model: route.model,
+            max_tokens: route.max_tokens,
+            credential_id: route.credential_id,
+        }
+    }
+}
+
+impl From<ModelRouteItem> for ModelRoute {
+    fn from(item: ModelRouteItem) -> Self {
+        Self {
+            model: item.model,
+            max_tokens: item.max_tokens,
+            credential_id: item.credential_id,
+        }
+    }
+}
+
+/// 模型路由表响应
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelRoutesResponse {
+    /// 客户端模型名 → 路由
+    pub routes: BTreeMap<String, ModelRouteItem>,
+}
+
+// ============ 租户 ============
+
+/// 租户
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantItem {
+    /// 租户 API Key 的标识（与请求记录、费用统计中的 `apiKeyId` 一致）
+    pub api_key_ids: Vec<String>,
+    /// 允许请求的模型（`*` 结尾表示前缀匹配），为空时不限制
+    pub allowed_models: Vec<String>,
+    /// 绑定的凭据 ID，为空时使用全部凭据
+    pub credential_ids: Vec<u64>,
+    /// 绑定的凭据均不可用时的处理方式
+    pub credential_fallback: CredentialFallback,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monthly_token_quota: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monthly_request_quota: Option<u64>,
+    /// 本月（UTC）用量
+    pub usage: TenantUsage,
+}
+
+/// 租户列表响应
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantsResponse {
+    /// 租户名 → 租户
+    pub tenants: BTreeMap<String, TenantItem>,
+}
+
+/// 新增或修改租户请求（API Key 通过 `POST /tenants/{name}/keys` 单独添加，修改时保留）
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SetTenantRequest {
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+    #[serde(default)]
+    pub credential_ids: Vec<u64>,
+    /// `credentialIds` 中的凭据均不可用时：`fail`（默认，返回错误）或 `pool`（改用全部凭据）
+    #[serde(default)]
+    pub credential_fallback: CredentialFallback,
+    /// 每月（UTC 自然月）输入与输出 token 合计上限
+    #[serde(default)]
+    pub monthly_token_quota: Option<u64>,
+    /// 每月（UTC 自然月）请求数上限
+    #[serde(default)]
+    pub monthly_request_quota: Option<u64>,
+}
+
+/// 为租户添加 API Key 的请求
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddTenantKeyRequest {
+    /// 使用指定的 Key，未提供时自动生成
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+/// 添加的租户 API Key（配置中只保存哈希，完整 Key 仅在此响应中返回）
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddTenantKeyResponse {
+    pub key_id: String,
+    pub key: String,
+}
+
+// ============ 配置热重载 ============
+
+/// 重新加载配置响应
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadConfigResponse {
+    pub success: bool,
+    pub message: String,
+    /// 已变更但需重启后生效的配置项
+    pub requires_restart: Vec<String>,
+}
+
+/// 修改配置类请求的查询参数
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct DryRunQuery {
+    /// 为 true 时只校验并返回将要发生的变更，不应用
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// 单个配置项的变更
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigChange {
+    /// 配置项（`config.json` 中的字段名）
+    pub field: String,
+    /// 当前值（密钥类配置项显示为 `[REDACTED]`）
+    pub before: serde_json::Value,
+    /// 变更后的值
+    pub after: serde_json::Value,
+    /// 是否需重启后生效
+    pub requires_restart: bool,
+}
+
+/// 配置变更预览（`?dry_run=true`）
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDiffResponse {
+    pub dry_run: bool,
+    /// 应用时是否会写入 `config.json`（重新加载配置只读取文件，不写入）
+    pub persists_to_file: bool,
+    pub changes: Vec<ConfigChange>,
+}
+
+// ============ 重复检测 ============
+
+/// 疑似重复凭据报告
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateCredentialsResponse {
+    pub groups: Vec<DuplicateGroup>,
+}
+
+// ============ 账号信息刷新 ============
+
+/// 刷新账号信息响应
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshAccountResponse {
+    pub success: bool,
+    pub message: String,
+    /// 账号邮箱（上游未返回时为已保存的值）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// 账号 ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// 账号是否已被暂停（已自动禁用该凭据）
+    pub suspended: bool,
+}
+
+// ============ Region 端点 ============
+
+/// 凭据的生效端点
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialEndpoints {
+    pub id: u64,
+    /// 刷新方式（social / idc）
+    pub auth_method: String,
+    pub auth_region: String,
+    pub api_region: String,
+    /// Token 刷新地址
+    pub refresh_url: String,
+    pub api_url: String,
+    pub mcp_url: String,
+}
+
+/// 所有凭据的生效端点列表
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialEndpointsResponse {
+    pub credentials: Vec<CredentialEndpoints>,
+}
+
+// ============ 日志 ============
+
+/// 日志查询参数（`/logs` 与 `/logs/stream` 共用）
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct LogsQuery {
+    /// 最低级别（trace / debug / info / warn / error）
+    pub level: Option<String>,
+    /// 来源模块前缀
+    pub target: Option<String>,
+    /// 最多返回条数（仅 `/logs`）
+    pub limit: Option<usize>,
+}
+
+impl LogsQuery {
+    pub fn filter(&self) -> Result<LogFilter, String> {
+        let level = self
+            .level
+            .as_deref()
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse::<Level>()
+                    .map_err(|_| format!("无效的日志级别: {}", s))
+            })
+            .transpose()?;
+        Ok(LogFilter {
+            level,
+            target: self.target.clone().filter(|s| !s.is_empty()),
+        })
+    }
+}
+
+/// 日志查询响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LogsResponse {
+    pub logs: Vec<LogEntry>,
+}
+
+// ============ 流式响应统计 ============
+
+/// 流式响应按结束方式的计数
+#[derive(Debug, Clone, Copy, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamOutcomeCounts {
+    /// 上游正常结束
+    pub completed: u64,
+    /// 上游响应流中途出错
+    pub upstream_error: u64,
+    /// 客户端提前断开连接
+    pub client_disconnected: u64,
+}
+
+impl StreamOutcomeCounts {
+    pub fn record(&mut self, outcome: StreamOutcome) {
+        match outcome {
+            StreamOutcome::Completed => self.completed += 1,
+            StreamOutcome::UpstreamError => self.upstream_error += 1,
+            StreamOutcome::ClientDisconnected => self.client_disconnected += 1,
+        }
+    }
+}
+
+/// 流式响应结束统计（进程启动以来，仅内存）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamStatsResponse {
+    /// 统计开始时间（RFC3339 格式）
+    pub since: String,
+    pub total: StreamOutcomeCounts,
+    /// 按 API Key 标识（Key 的 SHA-256 前 8 位）统计
+    pub by_api_key: BTreeMap<String, StreamOutcomeCounts>,
+}
+
+// ============ 请求记录 ============
+
+/// 一次上游 API 调用的记录（来自 `RequestCompleted` 事件，成功的调用随后由 `UsageRecorded` 事件补全用量，仅内存）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestRecord {
+    pub timestamp: DateTime<Utc>,
+    /// API Key 标识（Key 的 SHA-256 前 8 位）
+    pub api_key_id: Option<String>,
+    pub credential_id: Option<u64>,
+    pub model: Option<String>,
+    pub stream: bool,
+    /// 最终状态码（未拿到响应时为 None）
+    pub status: Option<u16>,
+    pub attempts: usize,
+    pub duration_ms: u64,
+    /// 估算的输入 tokens
+    pub input_tokens: Option<i32>,
+    /// 最终输出 tokens（收到响应的最终用量前为 None）
+    pub output_tokens: Option<i32>,
+    /// 最终缓存读取 tokens
+    pub cache_read_input_tokens: Option<i32>,
+    /// 最终缓存写入 tokens
+    pub cache_creation_input_tokens: Option<i32>,
+    /// 签名所用密钥的标识（配置 `usageSigningKey` 时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+    /// 签名格式版本（见 `SIGNED_PAYLOAD_VERSION`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_version: Option<u32>,
+    /// 对 `signed_payload()` 的 Ed25519 签名（base64）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// 签名格式版本：版本 2 起签名覆盖最终的输出与缓存 tokens，签名内容以 `v2` 开头
+pub const SIGNED_PAYLOAD_VERSION: u32 = 2;
+
+/// 签名覆盖的记录字段（按顺序）
+pub const SIGNED_RECORD_FIELDS: [&str; 12] = [
+    "timestamp",
+    "apiKeyId",
+    "credentialId",
+    "model",
+    "stream",
+    "status",
+    "attempts",
+    "durationMs",
+    "inputTokens",
+    "outputTokens",
+    "cacheReadInputTokens",
+    "cacheCreationInputTokens",
+];
+
+impl RequestRecord {
+    /// 签名内容：首行为 `v{SIGNED_PAYLOAD_VERSION}`，其后为 `SIGNED_RECORD_FIELDS` 中各字段的
+    /// JSON 值按顺序以换行连接，字符串取原文，null 为空串
+    pub fn signed_payload(&self) -> String {
+        let value = serde_json::to_value(self).unwrap_or_default();
+        let fields = SIGNED_RECORD_FIELDS
+            .iter()
+            .map(|field| match value.get(field) {
+                Some(serde_json::Value::String(s)) => s.clone(),
+                Some(serde_json::Value::Null) | None => String::new(),
+                Some(other) => other.to_string(),
+            });
+        std::iter::once(format!("v{}", SIGNED_PAYLOAD_VERSION))
+            .chain(fields)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// 请求记录查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestRecordsQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    /// 起始时间（RFC3339，含）
+    pub since: Option<DateTime<Utc>>,
+    /// 结束时间（RFC3339，不含）
+    pub until: Option<DateTime<Utc>>,
+    pub model: Option<String>,
+    pub credential_id: Option<u64>,
+    pub api_key_id: Option<String>,
+}
+
+impl RequestRecordsQuery {
+    pub fn matches(&self, record: &RequestRecord) -> bool {
+        self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp < until)
+            && self
+                .model
+                .as_ref()
+                .is_none_or(|model| record.model.as_ref() == Some(model))
+            && self
+                .credential_id
+                .is_none_or(|id| record.credential_id == Some(id))
+            && self
+                .api_key_id
+                .as_ref()
+                .is_none_or(|key| record.api_key_id.as_ref() == Some(key))
+    }
+}
+
+/// 请求记录分页响应（按时间倒序）
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestRecordsResponse {
+    /// 符合过滤条件的记录总数
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub records: Vec<RequestRecord>,
+}
+
+/// 请求记录签名的校验信息
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningKeyResponse {
+    pub algorithm: &'static str,
+    /// base64 编码的 Ed25519 公钥
+    pub public_key: String,
+    pub key_id: String,
+    /// 签名格式版本，签名内容的首行为 `v{version}`
+    pub version: u32,
+    /// 签名覆盖的字段，各字段值按此顺序接在版本行之后、以换行连接（字符串取原文，null 为空串）
+    pub signed_fields: [&'static str; 12],
+}
+
+// ============ 费用估算 ============
+
+/// 用量与估算费用汇总
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CostSummary {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    /// 估算费用（美元，未配置单价的请求不计入）
+    pub cost_usd: f64,
+    /// 模型未配置单价、未计入费用的请求数
+    pub unpriced_requests: u64,
+}
+
+impl CostSummary {
+    pub fn record(&mut self, usage: &TokenUsage, cost_usd: Option<f64>) {
+        self.requests += 1;
+        self.input_tokens += usage.input_tokens.max(0) as u64;
+        self.output_tokens += usage.output_tokens.max(0) as u64;
+        self.cache_creation_input_tokens += usage.cache_creation_input_tokens.max(0) as u64;
+        self.cache_read_input_tokens += usage.cache_read_input_tokens.max(0) as u64;
+        match cost_usd {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &CostSummary) {
+        self.requests += other.requests;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+        self.cost_usd += other.cost_usd;
+        self.unpriced_requests += other.unpriced_requests;
+    }
+}
+
+/// 费用查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct CostsQuery {
+    /// 起始日期（UTC，YYYY-MM-DD，含）
+    pub since: Option<NaiveDate>,
+    /// 结束日期（UTC，YYYY-MM-DD，含）
+    pub until: Option<NaiveDate>,
+    pub api_key_id: Option<String>,
+    pub credential_id: Option<u64>,
+}
+
+/// 估算费用响应（进程启动以来，仅内存）
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CostsResponse {
+    /// 统计开始时间（RFC3339 格式）
+    pub since: String,
+    pub currency: &'static str,
+    pub total: CostSummary,
+    /// 按 API Key 标识统计（没有 API Key 的请求记为 `unknown`）
+    pub by_api_key: BTreeMap<String, CostSummary>,
+    /// 按凭据 ID 统计（未知凭据记为 `unknown`）
+    pub by_credential: BTreeMap<String, CostSummary>,
+    /// 按 UTC 日期（YYYY-MM-DD）统计
+    pub by_day: BTreeMap<String, CostSummary>,
+    pub by_model: BTreeMap<String, CostSummary>,
+}
+
+// ============ Admin 账号 ============
+
+/// 当前请求的 Admin 账号（认证中间件写入请求扩展，`GET /me` 返回）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminIdentity {
+    pub name: String,
+    pub role: AdminRole,
+}
+
+/// 登录请求：提供 `key`，或以账号名为 `username`、Key 为 `password`
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginRequest {
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// 登录 / 续期响应
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    /// 会话 Token（通过 `Authorization: Bearer <token>` 使用）
+    pub token: String,
+    /// 过期时间（RFC3339 格式）
+    pub expires_at: String,
+    pub name: String,
+    pub role: AdminRole,
+}
+
 // ============ 通用响应 ============
 
 /// 操作成功响应
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SuccessResponse {
     pub success: bool,
     pub message: String,
@@ -195,12 +886,12 @@ impl SuccessResponse {
 }
 
 /// 错误响应
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AdminErrorResponse {
     pub error: AdminError,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AdminError {
     #[serde(rename = "type")]
     pub error_type: String,
@@ -222,7 +913,18 @@ impl AdminErrorResponse {
     }
 
     pub fn authentication_error() -> Self {
-        Self::new("authentication_error", "Invalid or missing admin API key")
+        Self::new(
+            "authentication_error",
+            Message::AdminAuthenticationFailed.localized(),
+        )
+    }
+
+    pub fn permission_error() -> Self {
+        Self::new("permission_error", Message::AdminReadonly.localized())
+    }
+
+    pub fn ip_denied() -> Self {
+        Self::new("permission_error", Message::IpDenied.localized())
     }
 
     pub fn not_found(message: impl Into<String>) -> Self {
@@ -1,7 +1,7 @@
 //! Admin UI 静态文件服务模块
 //!
-//! 使用 rust-embed 嵌入前端构建产物
+//! 使用 rust-embed 嵌入前端构建产物；`--dev` 模式下改为从磁盘实时读取
 
 mod router;
 
-pub use router::create_admin_ui_router;
+pub use router::{DEV_ASSET_DIR, create_admin_ui_router};
@@ -1,8 +1,12 @@
 //! Admin UI 路由配置
 
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use axum::{
     Router,
     body::Body,
+    extract::State,
     http::{Response, StatusCode, Uri, header},
     response::IntoResponse,
     routing::get,
@@ -14,20 +18,57 @@ use rust_embed::Embed;
 #[folder = "admin-ui/dist"]
 struct Asset;
 
+/// 开发模式下读取前端构建产物的默认目录
+pub const DEV_ASSET_DIR: &str = "admin-ui/dist";
+
+/// 静态文件来源
+#[derive(Clone)]
+enum AssetSource {
+    /// 编译时嵌入二进制
+    Embedded,
+    /// 开发模式：每次请求从磁盘读取，并禁用缓存
+    Disk(Arc<PathBuf>),
+}
+
+impl AssetSource {
+    async fn get(&self, path: &str) -> Option<Vec<u8>> {
+        match self {
+            Self::Embedded => Asset::get(path).map(|content| content.data.into_owned()),
+            Self::Disk(dir) => tokio::fs::read(dir.join(path)).await.ok(),
+        }
+    }
+
+    fn cache_control(&self, path: &str) -> &'static str {
+        match self {
+            Self::Embedded => get_cache_control(path),
+            Self::Disk(_) => "no-store",
+        }
+    }
+}
+
 /// 创建 Admin UI 路由
-pub fn create_admin_ui_router() -> Router {
+///
+/// `dev_dir` 为 Some 时进入开发模式：静态文件从该目录实时读取（不再使用嵌入的版本），
+/// 前端重新构建后刷新页面即可生效，无需重新编译二进制
+pub fn create_admin_ui_router(dev_dir: Option<PathBuf>) -> Router {
+    let source = match dev_dir {
+        Some(dir) => AssetSource::Disk(Arc::new(dir)),
+        None => AssetSource::Embedded,
+    };
+
     Router::new()
         .route("/", get(index_handler))
         .route("/{*file}", get(static_handler))
+        .with_state(source)
 }
 
 /// 处理首页请求
-async fn index_handler() -> impl IntoResponse {
-    serve_index()
+async fn index_handler(State(source): State<AssetSource>) -> impl IntoResponse {
+    serve_index(&source).await
 }
 
 /// 处理静态文件请求
-async fn static_handler(uri: Uri) -> impl IntoResponse {
+async fn static_handler(State(source): State<AssetSource>, uri: Uri) -> impl IntoResponse {
     let path = uri.path().trim_start_matches('/');
 
     // 安全检查：拒绝包含 .. 的路径
@@ -39,25 +80,25 @@ async fn static_handler(uri: Uri) -> impl IntoResponse {
     }
 
     // 尝试获取请求的文件
-    if let Some(content) = Asset::get(path) {
+    if let Some(content) = source.get(path).await {
         let mime = mime_guess::from_path(path)
             .first_or_octet_stream()
             .to_string();
 
         // 根据文件类型设置不同的缓存策略
-        let cache_control = get_cache_control(path);
+        let cache_control = source.cache_control(path);
 
         return Response::builder()
             .status(StatusCode::OK)
             .header(header::CONTENT_TYPE, mime)
             .header(header::CACHE_CONTROL, cache_control)
-            .body(Body::from(content.data.into_owned()))
+            .body(Body::from(content))
             .expect("Failed to build response");
     }
 
     // SPA fallback: 如果文件不存在且不是资源文件，返回 index.html
     if !is_asset_path(path) {
-        return serve_index();
+        return serve_index(&source).await;
     }
 
     // 404
@@ -68,13 +109,13 @@ async fn static_handler(uri: Uri) -> impl IntoResponse {
 }
 
 /// 提供 index.html
-fn serve_index() -> Response<Body> {
-    match Asset::get("index.html") {
+async fn serve_index(source: &AssetSource) -> Response<Body> {
+    match source.get("index.html").await {
         Some(content) => Response::builder()
             .status(StatusCode::OK)
             .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
-            .header(header::CACHE_CONTROL, "no-cache")
-            .body(Body::from(content.data.into_owned()))
+            .header(header::CACHE_CONTROL, source.cache_control("index.html"))
+            .body(Body::from(content))
             .expect("Failed to build response"),
         None => Response::builder()
             .status(StatusCode::NOT_FOUND)
@@ -0,0 +1,310 @@
+//! 告警通知
+//!
+//! 订阅进程内事件，`alerts.events` 中列出的事件通过 Telegram Bot 和 / 或 SMTP 邮件通知运维人员。
+//! 同一事件类型在 `alerts.minIntervalMinutes` 内最多发送一次告警，期间的同类事件只计数，
+//! 在下一次告警中附带被合并的数量。配置在每次发送时读取，热重载后立即生效。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde_json::json;
+use tokio::sync::broadcast;
+
+use crate::events::{self, AppEvent, EventEnvelope};
+use crate::http_client::build_client;
+use crate::kiro::token_manager::{DisabledReason, MultiTokenManager};
+use crate::model::config::{Config, SmtpAlertConfig, SmtpSecurity, TelegramAlertConfig};
+use crate::tls::Destination;
+
+/// 发送告警的超时（秒）
+const SEND_TIMEOUT_SECS: u64 = 30;
+
+type SmtpTransport = AsyncSmtpTransport<Tokio1Executor>;
+
+/// 按事件类型限制告警频率
+#[derive(Default)]
+struct AlertLimiter {
+    /// 事件类型 -> (上次发送时间, 此后被合并的同类事件数)
+    last_sent: HashMap<String, (Instant, u64)>,
+}
+
+impl AlertLimiter {
+    /// 判断该类型的告警现在能否发送；可以发送时返回上次发送后被合并的同类事件数
+    fn admit(&mut self, kind: &str, now: Instant, interval: Duration) -> Option<u64> {
+        if let Some((sent_at, suppressed)) = self.last_sent.get_mut(kind)
+            && now.duration_since(*sent_at) < interval
+        {
+            *suppressed += 1;
+            return None;
+        }
+        let suppressed = self.last_sent.get(kind).map_or(0, |(_, n)| *n);
+        self.last_sent.insert(kind.to_string(), (now, 0));
+        Some(suppressed)
+    }
+}
+
+/// 事件类型（与事件 JSON 中的 `type` 一致）
+fn event_kind(event: &AppEvent) -> String {
+    serde_json::to_value(event)
+        .ok()
+        .and_then(|v| v["type"].as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// 事件的一句话描述（用作告警标题）
+fn summarize(event: &AppEvent) -> String {
+    match event {
+        AppEvent::CredentialDisabled {
+            credential_id,
+            reason,
+        } => {
+            let reason = match reason {
+                DisabledReason::Manual => "手动禁用",
+                DisabledReason::TooManyFailures => "连续失败次数过多",
+                DisabledReason::QuotaExceeded => "额度已用尽",
+                DisabledReason::Suspended => "账号被暂停",
+            };
+            format!("凭据 #{} 已被禁用：{}", credential_id, reason)
+        }
+        AppEvent::CredentialEnabled { credential_id } => {
+            format!("凭据 #{} 已重新启用", credential_id)
+        }
+        AppEvent::CredentialAdded { credential_id } => format!("新增凭据 #{}", credential_id),
+        AppEvent::CredentialDeleted { credential_id } => format!("凭据 #{} 已删除", credential_id),
+        AppEvent::DiskSpaceChanged {
+            low: true,
+            free_bytes,
+            min_free_bytes,
+        } => format!(
+            "数据目录可用磁盘空间不足：剩余 {} MB，低于下限 {} MB",
+            free_bytes / 1024 / 1024,
+            min_free_bytes / 1024 / 1024
+        ),
+        AppEvent::DiskSpaceChanged { free_bytes, .. } => format!(
+            "数据目录可用磁盘空间已恢复：剩余 {} MB",
+            free_bytes / 1024 / 1024
+        ),
+        AppEvent::ProxyHealthChanged {
+            url,
+            healthy: false,
+            error,
+        } => format!(
+            "代理 {} 不可用：{}",
+            url,
+            error.as_deref().unwrap_or("连接失败")
+        ),
+        AppEvent::ProxyHealthChanged { url, .. } => format!("代理 {} 已恢复可用", url),
+        AppEvent::ConfigReloaded { requires_restart } if !requires_restart.is_empty() => {
+            format!(
+                "配置已重新加载，需重启生效：{}",
+                requires_restart.join(", ")
+            )
+        }
+        AppEvent::ConfigReloaded { .. } => "配置已重新加载".to_string(),
+        other => format!("事件 {}", event_kind(other)),
+    }
+}
+
+/// 告警正文
+fn render(envelope: &EventEnvelope, suppressed: u64) -> String {
+    let mut text = format!(
+        "{}\n\n时间：{}\n事件：{}",
+        summarize(&envelope.event),
+        envelope.timestamp,
+        serde_json::to_string(&envelope.event).unwrap_or_default()
+    );
+    if let Some(actor) = &envelope.actor {
+        text.push_str(&format!("\n操作人：{}", actor));
+    }
+    if suppressed > 0 {
+        text.push_str(&format!(
+            "\n\n上次告警后另有 {} 条同类事件未单独通知",
+            suppressed
+        ));
+    }
+    text
+}
+
+/// 启动告警通知（未配置 `alerts` 时收到的事件直接忽略）
+pub fn spawn(token_manager: Arc<MultiTokenManager>) {
+    let mut receiver = events::subscribe();
+    tokio::spawn(async move {
+        let mut limiter = AlertLimiter::default();
+        loop {
+            let envelope = match receiver.recv().await {
+                Ok(envelope) => envelope,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("告警通知处理过慢，跳过了 {} 个事件", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let config = token_manager.config();
+            let Some(alerts) = &config.alerts else {
+                continue;
+            };
+            if alerts.telegram.is_none() && alerts.smtp.is_none() {
+                continue;
+            }
+            // 手动禁用由运维人员操作，无需告警
+            if matches!(
+                envelope.event,
+                AppEvent::CredentialDisabled {
+                    reason: DisabledReason::Manual,
+                    ..
+                }
+            ) {
+                continue;
+            }
+            let kind = event_kind(&envelope.event);
+            if !alerts.events.contains(&kind) {
+                continue;
+            }
+            let interval = Duration::from_secs(alerts.min_interval_minutes * 60);
+            let Some(suppressed) = limiter.admit(&kind, Instant::now(), interval) else {
+                continue;
+            };
+
+            let subject = format!("[kiro-rs] {}", summarize(&envelope.event));
+            let text = render(&envelope, suppressed);
+            let token_manager = token_manager.clone();
+            tokio::spawn(async move { send(&token_manager, &config, &subject, &text).await });
+        }
+    });
+}
+
+/// 通过所有已配置的渠道发送告警
+async fn send(token_manager: &MultiTokenManager, config: &Config, subject: &str, text: &str) {
+    let Some(alerts) = &config.alerts else {
+        return;
+    };
+    if let Some(telegram) = &alerts.telegram
+        && let Err(e) = send_telegram(token_manager, config, telegram, text).await
+    {
+        tracing::warn!("发送 Telegram 告警失败: {}", e);
+    }
+    if let Some(smtp) = &alerts.smtp
+        && let Err(e) = send_email(smtp, subject, text).await
+    {
+        tracing::warn!("发送邮件告警失败: {}", e);
+    }
+}
+
+async fn send_telegram(
+    token_manager: &MultiTokenManager,
+    config: &Config,
+    telegram: &TelegramAlertConfig,
+    text: &str,
+) -> anyhow::Result<()> {
+    let client = build_client(
+        token_manager.proxy().as_ref(),
+        SEND_TIMEOUT_SECS,
+        config.tls_backend,
+        Destination::Integration,
+    )?;
+    let url = format!(
+        "https://api.telegram.org/bot{}/sendMessage",
+        telegram.bot_token
+    );
+    let response = client
+        .post(url)
+        .json(&json!({
+            "chat_id": telegram.chat_id,
+            "text": text,
+            "disable_web_page_preview": true,
+        }))
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!(
+            "HTTP {}: {}",
+            status,
+            response.text().await.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+async fn send_email(smtp: &SmtpAlertConfig, subject: &str, text: &str) -> anyhow::Result<()> {
+    let mut builder = match smtp.security {
+        SmtpSecurity::Starttls => SmtpTransport::starttls_relay(&smtp.host)?,
+        SmtpSecurity::Tls => SmtpTransport::relay(&smtp.host)?,
+        SmtpSecurity::None => SmtpTransport::builder_dangerous(&smtp.host),
+    };
+    if let Some(port) = smtp.port {
+        builder = builder.port(port);
+    }
+    if let Some(username) = &smtp.username {
+        let password = smtp.password.clone().unwrap_or_default();
+        builder = builder.credentials(Credentials::new(username.clone(), password));
+    }
+    let transport = builder
+        .timeout(Some(Duration::from_secs(SEND_TIMEOUT_SECS)))
+        .build();
+
+    let mut message = Message::builder().from(smtp.from.parse()?).subject(subject);
+    for to in &smtp.to {
+        message = message.to(to.parse()?);
+    }
+    let message = message
+        .header(ContentType::TEXT_PLAIN)
+        .body(text.to_string())?;
+    transport.send(message).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limiter_allows_one_alert_per_interval() {
+        let mut limiter = AlertLimiter::default();
+        let interval = Duration::from_secs(600);
+        let start = Instant::now();
+
+        assert_eq!(
+            limiter.admit("credentialDisabled", start, interval),
+            Some(0)
+        );
+        assert_eq!(limiter.admit("credentialDisabled", start, interval), None);
+        assert_eq!(
+            limiter.admit(
+                "credentialDisabled",
+                start + Duration::from_secs(300),
+                interval
+            ),
+            None
+        );
+        // 其他事件类型不受影响
+        assert_eq!(limiter.admit("diskSpaceChanged", start, interval), Some(0));
+        // 间隔过后再次发送，并带上期间合并的数量
+        assert_eq!(
+            limiter.admit("credentialDisabled", start + interval, interval),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_render_mentions_suppressed_events() {
+        let envelope = EventEnvelope {
+            timestamp: "2026-01-01T00:00:00+00:00".to_string(),
+            actor: None,
+            event: AppEvent::CredentialDisabled {
+                credential_id: 3,
+                reason: DisabledReason::QuotaExceeded,
+            },
+        };
+        assert_eq!(event_kind(&envelope.event), "credentialDisabled");
+        let text = render(&envelope, 4);
+        assert!(text.starts_with("凭据 #3 已被禁用：额度已用尽"));
+        assert!(text.contains("另有 4 条同类事件"));
+        assert!(!render(&envelope, 0).contains("另有"));
+    }
+}
@@ -0,0 +1,967 @@
+//! Message Batches API（`/v1/messages/batches`）
+//!
+//! 批次提交后写入 `<缓存目录>/batches/`：`<id>.json` 为批次元数据，`<id>.requests.jsonl`
+//! 为提交的请求，`<id>.results.jsonl` 为逐条追加的结果。后台任务以非流式方式逐条执行请求
+//! （走与 `/v1/messages` 相同的处理流程），所有批次共享 `batchConcurrency` 个并发名额。
+//! 请求中较大的 system、tools 与消息内容按内容寻址存入 `batches/blobs/`（见 `common::blob_store`），
+//! 同一批次或不同批次中重复的系统提示词只保存一份，删除批次时释放引用。
+//! 进程重启后未结束的批次会从尚未产生结果的请求继续执行；超过 `expires_at`（创建后 24 小时）
+//! 仍未开始的请求记为 `expired`。
+//! 租户 Key 创建的批次记录租户名，执行时按租户当前的 `credentialIds` / `credentialFallback` 选择凭据。
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json as JsonExtractor,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::common::blob_store::BlobStore;
+use crate::kiro::provider::KiroProvider;
+use crate::kiro::token_manager::MultiTokenManager;
+use crate::model::config::CredentialFallback;
+
+use super::handlers::post_messages;
+use super::middleware::{AppState, TenantName};
+use super::types::{ErrorResponse, MessagesRequest};
+
+/// 单个批次的最大请求数
+const MAX_REQUESTS_PER_BATCH: usize = 100_000;
+
+/// custom_id 最大长度
+const MAX_CUSTOM_ID_LEN: usize = 64;
+
+/// 按内容寻址存储的最小内容大小（字节）
+const DEDUP_MIN_BYTES: usize = 1024;
+
+/// 批次有效期：超过后未开始的请求记为 expired
+const BATCH_TTL_HOURS: i64 = 24;
+
+/// 列表接口默认 / 最大分页大小
+const DEFAULT_LIST_LIMIT: usize = 20;
+const MAX_LIST_LIMIT: usize = 1000;
+
+/// 批次处理状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingStatus {
+    InProgress,
+    Canceling,
+    Ended,
+}
+
+/// 各状态的请求数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RequestCounts {
+    pub processing: usize,
+    pub succeeded: usize,
+    pub errored: usize,
+    pub canceled: usize,
+    pub expired: usize,
+}
+
+impl RequestCounts {
+    /// 记录一条结果（从 processing 中扣除）
+    fn record(&mut self, result: &BatchResult) {
+        self.processing = self.processing.saturating_sub(1);
+        match result {
+            BatchResult::Succeeded { .. } => self.succeeded += 1,
+            BatchResult::Errored { .. } => self.errored += 1,
+            BatchResult::Canceled => self.canceled += 1,
+            BatchResult::Expired => self.expired += 1,
+        }
+    }
+}
+
+/// 批次元数据（即 API 返回的 `message_batch` 对象，同时作为 `<id>.json` 落盘）
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MessageBatch {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub object_type: String,
+    pub processing_status: ProcessingStatus,
+    pub request_counts: RequestCounts,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub cancel_initiated_at: Option<DateTime<Utc>>,
+    pub results_url: Option<String>,
+    /// 创建批次的租户（租户 Key 创建时）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+impl MessageBatch {
+    fn new(request_count: usize) -> Self {
+        let now = Utc::now();
+        Self {
+            id: format!("msgbatch_{}", Uuid::new_v4().simple()),
+            object_type: "message_batch".to_string(),
+            processing_status: ProcessingStatus::InProgress,
+            request_counts: RequestCounts {
+                processing: request_count,
+                ..Default::default()
+            },
+            ended_at: None,
+            created_at: now,
+            expires_at: now + Duration::hours(BATCH_TTL_HOURS),
+            archived_at: None,
+            cancel_initiated_at: None,
+            results_url: None,
+            tenant: None,
+        }
+    }
+
+    fn finish(&mut self) {
+        self.processing_status = ProcessingStatus::Ended;
+        self.ended_at = Some(Utc::now());
+        self.results_url = Some(format!("/v1/messages/batches/{}/results", self.id));
+    }
+}
+
+/// 批次中的单个请求
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchRequest {
+    pub custom_id: String,
+    /// Messages 请求体（提交时已校验，执行时再反序列化）
+    pub params: serde_json::Value,
+}
+
+/// 单个请求的执行结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchResult {
+    Succeeded { message: serde_json::Value },
+    Errored { error: serde_json::Value },
+    Canceled,
+    Expired,
+}
+
+/// 结果文件中的一行
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchResultLine {
+    pub custom_id: String,
+    pub result: BatchResult,
+}
+
+/// 创建批次的请求体
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateBatchRequest {
+    pub requests: Vec<BatchRequest>,
+}
+
+/// 列表查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListBatchesQuery {
+    pub limit: Option<usize>,
+    pub before_id: Option<String>,
+    pub after_id: Option<String>,
+}
+
+/// 删除批次的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteOutcome {
+    Deleted,
+    NotFound,
+    /// 批次尚未结束，不能删除
+    StillProcessing,
+}
+
+/// 批次管理器：负责批次的持久化与后台执行
+pub struct BatchManager {
+    dir: PathBuf,
+    batches: Mutex<HashMap<String, MessageBatch>>,
+    /// 所有批次共享的并发名额
+    permits: Arc<Semaphore>,
+    /// 请求中大块内容的存储
+    blobs: BlobStore,
+}
+
+impl BatchManager {
+    /// 创建管理器并加载目录中已有的批次
+    pub fn new(dir: PathBuf, concurrency: usize) -> Self {
+        if let Err(e) = fs::create_dir_all(&dir) {
+            tracing::warn!("创建批次目录失败 {}: {}", dir.display(), e);
+        }
+
+        let mut batches = HashMap::new();
+        if let Ok(entries) = fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_none_or(|ext| ext != "json") {
+                    continue;
+                }
+                let content = match fs::read_to_string(&path) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        tracing::warn!("加载批次失败 {}: {}", path.display(), e);
+                        continue;
+                    }
+                };
+                match serde_json::from_str::<MessageBatch>(&content) {
+                    Ok(batch) => {
+                        batches.insert(batch.id.clone(), batch);
+                    }
+                    Err(e) => {
+                        tracing::warn!("加载批次失败 {}: {}", path.display(), e);
+                        crate::recovery::quarantine(&path, e);
+                    }
+                }
+            }
+        }
+        if !batches.is_empty() {
+            tracing::info!("已加载 {} 个批次", batches.len());
+        }
+
+        let manager = Self {
+            blobs: BlobStore::new(dir.join("blobs")),
+            dir,
+            batches: Mutex::new(batches),
+            permits: Arc::new(Semaphore::new(concurrency.max(1))),
+        };
+
+        // 按现有请求文件重建引用计数，并清理无人引用的内容
+        let ids: Vec<String> = manager.batches.lock().keys().cloned().collect();
+        for id in ids {
+            for mut request in manager.read_requests(&id) {
+                for slot in dedup_slots(&mut request.params) {
+                    manager.blobs.retain(slot);
+                }
+            }
+        }
+        let removed = manager.blobs.collect_garbage();
+        if removed > 0 {
+            tracing::info!("清理了 {} 个无人引用的批次内容", removed);
+        }
+        manager
+    }
+
+    fn read_requests(&self, id: &str) -> Vec<BatchRequest> {
+        match fs::read_to_string(self.requests_path(id)) {
+            Ok(content) => content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+            Err(e) => {
+                tracing::error!(batch_id = %id, "读取批次请求失败: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// 将请求中的大块内容替换为内容存储的引用
+    fn dedupe_params(&self, params: &mut serde_json::Value) -> std::io::Result<()> {
+        for slot in dedup_slots(params) {
+            if serde_json::to_vec(slot)?.len() >= DEDUP_MIN_BYTES {
+                *slot = self.blobs.put(slot)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 还原请求中的内容引用
+    fn resolve_params(&self, params: &mut serde_json::Value) -> std::io::Result<()> {
+        for slot in dedup_slots(params) {
+            *slot = self.blobs.resolve(slot.take())?;
+        }
+        Ok(())
+    }
+
+    fn batch_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn requests_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.requests.jsonl", id))
+    }
+
+    fn results_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.results.jsonl", id))
+    }
+
+    fn save_batch(&self, batch: &MessageBatch) {
+        let result = serde_json::to_string_pretty(batch)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(fs::write(self.batch_path(&batch.id), json)?));
+        if let Err(e) = result {
+            tracing::warn!("保存批次 {} 失败: {}", batch.id, e);
+        }
+    }
+
+    /// 获取批次
+    pub fn get(&self, id: &str) -> Option<MessageBatch> {
+        self.batches.lock().get(id).cloned()
+    }
+
+    /// 按创建时间倒序列出所有批次
+    pub fn list(&self) -> Vec<MessageBatch> {
+        let mut batches: Vec<MessageBatch> = self.batches.lock().values().cloned().collect();
+        batches.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+        batches
+    }
+
+    /// 创建批次：写入请求文件与元数据，并在后台开始执行
+    pub fn create(
+        self: &Arc<Self>,
+        requests: Vec<BatchRequest>,
+        tenant: Option<String>,
+        state: &AppState,
+    ) -> anyhow::Result<MessageBatch> {
+        let mut batch = MessageBatch::new(requests.len());
+        batch.tenant = tenant;
+
+        let count = requests.len();
+        let mut lines = String::new();
+        for mut request in requests {
+            self.dedupe_params(&mut request.params)?;
+            lines.push_str(&serde_json::to_string(&request)?);
+            lines.push('\n');
+        }
+        fs::write(self.requests_path(&batch.id), lines)?;
+        fs::write(self.results_path(&batch.id), "")?;
+        self.save_batch(&batch);
+        self.batches.lock().insert(batch.id.clone(), batch.clone());
+
+        tracing::info!(batch_id = %batch.id, requests = count, "创建消息批次");
+        self.spawn_run(batch.id.clone(), state.clone());
+        Ok(batch)
+    }
+
+    /// 请求取消批次：尚未开始的请求记为 canceled，执行中的请求会继续完成
+    pub fn cancel(&self, id: &str) -> Option<MessageBatch> {
+        let mut batches = self.batches.lock();
+        let batch = batches.get_mut(id)?;
+        if batch.processing_status == ProcessingStatus::InProgress {
+            batch.processing_status = ProcessingStatus::Canceling;
+            batch.cancel_initiated_at = Some(Utc::now());
+            self.save_batch(batch);
+        }
+        Some(batch.clone())
+    }
+
+    /// 删除已结束的批次及其文件
+    pub fn delete(&self, id: &str) -> DeleteOutcome {
+        let mut batches = self.batches.lock();
+        match batches.get(id) {
+            None => return DeleteOutcome::NotFound,
+            Some(batch) if batch.processing_status != ProcessingStatus::Ended => {
+                return DeleteOutcome::StillProcessing;
+            }
+            Some(_) => {}
+        }
+        batches.remove(id);
+        for mut request in self.read_requests(id) {
+            for slot in dedup_slots(&mut request.params) {
+                self.blobs.release(slot);
+            }
+        }
+        for path in [
+            self.batch_path(id),
+            self.requests_path(id),
+            self.results_path(id),
+        ] {
+            let _ = fs::remove_file(path);
+        }
+        DeleteOutcome::Deleted
+    }
+
+    /// 读取批次结果文件（JSONL）
+    pub async fn read_results(&self, id: &str) -> std::io::Result<Vec<u8>> {
+        tokio::fs::read(self.results_path(id)).await
+    }
+
+    /// 继续执行所有未结束的批次（启动时调用）
+    pub fn resume(self: &Arc<Self>, state: &AppState) {
+        let pending: Vec<String> = self
+            .batches
+            .lock()
+            .values()
+            .filter(|b| b.processing_status != ProcessingStatus::Ended)
+            .map(|b| b.id.clone())
+            .collect();
+        for id in pending {
+            tracing::info!(batch_id = %id, "继续执行未完成的消息批次");
+            self.spawn_run(id, state.clone());
+        }
+    }
+
+    fn spawn_run(self: &Arc<Self>, id: String, state: AppState) {
+        let manager = Arc::clone(self);
+        tokio::spawn(async move { manager.run(id, state).await });
+    }
+
+    /// 读取已产生结果的 custom_id，并据此重算请求计数
+    fn completed_requests(&self, id: &str, total: usize) -> (HashSet<String>, RequestCounts) {
+        let mut done = HashSet::new();
+        let mut counts = RequestCounts {
+            processing: total,
+            ..Default::default()
+        };
+        let content = fs::read_to_string(self.results_path(id)).unwrap_or_default();
+        for line in content.lines() {
+            if let Ok(result) = serde_json::from_str::<BatchResultLine>(line)
+                && done.insert(result.custom_id)
+            {
+                counts.record(&result.result);
+            }
+        }
+        (done, counts)
+    }
+
+    /// 追加一条结果并更新计数
+    fn record_result(&self, id: &str, custom_id: String, result: BatchResult) {
+        let line = BatchResultLine { custom_id, result };
+        let mut batches = self.batches.lock();
+        let write = serde_json::to_string(&line)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| {
+                let mut file = OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .open(self.results_path(id))?;
+                writeln!(file, "{}", json)?;
+                Ok(())
+            });
+        if let Err(e) = write {
+            tracing::error!(batch_id = %id, "写入批次结果失败: {}", e);
+        }
+        if let Some(batch) = batches.get_mut(id) {
+            batch.request_counts.record(&line.result);
+            self.save_batch(batch);
+        }
+    }
+
+    /// 尚未开始的请求不应执行时返回对应结果（已取消或已过期）
+    fn skipped_result(&self, id: &str) -> Option<BatchResult> {
+        let batches = self.batches.lock();
+        let batch = batches.get(id)?;
+        if batch.processing_status == ProcessingStatus::Canceling {
+            Some(BatchResult::Canceled)
+        } else if Utc::now() >= batch.expires_at {
+            Some(BatchResult::Expired)
+        } else {
+            None
+        }
+    }
+
+    async fn run(self: Arc<Self>, id: String, state: AppState) {
+        let requests = self.read_requests(&id);
+
+        let (done, counts) = self.completed_requests(&id, requests.len());
+        let tenant = self.batches.lock().get_mut(&id).and_then(|batch| {
+            batch.request_counts = counts;
+            batch.tenant.clone()
+        });
+
+        let mut tasks = JoinSet::new();
+        for mut request in requests
+            .into_iter()
+            .filter(|r| !done.contains(&r.custom_id))
+        {
+            let permit = Arc::clone(&self.permits)
+                .acquire_owned()
+                .await
+                .expect("batch semaphore closed");
+            if let Some(result) = self.skipped_result(&id) {
+                self.record_result(&id, request.custom_id, result);
+                continue;
+            }
+
+            if let Err(e) = self.resolve_params(&mut request.params) {
+                let error = error_object("api_error", &format!("Failed to load request: {}", e));
+                self.record_result(&id, request.custom_id, BatchResult::Errored { error });
+                continue;
+            }
+
+            let manager = Arc::clone(&self);
+            let state = state.clone();
+            let id = id.clone();
+            let (credential_ids, fallback) = tenant_credentials(&state, tenant.as_deref());
+            tasks.spawn(async move {
+                let result = MultiTokenManager::with_credential_scope(
+                    &credential_ids,
+                    fallback,
+                    execute_request(state, request.params),
+                )
+                .await;
+                manager.record_result(&id, request.custom_id, result);
+                drop(permit);
+            });
+        }
+        while tasks.join_next().await.is_some() {}
+
+        let mut batches = self.batches.lock();
+        if let Some(batch) = batches.get_mut(&id) {
+            batch.finish();
+            self.save_batch(batch);
+            tracing::info!(
+                batch_id = %id,
+                succeeded = batch.request_counts.succeeded,
+                errored = batch.request_counts.errored,
+                canceled = batch.request_counts.canceled,
+                expired = batch.request_counts.expired,
+                "消息批次执行结束"
+            );
+        }
+    }
+}
+
+/// 请求中按内容寻址存储的位置：system、tools 与每条消息的 content
+fn dedup_slots(params: &mut serde_json::Value) -> Vec<&mut serde_json::Value> {
+    let Some(obj) = params.as_object_mut() else {
+        return Vec::new();
+    };
+    let mut slots = Vec::new();
+    for (key, value) in obj.iter_mut() {
+        match key.as_str() {
+            "system" | "tools" => slots.push(value),
+            "messages" => slots.extend(
+                value
+                    .as_array_mut()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|m| m.get_mut("content")),
+            ),
+            _ => {}
+        }
+    }
+    slots
+}
+
+/// 租户当前绑定的凭据与回退方式（非租户批次或租户已删除时不限制凭据）
+fn tenant_credentials(state: &AppState, tenant: Option<&str>) -> (Vec<u64>, CredentialFallback) {
+    let (Some(tenant), Some(provider)) = (tenant, &state.kiro_provider) else {
+        return (Vec::new(), CredentialFallback::default());
+    };
+    provider
+        .token_manager()
+        .config()
+        .tenants
+        .get(tenant)
+        .map(|t| (t.credential_ids.clone(), t.credential_fallback))
+        .unwrap_or_default()
+}
+
+/// 以非流式方式执行单个请求
+async fn execute_request(state: AppState, params: serde_json::Value) -> BatchResult {
+    let mut payload: MessagesRequest = match serde_json::from_value(params) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return BatchResult::Errored {
+                error: error_object("invalid_request_error", &e.to_string()),
+            };
+        }
+    };
+    payload.stream = false;
+
+    let response = KiroProvider::with_request_tags(
+        None,
+        post_messages(State(state), None, JsonExtractor(payload)),
+    )
+    .await;
+    let status = response.status();
+    let body = match axum::body::to_bytes(response.into_body(), usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            return BatchResult::Errored {
+                error: error_object("api_error", &e.to_string()),
+            };
+        }
+    };
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+
+    if status.is_success() {
+        BatchResult::Succeeded { message: body }
+    } else {
+        BatchResult::Errored {
+            error: json!({"type": "error", "error": body["error"]}),
+        }
+    }
+}
+
+fn error_object(error_type: &str, message: &str) -> serde_json::Value {
+    json!({"type": "error", "error": {"type": error_type, "message": message}})
+}
+
+/// 校验批次请求：数量、custom_id 格式与唯一性、params 能否解析为 Messages 请求
+fn validate_requests(requests: &[BatchRequest]) -> Result<(), String> {
+    if requests.is_empty() {
+        return Err("requests: at least one request is required".to_string());
+    }
+    if requests.len() > MAX_REQUESTS_PER_BATCH {
+        return Err(format!(
+            "requests: a batch may contain at most {} requests",
+            MAX_REQUESTS_PER_BATCH
+        ));
+    }
+
+    let mut seen = HashSet::new();
+    for (index, request) in requests.iter().enumerate() {
+        let id = &request.custom_id;
+        let valid_id = !id.is_empty()
+            && id.len() <= MAX_CUSTOM_ID_LEN
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_id {
+            return Err(format!(
+                "requests.{}.custom_id: must be 1-{} characters of [a-zA-Z0-9_-]",
+                index, MAX_CUSTOM_ID_LEN
+            ));
+        }
+        if !seen.insert(id.as_str()) {
+            return Err(format!(
+                "requests.{}.custom_id: duplicate custom_id '{}'",
+                index, id
+            ));
+        }
+        if let Err(e) = MessagesRequest::deserialize(&request.params) {
+            return Err(format!("requests.{}.params: {}", index, e));
+        }
+    }
+    Ok(())
+}
+
+fn error_response(status: StatusCode, error_type: &str, message: impl Into<String>) -> Response {
+    (status, Json(ErrorResponse::new(error_type, message))).into_response()
+}
+
+fn not_found(id: &str) -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        "not_found_error",
+        format!("Message batch '{}' not found", id),
+    )
+}
+
+/// 批次存储不可用（没有缓存目录）
+fn unavailable() -> Response {
+    error_response(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "service_unavailable",
+        "Message batches are not available",
+    )
+}
+
+/// POST /v1/messages/batches
+#[utoipa::path(
+    post,
+    path = "/v1/messages/batches",
+    tag = "batches",
+    request_body = CreateBatchRequest,
+    responses((status = 200, body = MessageBatch), (status = 400, body = ErrorResponse))
+)]
+pub async fn create_batch(
+    State(state): State<AppState>,
+    tenant: Option<Extension<TenantName>>,
+    JsonExtractor(payload): JsonExtractor<CreateBatchRequest>,
+) -> Response {
+    let Some(manager) = &state.batches else {
+        return unavailable();
+    };
+    if let Err(message) = validate_requests(&payload.requests) {
+        return error_response(StatusCode::BAD_REQUEST, "invalid_request_error", message);
+    }
+    let tenant = tenant.map(|Extension(TenantName(name))| name);
+    match manager.create(payload.requests, tenant, &state) {
+        Ok(batch) => Json(batch).into_response(),
+        Err(e) => {
+            tracing::error!("创建消息批次失败: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "api_error",
+                format!("Failed to store message batch: {}", e),
+            )
+        }
+    }
+}
+
+/// GET /v1/messages/batches
+#[utoipa::path(
+    get,
+    path = "/v1/messages/batches",
+    tag = "batches",
+    params(ListBatchesQuery),
+    responses((status = 200, description = "`data`（MessageBatch 数组）、`has_more`、`first_id`、`last_id`", body = serde_json::Value))
+)]
+pub async fn list_batches(
+    State(state): State<AppState>,
+    Query(query): Query<ListBatchesQuery>,
+) -> Response {
+    let Some(manager) = &state.batches else {
+        return unavailable();
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_LIST_LIMIT)
+        .clamp(1, MAX_LIST_LIMIT);
+    let (data, has_more) = paginate(
+        manager.list(),
+        limit,
+        query.before_id.as_deref(),
+        query.after_id.as_deref(),
+    );
+
+    Json(json!({
+        "data": data,
+        "has_more": has_more,
+        "first_id": data.first().map(|b| &b.id),
+        "last_id": data.last().map(|b| &b.id),
+    }))
+    .into_response()
+}
+
+/// 按游标分页（列表为创建时间倒序）：`after_id` 取其后的一页，`before_id` 取其前的一页
+fn paginate(
+    batches: Vec<MessageBatch>,
+    limit: usize,
+    before_id: Option<&str>,
+    after_id: Option<&str>,
+) -> (Vec<MessageBatch>, bool) {
+    let position = |id: &str| batches.iter().position(|b| b.id == id);
+    if let Some(before) = before_id {
+        let end = position(before).unwrap_or(0);
+        let start = end.saturating_sub(limit);
+        return (batches[start..end].to_vec(), start > 0);
+    }
+    let start = after_id.and_then(position).map(|i| i + 1).unwrap_or(0);
+    let end = (start + limit).min(batches.len());
+    (batches[start..end].to_vec(), end < batches.len())
+}
+
+/// GET /v1/messages/batches/{id}
+#[utoipa::path(
+    get,
+    path = "/v1/messages/batches/{id}",
+    tag = "batches",
+    params(("id" = String, Path, description = "批次 ID")),
+    responses((status = 200, body = MessageBatch), (status = 404, body = ErrorResponse))
+)]
+pub async fn get_batch(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let Some(manager) = &state.batches else {
+        return unavailable();
+    };
+    match manager.get(&id) {
+        Some(batch) => Json(batch).into_response(),
+        None => not_found(&id),
+    }
+}
+
+/// POST /v1/messages/batches/{id}/cancel
+#[utoipa::path(
+    post,
+    path = "/v1/messages/batches/{id}/cancel",
+    tag = "batches",
+    params(("id" = String, Path, description = "批次 ID")),
+    responses((status = 200, body = MessageBatch), (status = 404, body = ErrorResponse))
+)]
+pub async fn cancel_batch(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let Some(manager) = &state.batches else {
+        return unavailable();
+    };
+    match manager.cancel(&id) {
+        Some(batch) => Json(batch).into_response(),
+        None => not_found(&id),
+    }
+}
+
+/// GET /v1/messages/batches/{id}/results
+#[utoipa::path(
+    get,
+    path = "/v1/messages/batches/{id}/results",
+    tag = "batches",
+    params(("id" = String, Path, description = "批次 ID")),
+    responses((status = 200, description = "JSONL，每行一个 BatchResultLine", content_type = "application/x-jsonl", body = BatchResultLine), (status = 400, description = "批次尚未结束", body = ErrorResponse), (status = 404, body = ErrorResponse))
+)]
+pub async fn get_batch_results(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let Some(manager) = &state.batches else {
+        return unavailable();
+    };
+    let Some(batch) = manager.get(&id) else {
+        return not_found(&id);
+    };
+    if batch.processing_status != ProcessingStatus::Ended {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            format!("Message batch '{}' has not finished processing", id),
+        );
+    }
+    match manager.read_results(&id).await {
+        Ok(content) => (
+            [(header::CONTENT_TYPE, "application/x-jsonl")],
+            Body::from(content),
+        )
+            .into_response(),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "api_error",
+            format!("Failed to read batch results: {}", e),
+        ),
+    }
+}
+
+/// DELETE /v1/messages/batches/{id}
+#[utoipa::path(
+    delete,
+    path = "/v1/messages/batches/{id}",
+    tag = "batches",
+    params(("id" = String, Path, description = "批次 ID")),
+    responses((status = 200, body = serde_json::Value), (status = 404, body = ErrorResponse), (status = 400, description = "批次仍在处理中", body = ErrorResponse))
+)]
+pub async fn delete_batch(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let Some(manager) = &state.batches else {
+        return unavailable();
+    };
+    match manager.delete(&id) {
+        DeleteOutcome::Deleted => {
+            Json(json!({"id": id, "type": "message_batch_deleted"})).into_response()
+        }
+        DeleteOutcome::NotFound => not_found(&id),
+        DeleteOutcome::StillProcessing => error_response(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            format!(
+                "Message batch '{}' is still processing; cancel it before deleting",
+                id
+            ),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(custom_id: &str) -> BatchRequest {
+        BatchRequest {
+            custom_id: custom_id.to_string(),
+            params: json!({
+                "model": "claude-sonnet-4",
+                "max_tokens": 16,
+                "messages": [{"role": "user", "content": "hi"}]
+            }),
+        }
+    }
+
+    #[test]
+    fn test_validate_requests() {
+        assert!(validate_requests(&[request("a"), request("b-2")]).is_ok());
+        assert!(validate_requests(&[]).is_err());
+        assert!(validate_requests(&[request("a"), request("a")]).is_err());
+        assert!(validate_requests(&[request("bad id")]).is_err());
+
+        let mut invalid = request("c");
+        invalid.params = json!({"model": "claude-sonnet-4"});
+        let error = validate_requests(&[invalid]).unwrap_err();
+        assert!(error.starts_with("requests.0.params"));
+    }
+
+    #[test]
+    fn test_results_resume_and_counts() {
+        let dir = std::env::temp_dir().join(format!("kiro-batches-{}", Uuid::new_v4()));
+        let manager = BatchManager::new(dir.clone(), 2);
+        let mut batch = MessageBatch::new(3);
+        batch.id = "msgbatch_test".to_string();
+        manager
+            .batches
+            .lock()
+            .insert(batch.id.clone(), batch.clone());
+
+        manager.record_result(
+            &batch.id,
+            "a".to_string(),
+            BatchResult::Succeeded { message: json!({}) },
+        );
+        manager.record_result(&batch.id, "b".to_string(), BatchResult::Canceled);
+
+        let counts = manager.get(&batch.id).unwrap().request_counts;
+        assert_eq!(counts.processing, 1);
+        assert_eq!(counts.succeeded, 1);
+        assert_eq!(counts.canceled, 1);
+
+        // 重启后从元数据与结果文件恢复
+        let reloaded = BatchManager::new(dir.clone(), 2);
+        assert_eq!(reloaded.get(&batch.id).unwrap().request_counts, counts);
+        let (done, recomputed) = reloaded.completed_requests(&batch.id, 3);
+        assert_eq!(done, HashSet::from(["a".to_string(), "b".to_string()]));
+        assert_eq!(recomputed, counts);
+
+        let content = fs::read_to_string(reloaded.results_path(&batch.id)).unwrap();
+        let first: serde_json::Value =
+            serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert_eq!(first["result"]["type"], "succeeded");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_large_content_is_deduplicated() {
+        let dir = std::env::temp_dir().join(format!("kiro-batches-{}", Uuid::new_v4()));
+        let manager = BatchManager::new(dir.clone(), 1);
+        let system = "You are a meticulous reviewer. ".repeat(100);
+        let params = |question: &str| {
+            json!({
+                "model": "claude-sonnet-4",
+                "max_tokens": 16,
+                "system": system,
+                "messages": [{"role": "user", "content": question}]
+            })
+        };
+
+        let mut first = params("short question");
+        let mut second = params("another short question");
+        manager.dedupe_params(&mut first).unwrap();
+        manager.dedupe_params(&mut second).unwrap();
+        assert_eq!(first["system"], second["system"]);
+        assert!(BlobStore::blob_ref(&first["system"]).is_some());
+        // 小内容保持原样
+        assert_eq!(first["messages"][0]["content"], "short question");
+        assert_eq!(fs::read_dir(dir.join("blobs")).unwrap().count(), 1);
+
+        manager.resolve_params(&mut first).unwrap();
+        assert_eq!(first, params("short question"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let batches: Vec<MessageBatch> = (0..5)
+            .map(|i| {
+                let mut batch = MessageBatch::new(1);
+                batch.id = format!("b{}", i);
+                batch
+            })
+            .collect();
+        let ids = |page: &[MessageBatch]| page.iter().map(|b| b.id.clone()).collect::<Vec<_>>();
+
+        let (page, has_more) = paginate(batches.clone(), 2, None, None);
+        assert_eq!(ids(&page), vec!["b0", "b1"]);
+        assert!(has_more);
+
+        let (page, has_more) = paginate(batches.clone(), 2, None, Some("b3"));
+        assert_eq!(ids(&page), vec!["b4"]);
+        assert!(!has_more);
+
+        let (page, has_more) = paginate(batches, 2, Some("b3"), None);
+        assert_eq!(ids(&page), vec!["b1", "b2"]);
+        assert!(has_more);
+    }
+}
@@ -0,0 +1,458 @@
+//! 服务端会话记忆
+//!
+//! 开启 `conversationMemory` 后，携带 `x-kiro-conversation-id` 头的 Messages 请求只需发送
+//! 最新的消息：代理按「API Key + 会话 ID」保存之前的对话，转发前把历史消息拼接到请求开头，
+//! 响应成功结束后再把本轮的消息和 assistant 回复追加到历史中。
+//! 历史超过 `conversationMemoryMaxMessages` 条时丢弃最早的消息，
+//! 超过 `conversationMemoryTtlSecs` 未使用的会话会过期。
+//! 响应未正常结束（上游出错、客户端断开）时不记录本轮。
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{HeaderValue, Request, header},
+    middleware::Next,
+    response::Response,
+};
+use futures::{StreamExt, stream};
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+
+use super::middleware::{ApiKeyId, AppState};
+use super::router::MAX_BODY_SIZE;
+use super::types::Message;
+
+/// 指定会话 ID 的请求头
+pub const CONVERSATION_ID_HEADER: &str = "x-kiro-conversation-id";
+
+/// 响应头：本次请求拼接的历史消息条数
+const CONVERSATION_HISTORY_HEADER: &str = "x-kiro-conversation-history";
+
+/// 会话 ID 最大长度
+const MAX_CONVERSATION_ID_LEN: usize = 128;
+
+/// 记录的会话数上限，超出时清理已过期的会话
+const MAX_CONVERSATIONS: usize = 10_000;
+
+struct Conversation {
+    messages: Vec<Message>,
+    expires_at: Instant,
+}
+
+/// 会话记忆存储
+#[derive(Default)]
+pub struct ConversationMemory {
+    conversations: Mutex<HashMap<String, Conversation>>,
+}
+
+impl ConversationMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 获取会话历史（已过期的会话视为不存在）
+    pub fn history(&self, key: &str) -> Vec<Message> {
+        let mut conversations = self.conversations.lock();
+        match conversations.get(key) {
+            Some(c) if c.expires_at > Instant::now() => c.messages.clone(),
+            Some(_) => {
+                conversations.remove(key);
+                Vec::new()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// 追加一轮对话并刷新过期时间
+    pub fn append(&self, key: &str, turn: Vec<Message>, ttl: Duration, max_messages: usize) {
+        let now = Instant::now();
+        let mut conversations = self.conversations.lock();
+        let conversation = conversations
+            .entry(key.to_string())
+            .or_insert_with(|| Conversation {
+                messages: Vec::new(),
+                expires_at: now,
+            });
+        if conversation.expires_at <= now {
+            conversation.messages.clear();
+        }
+        conversation.messages.extend(turn);
+        trim_history(&mut conversation.messages, max_messages);
+        conversation.expires_at = now + ttl;
+
+        if conversations.len() > MAX_CONVERSATIONS {
+            conversations.retain(|_, c| c.expires_at > now);
+        }
+    }
+}
+
+/// 保留最近的 `max_messages` 条消息，并保证历史以普通 user 消息开头
+/// （不能以 assistant 回复或缺少对应 tool_use 的 tool_result 开头）
+fn trim_history(messages: &mut Vec<Message>, max_messages: usize) {
+    if messages.len() > max_messages {
+        messages.drain(..messages.len() - max_messages);
+    }
+    let start = messages
+        .iter()
+        .position(|m| m.role == "user" && !is_tool_result(m))
+        .unwrap_or(messages.len());
+    messages.drain(..start);
+}
+
+fn is_tool_result(message: &Message) -> bool {
+    message.content.as_array().is_some_and(|blocks| {
+        blocks
+            .iter()
+            .any(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_result"))
+    })
+}
+
+/// 一轮待记录的对话
+struct PendingTurn {
+    memory: Arc<ConversationMemory>,
+    key: String,
+    messages: Vec<Message>,
+    ttl: Duration,
+    max_messages: usize,
+}
+
+impl PendingTurn {
+    fn commit(mut self, reply: Message) {
+        self.messages.push(reply);
+        self.memory
+            .append(&self.key, self.messages, self.ttl, self.max_messages);
+    }
+}
+
+/// 从非流式响应体中取出 assistant 回复（不保留 thinking 块）
+fn reply_from_message(body: &Value) -> Option<Message> {
+    let blocks: Vec<Value> = body
+        .get("content")?
+        .as_array()?
+        .iter()
+        .filter(|b| !is_thinking_block(b))
+        .cloned()
+        .collect();
+    (!blocks.is_empty()).then(|| Message {
+        role: "assistant".to_string(),
+        content: Value::Array(blocks),
+    })
+}
+
+fn is_thinking_block(block: &Value) -> bool {
+    matches!(
+        block.get("type").and_then(|t| t.as_str()),
+        Some("thinking" | "redacted_thinking")
+    )
+}
+
+/// 从 SSE 响应中重建 assistant 回复
+#[derive(Default)]
+struct ReplyCollector {
+    buffer: Vec<u8>,
+    blocks: BTreeMap<u64, Value>,
+    partial_json: HashMap<u64, String>,
+    completed: bool,
+    failed: bool,
+}
+
+impl ReplyCollector {
+    fn feed(&mut self, chunk: &[u8]) {
+        // 按字节缓冲，避免多字节字符被分块截断
+        self.buffer.extend_from_slice(chunk);
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let event: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            for line in String::from_utf8_lossy(&event).lines() {
+                if let Some(data) = line.strip_prefix("data: ")
+                    && let Ok(data) = serde_json::from_str::<Value>(data)
+                {
+                    self.handle_event(&data);
+                }
+            }
+        }
+    }
+
+    fn handle_event(&mut self, data: &Value) {
+        let index = data.get("index").and_then(|i| i.as_u64()).unwrap_or(0);
+        match data.get("type").and_then(|t| t.as_str()) {
+            Some("content_block_start") => {
+                if let Some(block) = data.get("content_block") {
+                    self.blocks.insert(index, block.clone());
+                }
+            }
+            Some("content_block_delta") => {
+                let Some(delta) = data.get("delta") else {
+                    return;
+                };
+                let text = |field: &str| delta.get(field).and_then(|v| v.as_str());
+                match (
+                    delta.get("type").and_then(|t| t.as_str()),
+                    self.blocks.get_mut(&index),
+                ) {
+                    (Some("text_delta"), Some(block)) => append_str(block, "text", text("text")),
+                    (Some("input_json_delta"), Some(_)) => self
+                        .partial_json
+                        .entry(index)
+                        .or_default()
+                        .push_str(text("partial_json").unwrap_or_default()),
+                    _ => {}
+                }
+            }
+            Some("content_block_stop") => {
+                if let (Some(json), Some(block)) = (
+                    self.partial_json.remove(&index),
+                    self.blocks.get_mut(&index),
+                ) {
+                    block["input"] = serde_json::from_str(&json).unwrap_or_else(|_| json!({}));
+                }
+            }
+            Some("message_stop") => self.completed = true,
+            Some("error") => self.failed = true,
+            _ => {}
+        }
+    }
+
+    fn into_reply(self) -> Option<Message> {
+        if !self.completed || self.failed {
+            return None;
+        }
+        reply_from_message(&json!({"content": self.blocks.into_values().collect::<Vec<_>>()}))
+    }
+}
+
+fn append_str(block: &mut Value, field: &str, text: Option<&str>) {
+    let current = block
+        .get(field)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    block[field] = Value::String(format!("{}{}", current, text.unwrap_or_default()));
+}
+
+/// 会话记忆中间件（挂在 Messages 端点上，需在认证之后执行）
+pub async fn conversation_memory_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(provider) = &state.kiro_provider else {
+        return next.run(request).await;
+    };
+    let config = provider.token_manager().config();
+    let conversation_id = request
+        .headers()
+        .get(CONVERSATION_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_CONVERSATION_ID_LEN);
+    let (true, Some(conversation_id)) = (config.conversation_memory, conversation_id) else {
+        return next.run(request).await;
+    };
+
+    let api_key_id = request
+        .extensions()
+        .get::<ApiKeyId>()
+        .map(|id| id.0.clone())
+        .unwrap_or_default();
+    let key = format!("{}:{}", api_key_id, conversation_id);
+
+    let (mut parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_BODY_SIZE).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("读取请求体失败: {}", e);
+            return next.run(Request::from_parts(parts, Body::empty())).await;
+        }
+    };
+    // 请求体无法解析时原样转发，由 Messages 端点返回错误
+    let mut payload: Value = match serde_json::from_slice(&bytes) {
+        Ok(payload) => payload,
+        Err(_) => {
+            return next
+                .run(Request::from_parts(parts, Body::from(bytes)))
+                .await;
+        }
+    };
+    let Some(messages) = payload
+        .get("messages")
+        .and_then(|m| serde_json::from_value::<Vec<Message>>(m.clone()).ok())
+    else {
+        return next
+            .run(Request::from_parts(parts, Body::from(bytes)))
+            .await;
+    };
+
+    let history = state.conversations.history(&key);
+    let history_len = history.len();
+    let mut full = history;
+    full.extend(messages.iter().cloned());
+    payload["messages"] = serde_json::to_value(&full).unwrap_or_default();
+    let stream = payload
+        .get("stream")
+        .and_then(|s| s.as_bool())
+        .unwrap_or(false);
+    tracing::debug!(
+        conversation_id = %conversation_id,
+        history = history_len,
+        "拼接会话历史"
+    );
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let body = Body::from(serde_json::to_vec(&payload).unwrap_or_default());
+    let mut response = next.run(Request::from_parts(parts, body)).await;
+    response
+        .headers_mut()
+        .insert(CONVERSATION_HISTORY_HEADER, HeaderValue::from(history_len));
+    if !response.status().is_success() {
+        return response;
+    }
+
+    let turn = PendingTurn {
+        memory: Arc::clone(&state.conversations),
+        key,
+        messages,
+        ttl: Duration::from_secs(config.conversation_memory_ttl_secs),
+        max_messages: config.conversation_memory_max_messages,
+    };
+    let (parts, body) = response.into_parts();
+    if stream {
+        Response::from_parts(parts, Body::from_stream(collect_stream_reply(body, turn)))
+    } else {
+        let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::warn!("读取响应体失败: {}", e);
+                return Response::from_parts(parts, Body::empty());
+            }
+        };
+        if let Some(reply) = serde_json::from_slice::<Value>(&bytes)
+            .ok()
+            .and_then(|body| reply_from_message(&body))
+        {
+            turn.commit(reply);
+        }
+        Response::from_parts(parts, Body::from(bytes))
+    }
+}
+
+/// 原样转发 SSE 响应，流正常结束后记录本轮对话
+fn collect_stream_reply(
+    body: Body,
+    turn: PendingTurn,
+) -> impl futures::Stream<Item = Result<Bytes, axum::Error>> {
+    stream::unfold(
+        (
+            body.into_data_stream(),
+            ReplyCollector::default(),
+            Some(turn),
+        ),
+        |(mut body, mut collector, mut turn)| async move {
+            match body.next().await {
+                Some(Ok(chunk)) => {
+                    collector.feed(&chunk);
+                    Some((Ok(chunk), (body, collector, turn)))
+                }
+                Some(Err(e)) => Some((Err(e), (body, collector, turn))),
+                None => {
+                    if let Some(turn) = turn.take()
+                        && let Some(reply) = collector.into_reply()
+                    {
+                        turn.commit(reply);
+                    }
+                    None
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: Value) -> Message {
+        Message {
+            role: role.to_string(),
+            content,
+        }
+    }
+
+    #[test]
+    fn test_append_trims_to_user_message() {
+        let memory = ConversationMemory::new();
+        let ttl = Duration::from_secs(60);
+        let tool_result = json!([{"type": "tool_result", "tool_use_id": "t1", "content": "ok"}]);
+        memory.append(
+            "k",
+            vec![
+                message("user", json!("first")),
+                message("assistant", json!([{"type": "tool_use", "id": "t1"}])),
+                message("user", tool_result),
+                message("assistant", json!("done")),
+                message("user", json!("second")),
+                message("assistant", json!("reply")),
+            ],
+            ttl,
+            4,
+        );
+
+        // 截断后以 tool_result 开头，需继续丢弃到下一条普通 user 消息
+        let history = memory.history("k");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].content, "second");
+        assert!(memory.history("other").is_empty());
+
+        memory.append(
+            "k",
+            vec![message("user", json!("third"))],
+            Duration::ZERO,
+            10,
+        );
+        assert!(memory.history("k").is_empty());
+    }
+
+    #[test]
+    fn test_collect_stream_reply() {
+        let events = [
+            json!({"type": "message_start", "message": {}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": ""}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": "Hel"}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": "lo"}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "content_block_start", "index": 2, "content_block": {"type": "tool_use", "id": "t1", "name": "f", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "input_json_delta", "partial_json": "{\"a\":"}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "input_json_delta", "partial_json": "1}"}}),
+            json!({"type": "content_block_stop", "index": 2}),
+            json!({"type": "message_stop"}),
+        ];
+        let sse: String = events
+            .iter()
+            .map(|e| format!("event: {}\ndata: {}\n\n", e["type"].as_str().unwrap(), e))
+            .collect();
+
+        // 分块边界不与事件边界对齐
+        let mut collector = ReplyCollector::default();
+        for chunk in sse.as_bytes().chunks(7) {
+            collector.feed(chunk);
+        }
+        let reply = collector.into_reply().unwrap();
+        assert_eq!(reply.role, "assistant");
+        assert_eq!(
+            reply.content,
+            json!([
+                {"type": "text", "text": "Hello"},
+                {"type": "tool_use", "id": "t1", "name": "f", "input": {"a": 1}}
+            ])
+        );
+
+        let mut failed = ReplyCollector::default();
+        failed.feed(b"event: error\ndata: {\"type\":\"error\"}\n\n");
+        failed.feed(b"event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n");
+        assert!(failed.into_reply().is_none());
+    }
+}
@@ -2,6 +2,10 @@
 //!
 //! 负责将 Anthropic API 请求格式转换为 Kiro API 请求格式
 
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Serialize;
 use uuid::Uuid;
 
 use crate::kiro::model::requests::conversation::{
@@ -12,7 +16,7 @@ use crate::kiro::model::requests::tool::{
     InputSchema, Tool, ToolResult, ToolSpecification, ToolUseEntry,
 };
 
-use super::types::{ContentBlock, MessagesRequest};
+use super::types::{ContentBlock, ImageSource, MessagesRequest};
 
 /// 规范化 JSON Schema，修复 MCP 工具定义中常见的类型问题
 ///
@@ -29,14 +33,26 @@ fn normalize_json_schema(schema: serde_json::Value) -> serde_json::Value {
     };
 
     // type（必须是字符串）
-    if !obj.get("type").and_then(|v| v.as_str()).is_some_and(|s| !s.is_empty()) {
-        obj.insert("type".to_string(), serde_json::Value::String("object".to_string()));
+    if !obj
+        .get("type")
+        .and_then(|v| v.as_str())
+        .is_some_and(|s| !s.is_empty())
+    {
+        obj.insert(
+            "type".to_string(),
+            serde_json::Value::String("object".to_string()),
+        );
     }
 
     // properties（必须是 object）
     match obj.get("properties") {
         Some(serde_json::Value::Object(_)) => {}
-        _ => { obj.insert("properties".to_string(), serde_json::Value::Object(serde_json::Map::new())); }
+        _ => {
+            obj.insert(
+                "properties".to_string(),
+                serde_json::Value::Object(serde_json::Map::new()),
+            );
+        }
     }
 
     // required（必须是 string 数组）
@@ -53,7 +69,12 @@ fn normalize_json_schema(schema: serde_json::Value) -> serde_json::Value {
     // additionalProperties（允许 bool 或 object，其他按 true 处理）
     match obj.get("additionalProperties") {
         Some(serde_json::Value::Bool(_)) | Some(serde_json::Value::Object(_)) => {}
-        _ => { obj.insert("additionalProperties".to_string(), serde_json::Value::Bool(true)); }
+        _ => {
+            obj.insert(
+                "additionalProperties".to_string(),
+                serde_json::Value::Bool(true),
+            );
+        }
     }
 
     serde_json::Value::Object(obj)
@@ -102,11 +123,106 @@ pub fn map_model(model: &str) -> Option<String> {
     }
 }
 
+/// 转换告警
+///
+/// 转换器丢弃或改写了请求中的内容时记录，随响应返回给客户端，
+/// 便于客户端开发者了解行为与官方 Anthropic API 不一致的原因
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConversionWarning {
+    /// 机器可读的告警代码，如 `prefill_dropped`
+    pub code: &'static str,
+    /// 告警说明
+    pub message: String,
+}
+
+impl ConversionWarning {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// 转换选项
+///
+/// 来自 config.json 的可调参数，默认值与不做任何限制时的行为一致
+#[derive(Debug, Clone, Default)]
+pub struct ConversionOptions {
+    /// 单个 tool_result 文本的最大字符数，超出部分会被截断并插入显式标记
+    pub tool_result_max_chars: Option<usize>,
+    /// 追加到系统提示词的运行时上下文（当前日期、时区、语言区域）
+    pub runtime_context: Option<String>,
+}
+
+impl ConversionOptions {
+    /// 从应用配置构建转换选项
+    pub fn from_config(config: &crate::model::config::Config) -> Self {
+        let runtime_context = config.system_prompt_inject_date.then(|| {
+            let offset = config
+                .system_prompt_utc_offset
+                .as_deref()
+                .and_then(|s| {
+                    let parsed = parse_utc_offset(s);
+                    if parsed.is_none() {
+                        tracing::warn!("无效的 systemPromptUtcOffset: {}，回退到 UTC", s);
+                    }
+                    parsed
+                })
+                .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+            render_runtime_context(Utc::now(), offset, config.system_prompt_locale.as_deref())
+        });
+
+        Self {
+            tool_result_max_chars: config.tool_result_max_chars.filter(|&n| n > 0),
+            runtime_context,
+        }
+    }
+}
+
+/// 解析 UTC 偏移，支持 `+08:00`、`-0530`、`+8` 等格式
+fn parse_utc_offset(s: &str) -> Option<FixedOffset> {
+    let s = s.trim();
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// 渲染注入系统提示词的运行时上下文
+fn render_runtime_context(now: DateTime<Utc>, offset: FixedOffset, locale: Option<&str>) -> String {
+    let local = now.with_timezone(&offset);
+    let mut context = format!(
+        "Current date: {} ({}), timezone: UTC{}.",
+        local.format("%Y-%m-%d"),
+        local.format("%A"),
+        offset
+    );
+    if let Some(locale) = locale.filter(|l| !l.trim().is_empty()) {
+        context.push_str(&format!(" User locale: {}.", locale.trim()));
+    }
+    context
+}
+
 /// 转换结果
 #[derive(Debug)]
 pub struct ConversionResult {
     /// 转换后的 Kiro 请求
     pub conversation_state: ConversationState,
+    /// 转换过程中产生的告警
+    pub warnings: Vec<ConversionWarning>,
 }
 
 /// 转换错误
@@ -114,6 +230,8 @@ pub struct ConversionResult {
 pub enum ConversionError {
     UnsupportedModel(String),
     EmptyMessages,
+    /// 图片内容块无效（格式不支持、超过大小上限、数据损坏等）
+    InvalidImage(String),
 }
 
 impl std::fmt::Display for ConversionError {
@@ -121,6 +239,7 @@ impl std::fmt::Display for ConversionError {
         match self {
             ConversionError::UnsupportedModel(model) => write!(f, "模型不支持: {}", model),
             ConversionError::EmptyMessages => write!(f, "消息列表为空"),
+            ConversionError::InvalidImage(message) => write!(f, "图片无效: {}", message),
         }
     }
 }
@@ -185,8 +304,17 @@ fn create_placeholder_tool(name: &str) -> Tool {
     }
 }
 
-/// 将 Anthropic 请求转换为 Kiro 请求
+/// 将 Anthropic 请求转换为 Kiro 请求（使用默认转换选项）
+#[cfg(test)]
 pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, ConversionError> {
+    convert_request_with_options(req, &ConversionOptions::default())
+}
+
+/// 将 Anthropic 请求转换为 Kiro 请求
+pub fn convert_request_with_options(
+    req: &MessagesRequest,
+    options: &ConversionOptions,
+) -> Result<ConversionResult, ConversionError> {
     // 1. 映射模型
     let model_id = map_model(&req.model)
         .ok_or_else(|| ConversionError::UnsupportedModel(req.model.clone()))?;
@@ -196,6 +324,8 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
         return Err(ConversionError::EmptyMessages);
     }
 
+    let mut warnings = Vec::new();
+
     // 2.5. 预处理 prefill：如果末尾是 assistant，静默丢弃并截断到最后一条 user
     // Claude 4.x 已弃用 assistant prefill，Kiro API 也不支持
     let messages: &[_] = if req.messages.last().is_some_and(|m| m.role != "user") {
@@ -205,11 +335,29 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
             .iter()
             .rposition(|m| m.role == "user")
             .ok_or(ConversionError::EmptyMessages)?;
+        warnings.push(ConversionWarning::new(
+            "prefill_dropped",
+            "Trailing assistant message (prefill) is not supported and was dropped",
+        ));
         &req.messages[..=last_user_idx]
     } else {
         &req.messages
     };
 
+    // Kiro API 不支持 tool_choice，除 auto 以外的取值都无法生效
+    if let Some(tool_choice) = &req.tool_choice {
+        let choice_type = tool_choice.get("type").and_then(|v| v.as_str());
+        if choice_type != Some("auto") {
+            warnings.push(ConversionWarning::new(
+                "tool_choice_ignored",
+                format!(
+                    "tool_choice {} is not supported upstream and was ignored",
+                    choice_type.unwrap_or("<invalid>")
+                ),
+            ));
+        }
+    }
+
     // 3. 生成会话 ID 和代理 ID
     // 优先从 metadata.user_id 中提取 session UUID 作为 conversationId
     let conversation_id = req
@@ -225,13 +373,44 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
 
     // 5. 处理最后一条消息作为 current_message（经过 prefill 预处理，末尾必为 user）
     let last_message = messages.last().unwrap();
-    let (text_content, images, tool_results) = process_message_content(&last_message.content)?;
+    let (text_content, images, mut tool_results) =
+        process_message_content(&last_message.content, &mut warnings)?;
 
     // 6. 转换工具定义
-    let mut tools = convert_tools(&req.tools);
+    let mut tools = convert_tools(&req.tools, &mut warnings);
 
     // 7. 构建历史消息（需要先构建，以便收集历史中使用的工具）
-    let mut history = build_history(req, messages, &model_id)?;
+    let mut history = build_history(req, messages, &model_id, options, &mut warnings)?;
+
+    // 7.5. 截断过大的 tool_result，避免上游因请求过大返回不透明的错误
+    if let Some(max_chars) = options.tool_result_max_chars {
+        let mut truncated = truncate_tool_results(&mut tool_results, max_chars);
+        for msg in history.iter_mut() {
+            if let Message::User(user_msg) = msg {
+                truncated += truncate_tool_results(
+                    &mut user_msg
+                        .user_input_message
+                        .user_input_message_context
+                        .tool_results,
+                    max_chars,
+                );
+            }
+        }
+        if truncated > 0 {
+            tracing::info!(
+                "截断了 {} 个超过 {} 字符的 tool_result",
+                truncated,
+                max_chars
+            );
+            warnings.push(ConversionWarning::new(
+                "tool_result_truncated",
+                format!(
+                    "{} tool_result block(s) exceeded {} characters and were truncated",
+                    truncated, max_chars
+                ),
+            ));
+        }
+    }
 
     // 8. 验证并过滤 tool_use/tool_result 配对
     // 移除孤立的 tool_result（没有对应的 tool_use）
@@ -239,6 +418,26 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
     let (validated_tool_results, orphaned_tool_use_ids) =
         validate_tool_pairing(&history, &tool_results);
 
+    let dropped_results = tool_results.len() - validated_tool_results.len();
+    if dropped_results > 0 {
+        warnings.push(ConversionWarning::new(
+            "tool_result_dropped",
+            format!(
+                "{} tool_result block(s) without a matching tool_use were dropped",
+                dropped_results
+            ),
+        ));
+    }
+    if !orphaned_tool_use_ids.is_empty() {
+        warnings.push(ConversionWarning::new(
+            "tool_use_dropped",
+            format!(
+                "{} tool_use block(s) without a matching tool_result were removed from history",
+                orphaned_tool_use_ids.len()
+            ),
+        ));
+    }
+
     // 9. 从历史中移除孤立的 tool_use（Kiro API 要求 tool_use 必须有对应的 tool_result）
     remove_orphaned_tool_uses(&mut history, &orphaned_tool_use_ids);
 
@@ -288,7 +487,10 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
         .with_current_message(current_message)
         .with_history(history);
 
-    Ok(ConversionResult { conversation_state })
+    Ok(ConversionResult {
+        conversation_state,
+        warnings,
+    })
 }
 
 /// 确定聊天触发类型
@@ -300,6 +502,7 @@ fn determine_chat_trigger_type(_req: &MessagesRequest) -> String {
 /// 处理消息内容，提取文本、图片和工具结果
 fn process_message_content(
     content: &serde_json::Value,
+    warnings: &mut Vec<ConversionWarning>,
 ) -> Result<(String, Vec<KiroImage>, Vec<ToolResult>), ConversionError> {
     let mut text_parts = Vec::new();
     let mut images = Vec::new();
@@ -320,9 +523,7 @@ fn process_message_content(
                         }
                         "image" => {
                             if let Some(source) = block.source {
-                                if let Some(format) = get_image_format(&source.media_type) {
-                                    images.push(KiroImage::from_base64(format, source.data));
-                                }
+                                images.push(convert_image(source)?);
                             }
                         }
                         "tool_result" => {
@@ -344,7 +545,12 @@ fn process_message_content(
                         "tool_use" => {
                             // tool_use 在 assistant 消息中处理，这里忽略
                         }
-                        _ => {}
+                        other => {
+                            warnings.push(ConversionWarning::new(
+                                "content_block_dropped",
+                                format!("Unsupported content block type {} was dropped", other),
+                            ));
+                        }
                     }
                 }
             }
@@ -355,18 +561,60 @@ fn process_message_content(
     Ok((text_parts.join("\n"), images, tool_results))
 }
 
-/// 从 media_type 获取图片格式
-fn get_image_format(media_type: &str) -> Option<String> {
+/// 单张图片（解码后）的大小上限，与 Anthropic API 一致
+pub(crate) const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// 从 media_type 获取图片格式，不支持时返回错误信息
+pub(crate) fn get_image_format(media_type: &str) -> Result<String, String> {
     match media_type {
-        "image/jpeg" => Some("jpeg".to_string()),
-        "image/png" => Some("png".to_string()),
-        "image/gif" => Some("gif".to_string()),
-        "image/webp" => Some("webp".to_string()),
-        _ => None,
+        "image/jpeg" => Ok("jpeg".to_string()),
+        "image/png" => Ok("png".to_string()),
+        "image/gif" => Ok("gif".to_string()),
+        "image/webp" => Ok("webp".to_string()),
+        _ => Err(format!(
+            "不支持的图片格式 {}（支持 image/jpeg、image/png、image/gif、image/webp）",
+            media_type
+        )),
     }
 }
 
+/// 图片超过大小上限时的错误信息
+pub(crate) fn image_too_large(bytes: usize) -> String {
+    format!("图片大小 {} 字节超过上限 {} 字节", bytes, MAX_IMAGE_BYTES)
+}
+
+/// 校验图片来源并转换为 Kiro 图片
+///
+/// URL 来源的图片应已在转换前被下载并改写为 base64（见 `images`），此处仍为 URL 说明未开启下载
+fn convert_image(source: ImageSource) -> Result<KiroImage, ConversionError> {
+    match source.source_type.as_str() {
+        "base64" => {}
+        "url" => {
+            return Err(ConversionError::InvalidImage(
+                "不支持 URL 来源的图片（未开启 fetchImageUrls）".to_string(),
+            ));
+        }
+        other => {
+            return Err(ConversionError::InvalidImage(format!(
+                "不支持的图片来源类型: {}",
+                other
+            )));
+        }
+    }
+
+    let format = get_image_format(&source.media_type).map_err(ConversionError::InvalidImage)?;
+    let bytes = STANDARD
+        .decode(&source.data)
+        .map_err(|_| ConversionError::InvalidImage("图片数据不是有效的 base64".to_string()))?;
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(ConversionError::InvalidImage(image_too_large(bytes.len())));
+    }
+    Ok(KiroImage::from_base64(format, source.data))
+}
+
 /// 提取工具结果内容
+///
+/// 工具结果中的图片无法传给上游，以显式标记代替，避免模型误以为工具没有返回内容
 fn extract_tool_result_content(content: &Option<serde_json::Value>) -> String {
     match content {
         Some(serde_json::Value::String(s)) => s.clone(),
@@ -375,6 +623,8 @@ fn extract_tool_result_content(content: &Option<serde_json::Value>) -> String {
             for item in arr {
                 if let Some(text) = item.get("text").and_then(|v| v.as_str()) {
                     parts.push(text.to_string());
+                } else if item.get("type").and_then(|v| v.as_str()) == Some("image") {
+                    parts.push(TOOL_RESULT_IMAGE_OMITTED.to_string());
                 }
             }
             parts.join("\n")
@@ -384,6 +634,58 @@ fn extract_tool_result_content(content: &Option<serde_json::Value>) -> String {
     }
 }
 
+/// 工具结果中图片的占位标记
+const TOOL_RESULT_IMAGE_OMITTED: &str = "[image omitted by proxy]";
+
+/// 截断超长文本：保留首尾各一半内容，在中间插入显式截断标记
+///
+/// 未超过 `max_chars` 时返回 `None`
+fn truncate_tool_result_text(text: &str, max_chars: usize) -> Option<String> {
+    let total = text.chars().count();
+    if total <= max_chars {
+        return None;
+    }
+
+    let head_chars = max_chars / 2;
+    let tail_chars = max_chars - head_chars;
+    let head_end = text
+        .char_indices()
+        .nth(head_chars)
+        .map_or(text.len(), |(i, _)| i);
+    let tail_start = text
+        .char_indices()
+        .nth(total - tail_chars)
+        .map_or(text.len(), |(i, _)| i);
+
+    Some(format!(
+        "{}\n\n[... {} characters truncated by proxy ...]\n\n{}",
+        &text[..head_end],
+        total - max_chars,
+        &text[tail_start..]
+    ))
+}
+
+/// 截断一组 tool_result 中的超长文本，返回被截断的 tool_result 数量
+fn truncate_tool_results(results: &mut [ToolResult], max_chars: usize) -> usize {
+    let mut truncated = 0;
+    for result in results.iter_mut() {
+        let mut changed = false;
+        for item in result.content.iter_mut() {
+            if let Some(serde_json::Value::String(text)) = item.get_mut("text")
+                && let Some(shortened) = truncate_tool_result_text(text, max_chars)
+            {
+                *text = shortened;
+                changed = true;
+            }
+        }
+        if changed {
+            tracing::debug!("tool_result 已截断，tool_use_id={}", result.tool_use_id);
+            truncated += 1;
+        }
+    }
+    truncated
+}
+
 /// 验证并过滤 tool_use/tool_result 配对
 ///
 /// 收集所有 tool_use_id，验证 tool_result 是否匹配
@@ -505,7 +807,10 @@ fn remove_orphaned_tool_uses(
 }
 
 /// 转换工具定义
-fn convert_tools(tools: &Option<Vec<super::types::Tool>>) -> Vec<Tool> {
+fn convert_tools(
+    tools: &Option<Vec<super::types::Tool>>,
+    warnings: &mut Vec<ConversionWarning>,
+) -> Vec<Tool> {
     let Some(tools) = tools else {
         return Vec::new();
     };
@@ -528,7 +833,16 @@ fn convert_tools(tools: &Option<Vec<super::types::Tool>>) -> Vec<Tool> {
 
             // 限制描述长度为 10000 字符（安全截断 UTF-8，单次遍历）
             let description = match description.char_indices().nth(10000) {
-                Some((idx, _)) => description[..idx].to_string(),
+                Some((idx, _)) => {
+                    warnings.push(ConversionWarning::new(
+                        "tool_description_truncated",
+                        format!(
+                            "Description of tool {} was truncated to 10000 characters",
+                            t.name
+                        ),
+                    ));
+                    description[..idx].to_string()
+                }
                 None => description,
             };
 
@@ -536,7 +850,9 @@ fn convert_tools(tools: &Option<Vec<super::types::Tool>>) -> Vec<Tool> {
                 tool_specification: ToolSpecification {
                     name: t.name.clone(),
                     description,
-                    input_schema: InputSchema::from_json(normalize_json_schema(serde_json::json!(t.input_schema))),
+                    input_schema: InputSchema::from_json(normalize_json_schema(serde_json::json!(
+                        t.input_schema
+                    ))),
                 },
             }
         })
@@ -579,20 +895,37 @@ fn has_thinking_tags(content: &str) -> bool {
 ///   注意：该切片与 `req.messages` 可能不同（prefill 时会截断末尾的 assistant 消息），
 ///   调用方应始终使用此参数而非 `req.messages`。
 /// * `model_id` - 已映射的 Kiro 模型 ID
-fn build_history(req: &MessagesRequest, messages: &[super::types::Message], model_id: &str) -> Result<Vec<Message>, ConversionError> {
+/// * `options` - 转换选项（运行时上下文注入等）
+/// * `warnings` - 转换告警收集器
+fn build_history(
+    req: &MessagesRequest,
+    messages: &[super::types::Message],
+    model_id: &str,
+    options: &ConversionOptions,
+    warnings: &mut Vec<ConversionWarning>,
+) -> Result<Vec<Message>, ConversionError> {
     let mut history = Vec::new();
 
     // 生成thinking前缀（如果需要）
     let thinking_prefix = generate_thinking_prefix(req);
+    let runtime_context = options.runtime_context.as_deref();
 
     // 1. 处理系统消息
     if let Some(ref system) = req.system {
-        let system_content: String = system
+        let mut system_content: String = system
             .iter()
             .map(|s| s.text.clone())
             .collect::<Vec<_>>()
             .join("\n");
 
+        // 追加运行时上下文（当前日期等）
+        if let Some(context) = runtime_context {
+            if !system_content.is_empty() {
+                system_content.push('\n');
+            }
+            system_content.push_str(context);
+        }
+
         if !system_content.is_empty() {
             // 追加分块写入策略到系统消息
             let system_content = format!("{}\n{}", system_content, SYSTEM_CHUNKED_POLICY);
@@ -615,9 +948,14 @@ fn build_history(req: &MessagesRequest, messages: &[super::types::Message], mode
             let assistant_msg = HistoryAssistantMessage::new("I will follow these instructions.");
             history.push(Message::Assistant(assistant_msg));
         }
-    } else if let Some(ref prefix) = thinking_prefix {
-        // 没有系统消息但有thinking配置，插入新的系统消息
-        let user_msg = HistoryUserMessage::new(prefix.clone(), model_id);
+    } else if thinking_prefix.is_some() || runtime_context.is_some() {
+        // 没有系统消息但有thinking配置或运行时上下文，插入新的系统消息
+        let content = [thinking_prefix.as_deref(), runtime_context]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n");
+        let user_msg = HistoryUserMessage::new(content, model_id);
         history.push(Message::User(user_msg));
 
         let assistant_msg = HistoryAssistantMessage::new("I will follow these instructions.");
@@ -647,7 +985,7 @@ fn build_history(req: &MessagesRequest, messages: &[super::types::Message], mode
         } else if msg.role == "assistant" {
             // 先处理累积的 user 消息
             if !user_buffer.is_empty() {
-                let merged_user = merge_user_messages(&user_buffer, model_id)?;
+                let merged_user = merge_user_messages(&user_buffer, model_id, warnings)?;
                 history.push(Message::User(merged_user));
                 user_buffer.clear();
             }
@@ -664,7 +1002,7 @@ fn build_history(req: &MessagesRequest, messages: &[super::types::Message], mode
 
     // 处理结尾的孤立 user 消息
     if !user_buffer.is_empty() {
-        let merged_user = merge_user_messages(&user_buffer, model_id)?;
+        let merged_user = merge_user_messages(&user_buffer, model_id, warnings)?;
         history.push(Message::User(merged_user));
 
         // 自动配对一个 "OK" 的 assistant 响应
@@ -679,13 +1017,14 @@ fn build_history(req: &MessagesRequest, messages: &[super::types::Message], mode
 fn merge_user_messages(
     messages: &[&super::types::Message],
     model_id: &str,
+    warnings: &mut Vec<ConversionWarning>,
 ) -> Result<HistoryUserMessage, ConversionError> {
     let mut content_parts = Vec::new();
     let mut all_images = Vec::new();
     let mut all_tool_results = Vec::new();
 
     for msg in messages {
-        let (text, images, tool_results) = process_message_content(&msg.content)?;
+        let (text, images, tool_results) = process_message_content(&msg.content, warnings)?;
         if !text.is_empty() {
             content_parts.push(text);
         }
@@ -1459,9 +1798,15 @@ mod tests {
 
         let content = &result.assistant_response_message.content;
         assert!(content.contains("<thinking>"), "应包含 thinking 标签");
-        assert!(content.contains("Let me read that file"), "应包含第二条消息的 text 内容");
+        assert!(
+            content.contains("Let me read that file"),
+            "应包含第二条消息的 text 内容"
+        );
 
-        let tool_uses = result.assistant_response_message.tool_uses.expect("应有 tool_uses");
+        let tool_uses = result
+            .assistant_response_message
+            .tool_uses
+            .expect("应有 tool_uses");
         assert_eq!(tool_uses.len(), 1);
         assert_eq!(tool_uses[0].tool_use_id, "toolu_01ABC");
     }
@@ -1511,7 +1856,11 @@ mod tests {
         };
 
         let result = convert_request(&req);
-        assert!(result.is_ok(), "连续 assistant 消息场景不应报错: {:?}", result.err());
+        assert!(
+            result.is_ok(),
+            "连续 assistant 消息场景不应报错: {:?}",
+            result.err()
+        );
 
         let state = result.unwrap().conversation_state;
         let mut found_tool_use = false;
@@ -1527,4 +1876,266 @@ mod tests {
         }
         assert!(found_tool_use, "合并后的 assistant 消息应包含 tool_use");
     }
+
+    #[test]
+    fn test_convert_request_no_warnings_for_plain_request() {
+        let req: MessagesRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 1024,
+            "messages": [{"role": "user", "content": "Hello"}]
+        }))
+        .unwrap();
+
+        let result = convert_request(&req).unwrap();
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_convert_request_warns_on_dropped_content() {
+        let req: MessagesRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 1024,
+            "tool_choice": {"type": "any"},
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "Look at this"},
+                    {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "AAAA"}}
+                ]},
+                {"role": "assistant", "content": "Sure, "}
+            ]
+        }))
+        .unwrap();
+
+        let result = convert_request(&req).unwrap();
+        let codes: Vec<&str> = result.warnings.iter().map(|w| w.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                "prefill_dropped",
+                "tool_choice_ignored",
+                "content_block_dropped"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convert_request_images() {
+        let request = |source: serde_json::Value| {
+            let req: MessagesRequest = serde_json::from_value(serde_json::json!({
+                "model": "claude-sonnet-4",
+                "max_tokens": 1024,
+                "messages": [{"role": "user", "content": [
+                    {"type": "text", "text": "What is this?"},
+                    {"type": "image", "source": source}
+                ]}]
+            }))
+            .unwrap();
+            convert_request(&req)
+        };
+        let invalid = |source: serde_json::Value| match request(source) {
+            Err(ConversionError::InvalidImage(message)) => message,
+            other => panic!("expected InvalidImage, got {:?}", other.map(|_| ())),
+        };
+
+        let result = request(serde_json::json!({
+            "type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="
+        }))
+        .unwrap();
+        let images = &result
+            .conversation_state
+            .current_message
+            .user_input_message
+            .images;
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].format, "png");
+
+        let oversized = STANDARD.encode(vec![0u8; MAX_IMAGE_BYTES + 1]);
+        let cases = [
+            (
+                serde_json::json!({"type": "base64", "media_type": "image/bmp", "data": "AAAA"}),
+                "image/bmp",
+            ),
+            (
+                serde_json::json!({"type": "base64", "media_type": "image/png", "data": "@@@"}),
+                "base64",
+            ),
+            (
+                serde_json::json!({"type": "base64", "media_type": "image/png", "data": oversized}),
+                "超过上限",
+            ),
+            // 未下载的 URL 图片
+            (
+                serde_json::json!({"type": "url", "url": "https://example.com/cat.png"}),
+                "fetchImageUrls",
+            ),
+        ];
+        for (source, expected) in cases {
+            let message = invalid(source);
+            assert!(message.contains(expected), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_convert_request_warns_on_orphaned_tool_result() {
+        let req: MessagesRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 1024,
+            "tool_choice": {"type": "auto"},
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_missing", "content": "result"}
+                ]}
+            ]
+        }))
+        .unwrap();
+
+        let result = convert_request(&req).unwrap();
+        let codes: Vec<&str> = result.warnings.iter().map(|w| w.code).collect();
+        assert_eq!(codes, vec!["tool_result_dropped"]);
+    }
+
+    #[test]
+    fn test_truncate_tool_result_text() {
+        assert_eq!(truncate_tool_result_text("short", 10), None);
+
+        let text = "a".repeat(50) + &"b".repeat(50);
+        let truncated = truncate_tool_result_text(&text, 20).unwrap();
+        assert!(truncated.starts_with(&"a".repeat(10)));
+        assert!(truncated.ends_with(&"b".repeat(10)));
+        assert!(truncated.contains("[... 80 characters truncated by proxy ...]"));
+    }
+
+    #[test]
+    fn test_truncate_tool_result_text_multibyte() {
+        let text = "你好".repeat(20);
+        let truncated = truncate_tool_result_text(&text, 5).unwrap();
+        assert!(truncated.starts_with("你好"));
+        assert!(truncated.ends_with("好你好"));
+    }
+
+    #[test]
+    fn test_extract_tool_result_content_image_marker() {
+        let content = Some(serde_json::json!([
+            {"type": "text", "text": "screenshot taken"},
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}}
+        ]));
+        assert_eq!(
+            extract_tool_result_content(&content),
+            format!("screenshot taken\n{}", TOOL_RESULT_IMAGE_OMITTED)
+        );
+    }
+
+    #[test]
+    fn test_convert_request_truncates_tool_results() {
+        let req: MessagesRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 1024,
+            "messages": [
+                {"role": "user", "content": "Read the log"},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "read", "input": {}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "x".repeat(1000)}
+                ]}
+            ]
+        }))
+        .unwrap();
+        let options = ConversionOptions {
+            tool_result_max_chars: Some(100),
+            ..Default::default()
+        };
+
+        let result = convert_request_with_options(&req, &options).unwrap();
+        let results = &result
+            .conversation_state
+            .current_message
+            .user_input_message
+            .user_input_message_context
+            .tool_results;
+        let text = results[0].content[0]["text"].as_str().unwrap();
+        assert!(text.contains("900 characters truncated"));
+        assert!(
+            result
+                .warnings
+                .iter()
+                .any(|w| w.code == "tool_result_truncated")
+        );
+
+        // 默认选项不截断
+        let result = convert_request(&req).unwrap();
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_utc_offset() {
+        assert_eq!(parse_utc_offset("+08:00"), FixedOffset::east_opt(8 * 3600));
+        assert_eq!(
+            parse_utc_offset("-0530"),
+            FixedOffset::east_opt(-(5 * 3600 + 30 * 60))
+        );
+        assert_eq!(parse_utc_offset("+8"), FixedOffset::east_opt(8 * 3600));
+        assert_eq!(parse_utc_offset("08:00"), None);
+        assert_eq!(parse_utc_offset("+25:00"), None);
+    }
+
+    #[test]
+    fn test_render_runtime_context() {
+        let now = DateTime::parse_from_rfc3339("2026-01-31T20:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+
+        assert_eq!(
+            render_runtime_context(now, offset, Some("zh-CN")),
+            "Current date: 2026-02-01 (Sunday), timezone: UTC+08:00. User locale: zh-CN."
+        );
+        assert_eq!(
+            render_runtime_context(now, FixedOffset::east_opt(0).unwrap(), None),
+            "Current date: 2026-01-31 (Saturday), timezone: UTC+00:00."
+        );
+    }
+
+    #[test]
+    fn test_convert_request_injects_runtime_context() {
+        let options = ConversionOptions {
+            runtime_context: Some("Current date: 2026-02-01.".to_string()),
+            ..Default::default()
+        };
+
+        // 有系统消息时追加到系统消息中
+        let req: MessagesRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 1024,
+            "system": "You are helpful.",
+            "messages": [{"role": "user", "content": "What day is it?"}]
+        }))
+        .unwrap();
+        let result = convert_request_with_options(&req, &options).unwrap();
+        let Message::User(first) = &result.conversation_state.history[0] else {
+            panic!("first history message should be user");
+        };
+        let content = &first.user_input_message.content;
+        assert!(content.starts_with("You are helpful.\nCurrent date: 2026-02-01."));
+
+        // 没有系统消息时插入新的系统消息
+        let req: MessagesRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 1024,
+            "messages": [{"role": "user", "content": "What day is it?"}]
+        }))
+        .unwrap();
+        let result = convert_request_with_options(&req, &options).unwrap();
+        let Message::User(first) = &result.conversation_state.history[0] else {
+            panic!("first history message should be user");
+        };
+        assert_eq!(
+            first.user_input_message.content,
+            "Current date: 2026-02-01."
+        );
+
+        // 默认不注入
+        let result = convert_request(&req).unwrap();
+        assert!(result.conversation_state.history.is_empty());
+    }
 }
@@ -2,13 +2,17 @@
 
 use std::convert::Infallible;
 
-use anyhow::Error;
+use crate::events::{self, AppEvent, StreamEndGuard, StreamOutcome, TokenUsage};
 use crate::kiro::model::events::Event;
 use crate::kiro::model::requests::kiro::KiroRequest;
 use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::kiro::provider::KiroProvider;
+use crate::model::config::{Config, ModelRoute};
+use crate::moderation::{self, ModerationVerdict};
 use crate::token;
+use anyhow::Error;
 use axum::{
-    Json as JsonExtractor,
+    Extension, Json as JsonExtractor,
     body::Body,
     extract::State,
     http::{StatusCode, header},
@@ -21,14 +25,24 @@ use std::time::Duration;
 use tokio::time::interval;
 use uuid::Uuid;
 
-use super::converter::{ConversionError, convert_request};
-use super::middleware::AppState;
-use super::stream::{BufferedStreamContext, SseEvent, StreamContext};
-use super::types::{CountTokensRequest, CountTokensResponse, ErrorResponse, MessagesRequest, Model, ModelsResponse, OutputConfig, Thinking};
+use super::converter::{
+    ConversionError, ConversionOptions, ConversionWarning, convert_request_with_options,
+};
+use super::images;
+use super::middleware::{ApiKeyId, AppState};
+use super::prompt_cache::CacheUsage;
+use super::secret_scan::SecretScanner;
+use super::stream::{
+    BufferedStreamContext, SseEvent, StreamContext, split_thinking, thinking_signature,
+};
+use super::types::{
+    CountTokensRequest, CountTokensResponse, ErrorResponse, EstimateResponse, EstimatedCost,
+    EstimatedCredential, MessagesRequest, Model, ModelsResponse, OutputConfig, Thinking,
+};
 use super::websearch;
 
 /// 将 KiroProvider 错误映射为 HTTP 响应
-fn map_provider_error(err: Error) -> Response {
+pub(crate) fn map_provider_error(err: Error) -> Response {
     let err_str = err.to_string();
 
     // 上下文窗口满了（对话历史累积超出模型上下文窗口限制）
@@ -67,13 +81,192 @@ fn map_provider_error(err: Error) -> Response {
         .into_response()
 }
 
+/// 转换告警响应头，值为逗号分隔的告警代码
+const WARNINGS_HEADER: &str = "x-kiro-warnings";
+
+/// 将转换告警代码写入响应头
+pub(crate) fn attach_warnings_header(
+    mut response: Response,
+    warnings: &[ConversionWarning],
+) -> Response {
+    if warnings.is_empty() {
+        return response;
+    }
+
+    let mut codes: Vec<&str> = Vec::new();
+    for warning in warnings {
+        if !codes.contains(&warning.code) {
+            codes.push(warning.code);
+        }
+    }
+    if let Ok(value) = header::HeaderValue::from_str(&codes.join(",")) {
+        response.headers_mut().insert(WARNINGS_HEADER, value);
+    }
+    response
+}
+
+/// 内容审核拒绝时返回 Anthropic 风格的 refusal 响应（不调用上游）
+fn refusal_response(payload: &MessagesRequest, reason: &str) -> Response {
+    tracing::warn!(model = %payload.model, "请求被内容审核拒绝: {}", reason);
+
+    let input_tokens = token::count_all_tokens(
+        payload.model.clone(),
+        payload.system.clone(),
+        payload.messages.clone(),
+        payload.tools.clone(),
+    ) as i32;
+
+    if payload.stream {
+        let bytes: Vec<Result<Bytes, Infallible>> =
+            moderation::refusal_events(&payload.model, input_tokens)
+                .into_iter()
+                .map(|e| Ok(Bytes::from(e.to_sse_string())))
+                .collect();
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(Body::from_stream(stream::iter(bytes)))
+            .unwrap();
+    }
+
+    (
+        StatusCode::OK,
+        Json(moderation::refusal_message(&payload.model, input_tokens)),
+    )
+        .into_response()
+}
+
+/// URL 来源的图片下载失败时返回 400
+pub(crate) fn invalid_image_response(message: &str) -> Response {
+    tracing::warn!("图片下载失败: {}", message);
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse::new(
+            "invalid_request_error",
+            format!("图片无效: {}", message),
+        )),
+    )
+        .into_response()
+}
+
+/// 按模型路由表（配置 `modelAliases`）将请求中的模型名替换为实际模型名
+///
+/// 返回命中的路由，供调用方应用默认 max_tokens 与固定凭据；
+/// 未命中时模型名保持不变，由转换器按内置规则映射到 Kiro 模型
+pub(crate) fn apply_model_route(model: &mut String, config: &Config) -> Option<ModelRoute> {
+    let route = config.model_aliases.get(model.as_str())?.clone();
+    tracing::debug!(
+        alias = %model,
+        target = %route.model,
+        credential_id = ?route.credential_id,
+        "应用模型路由"
+    );
+    *model = route.model.clone();
+    Some(route)
+}
+
+/// GET /healthz
+///
+/// 存活检查：进程能处理请求即返回 200
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "anthropic",
+    responses((status = 200, description = "存活", body = serde_json::Value)),
+    security(())
+)]
+pub async fn healthz() -> impl IntoResponse {
+    Json(json!({ "status": "ok" }))
+}
+
+/// GET /readyz
+///
+/// 就绪检查：没有可用凭据（演示模式除外），或数据目录可用磁盘空间低于 `minFreeDiskMb` 时返回 503
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "anthropic",
+    responses((status = 200, description = "就绪", body = serde_json::Value), (status = 503, description = "没有可用凭据或数据目录可用磁盘空间不足", body = serde_json::Value)),
+    security(())
+)]
+pub async fn readyz(State(state): State<AppState>) -> Response {
+    let disk = crate::disk_monitor::status();
+    let credentials = state.kiro_provider.as_ref().map(|provider| {
+        let token_manager = provider.token_manager();
+        let snapshot = token_manager.snapshot();
+        let demo_mode = token_manager.config().demo_mode;
+        (snapshot.total, snapshot.available, demo_mode)
+    });
+    let credentials_ready =
+        credentials.is_none_or(|(_, available, demo_mode)| demo_mode || available > 0);
+    let ready = credentials_ready && disk.as_ref().is_none_or(|d| !d.low);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(json!({
+            "status": if ready { "ready" } else { "not_ready" },
+            "credentials": credentials.map(|(total, available, _)| json!({
+                "total": total,
+                "available": available,
+            })),
+            "disk": disk,
+        })),
+    )
+        .into_response()
+}
+
 /// GET /v1/models
 ///
-/// 返回可用的模型列表
-pub async fn get_models() -> impl IntoResponse {
+/// 返回可用的模型列表（内置模型 + 配置的模型别名）
+#[utoipa::path(
+    get,
+    path = "/v1/models",
+    tag = "anthropic",
+    responses((status = 200, body = ModelsResponse), (status = 401, body = ErrorResponse))
+)]
+pub async fn get_models(State(state): State<AppState>) -> impl IntoResponse {
     tracing::info!("Received GET /v1/models request");
 
-    let models = vec![
+    let mut models = builtin_models();
+
+    if let Some(provider) = &state.kiro_provider {
+        let aliases = &provider.token_manager().config().model_aliases;
+        let alias_models: Vec<Model> = aliases
+            .iter()
+            .filter(|(alias, _)| !models.iter().any(|m| &m.id == *alias))
+            .map(|(alias, route)| {
+                let base = models.iter().find(|m| m.id == route.model);
+                Model {
+                    id: alias.clone(),
+                    object: "model".to_string(),
+                    created: base.map(|m| m.created).unwrap_or_default(),
+                    owned_by: "anthropic".to_string(),
+                    display_name: format!("{} (alias of {})", alias, route.model),
+                    model_type: "chat".to_string(),
+                    max_tokens: route
+                        .max_tokens
+                        .or(base.map(|m| m.max_tokens))
+                        .unwrap_or(32000),
+                }
+            })
+            .collect();
+        models.extend(alias_models);
+    }
+
+    Json(ModelsResponse {
+        object: "list".to_string(),
+        data: models,
+    })
+}
+
+/// 内置模型列表
+fn builtin_models() -> Vec<Model> {
+    vec![
         Model {
             id: "claude-sonnet-4-5-20250929".to_string(),
             object: "model".to_string(),
@@ -164,19 +357,23 @@ pub async fn get_models() -> impl IntoResponse {
             model_type: "chat".to_string(),
             max_tokens: 32000,
         },
-    ];
-
-    Json(ModelsResponse {
-        object: "list".to_string(),
-        data: models,
-    })
+    ]
 }
 
 /// POST /v1/messages
 ///
 /// 创建消息（对话）
+#[utoipa::path(
+    post,
+    path = "/v1/messages",
+    tag = "anthropic",
+    params(("x-kiro-conversation-id" = Option<String>, Header, description = "开启 conversationMemory 时使用服务端保存的会话历史")),
+    request_body = MessagesRequest,
+    responses((status = 200, description = "Messages API 响应；`stream: true` 时为 SSE 事件流", body = serde_json::Value), (status = 400, body = ErrorResponse), (status = 401, body = ErrorResponse), (status = 429, body = ErrorResponse), (status = 502, body = ErrorResponse))
+)]
 pub async fn post_messages(
     State(state): State<AppState>,
+    api_key_id: Option<Extension<ApiKeyId>>,
     JsonExtractor(mut payload): JsonExtractor<MessagesRequest>,
 ) -> Response {
     tracing::info!(
@@ -202,9 +399,17 @@ pub async fn post_messages(
         }
     };
 
+    let route = apply_model_route(&mut payload.model, &provider.token_manager().config());
+    let pinned_credential = route.and_then(|r| r.credential_id);
+
     // 检测模型名是否包含 "thinking" 后缀，若包含则覆写 thinking 配置
     override_thinking_from_model_name(&mut payload);
 
+    // 内容审核前置过滤
+    if let ModerationVerdict::Deny(reason) = moderation::check_messages(&payload.messages).await {
+        return refusal_response(&payload, &reason);
+    }
+
     // 检查是否为 WebSearch 请求
     if websearch::has_web_search_tool(&payload) {
         tracing::info!("检测到 WebSearch 工具，路由到 WebSearch 处理");
@@ -216,12 +421,23 @@ pub async fn post_messages(
             payload.messages.clone(),
             payload.tools.clone(),
         ) as i32;
+        state.record_tokens(input_tokens);
+
+        return KiroProvider::with_pinned_credential(
+            pinned_credential,
+            websearch::handle_websearch_request(provider, &payload, input_tokens),
+        )
+        .await;
+    }
 
-        return websearch::handle_websearch_request(provider, &payload, input_tokens).await;
+    // 下载 URL 来源的图片
+    if let Err(message) = images::resolve_image_urls(&mut payload.messages).await {
+        return invalid_image_response(&message);
     }
 
     // 转换请求
-    let conversion_result = match convert_request(&payload) {
+    let options = ConversionOptions::from_config(&provider.token_manager().config());
+    let conversion_result = match convert_request_with_options(&payload, &options) {
         Ok(result) => result,
         Err(e) => {
             let (error_type, message) = match &e {
@@ -231,6 +447,9 @@ pub async fn post_messages(
                 ConversionError::EmptyMessages => {
                     ("invalid_request_error", "消息列表为空".to_string())
                 }
+                ConversionError::InvalidImage(message) => {
+                    ("invalid_request_error", format!("图片无效: {}", message))
+                }
             };
             tracing::warn!("请求转换失败: {}", e);
             return (
@@ -241,6 +460,11 @@ pub async fn post_messages(
         }
     };
 
+    let warnings = conversion_result.warnings;
+    for warning in &warnings {
+        tracing::debug!(code = warning.code, "请求转换告警: {}", warning.message);
+    }
+
     // 构建 Kiro 请求
     let kiro_request = KiroRequest {
         conversation_state: conversion_result.conversation_state,
@@ -264,6 +488,9 @@ pub async fn post_messages(
 
     tracing::debug!("Kiro request body: {}", request_body);
 
+    // 推算 prompt caching 用量（需在 payload 字段被移走之前）
+    let cache_usage = state.prompt_cache.record(&payload).await;
+
     // 估算输入 tokens
     let input_tokens = token::count_all_tokens(
         payload.model.clone(),
@@ -271,6 +498,7 @@ pub async fn post_messages(
         payload.messages,
         payload.tools,
     ) as i32;
+    state.record_tokens(input_tokens);
 
     // 检查是否启用了thinking
     let thinking_enabled = payload
@@ -279,20 +507,37 @@ pub async fn post_messages(
         .map(|t| t.is_enabled())
         .unwrap_or(false);
 
-    if payload.stream {
-        // 流式响应
-        handle_stream_request(
-            provider,
-            &request_body,
-            &payload.model,
-            input_tokens,
-            thinking_enabled,
-        )
-        .await
-    } else {
-        // 非流式响应
-        handle_non_stream_request(provider, &request_body, &payload.model, input_tokens).await
-    }
+    let response = KiroProvider::with_pinned_credential(pinned_credential, async {
+        if payload.stream {
+            // 流式响应
+            handle_stream_request(
+                provider,
+                &request_body,
+                &payload.model,
+                input_tokens,
+                cache_usage,
+                thinking_enabled,
+                api_key_id.map(|Extension(ApiKeyId(id))| id),
+            )
+            .await
+        } else {
+            // 非流式响应
+            handle_non_stream_request(
+                provider,
+                &request_body,
+                &payload.model,
+                input_tokens,
+                cache_usage,
+                thinking_enabled,
+                &warnings,
+                api_key_id.map(|Extension(ApiKeyId(id))| id),
+            )
+            .await
+        }
+    })
+    .await;
+
+    attach_warnings_header(response, &warnings)
 }
 
 /// 处理流式请求
@@ -301,7 +546,9 @@ async fn handle_stream_request(
     request_body: &str,
     model: &str,
     input_tokens: i32,
+    cache_usage: CacheUsage,
     thinking_enabled: bool,
+    api_key_id: Option<String>,
 ) -> Response {
     // 调用 Kiro API（支持多凭据故障转移）
     let response = match provider.call_api_stream(request_body).await {
@@ -310,13 +557,23 @@ async fn handle_stream_request(
     };
 
     // 创建流处理上下文
-    let mut ctx = StreamContext::new_with_thinking(model, input_tokens, thinking_enabled);
+    let mut ctx = StreamContext::new_with_thinking(model, input_tokens, thinking_enabled)
+        .with_secret_scanner(SecretScanner::from_provider(&provider))
+        .with_cache_usage(cache_usage);
 
     // 生成初始事件
     let initial_events = ctx.generate_initial_events();
 
     // 创建 SSE 流
-    let stream = create_sse_stream(response, ctx, initial_events);
+    let guard = StreamEndGuard::new(api_key_id, model)
+        .with_credential_id(KiroProvider::served_credential());
+    let stream = create_sse_stream(
+        response,
+        ctx,
+        initial_events,
+        ping_interval(&provider),
+        guard,
+    );
 
     // 返回 SSE 响应
     Response::builder()
@@ -328,19 +585,28 @@ async fn handle_stream_request(
         .unwrap()
 }
 
-/// Ping 事件间隔（25秒）
-const PING_INTERVAL_SECS: u64 = 25;
+/// 流式响应的 ping 间隔（配置项 `pingIntervalSecs`，最小 1 秒）
+pub(crate) fn ping_interval(provider: &KiroProvider) -> Duration {
+    Duration::from_secs(provider.token_manager().config().ping_interval_secs.max(1))
+}
 
 /// 创建 ping 事件的 SSE 字符串
 fn create_ping_sse() -> Bytes {
     Bytes::from("event: ping\ndata: {\"type\": \"ping\"}\n\n")
 }
 
+/// 上游响应流中途中断时发给客户端的错误信息
+fn stream_interrupted_message(err: &reqwest::Error) -> String {
+    format!("上游响应流中断，响应不完整: {}", err)
+}
+
 /// 创建 SSE 事件流
 fn create_sse_stream(
     response: reqwest::Response,
     ctx: StreamContext,
     initial_events: Vec<SseEvent>,
+    ping_period: Duration,
+    guard: StreamEndGuard,
 ) -> impl Stream<Item = Result<Bytes, Infallible>> {
     // 先发送初始事件
     let initial_stream = stream::iter(
@@ -349,12 +615,12 @@ fn create_sse_stream(
             .map(|e| Ok(Bytes::from(e.to_sse_string()))),
     );
 
-    // 然后处理 Kiro 响应流，同时每25秒发送 ping 保活
+    // 然后处理 Kiro 响应流，上游持续 ping_period 没有输出时发送 ping 保活
     let body_stream = response.bytes_stream();
 
     let processing_stream = stream::unfold(
-        (body_stream, ctx, EventStreamDecoder::new(), false, interval(Duration::from_secs(PING_INTERVAL_SECS))),
-        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval)| async move {
+        (body_stream, ctx, EventStreamDecoder::new(), false, interval(ping_period), guard),
+        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval, mut guard)| async move {
             if finished {
                 return None;
             }
@@ -385,32 +651,47 @@ fn create_sse_stream(
                                 }
                             }
 
+                            // 有输出时重新计时，ping 只在静默期发送
+                            if !events.is_empty() {
+                                ping_interval.reset();
+                            }
+                            guard.record_usage(ctx.usage());
+
                             // 转换为 SSE 字节流
                             let bytes: Vec<Result<Bytes, Infallible>> = events
                                 .into_iter()
                                 .map(|e| Ok(Bytes::from(e.to_sse_string())))
                                 .collect();
 
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval)))
+                            Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval, guard)))
                         }
                         Some(Err(e)) => {
-                            tracing::error!("读取响应流失败: {}", e);
-                            // 发送最终事件并结束
-                            let final_events = ctx.generate_final_events();
+                            tracing::error!(
+                                output_tokens = ctx.output_tokens,
+                                "读取响应流失败，以 error 结束并保留已生成的部分输出: {}",
+                                e
+                            );
+                            guard.finish(StreamOutcome::UpstreamError);
+                            // 发送最终事件与 error 事件并结束（stop_reason = error）
+                            let final_events =
+                                ctx.generate_abort_events(&stream_interrupted_message(&e));
+                            guard.record_usage(ctx.usage());
                             let bytes: Vec<Result<Bytes, Infallible>> = final_events
                                 .into_iter()
                                 .map(|e| Ok(Bytes::from(e.to_sse_string())))
                                 .collect();
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)))
+                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval, guard)))
                         }
                         None => {
+                            guard.finish(StreamOutcome::Completed);
                             // 流结束，发送最终事件
                             let final_events = ctx.generate_final_events();
+                            guard.record_usage(ctx.usage());
                             let bytes: Vec<Result<Bytes, Infallible>> = final_events
                                 .into_iter()
                                 .map(|e| Ok(Bytes::from(e.to_sse_string())))
                                 .collect();
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)))
+                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval, guard)))
                         }
                     }
                 }
@@ -418,7 +699,7 @@ fn create_sse_stream(
                 _ = ping_interval.tick() => {
                     tracing::trace!("发送 ping 保活事件");
                     let bytes: Vec<Result<Bytes, Infallible>> = vec![Ok(create_ping_sse())];
-                    Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval)))
+                    Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval, guard)))
                 }
             }
         },
@@ -432,11 +713,16 @@ fn create_sse_stream(
 const CONTEXT_WINDOW_SIZE: i32 = 200_000;
 
 /// 处理非流式请求
+#[allow(clippy::too_many_arguments)]
 async fn handle_non_stream_request(
     provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
     request_body: &str,
     model: &str,
     input_tokens: i32,
+    cache_usage: CacheUsage,
+    thinking_enabled: bool,
+    warnings: &[ConversionWarning],
+    api_key_id: Option<String>,
 ) -> Response {
     // 调用 Kiro API（支持多凭据故障转移）
     let response = match provider.call_api(request_body).await {
@@ -499,14 +785,14 @@ async fn handle_non_stream_request(
                                 let input: serde_json::Value = if buffer.is_empty() {
                                     serde_json::json!({})
                                 } else {
-                                    serde_json::from_str(buffer)
-                                        .unwrap_or_else(|e| {
-                                            tracing::warn!(
-                                                "工具输入 JSON 解析失败: {}, tool_use_id: {}",
-                                                e, tool_use.tool_use_id
-                                            );
-                                            serde_json::json!({})
-                                        })
+                                    serde_json::from_str(buffer).unwrap_or_else(|e| {
+                                        tracing::warn!(
+                                            "工具输入 JSON 解析失败: {}, tool_use_id: {}",
+                                            e,
+                                            tool_use.tool_use_id
+                                        );
+                                        serde_json::json!({})
+                                    })
                                 };
 
                                 tool_uses.push(json!({
@@ -556,8 +842,24 @@ async fn handle_non_stream_request(
     }
 
     // 构建响应内容
+    let message_id = format!("msg_{}", Uuid::new_v4().to_string().replace('-', ""));
     let mut content: Vec<serde_json::Value> = Vec::new();
 
+    // 启用 thinking 时，开头的 `<thinking>` 内容作为 thinking 块返回（与流式响应一致）
+    if thinking_enabled && let Some((thinking, text)) = split_thinking(&text_content) {
+        content.push(json!({
+            "type": "thinking",
+            "thinking": thinking,
+            "signature": thinking_signature(&message_id)
+        }));
+        text_content = text;
+        // 只有 thinking、没有 text 和 tool_use：模型耗尽了 token 预算在思考上
+        if text_content.is_empty() && tool_uses.is_empty() {
+            stop_reason = "max_tokens".to_string();
+            text_content = " ".to_string();
+        }
+    }
+
     if !text_content.is_empty() {
         content.push(json!({
             "type": "text",
@@ -567,15 +869,36 @@ async fn handle_non_stream_request(
 
     content.extend(tool_uses);
 
+    // 屏蔽输出中的代理密钥
+    if let Some(scanner) = SecretScanner::from_provider(&provider) {
+        content
+            .iter_mut()
+            .for_each(|block| scanner.mask_json(block));
+    }
+
     // 估算输出 tokens
     let output_tokens = token::estimate_output_tokens(&content);
 
-    // 使用从 contextUsageEvent 计算的 input_tokens，如果没有则使用估算值
-    let final_input_tokens = context_input_tokens.unwrap_or(input_tokens);
+    // 使用从 contextUsageEvent 计算的 input_tokens，如果没有则使用估算值，并扣除缓存部分
+    let total_input_tokens = context_input_tokens.unwrap_or(input_tokens);
+    let cache_usage = cache_usage.clamp(total_input_tokens);
+    let final_input_tokens = cache_usage.uncached_input_tokens(total_input_tokens);
+
+    events::publish(AppEvent::UsageRecorded {
+        api_key_id,
+        credential_id: KiroProvider::served_credential(),
+        model: model.to_string(),
+        usage: TokenUsage {
+            input_tokens: final_input_tokens,
+            output_tokens,
+            cache_creation_input_tokens: cache_usage.cache_creation_input_tokens,
+            cache_read_input_tokens: cache_usage.cache_read_input_tokens,
+        },
+    });
 
     // 构建 Anthropic 响应
-    let response_body = json!({
-        "id": format!("msg_{}", Uuid::new_v4().to_string().replace('-', "")),
+    let mut response_body = json!({
+        "id": message_id,
         "type": "message",
         "role": "assistant",
         "content": content,
@@ -584,10 +907,17 @@ async fn handle_non_stream_request(
         "stop_sequence": null,
         "usage": {
             "input_tokens": final_input_tokens,
-            "output_tokens": output_tokens
+            "output_tokens": output_tokens,
+            "cache_creation_input_tokens": cache_usage.cache_creation_input_tokens,
+            "cache_read_input_tokens": cache_usage.cache_read_input_tokens
         }
     });
 
+    // 转换告警作为额外的响应元数据返回
+    if !warnings.is_empty() {
+        response_body["warnings"] = json!(warnings);
+    }
+
     (StatusCode::OK, Json(response_body)).into_response()
 }
 
@@ -596,20 +926,16 @@ async fn handle_non_stream_request(
 /// - Opus 4.6：覆写为 adaptive 类型
 /// - 其他模型：覆写为 enabled 类型
 /// - budget_tokens 固定为 20000
-fn override_thinking_from_model_name(payload: &mut MessagesRequest) {
+pub(crate) fn override_thinking_from_model_name(payload: &mut MessagesRequest) {
     let model_lower = payload.model.to_lowercase();
     if !model_lower.contains("thinking") {
         return;
     }
 
-    let is_opus_4_6 =
-        model_lower.contains("opus") && (model_lower.contains("4-6") || model_lower.contains("4.6"));
+    let is_opus_4_6 = model_lower.contains("opus")
+        && (model_lower.contains("4-6") || model_lower.contains("4.6"));
 
-    let thinking_type = if is_opus_4_6 {
-        "adaptive"
-    } else {
-        "enabled"
-    };
+    let thinking_type = if is_opus_4_6 { "adaptive" } else { "enabled" };
 
     tracing::info!(
         model = %payload.model,
@@ -621,7 +947,7 @@ fn override_thinking_from_model_name(payload: &mut MessagesRequest) {
         thinking_type: thinking_type.to_string(),
         budget_tokens: 20000,
     });
-    
+
     if is_opus_4_6 {
         payload.output_config = Some(OutputConfig {
             effort: "high".to_string(),
@@ -632,8 +958,16 @@ fn override_thinking_from_model_name(payload: &mut MessagesRequest) {
 /// POST /v1/messages/count_tokens
 ///
 /// 计算消息的 token 数量
+#[utoipa::path(
+    post,
+    path = "/v1/messages/count_tokens",
+    tag = "anthropic",
+    request_body = CountTokensRequest,
+    responses((status = 200, body = CountTokensResponse), (status = 401, body = ErrorResponse))
+)]
 pub async fn count_tokens(
-    JsonExtractor(payload): JsonExtractor<CountTokensRequest>,
+    State(state): State<AppState>,
+    JsonExtractor(mut payload): JsonExtractor<CountTokensRequest>,
 ) -> impl IntoResponse {
     tracing::info!(
         model = %payload.model,
@@ -641,6 +975,11 @@ pub async fn count_tokens(
         "Received POST /v1/messages/count_tokens request"
     );
 
+    // 与 /v1/messages 一致解析模型别名，外部 count_tokens API 收到的是实际模型名
+    if let Some(provider) = &state.kiro_provider {
+        apply_model_route(&mut payload.model, &provider.token_manager().config());
+    }
+
     let total_tokens = token::count_all_tokens(
         payload.model,
         payload.system,
@@ -653,13 +992,138 @@ pub async fn count_tokens(
     })
 }
 
+/// POST /v1/messages/estimate
+///
+/// 预估请求：与 /v1/messages 一样解析模型路由、转换请求并估算输入 tokens，
+/// 返回预计使用的凭据与费用，但不调用上游、不计入限流的 token 数
+#[utoipa::path(
+    post,
+    path = "/v1/messages/estimate",
+    tag = "anthropic",
+    request_body = MessagesRequest,
+    responses((status = 200, body = EstimateResponse), (status = 400, body = ErrorResponse), (status = 401, body = ErrorResponse))
+)]
+pub async fn estimate_messages(
+    State(state): State<AppState>,
+    JsonExtractor(mut payload): JsonExtractor<MessagesRequest>,
+) -> Response {
+    let Some(provider) = state.kiro_provider.clone() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse::new(
+                "service_unavailable",
+                "Kiro API provider not configured",
+            )),
+        )
+            .into_response();
+    };
+    let config = provider.token_manager().config();
+
+    let route = apply_model_route(&mut payload.model, &config);
+    override_thinking_from_model_name(&mut payload);
+    let web_search = websearch::has_web_search_tool(&payload);
+
+    if let Err(message) = images::resolve_image_urls(&mut payload.messages).await {
+        return invalid_image_response(&message);
+    }
+
+    let options = ConversionOptions::from_config(&config);
+    let conversion_result = match convert_request_with_options(&payload, &options) {
+        Ok(result) => result,
+        Err(e) => {
+            let message = match &e {
+                ConversionError::UnsupportedModel(model) => format!("模型不支持: {}", model),
+                ConversionError::EmptyMessages => "消息列表为空".to_string(),
+                ConversionError::InvalidImage(message) => format!("图片无效: {}", message),
+            };
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new("invalid_request_error", message)),
+            )
+                .into_response();
+        }
+    };
+    let kiro_model = conversion_result
+        .conversation_state
+        .current_message
+        .user_input_message
+        .model_id
+        .clone();
+    let kiro_request = KiroRequest {
+        conversation_state: conversion_result.conversation_state,
+        profile_arn: state.profile_arn.clone(),
+    };
+    let request_body = serde_json::to_string(&kiro_request).unwrap_or_default();
+
+    // 与 /v1/messages 相同的凭据固定规则：请求头指定的凭据优先于模型路由
+    let (credential_id, pinned) =
+        KiroProvider::with_pinned_credential(route.and_then(|r| r.credential_id), async {
+            (
+                provider.preview_credential(&request_body),
+                KiroProvider::pinned_credential().is_some(),
+            )
+        })
+        .await;
+    let credential = credential_id.and_then(|id| {
+        let snapshot = provider.token_manager().snapshot();
+        let entry = snapshot
+            .entries
+            .into_iter()
+            .find(|e| e.id == id && !e.disabled)?;
+        Some(EstimatedCredential {
+            id,
+            auth_method: entry.auth_method,
+            pinned,
+        })
+    });
+
+    let thinking = payload
+        .thinking
+        .as_ref()
+        .map(|t| t.is_enabled())
+        .unwrap_or(false);
+    let input_tokens = token::count_all_tokens(
+        payload.model.clone(),
+        payload.system,
+        payload.messages,
+        payload.tools,
+    ) as i32;
+    let estimated_cost_usd = config
+        .pricing_for(&payload.model)
+        .map(|pricing| EstimatedCost {
+            input: pricing.cost(input_tokens, 0, 0, 0),
+            max_total: pricing.cost(input_tokens, payload.max_tokens, 0, 0),
+        });
+
+    Json(EstimateResponse {
+        model: payload.model,
+        kiro_model,
+        input_tokens: input_tokens.max(1),
+        max_tokens: payload.max_tokens,
+        thinking,
+        web_search,
+        credential,
+        estimated_cost_usd,
+    })
+    .into_response()
+}
+
 /// POST /cc/v1/messages
 ///
 /// Claude Code 兼容端点，与 /v1/messages 的区别在于：
 /// - 流式响应会等待 kiro 端返回 contextUsageEvent 后再发送 message_start
 /// - message_start 中的 input_tokens 是从 contextUsageEvent 计算的准确值
+#[utoipa::path(
+    post,
+    path = "/cc/v1/messages",
+    tag = "anthropic",
+    params(("x-kiro-conversation-id" = Option<String>, Header, description = "开启 conversationMemory 时使用服务端保存的会话历史")),
+    request_body = MessagesRequest,
+    responses((status = 200, description = "Messages API 响应；`stream: true` 时为 SSE 事件流", body = serde_json::Value), (status = 400, body = ErrorResponse), (status = 401, body = ErrorResponse), (status = 502, body = ErrorResponse))
+)]
 pub async fn post_messages_cc(
     State(state): State<AppState>,
+    api_key_id: Option<Extension<ApiKeyId>>,
     JsonExtractor(mut payload): JsonExtractor<MessagesRequest>,
 ) -> Response {
     tracing::info!(
@@ -686,9 +1150,17 @@ pub async fn post_messages_cc(
         }
     };
 
+    let route = apply_model_route(&mut payload.model, &provider.token_manager().config());
+    let pinned_credential = route.and_then(|r| r.credential_id);
+
     // 检测模型名是否包含 "thinking" 后缀，若包含则覆写 thinking 配置
     override_thinking_from_model_name(&mut payload);
 
+    // 内容审核前置过滤
+    if let ModerationVerdict::Deny(reason) = moderation::check_messages(&payload.messages).await {
+        return refusal_response(&payload, &reason);
+    }
+
     // 检查是否为 WebSearch 请求
     if websearch::has_web_search_tool(&payload) {
         tracing::info!("检测到 WebSearch 工具，路由到 WebSearch 处理");
@@ -700,12 +1172,23 @@ pub async fn post_messages_cc(
             payload.messages.clone(),
             payload.tools.clone(),
         ) as i32;
+        state.record_tokens(input_tokens);
 
-        return websearch::handle_websearch_request(provider, &payload, input_tokens).await;
+        return KiroProvider::with_pinned_credential(
+            pinned_credential,
+            websearch::handle_websearch_request(provider, &payload, input_tokens),
+        )
+        .await;
+    }
+
+    // 下载 URL 来源的图片
+    if let Err(message) = images::resolve_image_urls(&mut payload.messages).await {
+        return invalid_image_response(&message);
     }
 
     // 转换请求
-    let conversion_result = match convert_request(&payload) {
+    let options = ConversionOptions::from_config(&provider.token_manager().config());
+    let conversion_result = match convert_request_with_options(&payload, &options) {
         Ok(result) => result,
         Err(e) => {
             let (error_type, message) = match &e {
@@ -715,6 +1198,9 @@ pub async fn post_messages_cc(
                 ConversionError::EmptyMessages => {
                     ("invalid_request_error", "消息列表为空".to_string())
                 }
+                ConversionError::InvalidImage(message) => {
+                    ("invalid_request_error", format!("图片无效: {}", message))
+                }
             };
             tracing::warn!("请求转换失败: {}", e);
             return (
@@ -725,6 +1211,11 @@ pub async fn post_messages_cc(
         }
     };
 
+    let warnings = conversion_result.warnings;
+    for warning in &warnings {
+        tracing::debug!(code = warning.code, "请求转换告警: {}", warning.message);
+    }
+
     // 构建 Kiro 请求
     let kiro_request = KiroRequest {
         conversation_state: conversion_result.conversation_state,
@@ -748,6 +1239,9 @@ pub async fn post_messages_cc(
 
     tracing::debug!("Kiro request body: {}", request_body);
 
+    // 推算 prompt caching 用量（需在 payload 字段被移走之前）
+    let cache_usage = state.prompt_cache.record(&payload).await;
+
     // 估算输入 tokens
     let input_tokens = token::count_all_tokens(
         payload.model.clone(),
@@ -755,6 +1249,7 @@ pub async fn post_messages_cc(
         payload.messages,
         payload.tools,
     ) as i32;
+    state.record_tokens(input_tokens);
 
     // 检查是否启用了thinking
     let thinking_enabled = payload
@@ -763,20 +1258,37 @@ pub async fn post_messages_cc(
         .map(|t| t.is_enabled())
         .unwrap_or(false);
 
-    if payload.stream {
-        // 流式响应（缓冲模式）
-        handle_stream_request_buffered(
-            provider,
-            &request_body,
-            &payload.model,
-            input_tokens,
-            thinking_enabled,
-        )
-        .await
-    } else {
-        // 非流式响应（复用现有逻辑，已经使用正确的 input_tokens）
-        handle_non_stream_request(provider, &request_body, &payload.model, input_tokens).await
-    }
+    let response = KiroProvider::with_pinned_credential(pinned_credential, async {
+        if payload.stream {
+            // 流式响应（缓冲模式）
+            handle_stream_request_buffered(
+                provider,
+                &request_body,
+                &payload.model,
+                input_tokens,
+                cache_usage,
+                thinking_enabled,
+                api_key_id.map(|Extension(ApiKeyId(id))| id),
+            )
+            .await
+        } else {
+            // 非流式响应（复用现有逻辑，已经使用正确的 input_tokens）
+            handle_non_stream_request(
+                provider,
+                &request_body,
+                &payload.model,
+                input_tokens,
+                cache_usage,
+                thinking_enabled,
+                &warnings,
+                api_key_id.map(|Extension(ApiKeyId(id))| id),
+            )
+            .await
+        }
+    })
+    .await;
+
+    attach_warnings_header(response, &warnings)
 }
 
 /// 处理流式请求（缓冲版本）
@@ -788,7 +1300,9 @@ async fn handle_stream_request_buffered(
     request_body: &str,
     model: &str,
     estimated_input_tokens: i32,
+    cache_usage: CacheUsage,
     thinking_enabled: bool,
+    api_key_id: Option<String>,
 ) -> Response {
     // 调用 Kiro API（支持多凭据故障转移）
     let response = match provider.call_api_stream(request_body).await {
@@ -797,10 +1311,14 @@ async fn handle_stream_request_buffered(
     };
 
     // 创建缓冲流处理上下文
-    let ctx = BufferedStreamContext::new(model, estimated_input_tokens, thinking_enabled);
+    let ctx = BufferedStreamContext::new(model, estimated_input_tokens, thinking_enabled)
+        .with_secret_scanner(SecretScanner::from_provider(&provider))
+        .with_cache_usage(cache_usage);
 
     // 创建缓冲 SSE 流
-    let stream = create_buffered_sse_stream(response, ctx);
+    let guard = StreamEndGuard::new(api_key_id, model)
+        .with_credential_id(KiroProvider::served_credential());
+    let stream = create_buffered_sse_stream(response, ctx, ping_interval(&provider), guard);
 
     // 返回 SSE 响应
     Response::builder()
@@ -822,6 +1340,8 @@ async fn handle_stream_request_buffered(
 fn create_buffered_sse_stream(
     response: reqwest::Response,
     ctx: BufferedStreamContext,
+    ping_period: Duration,
+    guard: StreamEndGuard,
 ) -> impl Stream<Item = Result<Bytes, Infallible>> {
     let body_stream = response.bytes_stream();
 
@@ -831,9 +1351,10 @@ fn create_buffered_sse_stream(
             ctx,
             EventStreamDecoder::new(),
             false,
-            interval(Duration::from_secs(PING_INTERVAL_SECS)),
+            interval(ping_period),
+            guard,
         ),
-        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval)| async move {
+        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval, mut guard)| async move {
             if finished {
                 return None;
             }
@@ -848,7 +1369,7 @@ fn create_buffered_sse_stream(
                     _ = ping_interval.tick() => {
                         tracing::trace!("发送 ping 保活事件（缓冲模式）");
                         let bytes: Vec<Result<Bytes, Infallible>> = vec![Ok(create_ping_sse())];
-                        return Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval)));
+                        return Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval, guard)));
                     }
 
                     // 然后处理数据流
@@ -873,26 +1394,36 @@ fn create_buffered_sse_stream(
                                         }
                                     }
                                 }
+                                guard.record_usage(ctx.usage());
                                 // 继续读取下一个 chunk，不发送任何数据
                             }
                             Some(Err(e)) => {
-                                tracing::error!("读取响应流失败: {}", e);
-                                // 发生错误，完成处理并返回所有事件
-                                let all_events = ctx.finish_and_get_all_events();
+                                tracing::error!(
+                                    output_tokens = ctx.output_tokens(),
+                                    "读取响应流失败，以 error 结束并保留已生成的部分输出: {}",
+                                    e
+                                );
+                                guard.finish(StreamOutcome::UpstreamError);
+                                // 发生错误，完成处理并返回所有事件（stop_reason = error）
+                                let all_events =
+                                    ctx.abort_and_get_all_events(&stream_interrupted_message(&e));
+                                guard.record_usage(ctx.usage());
                                 let bytes: Vec<Result<Bytes, Infallible>> = all_events
                                     .into_iter()
                                     .map(|e| Ok(Bytes::from(e.to_sse_string())))
                                     .collect();
-                                return Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)));
+                                return Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval, guard)));
                             }
                             None => {
+                                guard.finish(StreamOutcome::Completed);
                                 // 流结束，完成处理并返回所有事件（已更正 input_tokens）
                                 let all_events = ctx.finish_and_get_all_events();
+                                guard.record_usage(ctx.usage());
                                 let bytes: Vec<Result<Bytes, Infallible>> = all_events
                                     .into_iter()
                                     .map(|e| Ok(Bytes::from(e.to_sse_string())))
                                     .collect();
-                                return Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)));
+                                return Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval, guard)));
                             }
                         }
                     }
@@ -0,0 +1,371 @@
+//! 下载 URL 来源的图片（配置 `fetchImageUrls`）
+//!
+//! Kiro 只接受内联的图片数据：开启后，消息中 `source.type = "url"` 的图片在转换前由代理下载，
+//! 改写为 base64 来源；未开启时这类图片在转换时返回 400。
+//! 下载的图片同样只接受 jpeg / png / gif / webp（按响应的 `Content-Type` 判断），且不超过 5MB。
+//!
+//! 为防止借代理访问内网（SSRF），下载前解析目标地址，拒绝回环、私有、链路本地、唯一本地与未指定地址，
+//! 并只连接到已校验的地址；重定向不自动跟随，而是逐跳重新校验（最多 [`MAX_REDIRECTS`] 次）。
+//! 配置了出站代理（`proxyUrl`）时目标域名由代理解析，本机的校验与地址固定无法阻止 DNS 重绑定，
+//! 因此拒绝下载 URL 来源的图片。
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::OnceLock;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::Url;
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::redirect::Policy;
+use serde_json::json;
+
+use super::converter::{MAX_IMAGE_BYTES, get_image_format, image_too_large};
+use super::types::Message;
+use crate::http_client::{ProxyConfig, client_builder};
+use crate::model::config::TlsBackend;
+use crate::tls::Destination;
+
+/// 单张图片的下载超时（秒）
+const IMAGE_FETCH_TIMEOUT_SECS: u64 = 30;
+
+/// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 5;
+
+/// 图片下载配置
+#[derive(Clone, Default)]
+pub struct ImageFetchConfig {
+    /// 是否下载 URL 来源的图片
+    pub enabled: bool,
+    /// 代理配置
+    pub proxy: Option<ProxyConfig>,
+
+    pub tls_backend: TlsBackend,
+}
+
+/// 全局配置存储
+static IMAGE_FETCH_CONFIG: OnceLock<ImageFetchConfig> = OnceLock::new();
+
+/// 初始化图片下载配置
+///
+/// 应在应用启动时调用一次
+pub fn init_config(config: ImageFetchConfig) {
+    let _ = IMAGE_FETCH_CONFIG.set(config);
+}
+
+/// 下载消息中所有 URL 来源的图片并改写为 base64 来源
+///
+/// 未开启时原样返回；任一图片下载失败时返回错误信息（用于 400 响应）
+pub(crate) async fn resolve_image_urls(messages: &mut [Message]) -> Result<(), String> {
+    let Some(config) = IMAGE_FETCH_CONFIG.get().filter(|c| c.enabled) else {
+        return Ok(());
+    };
+
+    for message in messages {
+        let serde_json::Value::Array(blocks) = &mut message.content else {
+            continue;
+        };
+        for block in blocks {
+            if block.get("type").and_then(|v| v.as_str()) != Some("image") {
+                continue;
+            }
+            let Some(source) = block.get_mut("source") else {
+                continue;
+            };
+            if source.get("type").and_then(|v| v.as_str()) != Some("url") {
+                continue;
+            }
+            let Some(url) = source.get("url").and_then(|v| v.as_str()) else {
+                return Err("URL 来源的图片缺少 url".to_string());
+            };
+            let (media_type, data) = fetch_image(url, config).await?;
+            *source = json!({"type": "base64", "media_type": media_type, "data": data});
+        }
+    }
+    Ok(())
+}
+
+/// 下载图片，返回 media_type 与 base64 数据
+async fn fetch_image(url: &str, config: &ImageFetchConfig) -> Result<(String, String), String> {
+    fetch_image_checked(url, config, is_public_address).await
+}
+
+/// 下载图片，只连接 `allowed` 返回 true 的地址
+async fn fetch_image_checked(
+    url: &str,
+    config: &ImageFetchConfig,
+    allowed: fn(IpAddr) -> bool,
+) -> Result<(String, String), String> {
+    // 经代理转发时实际连接的地址由代理决定，无法保证不访问内网
+    if config.proxy.is_some() {
+        return Err(format!(
+            "配置了出站代理时不支持下载 URL 来源的图片: {}",
+            url
+        ));
+    }
+    let mut current = Url::parse(url).map_err(|_| format!("无效的图片 URL: {}", url))?;
+    let mut redirects = 0;
+    let mut response = loop {
+        let client = pinned_client(&current, config, allowed).await?;
+        let response = client
+            .get(current.clone())
+            .send()
+            .await
+            .map_err(|e| format!("下载图片 {} 失败: {}", url, e))?;
+        if !response.status().is_redirection() {
+            break response;
+        }
+
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| format!("下载图片 {} 失败: 重定向缺少 Location", url))?;
+        redirects += 1;
+        if redirects > MAX_REDIRECTS {
+            return Err(format!("下载图片 {} 失败: 重定向次数过多", url));
+        }
+        current = current
+            .join(location)
+            .map_err(|_| format!("下载图片 {} 失败: 无效的重定向地址 {}", url, location))?;
+    };
+    if !response.status().is_success() {
+        return Err(format!("下载图片 {} 失败: HTTP {}", url, response.status()));
+    }
+
+    let media_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    get_image_format(&media_type).map_err(|e| format!("{}: {}", url, e))?;
+    if let Some(length) = response.content_length()
+        && length as usize > MAX_IMAGE_BYTES
+    {
+        return Err(format!("{}: {}", url, image_too_large(length as usize)));
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("下载图片 {} 失败: {}", url, e))?
+    {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > MAX_IMAGE_BYTES {
+            return Err(format!("{}: {}", url, image_too_large(bytes.len())));
+        }
+    }
+
+    Ok((media_type, STANDARD.encode(bytes)))
+}
+
+/// 解析并校验 URL 的目标地址，创建只连接这些地址、不自动跟随重定向的 Client
+async fn pinned_client(
+    url: &Url,
+    config: &ImageFetchConfig,
+    allowed: fn(IpAddr) -> bool,
+) -> Result<reqwest::Client, String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("图片 URL 仅支持 http / https: {}", url));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| format!("图片 URL 缺少主机名: {}", url))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    // IPv6 字面量形如 `[::1]`
+    let literal = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .ok();
+    let (domain, addrs) = match literal {
+        Some(ip) => (None, vec![SocketAddr::new(ip, port)]),
+        None => {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|e| format!("解析图片地址 {} 失败: {}", host, e))?
+                .collect();
+            (Some(host), addrs)
+        }
+    };
+    if addrs.is_empty() {
+        return Err(format!("解析图片地址 {} 失败: 没有可用地址", url));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !allowed(addr.ip())) {
+        return Err(format!("不允许下载内网地址的图片: {} ({})", url, addr.ip()));
+    }
+
+    let mut builder = client_builder(
+        config.proxy.as_ref(),
+        IMAGE_FETCH_TIMEOUT_SECS,
+        config.tls_backend,
+        Destination::Integration,
+    )
+    .map_err(|e| format!("创建 HTTP Client 失败: {}", e))?
+    .redirect(Policy::none());
+    if let Some(domain) = domain {
+        builder = builder.resolve_to_addrs(domain, &addrs);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("创建 HTTP Client 失败: {}", e))
+}
+
+/// 是否为公网地址（拒绝回环、私有、链路本地、唯一本地、共享、未指定、组播与广播地址）
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_broadcast()
+                // 100.64.0.0/10 运营商级 NAT
+                || (a == 100 && (64..128).contains(&b))
+                // 0.0.0.0/8
+                || a == 0)
+        }
+        IpAddr::V6(ip) => {
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Router;
+    use axum::http::header;
+    use axum::response::Redirect;
+    use axum::routing::get;
+
+    use super::*;
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        base
+    }
+
+    #[tokio::test]
+    async fn test_fetch_image() {
+        let app = Router::new()
+            .route(
+                "/cat.png",
+                get(|| async { ([(header::CONTENT_TYPE, "image/png")], vec![1u8, 2, 3]) }),
+            )
+            .route(
+                "/cat.bmp",
+                get(|| async { ([(header::CONTENT_TYPE, "image/bmp")], vec![1u8]) }),
+            )
+            .route(
+                "/redirect/cat.png",
+                get(|| async { Redirect::temporary("/cat.png") }),
+            )
+            .route(
+                "/huge.jpg",
+                get(|| async {
+                    (
+                        [(header::CONTENT_TYPE, "image/jpeg")],
+                        vec![0u8; MAX_IMAGE_BYTES + 1],
+                    )
+                }),
+            );
+        let base = serve(app).await;
+        let config = ImageFetchConfig::default();
+        // 测试服务监听在回环地址上
+        let fetch_image = |url: String| {
+            let config = config.clone();
+            async move { fetch_image_checked(&url, &config, |ip| ip.is_loopback()).await }
+        };
+
+        let (media_type, data) = fetch_image(format!("{}/cat.png", base)).await.unwrap();
+        assert_eq!(media_type, "image/png");
+        assert_eq!(data, STANDARD.encode([1u8, 2, 3]));
+
+        let err = fetch_image(format!("{}/cat.bmp", base)).await;
+        assert!(err.unwrap_err().contains("image/bmp"));
+        let err = fetch_image(format!("{}/huge.jpg", base)).await;
+        assert!(err.unwrap_err().contains("超过上限"));
+        let err = fetch_image(format!("{}/missing.png", base)).await;
+        assert!(err.unwrap_err().contains("404"));
+        let err = fetch_image("file:///etc/passwd".to_string()).await;
+        assert!(err.unwrap_err().contains("http / https"));
+
+        // 跟随重定向
+        let (media_type, _) = fetch_image(format!("{}/redirect/cat.png", base))
+            .await
+            .unwrap();
+        assert_eq!(media_type, "image/png");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_image_rejects_private_addresses() {
+        let app = Router::new()
+            .route(
+                "/cat.png",
+                get(|| async { ([(header::CONTENT_TYPE, "image/png")], vec![1u8]) }),
+            )
+            .route(
+                "/metadata",
+                get(|| async { Redirect::temporary("http://169.254.169.254/latest/meta-data") }),
+            );
+        let base = serve(app).await;
+        let config = ImageFetchConfig::default();
+
+        for url in [
+            format!("{}/cat.png", base),
+            "http://127.0.0.1/cat.png".to_string(),
+            "http://169.254.169.254/latest/meta-data".to_string(),
+            "http://10.0.0.1/cat.png".to_string(),
+            "http://[::1]/cat.png".to_string(),
+            "http://[fd00::1]/cat.png".to_string(),
+            "http://0.0.0.0/cat.png".to_string(),
+            "http://localhost/cat.png".to_string(),
+        ] {
+            let err = fetch_image(&url, &config).await.unwrap_err();
+            assert!(err.contains("内网地址"), "{}: {}", url, err);
+        }
+
+        // 重定向到内网地址时逐跳校验
+        let err = fetch_image_checked(&format!("{}/metadata", base), &config, |ip| {
+            ip.is_loopback()
+        })
+        .await
+        .unwrap_err();
+        assert!(err.contains("169.254.169.254"), "{}", err);
+
+        assert!(is_public_address("93.184.216.34".parse().unwrap()));
+        assert!(is_public_address("2606:4700::1111".parse().unwrap()));
+        assert!(!is_public_address("::ffff:192.168.1.1".parse().unwrap()));
+        assert!(!is_public_address("100.64.0.1".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_image_refused_with_proxy() {
+        let app = Router::new().route(
+            "/cat.png",
+            get(|| async { ([(header::CONTENT_TYPE, "image/png")], vec![1u8]) }),
+        );
+        let base = serve(app).await;
+        let config = ImageFetchConfig {
+            enabled: true,
+            proxy: Some(ProxyConfig::new("http://127.0.0.1:1")),
+            ..Default::default()
+        };
+
+        // 代理负责解析域名，即使目标地址允许也不下载
+        let err = fetch_image_checked(&format!("{}/cat.png", base), &config, |_| true)
+            .await
+            .unwrap_err();
+        assert!(err.contains("出站代理"), "{}", err);
+    }
+}
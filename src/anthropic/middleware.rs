@@ -5,14 +5,24 @@ use std::sync::Arc;
 use axum::{
     body::Body,
     extract::State,
-    http::{Request, StatusCode},
+    http::{HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode, header},
     middleware::Next,
     response::{IntoResponse, Json, Response},
 };
 
 use crate::common::auth;
+use crate::common::cors::CorsPolicy;
+use crate::common::ip_access;
+use crate::common::rate_limit::{RateLimitStatus, RateLimiter};
 use crate::kiro::provider::KiroProvider;
+use crate::kiro::token_manager::MultiTokenManager;
+use crate::model::config::AdminRole;
+use crate::unix_socket::TrustedLocalPeer;
 
+use super::batches::BatchManager;
+use super::conversation_memory::ConversationMemory;
+use super::prompt_cache::PromptCacheTracker;
+use super::router::MAX_BODY_SIZE;
 use super::types::ErrorResponse;
 
 /// 应用共享状态
@@ -25,6 +35,14 @@ pub struct AppState {
     pub kiro_provider: Option<Arc<KiroProvider>>,
     /// Profile ARN（可选，用于请求）
     pub profile_arn: Option<String>,
+    /// 限流器（可选，未配置限流时为 None）
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Prompt caching 前缀记录（用于推算缓存用量）
+    pub prompt_cache: Arc<PromptCacheTracker>,
+    /// 服务端会话记忆
+    pub conversations: Arc<ConversationMemory>,
+    /// Message Batches 管理器（可选，没有缓存目录时为 None）
+    pub batches: Option<Arc<BatchManager>>,
 }
 
 impl AppState {
@@ -34,6 +52,10 @@ impl AppState {
             api_key: api_key.into(),
             kiro_provider: None,
             profile_arn: None,
+            rate_limiter: None,
+            prompt_cache: Arc::new(PromptCacheTracker::new()),
+            conversations: Arc::new(ConversationMemory::new()),
+            batches: None,
         }
     }
 
@@ -48,37 +70,360 @@ impl AppState {
         self.profile_arn = Some(arn.into());
         self
     }
+
+    /// 设置限流器
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(Arc::new(limiter));
+        self
+    }
+
+    /// 设置 Prompt caching 前缀记录
+    pub fn with_prompt_cache(mut self, tracker: PromptCacheTracker) -> Self {
+        self.prompt_cache = Arc::new(tracker);
+        self
+    }
+
+    /// 设置 Message Batches 管理器
+    pub fn with_batch_manager(mut self, manager: BatchManager) -> Self {
+        self.batches = Some(Arc::new(manager));
+        self
+    }
+
+    /// 按 API Key 查找租户名（每次读取最新配置）
+    fn find_tenant(&self, key: &str) -> Option<String> {
+        let provider = self.kiro_provider.as_ref()?;
+        let config = provider.token_manager().config();
+        config.find_tenant(key).map(|(name, _)| name.to_string())
+    }
+
+    /// API Key 是否已超过配置的过期时间 `apiKeyExpiresAt`（每次读取最新配置）
+    fn api_key_expired(&self) -> bool {
+        self.kiro_provider.as_ref().is_some_and(|provider| {
+            let config = provider.token_manager().config();
+            config
+                .api_key_expires_at
+                .is_some_and(|expires_at| expires_at <= chrono::Utc::now())
+        })
+    }
+
+    /// 记录本次请求消耗的 token 数（用于每分钟 token 限流与请求记录）
+    pub fn record_tokens(&self, tokens: i32) {
+        KiroProvider::tag_input_tokens(tokens);
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.record_tokens(tokens.max(0) as u64);
+            crate::cluster::record_tokens(tokens.max(0) as u64);
+        }
+    }
+}
+
+/// 已认证请求的 API Key 标识（由认证中间件写入请求扩展，用于按 Key 统计）
+#[derive(Debug, Clone)]
+pub struct ApiKeyId(pub String);
+
+/// IP 访问控制中间件（规则见配置 `ipAccess.anthropic`），在认证之前执行
+pub async fn ip_access_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if let Some(provider) = &state.kiro_provider
+        && let Some(access) = &provider.token_manager().config().ip_access
+    {
+        let ip = ip_access::client_ip(&request, access.trust_forwarded_for);
+        if !access.anthropic.permits(ip) {
+            tracing::warn!(client_ip = ?ip, path = %request.uri().path(), "IP 访问控制拒绝请求");
+            let error = ErrorResponse::new(
+                "permission_error",
+                "Access from this IP address is not allowed",
+            );
+            return (StatusCode::FORBIDDEN, Json(error)).into_response();
+        }
+    }
+    next.run(request).await
 }
 
+/// 租户 Key 发起的请求所属的租户名（由认证中间件写入请求扩展）
+#[derive(Debug, Clone)]
+pub struct TenantName(pub String);
+
 /// API Key 认证中间件
+///
+/// 接受 `apiKey` 与各租户（`tenants`）的 API Key；租户 Key 的请求在扩展中记录租户名。
+/// 经 Unix 套接字（`unixSocket.requireApiKey` 为 false）且未携带 Key 的请求直接放行
 pub async fn auth_middleware(
+    State(state): State<AppState>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(key) = auth::extract_api_key(&request) else {
+        if request.extensions().get::<TrustedLocalPeer>().is_some() {
+            return KiroProvider::with_request_tags(None, next.run(request)).await;
+        }
+        let error = ErrorResponse::authentication_error();
+        return (StatusCode::UNAUTHORIZED, Json(error)).into_response();
+    };
+    if auth::verify_api_key(&key, &state.api_key) {
+        if state.api_key_expired() {
+            tracing::warn!("拒绝已过期的 API Key（apiKeyExpiresAt）");
+            let error = ErrorResponse::api_key_expired();
+            return (StatusCode::UNAUTHORIZED, Json(error)).into_response();
+        }
+    } else if let Some(tenant) = state.find_tenant(&key) {
+        request.extensions_mut().insert(TenantName(tenant));
+    } else {
+        let error = ErrorResponse::authentication_error();
+        return (StatusCode::UNAUTHORIZED, Json(error)).into_response();
+    }
+    let id = auth::api_key_id(&key);
+    request.extensions_mut().insert(ApiKeyId(id.clone()));
+    KiroProvider::with_request_tags(Some(id), next.run(request)).await
+}
+
+/// 请求体中请求的模型：Messages / Chat Completions 的 `model`，以及 Message Batches 中每个请求的 `params.model`
+fn requested_models(body: &serde_json::Value) -> Vec<&str> {
+    let batch = body["requests"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item["params"]["model"].as_str());
+    body["model"].as_str().into_iter().chain(batch).collect()
+}
+
+/// 计入租户请求数的生成请求路径（`POST`，相对于 `/v1` 或 `/cc/v1`）
+const COUNTED_TENANT_PATHS: &[&str] = &["/messages", "/chat/completions", "/messages/batches"];
+
+/// 租户中间件（在认证之后执行）
+///
+/// 租户 Key 的请求：请求不在 `allowedModels` 中的模型时返回 403，本月用量达到
+/// `monthlyRequestQuota` / `monthlyTokenQuota` 时返回 429，并且只从租户的 `credentialIds`
+/// 中选择凭据（均不可用时按 `credentialFallback` 处理）
+pub async fn tenant_middleware(
     State(state): State<AppState>,
     request: Request<Body>,
     next: Next,
 ) -> Response {
-    match auth::extract_api_key(&request) {
-        Some(key) if auth::constant_time_eq(&key, &state.api_key) => next.run(request).await,
-        _ => {
-            let error = ErrorResponse::authentication_error();
-            (StatusCode::UNAUTHORIZED, Json(error)).into_response()
+    let (Some(TenantName(name)), Some(provider)) = (
+        request.extensions().get::<TenantName>().cloned(),
+        &state.kiro_provider,
+    ) else {
+        return next.run(request).await;
+    };
+    let Some(tenant) = provider
+        .token_manager()
+        .config()
+        .tenants
+        .get(&name)
+        .cloned()
+    else {
+        return next.run(request).await;
+    };
+
+    let request = if tenant.allowed_models.is_empty() {
+        request
+    } else {
+        let (parts, body) = request.into_parts();
+        let bytes = match axum::body::to_bytes(body, MAX_BODY_SIZE).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::warn!("读取请求体失败: {}", e);
+                return next.run(Request::from_parts(parts, Body::empty())).await;
+            }
+        };
+        // 请求体无法解析时原样转发，由端点返回错误
+        if let Ok(body) = serde_json::from_slice::<serde_json::Value>(&bytes)
+            && let Some(model) = requested_models(&body)
+                .into_iter()
+                .find(|model| !tenant.allows_model(model))
+        {
+            tracing::warn!(tenant = %name, model, "租户不允许使用该模型");
+            let error = ErrorResponse::new(
+                "permission_error",
+                format!("Model {} is not allowed for this API key", model),
+            );
+            return (StatusCode::FORBIDDEN, Json(error)).into_response();
         }
+        Request::from_parts(parts, Body::from(bytes))
+    };
+
+    // 生成请求放行时计入一次请求（与配额检查原子执行），其他请求只检查配额
+    let counted =
+        request.method() == Method::POST && COUNTED_TENANT_PATHS.contains(&request.uri().path());
+    let quota = if counted {
+        crate::tenants::acquire(&name, &tenant).await.map(Some)
+    } else {
+        crate::tenants::check_quota(&name, &tenant)
+            .await
+            .map(|()| None)
+    };
+    let reservation = match quota {
+        Ok(reservation) => reservation,
+        Err(exceeded) => {
+            tracing::warn!(tenant = %name, "租户本月用量已达到配额: {}", exceeded);
+            let error = ErrorResponse::new("rate_limit_error", exceeded.to_string());
+            return (StatusCode::TOO_MANY_REQUESTS, Json(error)).into_response();
+        }
+    };
+
+    let response = MultiTokenManager::with_credential_scope(
+        &tenant.credential_ids,
+        tenant.credential_fallback,
+        next.run(request),
+    )
+    .await;
+    // 请求失败时不计入
+    if let Some(reservation) = reservation
+        && !response.status().is_success()
+    {
+        reservation.release().await;
     }
+    response
 }
 
-/// CORS 中间件层
+/// 将限流器的当前状态写入响应头
+///
+/// 请求数：`x-ratelimit-limit` / `x-ratelimit-remaining` / `x-ratelimit-reset`（秒）；
+/// token 数：`x-ratelimit-limit-tokens` / `x-ratelimit-remaining-tokens` / `x-ratelimit-reset-tokens`
+fn insert_rate_limit_headers(headers: &mut HeaderMap, status: RateLimitStatus) {
+    let entries = [("", status.requests), ("-tokens", status.tokens)];
+    for (suffix, limit) in entries {
+        let Some(limit) = limit else {
+            continue;
+        };
+        let reset = limit.reset.as_secs() + u64::from(limit.reset.subsec_nanos() > 0);
+        for (name, value) in [
+            ("limit", limit.limit),
+            ("remaining", limit.remaining),
+            ("reset", reset),
+        ] {
+            let name = format!("x-ratelimit-{}{}", name, suffix);
+            if let Ok(name) = HeaderName::try_from(name) {
+                headers.insert(name, HeaderValue::from(value));
+            }
+        }
+    }
+}
+
+/// 限流中间件
+///
+/// 超过每分钟请求数或 token 数上限时返回 429，并通过 `retry-after` 头告知需要等待的秒数；
+/// 所有响应都带上 `x-ratelimit-*` 头，便于客户端展示剩余额度。
+/// 集群模式下按整个集群的用量限流（Redis 不可用时退回本实例限流）
+pub async fn rate_limit_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(limiter) = &state.rate_limiter else {
+        return next.run(request).await;
+    };
+
+    let shared = crate::cluster::check_rate_limit(
+        limiter.requests_per_minute(),
+        limiter.tokens_per_minute(),
+    )
+    .await;
+    let (decision, shared_status) = match shared {
+        Some((decision, status)) => (decision, Some(status)),
+        None => (limiter.check(), None),
+    };
+
+    let mut response = match decision {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            let secs = secs.max(1);
+            tracing::warn!(retry_after_secs = secs, "请求超出限流上限");
+            let error = ErrorResponse::new(
+                "rate_limit_error",
+                format!("Rate limit exceeded. Retry after {} seconds.", secs),
+            );
+            let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(error)).into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            response
+        }
+    };
+    let status = shared_status.unwrap_or_else(|| limiter.status());
+    insert_rate_limit_headers(response.headers_mut(), status);
+    response
+}
+
+/// 指定本次请求所用凭据的请求头
+pub const CREDENTIAL_OVERRIDE_HEADER: &str = "x-kiro-credential-id";
+
+/// 使用凭据覆盖头时携带 Admin API Key 的请求头
+pub const ADMIN_KEY_HEADER: &str = "x-admin-api-key";
+
+/// 凭据覆盖中间件
 ///
-/// **安全说明**：当前配置允许所有来源（Any），这是为了支持公开 API 服务。
-/// 如果需要更严格的安全控制，请根据实际需求配置具体的允许来源、方法和头信息。
+/// 请求携带 `x-kiro-credential-id` 时，本次请求固定使用该凭据（不经过负载均衡，也不做故障转移），
+/// 用于排查单个账号返回异常内容的问题。需同时在 `x-admin-api-key` 头中携带完整权限 Admin 账号的 Key，
+/// 或在配置中开启 `allowCredentialOverride`
+pub async fn credential_override_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let (Some(value), Some(provider)) = (
+        request.headers().get(CREDENTIAL_OVERRIDE_HEADER),
+        &state.kiro_provider,
+    ) else {
+        return next.run(request).await;
+    };
+
+    let config = provider.token_manager().config();
+    let permitted = config.allow_credential_override
+        || request
+            .headers()
+            .get(ADMIN_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|presented| config.find_admin_account(presented))
+            .is_some_and(|account| account.role == AdminRole::Admin);
+    if !permitted {
+        let error = ErrorResponse::new(
+            "permission_error",
+            format!(
+                "{} requires a valid {} header",
+                CREDENTIAL_OVERRIDE_HEADER, ADMIN_KEY_HEADER
+            ),
+        );
+        return (StatusCode::FORBIDDEN, Json(error)).into_response();
+    }
+
+    let id = value
+        .to_str()
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let exists = id.is_some_and(|id| {
+        provider
+            .token_manager()
+            .snapshot()
+            .entries
+            .iter()
+            .any(|e| e.id == id)
+    });
+    let Some(id) = id.filter(|_| exists) else {
+        let error = ErrorResponse::new(
+            "invalid_request_error",
+            format!(
+                "Unknown credential in {} header",
+                CREDENTIAL_OVERRIDE_HEADER
+            ),
+        );
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    };
+
+    tracing::info!(credential_id = id, "请求通过请求头指定凭据");
+    KiroProvider::with_pinned_credential(Some(id), next.run(request)).await
+}
+
+/// CORS 中间件层
 ///
-/// # 配置说明
-/// - `allow_origin(Any)`: 允许任何来源的请求
-/// - `allow_methods(Any)`: 允许任何 HTTP 方法
-/// - `allow_headers(Any)`: 允许任何请求头
-pub fn cors_layer() -> tower_http::cors::CorsLayer {
-    use tower_http::cors::{Any, CorsLayer};
-
-    CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any)
+/// 规则见配置 `cors.anthropic`；未配置时允许任意来源、方法与请求头，以支持公开 API 服务
+pub fn cors_layer(policy: Option<&CorsPolicy>) -> tower_http::cors::CorsLayer {
+    policy
+        .cloned()
+        .unwrap_or_else(CorsPolicy::permissive)
+        .layer()
 }
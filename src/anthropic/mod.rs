@@ -8,6 +8,9 @@
 //! - `GET /v1/models` - 获取可用模型列表
 //! - `POST /v1/messages` - 创建消息（对话）
 //! - `POST /v1/messages/count_tokens` - 计算 token 数量
+//! - `/v1/messages/batches` - Message Batches API（见 `batches`）
+//! - `POST /v1/chat/completions` - OpenAI Chat Completions 兼容端点（见 `crate::openai`）
+//! - `POST /v1/embeddings` - OpenAI Embeddings 兼容端点（见 `crate::openai`）
 //!
 //! ## Claude Code 兼容端点 (/cc/v1)
 //! - `POST /cc/v1/messages` - 创建消息（流式响应会等待 contextUsageEvent 后再发送 message_start，确保 input_tokens 准确）
@@ -22,12 +25,19 @@
 //! axum::serve(listener, app).await?;
 //! ```
 
-mod converter;
-mod handlers;
-mod middleware;
+pub(crate) mod batches;
+pub(crate) mod conversation_memory;
+pub(crate) mod converter;
+pub(crate) mod handlers;
+pub(crate) mod images;
+pub(crate) mod middleware;
+mod openapi;
+pub(crate) mod prompt_cache;
 mod router;
-mod stream;
+pub(crate) mod secret_scan;
+pub(crate) mod stream;
 pub mod types;
 mod websearch;
 
+pub use openapi::AnthropicApiDoc;
 pub use router::create_router_with_provider;
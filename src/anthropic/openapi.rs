@@ -0,0 +1,24 @@
+//! Anthropic 兼容 API 的 OpenAPI 描述
+
+use utoipa::OpenApi;
+
+use super::{batches, handlers};
+
+/// Anthropic 兼容 API（`/v1`、`/cc/v1`）与就绪检查
+#[derive(OpenApi)]
+#[openapi(paths(
+    handlers::healthz,
+    handlers::readyz,
+    handlers::get_models,
+    handlers::post_messages,
+    handlers::count_tokens,
+    handlers::estimate_messages,
+    handlers::post_messages_cc,
+    batches::create_batch,
+    batches::list_batches,
+    batches::get_batch,
+    batches::cancel_batch,
+    batches::get_batch_results,
+    batches::delete_batch,
+))]
+pub struct AnthropicApiDoc;
@@ -0,0 +1,342 @@
+//! Prompt caching 用量统计
+//!
+//! Kiro 上游不返回缓存命中信息，这里按 Anthropic 的规则在本地推算：请求中每个带
+//! `cache_control` 的块是一个缓存断点，对应从请求开头（tools → system → messages）
+//! 到该块为止的前缀。`PromptCacheTracker` 按模型记录前缀哈希，TTL 内再次出现相同前缀时
+//! 计为 `cache_read_input_tokens`，否则计为 `cache_creation_input_tokens`。
+//! token 数为本地估算值，未命中缓存的部分仍计入 `input_tokens`。
+//!
+//! 前缀记录通过 `PrefixStore` 存储：默认保存在进程内存中；集群模式开启
+//! `cluster.sharePromptCache` 时存入 Redis（见 `cluster`），重启后立即生效并在实例间共享。
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+
+use super::types::{CacheControl, MessagesRequest};
+use crate::token::count_tokens;
+
+/// 默认缓存 TTL（5 分钟）
+const DEFAULT_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// `ttl: "1h"` 的缓存 TTL
+const EXTENDED_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// 可缓存前缀的最小 token 数（低于此值的断点不生效）
+const MIN_CACHEABLE_TOKENS: i32 = 1024;
+
+/// 记录的前缀数上限，超出时清理已过期的条目
+const MAX_ENTRIES: usize = 10_000;
+
+/// 单次请求的缓存用量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheUsage {
+    pub cache_creation_input_tokens: i32,
+    pub cache_read_input_tokens: i32,
+}
+
+impl CacheUsage {
+    /// 从总输入 tokens 中扣除缓存部分，得到 `input_tokens`
+    ///
+    /// 缓存部分为本地估算值，超出总数时按总数截断
+    pub fn uncached_input_tokens(&self, total_input_tokens: i32) -> i32 {
+        (total_input_tokens - self.cache_creation_input_tokens - self.cache_read_input_tokens)
+            .max(0)
+    }
+
+    /// 按总输入 tokens 截断缓存部分（优先保留 cache_read）
+    pub fn clamp(self, total_input_tokens: i32) -> Self {
+        let read = self.cache_read_input_tokens.min(total_input_tokens);
+        let creation = self
+            .cache_creation_input_tokens
+            .min(total_input_tokens - read);
+        Self {
+            cache_creation_input_tokens: creation,
+            cache_read_input_tokens: read,
+        }
+    }
+}
+
+/// 缓存断点：前缀哈希、前缀 token 数与 TTL
+#[derive(Debug, Clone, Copy)]
+pub struct Breakpoint {
+    pub hash: u64,
+    pub tokens: i32,
+    pub ttl: Duration,
+}
+
+fn ttl_of(cache_control: &CacheControl) -> Duration {
+    match cache_control.ttl.as_deref() {
+        Some("1h") => EXTENDED_TTL,
+        _ => DEFAULT_TTL,
+    }
+}
+
+/// 按请求顺序累积前缀，收集所有缓存断点
+fn collect_breakpoints(request: &MessagesRequest) -> Vec<Breakpoint> {
+    let mut hasher = DefaultHasher::new();
+    request.model.hash(&mut hasher);
+    let mut tokens: i32 = 0;
+    let mut breakpoints = Vec::new();
+    let mut mark = |hasher: &DefaultHasher, tokens: i32, ttl: Duration| {
+        breakpoints.push(Breakpoint {
+            hash: hasher.clone().finish(),
+            tokens,
+            ttl,
+        });
+    };
+
+    for tool in request.tools.iter().flatten() {
+        let schema = serde_json::to_string(&tool.input_schema).unwrap_or_default();
+        ("tool", &tool.name, &tool.description, &schema).hash(&mut hasher);
+        tokens += (count_tokens(&tool.name)
+            + count_tokens(&tool.description)
+            + count_tokens(&schema)) as i32;
+        if let Some(cache_control) = &tool.cache_control {
+            mark(&hasher, tokens, ttl_of(cache_control));
+        }
+    }
+
+    for system in request.system.iter().flatten() {
+        ("system", &system.text).hash(&mut hasher);
+        tokens += count_tokens(&system.text) as i32;
+        if let Some(cache_control) = &system.cache_control {
+            mark(&hasher, tokens, ttl_of(cache_control));
+        }
+    }
+
+    for message in &request.messages {
+        match &message.content {
+            serde_json::Value::Array(blocks) => {
+                for block in blocks {
+                    // cache_control 本身不属于缓存内容，移动断点不应改变前缀哈希
+                    let mut content = block.clone();
+                    let cache_control = content
+                        .as_object_mut()
+                        .and_then(|obj| obj.remove("cache_control"))
+                        .and_then(|v| serde_json::from_value::<CacheControl>(v).ok());
+
+                    let serialized = content.to_string();
+                    (&message.role, &serialized).hash(&mut hasher);
+                    tokens += match content.get("text").and_then(|v| v.as_str()) {
+                        Some(text) => count_tokens(text),
+                        None => count_tokens(&serialized),
+                    } as i32;
+                    if let Some(cache_control) = &cache_control {
+                        mark(&hasher, tokens, ttl_of(cache_control));
+                    }
+                }
+            }
+            other => {
+                (&message.role, other.to_string()).hash(&mut hasher);
+                tokens += count_tokens(other.as_str().unwrap_or_default()) as i32;
+            }
+        }
+    }
+
+    breakpoints
+}
+
+/// 前缀记录的存储后端
+pub trait PrefixStore: Send + Sync {
+    /// 写入（或刷新）断点对应的前缀，返回每个断点在写入前是否仍在 TTL 内
+    ///
+    /// 同一前缀的“检查并写入”需为原子操作，多个实例并发请求时只有一个计为 cache_creation
+    fn touch<'a>(
+        &'a self,
+        breakpoints: &'a [Breakpoint],
+    ) -> BoxFuture<'a, anyhow::Result<Vec<bool>>>;
+}
+
+/// 进程内存中的前缀记录（默认）
+#[derive(Default)]
+pub struct MemoryPrefixStore {
+    /// 前缀哈希 -> 过期时间
+    entries: Mutex<HashMap<u64, Instant>>,
+}
+
+impl MemoryPrefixStore {
+    fn touch_now(&self, breakpoints: &[Breakpoint]) -> Vec<bool> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        let hits = breakpoints
+            .iter()
+            .map(|b| {
+                let hit = entries.get(&b.hash).is_some_and(|expires| *expires > now);
+                entries.insert(b.hash, now + b.ttl);
+                hit
+            })
+            .collect();
+        if entries.len() > MAX_ENTRIES {
+            entries.retain(|_, expires| *expires > now);
+        }
+        hits
+    }
+}
+
+impl PrefixStore for MemoryPrefixStore {
+    fn touch<'a>(
+        &'a self,
+        breakpoints: &'a [Breakpoint],
+    ) -> BoxFuture<'a, anyhow::Result<Vec<bool>>> {
+        Box::pin(std::future::ready(Ok(self.touch_now(breakpoints))))
+    }
+}
+
+/// Prompt caching 前缀记录
+pub struct PromptCacheTracker {
+    store: Arc<dyn PrefixStore>,
+    /// 存储后端出错时使用的本地记录
+    fallback: MemoryPrefixStore,
+}
+
+impl Default for PromptCacheTracker {
+    fn default() -> Self {
+        Self::with_store(Arc::new(MemoryPrefixStore::default()))
+    }
+}
+
+impl PromptCacheTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用指定的存储后端
+    pub fn with_store(store: Arc<dyn PrefixStore>) -> Self {
+        Self {
+            store,
+            fallback: MemoryPrefixStore::default(),
+        }
+    }
+
+    /// 计算本次请求的缓存用量，并写入（或刷新）请求中的所有缓存前缀
+    ///
+    /// 命中的最长前缀计为 cache_read，最长断点超出命中部分的 tokens 计为 cache_creation
+    pub async fn record(&self, request: &MessagesRequest) -> CacheUsage {
+        let breakpoints: Vec<Breakpoint> = collect_breakpoints(request)
+            .into_iter()
+            .filter(|b| b.tokens >= MIN_CACHEABLE_TOKENS)
+            .collect();
+        let Some(longest) = breakpoints.iter().map(|b| b.tokens).max() else {
+            return CacheUsage::default();
+        };
+
+        let hits = match self.store.touch(&breakpoints).await {
+            Ok(hits) => hits,
+            Err(e) => {
+                tracing::warn!("读写 prompt caching 前缀记录失败，使用本地记录: {}", e);
+                self.fallback.touch_now(&breakpoints)
+            }
+        };
+        let read = breakpoints
+            .iter()
+            .zip(hits)
+            .filter(|(_, hit)| *hit)
+            .map(|(b, _)| b.tokens)
+            .max()
+            .unwrap_or(0);
+
+        CacheUsage {
+            cache_creation_input_tokens: longest - read,
+            cache_read_input_tokens: read,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(system_text: &str, user_text: &str) -> MessagesRequest {
+        serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 1024,
+            "system": [{
+                "type": "text",
+                "text": system_text,
+                "cache_control": {"type": "ephemeral"}
+            }],
+            "messages": [{
+                "role": "user",
+                "content": [{
+                    "type": "text",
+                    "text": user_text,
+                    "cache_control": {"type": "ephemeral", "ttl": "1h"}
+                }]
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cache_creation_then_read() {
+        let tracker = PromptCacheTracker::new();
+        let system = "You are a helpful assistant. ".repeat(400);
+
+        let first = tracker.record(&request(&system, "hello")).await;
+        assert_eq!(first.cache_read_input_tokens, 0);
+        assert!(first.cache_creation_input_tokens >= MIN_CACHEABLE_TOKENS);
+
+        // 相同前缀再次出现时命中缓存
+        let second = tracker.record(&request(&system, "hello")).await;
+        assert_eq!(second.cache_creation_input_tokens, 0);
+        assert_eq!(
+            second.cache_read_input_tokens,
+            first.cache_creation_input_tokens
+        );
+
+        // 只有 system 前缀相同：命中 system 断点，其余部分重新写入
+        let third = tracker
+            .record(&request(&system, "a different question"))
+            .await;
+        assert!(third.cache_read_input_tokens > 0);
+        assert!(third.cache_creation_input_tokens > 0);
+        assert!(third.cache_read_input_tokens < second.cache_read_input_tokens);
+    }
+
+    #[tokio::test]
+    async fn test_short_prefix_is_not_cached() {
+        let tracker = PromptCacheTracker::new();
+        let usage = tracker.record(&request("short system prompt", "hi")).await;
+        assert_eq!(usage, CacheUsage::default());
+    }
+
+    /// 总是出错的存储后端
+    struct FailingStore;
+
+    impl PrefixStore for FailingStore {
+        fn touch<'a>(&'a self, _: &'a [Breakpoint]) -> BoxFuture<'a, anyhow::Result<Vec<bool>>> {
+            Box::pin(async { anyhow::bail!("connection refused") })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_error_falls_back_to_local() {
+        let tracker = PromptCacheTracker::with_store(Arc::new(FailingStore));
+        let system = "You are a helpful assistant. ".repeat(400);
+
+        let first = tracker.record(&request(&system, "hello")).await;
+        assert_eq!(first.cache_read_input_tokens, 0);
+        let second = tracker.record(&request(&system, "hello")).await;
+        assert_eq!(
+            second.cache_read_input_tokens,
+            first.cache_creation_input_tokens
+        );
+    }
+
+    #[test]
+    fn test_cache_usage_clamp() {
+        let usage = CacheUsage {
+            cache_creation_input_tokens: 800,
+            cache_read_input_tokens: 600,
+        };
+        let clamped = usage.clamp(1000);
+        assert_eq!(clamped.cache_read_input_tokens, 600);
+        assert_eq!(clamped.cache_creation_input_tokens, 400);
+        assert_eq!(clamped.uncached_input_tokens(1000), 0);
+    }
+}
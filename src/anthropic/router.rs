@@ -6,16 +6,30 @@ use axum::{
     middleware,
     routing::{get, post},
 };
+use tower_http::decompression::RequestDecompressionLayer;
 
+use crate::common::rate_limit::RateLimiter;
 use crate::kiro::provider::KiroProvider;
 
 use super::{
-    handlers::{count_tokens, get_models, post_messages, post_messages_cc},
-    middleware::{AppState, auth_middleware, cors_layer},
+    batches::{
+        BatchManager, cancel_batch, create_batch, delete_batch, get_batch, get_batch_results,
+        list_batches,
+    },
+    conversation_memory::conversation_memory_middleware,
+    handlers::{
+        count_tokens, estimate_messages, get_models, healthz, post_messages, post_messages_cc,
+        readyz,
+    },
+    middleware::{
+        AppState, auth_middleware, cors_layer, credential_override_middleware,
+        ip_access_middleware, rate_limit_middleware, tenant_middleware,
+    },
+    prompt_cache::PromptCacheTracker,
 };
 
 /// 请求体最大大小限制 (50MB)
-const MAX_BODY_SIZE: usize = 50 * 1024 * 1024;
+pub(crate) const MAX_BODY_SIZE: usize = 50 * 1024 * 1024;
 
 /// 创建 Anthropic API 路由
 ///
@@ -23,12 +37,31 @@ const MAX_BODY_SIZE: usize = 50 * 1024 * 1024;
 /// - `GET /v1/models` - 获取可用模型列表
 /// - `POST /v1/messages` - 创建消息（对话）
 /// - `POST /v1/messages/count_tokens` - 计算 token 数量
+/// - `POST /v1/messages/estimate` - 预估输入 tokens、费用与将使用的凭据（不调用上游）
+/// - `POST /v1/messages/batches` - 创建消息批次，`GET` 列出批次
+/// - `GET /v1/messages/batches/{id}` - 查询批次，`DELETE` 删除已结束的批次
+/// - `POST /v1/messages/batches/{id}/cancel` - 取消批次
+/// - `GET /v1/messages/batches/{id}/results` - 下载批次结果（JSONL）
+/// - `POST /v1/chat/completions` - OpenAI Chat Completions 兼容端点
+/// - `POST /v1/embeddings` - OpenAI Embeddings 兼容端点（转发到配置的上游）
+///
+/// `GET /healthz` 为存活检查、`GET /readyz` 为就绪检查，不需要认证
 ///
 /// # 认证
 /// 所有 `/v1` 路径需要 API Key 认证，支持：
 /// - `x-api-key` header
 /// - `Authorization: Bearer <token>` header
 ///
+/// 配置了 `rateLimitRequestsPerMinute` / `rateLimitTokensPerMinute` 时，认证通过后还会进行限流
+///
+/// 开启 `conversationMemory` 时，Messages 端点携带 `x-kiro-conversation-id` 头的请求会拼接服务端保存的会话历史（见 `conversation_memory`）
+///
+/// `tenants` 中租户的 API Key 同样可以认证，请求受租户的可用模型、凭据与每月配额限制（见 `tenant_middleware`）
+///
+/// 配置了 `ipAccess.anthropic` 时，认证之前先按客户端 IP 过滤，不允许的地址返回 403
+///
+/// 携带 `x-kiro-credential-id` 头的请求固定使用指定凭据（需 Admin API Key，见 `credential_override_middleware`）
+///
 /// # 参数
 /// - `api_key`: API 密钥，用于验证客户端请求
 /// - `kiro_provider`: 可选的 KiroProvider，用于调用上游 API
@@ -40,37 +73,124 @@ pub fn create_router_with_provider(
     profile_arn: Option<String>,
 ) -> Router {
     let mut state = AppState::new(api_key);
+    let mut cors = None;
     if let Some(provider) = kiro_provider {
+        let config = provider.token_manager().config();
+        cors = config.cors.as_ref().and_then(|cors| cors.anthropic.clone());
+        if let Some(limiter) = RateLimiter::new(
+            config.rate_limit_requests_per_minute,
+            config.rate_limit_tokens_per_minute,
+        ) {
+            state = state.with_rate_limiter(limiter);
+        }
+        if config
+            .cluster
+            .as_ref()
+            .is_some_and(|cluster| cluster.share_prompt_cache)
+            && let Some(store) = crate::cluster::prompt_cache_store()
+        {
+            state = state.with_prompt_cache(PromptCacheTracker::with_store(store));
+        }
+        if let Some(cache_dir) = provider.token_manager().cache_dir() {
+            state = state.with_batch_manager(BatchManager::new(
+                cache_dir.join("batches"),
+                config.batch_concurrency,
+            ));
+        }
         state = state.with_kiro_provider(provider);
     }
     if let Some(arn) = profile_arn {
         state = state.with_profile_arn(arn);
     }
+    // 继续执行上次退出时未结束的批次
+    if let Some(batches) = &state.batches {
+        batches.resume(&state);
+    }
 
     // 需要认证的 /v1 路由
     let v1_routes = Router::new()
         .route("/models", get(get_models))
-        .route("/messages", post(post_messages))
+        .route(
+            "/messages",
+            post(post_messages).layer(middleware::from_fn_with_state(
+                state.clone(),
+                conversation_memory_middleware,
+            )),
+        )
         .route("/messages/count_tokens", post(count_tokens))
+        .route("/messages/estimate", post(estimate_messages))
+        .route("/messages/batches", post(create_batch).get(list_batches))
+        .route(
+            "/messages/batches/{id}",
+            get(get_batch).delete(delete_batch),
+        )
+        .route("/messages/batches/{id}/cancel", post(cancel_batch))
+        .route("/messages/batches/{id}/results", get(get_batch_results))
+        .route("/chat/completions", post(crate::openai::chat_completions))
+        .route("/embeddings", post(crate::openai::embeddings))
+        // 先过滤 IP、认证再限流（后添加的 layer 在外层，先执行），然后处理凭据覆盖头，最后检查租户限制
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            tenant_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            credential_override_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit_middleware,
+        ))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            ip_access_middleware,
         ));
 
     // 需要认证的 /cc/v1 路由（Claude Code 兼容端点）
     // 与 /v1 的区别：流式响应会等待 contextUsageEvent 后再发送 message_start
     let cc_v1_routes = Router::new()
-        .route("/messages", post(post_messages_cc))
+        .route(
+            "/messages",
+            post(post_messages_cc).layer(middleware::from_fn_with_state(
+                state.clone(),
+                conversation_memory_middleware,
+            )),
+        )
         .route("/messages/count_tokens", post(count_tokens))
+        // 中间件顺序与 /v1 相同，限流计数与 /v1 共享
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            tenant_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            credential_override_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit_middleware,
+        ))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            ip_access_middleware,
         ));
 
     Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
         .nest("/v1", v1_routes)
         .nest("/cc/v1", cc_v1_routes)
-        .layer(cors_layer())
+        .layer(cors_layer(cors.as_ref()))
         .layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
+        // 解压 `Content-Encoding: gzip / br` 的请求体（大小限制按解压后计算），不支持的编码返回 415
+        .layer(RequestDecompressionLayer::new())
         .with_state(state)
 }
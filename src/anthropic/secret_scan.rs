@@ -0,0 +1,314 @@
+//! 输出密钥扫描
+//!
+//! 在生成内容返回给客户端之前，屏蔽与本代理自身凭据格式匹配的字符串，
+//! 防止上下文中出现的代理密钥被模型原样输出：
+//! - 配置中的密钥（apiKey、adminApiKey 等）与凭据中的 refreshToken / accessToken / clientSecret（字面匹配）
+//! - `sk-` 开头的 API Key 格式字符串
+//!
+//! 流式输出中密钥可能被拆分到多个 delta，`SecretScrubber` 会暂留每个内容块末尾
+//! 尚未结束的 ASCII 字符串，等下一个 delta 或内容块结束时再扫描发送。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::kiro::provider::KiroProvider;
+
+use super::stream::SseEvent;
+
+/// 替换密钥的占位文本
+pub const REDACTED: &str = "[REDACTED]";
+
+/// 字面匹配的最短密钥长度（过短的值容易误伤正常文本）
+const MIN_LITERAL_LEN: usize = 8;
+
+/// `sk-` 之后至少需要的字符数
+const MIN_SK_KEY_LEN: usize = 20;
+
+/// 流式输出中每个内容块最多暂留的字符数
+const MIN_HOLDBACK_CHARS: usize = 256;
+
+/// 密钥扫描器
+#[derive(Clone)]
+pub struct SecretScanner {
+    /// 字面匹配的密钥（按长度降序）
+    literals: Arc<Vec<String>>,
+}
+
+impl SecretScanner {
+    pub fn new(literals: impl IntoIterator<Item = String>) -> Self {
+        let mut literals: Vec<String> = literals
+            .into_iter()
+            .filter(|s| s.len() >= MIN_LITERAL_LEN)
+            .collect();
+        literals.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        literals.dedup();
+        Self {
+            literals: Arc::new(literals),
+        }
+    }
+
+    /// 根据配置创建扫描器，未启用 `secretScanning` 时返回 None
+    pub fn from_provider(provider: &KiroProvider) -> Option<Self> {
+        let token_manager = provider.token_manager();
+        let config = token_manager.config();
+        if !config.secret_scanning {
+            return None;
+        }
+
+        let config_secrets = [
+            &config.api_key,
+            &config.count_tokens_api_key,
+            &config.proxy_password,
+            &config.moderation_api_key,
+            &config.embeddings_api_key,
+        ];
+        let secrets = config_secrets
+            .into_iter()
+            .flatten()
+            .cloned()
+            .chain(config.all_admin_accounts().into_iter().map(|a| a.key))
+            .chain(config.usage_signing_key.iter().map(|k| k.secret()))
+            .chain(token_manager.secret_values());
+
+        Some(Self::new(secrets))
+    }
+
+    /// 屏蔽文本中的密钥
+    pub fn mask(&self, text: &str) -> String {
+        let mut result = text.to_string();
+        for literal in self.literals.iter() {
+            if result.contains(literal.as_str()) {
+                result = result.replace(literal.as_str(), REDACTED);
+            }
+        }
+        mask_sk_keys(&result)
+    }
+
+    /// 递归屏蔽 JSON 值中的所有字符串
+    pub fn mask_json(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(s) => *s = self.mask(s),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|v| self.mask_json(v)),
+            serde_json::Value::Object(map) => map.values_mut().for_each(|v| self.mask_json(v)),
+            _ => {}
+        }
+    }
+
+    fn holdback_chars(&self) -> usize {
+        self.literals
+            .first()
+            .map(|s| s.len())
+            .unwrap_or_default()
+            .max(MIN_HOLDBACK_CHARS)
+    }
+}
+
+fn is_sk_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// 屏蔽 `sk-` 开头的 API Key 格式字符串
+fn mask_sk_keys(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(pos) = rest.find("sk-") {
+        let preceded_by_key_char = rest[..pos].chars().next_back().is_some_and(is_sk_key_char)
+            || (pos == 0 && result.chars().next_back().is_some_and(is_sk_key_char));
+        let after = &rest[pos + 3..];
+        let key_len = after
+            .find(|c: char| !is_sk_key_char(c))
+            .unwrap_or(after.len());
+
+        result.push_str(&rest[..pos]);
+        if !preceded_by_key_char && key_len >= MIN_SK_KEY_LEN {
+            result.push_str(REDACTED);
+        } else {
+            result.push_str(&rest[pos..pos + 3 + key_len]);
+        }
+        rest = &after[key_len..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// delta 类型 → 承载文本的字段名
+fn delta_text_field(delta_type: &str) -> Option<&'static str> {
+    match delta_type {
+        "text_delta" => Some("text"),
+        "thinking_delta" => Some("thinking"),
+        "input_json_delta" => Some("partial_json"),
+        _ => None,
+    }
+}
+
+/// 流式输出的密钥屏蔽器
+pub struct SecretScrubber {
+    scanner: SecretScanner,
+    /// 内容块索引 → (delta 类型, 暂留的文本)
+    pending: HashMap<i64, (String, String)>,
+}
+
+impl SecretScrubber {
+    pub fn new(scanner: SecretScanner) -> Self {
+        Self {
+            scanner,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// 处理一批 SSE 事件：屏蔽 delta 中的密钥，并在内容块结束前补发暂留的文本
+    pub fn scrub(&mut self, events: Vec<SseEvent>) -> Vec<SseEvent> {
+        let mut output = Vec::with_capacity(events.len());
+
+        for mut event in events {
+            let index = event.data["index"].as_i64().unwrap_or_default();
+            match event.event.as_str() {
+                "content_block_delta" => {
+                    let delta_type = event.data["delta"]["type"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string();
+                    let Some(field) = delta_text_field(&delta_type) else {
+                        output.push(event);
+                        continue;
+                    };
+                    let text = event.data["delta"][field].as_str().unwrap_or_default();
+                    let held = self
+                        .pending
+                        .remove(&index)
+                        .map(|(_, held)| held)
+                        .unwrap_or_default();
+
+                    // 空 delta 有语义（如关闭 thinking 块前的空 thinking_delta），需先补发暂留文本
+                    if text.is_empty() {
+                        if !held.is_empty() {
+                            output.push(delta_event(
+                                index,
+                                &delta_type,
+                                field,
+                                &self.scanner.mask(&held),
+                            ));
+                        }
+                        output.push(event);
+                        continue;
+                    }
+
+                    let masked = self.scanner.mask(&format!("{}{}", held, text));
+                    let cut = self.safe_cut(&masked);
+                    if cut < masked.len() {
+                        self.pending
+                            .insert(index, (delta_type.clone(), masked[cut..].to_string()));
+                    }
+                    if cut > 0 {
+                        event.data["delta"][field] =
+                            serde_json::Value::String(masked[..cut].to_string());
+                        output.push(event);
+                    }
+                }
+                "content_block_stop" => {
+                    if let Some((delta_type, held)) = self.pending.remove(&index)
+                        && let Some(field) = delta_text_field(&delta_type)
+                    {
+                        output.push(delta_event(
+                            index,
+                            &delta_type,
+                            field,
+                            &self.scanner.mask(&held),
+                        ));
+                    }
+                    output.push(event);
+                }
+                _ => output.push(event),
+            }
+        }
+
+        output
+    }
+
+    /// 计算可以安全发送的前缀长度：末尾连续的 ASCII 可见字符（最多 holdback 个）需要暂留
+    fn safe_cut(&self, text: &str) -> usize {
+        let limit = self.scanner.holdback_chars();
+        let mut cut = text.len();
+        for (count, (i, c)) in text.char_indices().rev().enumerate() {
+            if count >= limit || !c.is_ascii_graphic() {
+                break;
+            }
+            cut = i;
+        }
+        cut
+    }
+}
+
+fn delta_event(index: i64, delta_type: &str, field: &str, text: &str) -> SseEvent {
+    let mut delta = serde_json::json!({ "type": delta_type });
+    delta[field] = serde_json::Value::String(text.to_string());
+    SseEvent::new(
+        "content_block_delta",
+        serde_json::json!({
+            "type": "content_block_delta",
+            "index": index,
+            "delta": delta
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scanner() -> SecretScanner {
+        SecretScanner::new(vec![
+            "aorAAAA-refresh-token".to_string(),
+            "short".to_string(),
+        ])
+    }
+
+    #[test]
+    fn test_mask_literals_and_sk_keys() {
+        let s = scanner();
+        assert_eq!(
+            s.mask("token=aorAAAA-refresh-token, key sk-abcdefghijklmnopqrstuvwx."),
+            "token=[REDACTED], key [REDACTED]."
+        );
+        // 过短的字面值与不完整的 sk- 不屏蔽
+        assert_eq!(
+            s.mask("short sk-abc task-abcdefghijklmnopqrstuvwxyz"),
+            "short sk-abc task-abcdefghijklmnopqrstuvwxyz"
+        );
+    }
+
+    #[test]
+    fn test_mask_json() {
+        let mut value = json!({"args": ["sk-abcdefghijklmnopqrstuvwx", 1]});
+        scanner().mask_json(&mut value);
+        assert_eq!(value["args"][0], REDACTED);
+    }
+
+    fn text_delta(text: &str) -> SseEvent {
+        SseEvent::new(
+            "content_block_delta",
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": text}}),
+        )
+    }
+
+    #[test]
+    fn test_scrubber_masks_secret_split_across_deltas() {
+        let mut scrubber = SecretScrubber::new(scanner());
+        let mut events = scrubber.scrub(vec![text_delta("your key is sk-abcdefghij")]);
+        events.extend(scrubber.scrub(vec![text_delta("klmnopqrstuvwx ok")]));
+        events.extend(scrubber.scrub(vec![SseEvent::new(
+            "content_block_stop",
+            json!({"type": "content_block_stop", "index": 0}),
+        )]));
+
+        let text: String = events
+            .iter()
+            .filter_map(|e| e.data["delta"]["text"].as_str())
+            .collect();
+        assert_eq!(text, "your key is [REDACTED] ok");
+        assert_eq!(events.last().unwrap().event, "content_block_stop");
+    }
+}
@@ -7,8 +7,12 @@ use std::collections::HashMap;
 use serde_json::json;
 use uuid::Uuid;
 
+use crate::events::TokenUsage;
 use crate::kiro::model::events::Event;
 
+use super::prompt_cache::CacheUsage;
+use super::secret_scan::{SecretScanner, SecretScrubber};
+
 /// 找到小于等于目标位置的最近有效UTF-8字符边界
 ///
 /// UTF-8字符可能占用1-4个字节，直接按字节位置切片可能会切在多字节字符中间导致panic。
@@ -319,21 +323,29 @@ impl SseStateManager {
     ) -> Vec<SseEvent> {
         let mut events = Vec::new();
 
-        // 如果是 tool_use 块，先关闭之前的文本块
+        // tool_use 块前关闭之前的文本块；开始新的 tool_use 块时还要关闭未收到 stop 的 tool_use 块。
+        // 开始其他块时不关闭 tool_use 块，上游在文本之间继续发送的工具参数仍需输出
         if block_type == "tool_use" {
             self.has_tool_use = true;
+            let is_new = !self.active_blocks.get(&index).is_some_and(|b| b.started);
             for (block_index, block) in self.active_blocks.iter_mut() {
-                if block.block_type == "text" && block.started && !block.stopped {
-                    // 自动发送 content_block_stop 关闭文本块
-                    events.push(SseEvent::new(
-                        "content_block_stop",
-                        json!({
-                            "type": "content_block_stop",
-                            "index": block_index
-                        }),
-                    ));
-                    block.stopped = true;
+                let closes = match block.block_type.as_str() {
+                    "text" => true,
+                    "tool_use" => is_new,
+                    _ => false,
+                };
+                if *block_index == index || !closes || !block.started || block.stopped {
+                    continue;
                 }
+                // 自动发送 content_block_stop 关闭之前的块
+                events.push(SseEvent::new(
+                    "content_block_stop",
+                    json!({
+                        "type": "content_block_stop",
+                        "index": block_index
+                    }),
+                ));
+                block.stopped = true;
             }
         }
 
@@ -399,11 +411,12 @@ impl SseStateManager {
         None
     }
 
-    /// 生成最终事件序列
+    /// 生成最终事件序列（`input_tokens` 为未命中缓存的部分）
     pub fn generate_final_events(
         &mut self,
         input_tokens: i32,
         output_tokens: i32,
+        cache_usage: CacheUsage,
     ) -> Vec<SseEvent> {
         let mut events = Vec::new();
 
@@ -434,7 +447,9 @@ impl SseStateManager {
                     },
                     "usage": {
                         "input_tokens": input_tokens,
-                        "output_tokens": output_tokens
+                        "output_tokens": output_tokens,
+                        "cache_creation_input_tokens": cache_usage.cache_creation_input_tokens,
+                        "cache_read_input_tokens": cache_usage.cache_read_input_tokens
                     }
                 }),
             ));
@@ -453,6 +468,62 @@ impl SseStateManager {
     }
 }
 
+/// 单个 input_json_delta 的最大长度（字节）
+///
+/// 上游有时一次返回整段工具参数，拆成多个 delta 让解析部分参数的客户端（如 Claude Code）逐步显示
+const TOOL_INPUT_CHUNK_BYTES: usize = 256;
+
+/// 按 `TOOL_INPUT_CHUNK_BYTES` 拆分工具参数片段（在字符边界处切分）
+fn split_tool_input(input: &str) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = input;
+    while rest.len() > TOOL_INPUT_CHUNK_BYTES {
+        let mut cut = TOOL_INPUT_CHUNK_BYTES;
+        while !rest.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        let (chunk, tail) = rest.split_at(cut);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+/// thinking 块的签名
+///
+/// Kiro 不返回签名，这里按 `seed`（消息 ID）生成不透明的占位值，保证 thinking 块带有
+/// `signature` 字段；客户端回传历史消息时只使用 thinking 文本，不校验签名
+pub(crate) fn thinking_signature(seed: &str) -> String {
+    use base64::Engine;
+    use sha2::{Digest, Sha256};
+
+    base64::engine::general_purpose::STANDARD.encode(Sha256::digest(seed.as_bytes()))
+}
+
+/// 从完整的响应文本中拆出开头的 thinking 内容（非流式响应使用，规则与流式处理一致）
+///
+/// 返回 `(thinking, text)`；文本不以 `<thinking>` 开头（前导空白除外）时返回 `None`
+pub(crate) fn split_thinking(content: &str) -> Option<(String, String)> {
+    let start = find_real_thinking_start_tag(content)?;
+    if !content[..start].trim().is_empty() {
+        return None;
+    }
+    let body = &content[start + "<thinking>".len()..];
+    let (thinking, text) = match find_real_thinking_end_tag(body) {
+        Some(end) => (&body[..end], &body[end + "</thinking>\n\n".len()..]),
+        // 结束标签在末尾（紧跟 tool_use 或流结束），或缺失时全部作为 thinking
+        None => match find_real_thinking_end_tag_at_buffer_end(body) {
+            Some(end) => (&body[..end], ""),
+            None => (body, ""),
+        },
+    };
+    let thinking = thinking.strip_prefix('\n').unwrap_or(thinking);
+    Some((thinking.to_string(), text.to_string()))
+}
+
 /// 上下文窗口大小（200k tokens）
 const CONTEXT_WINDOW_SIZE: i32 = 200_000;
 
@@ -487,6 +558,12 @@ pub struct StreamContext {
     /// 是否需要剥离 thinking 内容开头的换行符
     /// 模型输出 `<thinking>\n` 时，`\n` 可能与标签在同一 chunk 或下一 chunk
     strip_thinking_leading_newline: bool,
+    /// 上游流是否中途中断
+    aborted: bool,
+    /// Prompt caching 用量（本地推算，计入 input_tokens 的总数中）
+    cache_usage: CacheUsage,
+    /// 输出密钥屏蔽器（启用 secretScanning 时存在）
+    secret_scrubber: Option<SecretScrubber>,
 }
 
 impl StreamContext {
@@ -511,11 +588,56 @@ impl StreamContext {
             thinking_block_index: None,
             text_block_index: None,
             strip_thinking_leading_newline: false,
+            aborted: false,
+            cache_usage: CacheUsage::default(),
+            secret_scrubber: None,
+        }
+    }
+
+    /// 启用输出密钥屏蔽
+    pub fn with_secret_scanner(mut self, scanner: Option<SecretScanner>) -> Self {
+        self.secret_scrubber = scanner.map(SecretScrubber::new);
+        self
+    }
+
+    /// 设置 prompt caching 用量
+    pub fn with_cache_usage(mut self, cache_usage: CacheUsage) -> Self {
+        self.cache_usage = cache_usage;
+        self
+    }
+
+    /// 按总输入 tokens 拆分出的 usage 字段（input_tokens 不含缓存部分）
+    fn input_usage(&self, total_input_tokens: i32) -> (i32, CacheUsage) {
+        let cache_usage = self.cache_usage.clamp(total_input_tokens);
+        (
+            cache_usage.uncached_input_tokens(total_input_tokens),
+            cache_usage,
+        )
+    }
+
+    /// 目前为止的 token 用量（有 contextUsageEvent 时使用其计算的输入 tokens）
+    pub fn usage(&self) -> TokenUsage {
+        let (input_tokens, cache_usage) =
+            self.input_usage(self.context_input_tokens.unwrap_or(self.input_tokens));
+        TokenUsage {
+            input_tokens,
+            output_tokens: self.output_tokens,
+            cache_creation_input_tokens: cache_usage.cache_creation_input_tokens,
+            cache_read_input_tokens: cache_usage.cache_read_input_tokens,
+        }
+    }
+
+    /// 对生成的事件进行密钥屏蔽（未启用时原样返回）
+    fn scrub(&mut self, events: Vec<SseEvent>) -> Vec<SseEvent> {
+        match &mut self.secret_scrubber {
+            Some(scrubber) => scrubber.scrub(events),
+            None => events,
         }
     }
 
     /// 生成 message_start 事件
     pub fn create_message_start_event(&self) -> serde_json::Value {
+        let (input_tokens, cache_usage) = self.input_usage(self.input_tokens);
         json!({
             "type": "message_start",
             "message": {
@@ -527,8 +649,10 @@ impl StreamContext {
                 "stop_reason": null,
                 "stop_sequence": null,
                 "usage": {
-                    "input_tokens": self.input_tokens,
-                    "output_tokens": 1
+                    "input_tokens": input_tokens,
+                    "output_tokens": 1,
+                    "cache_creation_input_tokens": cache_usage.cache_creation_input_tokens,
+                    "cache_read_input_tokens": cache_usage.cache_read_input_tokens
                 }
             }
         })
@@ -575,6 +699,11 @@ impl StreamContext {
 
     /// 处理 Kiro 事件并转换为 Anthropic SSE 事件
     pub fn process_kiro_event(&mut self, event: &Event) -> Vec<SseEvent> {
+        let events = self.convert_kiro_event(event);
+        self.scrub(events)
+    }
+
+    fn convert_kiro_event(&mut self, event: &Event) -> Vec<SseEvent> {
         match event {
             Event::AssistantResponse(resp) => self.process_assistant_response(&resp.content),
             Event::ToolUse(tool_use) => self.process_tool_use(tool_use),
@@ -674,7 +803,8 @@ impl StreamContext {
                             "index": thinking_index,
                             "content_block": {
                                 "type": "thinking",
-                                "thinking": ""
+                                "thinking": "",
+                                "signature": ""
                             }
                         }),
                     );
@@ -730,17 +860,7 @@ impl StreamContext {
                     self.in_thinking_block = false;
                     self.thinking_extracted = true;
 
-                    // 发送空的 thinking_delta 事件，然后发送 content_block_stop 事件
-                    if let Some(thinking_index) = self.thinking_block_index {
-                        // 先发送空的 thinking_delta
-                        events.push(self.create_thinking_delta_event(thinking_index, ""));
-                        // 再发送 content_block_stop
-                        if let Some(stop_event) =
-                            self.state_manager.handle_content_block_stop(thinking_index)
-                        {
-                            events.push(stop_event);
-                        }
-                    }
+                    self.close_thinking_block(&mut events);
 
                     // 剥离 `</thinking>\n\n`（find_real_thinking_end_tag 已确认 \n\n 存在）
                     self.thinking_buffer =
@@ -844,6 +964,28 @@ impl StreamContext {
         events
     }
 
+    /// 关闭 thinking 块：依次发送空的 thinking_delta、signature_delta 与 content_block_stop
+    fn close_thinking_block(&mut self, events: &mut Vec<SseEvent>) {
+        let Some(thinking_index) = self.thinking_block_index else {
+            return;
+        };
+        events.push(self.create_thinking_delta_event(thinking_index, ""));
+        events.push(SseEvent::new(
+            "content_block_delta",
+            json!({
+                "type": "content_block_delta",
+                "index": thinking_index,
+                "delta": {
+                    "type": "signature_delta",
+                    "signature": thinking_signature(&self.message_id)
+                }
+            }),
+        ));
+        if let Some(stop_event) = self.state_manager.handle_content_block_stop(thinking_index) {
+            events.push(stop_event);
+        }
+    }
+
     /// 创建 thinking_delta 事件
     fn create_thinking_delta_event(&self, index: i32, thinking: &str) -> SseEvent {
         SseEvent::new(
@@ -887,16 +1029,7 @@ impl StreamContext {
                 self.in_thinking_block = false;
                 self.thinking_extracted = true;
 
-                if let Some(thinking_index) = self.thinking_block_index {
-                    // 先发送空的 thinking_delta
-                    events.push(self.create_thinking_delta_event(thinking_index, ""));
-                    // 再发送 content_block_stop
-                    if let Some(stop_event) =
-                        self.state_manager.handle_content_block_stop(thinking_index)
-                    {
-                        events.push(stop_event);
-                    }
-                }
+                self.close_thinking_block(&mut events);
 
                 // 把结束标签后的内容当作普通文本（通常为空或空白）
                 let after_pos = end_pos + "</thinking>".len();
@@ -945,12 +1078,20 @@ impl StreamContext {
                 }
             }),
         );
-        events.extend(start_events);
 
-        // 发送参数增量 (ToolUseEvent.input 是 String 类型)
-        if !tool_use.input.is_empty() {
-            self.output_tokens += (tool_use.input.len() as i32 + 3) / 4; // 估算 token
+        // 与 Anthropic 一致，块开始后先发送一个空的 input_json_delta（无参数的工具也有 delta）
+        let started = start_events
+            .iter()
+            .any(|event| event.event == "content_block_start");
+        events.extend(start_events);
+        let mut fragments = split_tool_input(&tool_use.input);
+        if started {
+            fragments.insert(0, "");
+        }
 
+        // 发送参数增量 (ToolUseEvent.input 是 String 类型)，过长的片段拆成多个 delta
+        self.output_tokens += (tool_use.input.len() as i32 + 3) / 4; // 估算 token
+        for fragment in fragments {
             if let Some(delta_event) = self.state_manager.handle_content_block_delta(
                 block_index,
                 json!({
@@ -958,7 +1099,7 @@ impl StreamContext {
                     "index": block_index,
                     "delta": {
                         "type": "input_json_delta",
-                        "partial_json": tool_use.input
+                        "partial_json": fragment
                     }
                 }),
             ) {
@@ -996,15 +1137,7 @@ impl StreamContext {
                         }
                     }
 
-                    // 关闭 thinking 块：先发送空的 thinking_delta，再发送 content_block_stop
-                    if let Some(thinking_index) = self.thinking_block_index {
-                        events.push(self.create_thinking_delta_event(thinking_index, ""));
-                        if let Some(stop_event) =
-                            self.state_manager.handle_content_block_stop(thinking_index)
-                        {
-                            events.push(stop_event);
-                        }
-                    }
+                    self.close_thinking_block(&mut events);
 
                     // 把结束标签后的内容当作普通文本（通常为空或空白）
                     let after_pos = end_pos + "</thinking>".len();
@@ -1022,17 +1155,7 @@ impl StreamContext {
                             self.create_thinking_delta_event(thinking_index, &self.thinking_buffer),
                         );
                     }
-                    // 关闭 thinking 块：先发送空的 thinking_delta，再发送 content_block_stop
-                    if let Some(thinking_index) = self.thinking_block_index {
-                        // 先发送空的 thinking_delta
-                        events.push(self.create_thinking_delta_event(thinking_index, ""));
-                        // 再发送 content_block_stop
-                        if let Some(stop_event) =
-                            self.state_manager.handle_content_block_stop(thinking_index)
-                        {
-                            events.push(stop_event);
-                        }
-                    }
+                    self.close_thinking_block(&mut events);
                 }
             } else {
                 // 否则发送剩余内容作为 text_delta
@@ -1053,14 +1176,33 @@ impl StreamContext {
             events.extend(self.create_text_delta_events(" "));
         }
 
+        // 上游中断时以 error 结束，覆盖其他推断出的 stop_reason
+        if self.aborted {
+            self.state_manager.set_stop_reason("error");
+        }
+
         // 使用从 contextUsageEvent 计算的 input_tokens，如果没有则使用估算值
-        let final_input_tokens = self.context_input_tokens.unwrap_or(self.input_tokens);
+        let (final_input_tokens, cache_usage) =
+            self.input_usage(self.context_input_tokens.unwrap_or(self.input_tokens));
 
         // 生成最终事件
-        events.extend(
-            self.state_manager
-                .generate_final_events(final_input_tokens, self.output_tokens),
-        );
+        events.extend(self.state_manager.generate_final_events(
+            final_input_tokens,
+            self.output_tokens,
+            cache_usage,
+        ));
+        self.scrub(events)
+    }
+
+    /// 上游流中途中断时生成最终事件序列
+    ///
+    /// 已生成的内容块正常关闭，message_delta 的 stop_reason 为 `error`，
+    /// usage 中的 output_tokens 反映中断前已生成的部分，保证额度统计准确；
+    /// 随后发送 Anthropic 格式的 `error` 事件告知客户端响应不完整，最后以 message_stop 结束
+    pub fn generate_abort_events(&mut self, message: &str) -> Vec<SseEvent> {
+        self.aborted = true;
+        let mut events = self.generate_final_events();
+        insert_stream_error_event(&mut events, message);
         events
     }
 }
@@ -1103,6 +1245,18 @@ impl BufferedStreamContext {
         }
     }
 
+    /// 启用输出密钥屏蔽
+    pub fn with_secret_scanner(mut self, scanner: Option<SecretScanner>) -> Self {
+        self.inner = self.inner.with_secret_scanner(scanner);
+        self
+    }
+
+    /// 设置 prompt caching 用量
+    pub fn with_cache_usage(mut self, cache_usage: CacheUsage) -> Self {
+        self.inner = self.inner.with_cache_usage(cache_usage);
+        self
+    }
+
     /// 处理 Kiro 事件并缓冲结果
     ///
     /// 复用 StreamContext 的事件处理逻辑，但把结果缓存而不是立即发送。
@@ -1137,11 +1291,12 @@ impl BufferedStreamContext {
         let final_events = self.inner.generate_final_events();
         self.event_buffer.extend(final_events);
 
-        // 获取正确的 input_tokens
-        let final_input_tokens = self
-            .inner
-            .context_input_tokens
-            .unwrap_or(self.estimated_input_tokens);
+        // 获取正确的 input_tokens（扣除缓存部分）
+        let (final_input_tokens, cache_usage) = self.inner.input_usage(
+            self.inner
+                .context_input_tokens
+                .unwrap_or(self.estimated_input_tokens),
+        );
 
         // 更正 message_start 事件中的 input_tokens
         for event in &mut self.event_buffer {
@@ -1149,6 +1304,10 @@ impl BufferedStreamContext {
                 if let Some(message) = event.data.get_mut("message") {
                     if let Some(usage) = message.get_mut("usage") {
                         usage["input_tokens"] = serde_json::json!(final_input_tokens);
+                        usage["cache_creation_input_tokens"] =
+                            serde_json::json!(cache_usage.cache_creation_input_tokens);
+                        usage["cache_read_input_tokens"] =
+                            serde_json::json!(cache_usage.cache_read_input_tokens);
                     }
                 }
             }
@@ -1156,6 +1315,43 @@ impl BufferedStreamContext {
 
         std::mem::take(&mut self.event_buffer)
     }
+
+    /// 上游流中途中断时完成处理并返回所有事件（stop_reason 为 `error`，并附带 `error` 事件）
+    pub fn abort_and_get_all_events(&mut self, message: &str) -> Vec<SseEvent> {
+        self.inner.aborted = true;
+        let mut events = self.finish_and_get_all_events();
+        insert_stream_error_event(&mut events, message);
+        events
+    }
+
+    /// 已生成的输出 tokens
+    pub fn output_tokens(&self) -> i32 {
+        self.inner.output_tokens
+    }
+
+    /// 目前为止的 token 用量
+    pub fn usage(&self) -> TokenUsage {
+        self.inner.usage()
+    }
+}
+
+/// 在 message_stop 之前插入 `error` 事件（没有 message_stop 时追加到末尾）
+fn insert_stream_error_event(events: &mut Vec<SseEvent>, message: &str) {
+    let error = SseEvent::new(
+        "error",
+        json!({
+            "type": "error",
+            "error": {
+                "type": "api_error",
+                "message": message
+            }
+        }),
+    );
+    let position = events
+        .iter()
+        .position(|e| e.event == "message_stop")
+        .unwrap_or(events.len());
+    events.insert(position, error);
 }
 
 /// 简单的 token 估算
@@ -1286,6 +1482,136 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tool_use_streams_input_json_deltas() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        ctx.generate_initial_events();
+
+        let input = format!(r#"{{"content": "{}"}}"#, "中文".repeat(100));
+        let mut events = ctx.process_tool_use(&crate::kiro::model::events::ToolUseEvent {
+            name: "write".to_string(),
+            tool_use_id: "tool_1".to_string(),
+            input: input[..19].to_string(),
+            stop: false,
+        });
+        events.extend(
+            ctx.process_tool_use(&crate::kiro::model::events::ToolUseEvent {
+                name: "write".to_string(),
+                tool_use_id: "tool_1".to_string(),
+                input: input[19..].to_string(),
+                stop: true,
+            }),
+        );
+
+        let deltas: Vec<&str> = events
+            .iter()
+            .filter(|e| e.data["delta"]["type"] == "input_json_delta")
+            .map(|e| e.data["delta"]["partial_json"].as_str().unwrap())
+            .collect();
+        // 块开始后的空 delta、第一段，以及按长度拆分后的第二段
+        assert_eq!(deltas[0], "");
+        assert_eq!(deltas[1], &input[..19]);
+        assert!(deltas.len() > 3);
+        assert!(deltas.iter().all(|d| d.len() <= TOOL_INPUT_CHUNK_BYTES));
+        assert_eq!(deltas.concat(), input);
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| e.event == "content_block_start")
+                .count(),
+            1
+        );
+        assert_eq!(events.last().unwrap().event, "content_block_stop");
+    }
+
+    #[test]
+    fn test_unstopped_tool_use_closed_before_next_block() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        ctx.generate_initial_events();
+
+        let tool = |id: &str| crate::kiro::model::events::ToolUseEvent {
+            name: "read".to_string(),
+            tool_use_id: id.to_string(),
+            input: "{}".to_string(),
+            stop: false,
+        };
+        let first = ctx.process_tool_use(&tool("tool_1"));
+        let first_index = first
+            .iter()
+            .find(|e| e.event == "content_block_start")
+            .map(|e| e.data["index"].clone())
+            .unwrap();
+
+        // 上一个 tool_use 未收到 stop 就开始下一个：先关闭上一个块
+        let second = ctx.process_tool_use(&tool("tool_2"));
+        let stop = second
+            .iter()
+            .position(|e| e.event == "content_block_stop" && e.data["index"] == first_index);
+        let start = second.iter().position(|e| e.event == "content_block_start");
+        assert!(stop.unwrap() < start.unwrap());
+
+        // 之后的文本不关闭仍未结束的 tool_use 块，由最终事件关闭
+        let text = ctx.process_assistant_response("done");
+        assert!(!text.iter().any(|e| e.event == "content_block_stop"));
+        let tool_2_index = second
+            .iter()
+            .find(|e| e.event == "content_block_start")
+            .map(|e| e.data["index"].clone())
+            .unwrap();
+        let finals = ctx.generate_final_events();
+        assert!(
+            finals
+                .iter()
+                .any(|e| e.event == "content_block_stop" && e.data["index"] == tool_2_index)
+        );
+    }
+
+    #[test]
+    fn test_tool_input_interleaved_with_text_is_not_truncated() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        ctx.generate_initial_events();
+
+        let tool = |input: &str, stop: bool| crate::kiro::model::events::ToolUseEvent {
+            name: "read".to_string(),
+            tool_use_id: "tool_1".to_string(),
+            input: input.to_string(),
+            stop,
+        };
+        let mut events = ctx.process_tool_use(&tool(r#"{"path": "#, false));
+        events.extend(ctx.process_assistant_response("checking"));
+        events.extend(ctx.process_tool_use(&tool(r#""a.txt"}"#, false)));
+        events.extend(ctx.process_assistant_response(" more"));
+        events.extend(ctx.process_tool_use(&tool("", true)));
+
+        let tool_index = events
+            .iter()
+            .find(|e| {
+                e.event == "content_block_start" && e.data["content_block"]["type"] == "tool_use"
+            })
+            .map(|e| e.data["index"].clone())
+            .unwrap();
+        let input: String = events
+            .iter()
+            .filter(|e| e.data["delta"]["type"] == "input_json_delta")
+            .map(|e| e.data["delta"]["partial_json"].as_str().unwrap())
+            .collect();
+        assert_eq!(input, r#"{"path": "a.txt"}"#);
+        let text: String = events
+            .iter()
+            .filter(|e| e.data["delta"]["type"] == "text_delta")
+            .map(|e| e.data["delta"]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(text, "checking more");
+        // tool_use 块只在收到 stop 时关闭一次
+        let stops: Vec<usize> = events
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.event == "content_block_stop" && e.data["index"] == tool_index)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(stops, vec![events.len() - 1]);
+    }
+
     #[test]
     fn test_tool_use_flushes_pending_thinking_buffer_text_before_tool_block() {
         // thinking 模式下，短文本可能被暂存在 thinking_buffer 以等待 `<thinking>` 的跨 chunk 匹配。
@@ -1845,6 +2171,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_abort_events_report_error_stop_reason_with_partial_usage() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        let _initial_events = ctx.generate_initial_events();
+
+        let mut all_events = Vec::new();
+        all_events.extend(ctx.process_assistant_response("partial answer"));
+        all_events.extend(ctx.generate_abort_events("upstream stream interrupted"));
+
+        let message_delta = all_events
+            .iter()
+            .find(|e| e.event == "message_delta")
+            .expect("should have message_delta event");
+        assert_eq!(message_delta.data["delta"]["stop_reason"], "error");
+        assert!(message_delta.data["usage"]["output_tokens"].as_i64().unwrap() > 0);
+
+        // 已打开的文本块应被关闭，error 事件紧跟在 message_stop 之前
+        assert!(all_events.iter().any(|e| e.event == "content_block_stop"));
+        assert_eq!(all_events.last().unwrap().event, "message_stop");
+        let error = &all_events[all_events.len() - 2];
+        assert_eq!(error.event, "error");
+        assert_eq!(error.data["error"]["type"], "api_error");
+        assert_eq!(
+            error.data["error"]["message"],
+            "upstream stream interrupted"
+        );
+    }
+
+    #[test]
+    fn test_buffered_abort_events_report_error_stop_reason() {
+        let mut ctx = BufferedStreamContext::new("test-model", 1, true);
+        let events = ctx.abort_and_get_all_events("upstream stream interrupted");
+
+        assert_eq!(events.first().unwrap().event, "message_start");
+        let message_delta = events
+            .iter()
+            .find(|e| e.event == "message_delta")
+            .expect("should have message_delta event");
+        assert_eq!(message_delta.data["delta"]["stop_reason"], "error");
+        assert!(events.iter().any(|e| e.event == "error"));
+        assert_eq!(events.last().unwrap().event, "message_stop");
+    }
+
     #[test]
     fn test_thinking_with_text_keeps_end_turn_stop_reason() {
         // thinking + text 的情况，stop_reason 应为 end_turn
@@ -1892,4 +2261,52 @@ mod tests {
             "stop_reason should be tool_use when tool_use is present"
         );
     }
+
+    #[test]
+    fn test_thinking_block_ends_with_signature_delta() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, true);
+        let _initial_events = ctx.generate_initial_events();
+
+        let events = ctx.process_assistant_response("<thinking>\nabc</thinking>\n\nhello");
+        let start = events
+            .iter()
+            .find(|e| e.event == "content_block_start")
+            .unwrap();
+        assert_eq!(start.data["content_block"]["signature"], "");
+
+        let signature = events
+            .iter()
+            .position(|e| e.data["delta"]["type"] == "signature_delta")
+            .expect("should send signature_delta");
+        let stop = events
+            .iter()
+            .position(|e| e.event == "content_block_stop")
+            .unwrap();
+        assert_eq!(signature + 1, stop);
+        assert_eq!(
+            events[signature].data["delta"]["signature"],
+            thinking_signature(&ctx.message_id)
+        );
+    }
+
+    #[test]
+    fn test_split_thinking() {
+        assert_eq!(
+            split_thinking("\n\n<thinking>\nplan</thinking>\n\nanswer"),
+            Some(("plan".to_string(), "answer".to_string()))
+        );
+        // 结束标签紧跟工具调用或响应结束
+        assert_eq!(
+            split_thinking("<thinking>plan</thinking>"),
+            Some(("plan".to_string(), String::new()))
+        );
+        // 正文中提到的标签不是 thinking 块
+        assert_eq!(split_thinking("use `<thinking>` tags"), None);
+        assert_eq!(split_thinking("answer <thinking>x</thinking>\n\n"), None);
+        // 思考内容中引用的结束标签不会提前结束 thinking
+        assert_eq!(
+            split_thinking("<thinking>say `</thinking>` here</thinking>\n\nok"),
+            Some(("say `</thinking>` here".to_string(), "ok".to_string()))
+        );
+    }
 }
@@ -2,17 +2,18 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use utoipa::ToSchema;
 
 // === 错误响应 ===
 
 /// API 错误响应
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ErrorResponse {
     pub error: ErrorDetail,
 }
 
 /// 错误详情
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ErrorDetail {
     #[serde(rename = "type")]
     pub error_type: String,
@@ -34,12 +35,17 @@ impl ErrorResponse {
     pub fn authentication_error() -> Self {
         Self::new("authentication_error", "Invalid API key")
     }
+
+    /// 创建 API Key 已过期的错误响应
+    pub fn api_key_expired() -> Self {
+        Self::new("authentication_error", "API key has expired")
+    }
 }
 
 // === Models 端点类型 ===
 
 /// 模型信息
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct Model {
     pub id: String,
     pub object: String,
@@ -52,7 +58,7 @@ pub struct Model {
 }
 
 /// 模型列表响应
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ModelsResponse {
     pub object: String,
     pub data: Vec<Model>,
@@ -64,7 +70,7 @@ pub struct ModelsResponse {
 const MAX_BUDGET_TOKENS: i32 = 24576;
 
 /// Thinking 配置
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, ToSchema)]
 pub struct Thinking {
     #[serde(rename = "type")]
     pub thinking_type: String,
@@ -94,7 +100,7 @@ where
 }
 
 /// OutputConfig 配置
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, ToSchema)]
 pub struct OutputConfig {
     #[serde(default = "default_effort")]
     pub effort: String,
@@ -105,14 +111,14 @@ fn default_effort() -> String {
 }
 
 /// Claude Code 请求中的 metadata
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct Metadata {
     /// 用户 ID，格式如: user_xxx_account__session_0b4445e1-f5be-49e1-87ce-62bbc28ad705
     pub user_id: Option<String>,
 }
 
 /// Messages 请求体
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct MessagesRequest {
     pub model: String,
     pub max_tokens: i32,
@@ -152,6 +158,7 @@ where
         {
             Ok(Some(vec![SystemMessage {
                 text: value.to_string(),
+                cache_control: None,
             }]))
         }
 
@@ -189,7 +196,7 @@ where
 }
 
 /// 消息
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct Message {
     pub role: String,
     /// 可以是 string 或 ContentBlock 数组
@@ -197,9 +204,21 @@ pub struct Message {
 }
 
 /// 系统消息
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct SystemMessage {
     pub text: String,
+    /// Prompt caching 断点
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+/// Prompt caching 断点标记（`{"type": "ephemeral", "ttl": "5m" | "1h"}`）
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct CacheControl {
+    #[serde(rename = "type")]
+    pub cache_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<String>,
 }
 
 /// 工具定义
@@ -207,7 +226,7 @@ pub struct SystemMessage {
 /// 支持两种格式：
 /// 1. 普通工具：{ name, description, input_schema }
 /// 2. WebSearch 工具：{ type: "web_search_20250305", name: "web_search", max_uses: 8 }
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct Tool {
     /// 工具类型，如 "web_search_20250305"（可选，仅 WebSearch 工具）
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
@@ -224,6 +243,9 @@ pub struct Tool {
     /// 最大使用次数（仅 WebSearch 工具）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_uses: Option<i32>,
+    /// Prompt caching 断点
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
 }
 
 impl Tool {
@@ -236,7 +258,7 @@ impl Tool {
 }
 
 /// 内容块
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct ContentBlock {
     #[serde(rename = "type")]
     pub block_type: String,
@@ -260,19 +282,23 @@ pub struct ContentBlock {
     pub source: Option<ImageSource>,
 }
 
-/// 图片数据源
-#[derive(Debug, Deserialize, Serialize)]
+/// 图片数据源：`base64`（`media_type` + `data`）或 `url`
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct ImageSource {
     #[serde(rename = "type")]
     pub source_type: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub media_type: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub data: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
 
 // === Count Tokens 端点类型 ===
 
 /// Token 计数请求
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CountTokensRequest {
     pub model: String,
     pub messages: Vec<Message>,
@@ -287,7 +313,45 @@ pub struct CountTokensRequest {
 }
 
 /// Token 计数响应
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CountTokensResponse {
     pub input_tokens: i32,
 }
+
+/// 请求预估响应（`POST /v1/messages/estimate`）
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EstimateResponse {
+    /// 按 `modelAliases` 解析后的模型名
+    pub model: String,
+    /// 实际使用的 Kiro 模型 ID
+    pub kiro_model: String,
+    /// 估算的输入 tokens
+    pub input_tokens: i32,
+    pub max_tokens: i32,
+    pub thinking: bool,
+    /// 是否会走内置 WebSearch 处理
+    pub web_search: bool,
+    /// 预计使用的凭据（无可用凭据时为 null）
+    pub credential: Option<EstimatedCredential>,
+    /// 按 `modelPricing` 估算的费用（未配置该模型单价时为 null）
+    pub estimated_cost_usd: Option<EstimatedCost>,
+}
+
+/// 预计使用的凭据
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EstimatedCredential {
+    pub id: u64,
+    /// 认证方式（social / idc）
+    pub auth_method: Option<String>,
+    /// 是否由模型路由或 `x-kiro-credential-id` 固定
+    pub pinned: bool,
+}
+
+/// 估算费用（美元）
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EstimatedCost {
+    /// 仅输入部分
+    pub input: f64,
+    /// 输出达到 max_tokens 时的上限
+    pub max_total: f64,
+}
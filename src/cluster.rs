@@ -0,0 +1,1013 @@
+//! 集群模式：多个实例通过 Redis 共享状态
+//!
+//! 配置 `cluster.redisUrl` 后：
+//! - 凭据禁用状态：本实例的 `CredentialDisabled` / `CredentialEnabled` 事件写入 Redis 哈希并通过
+//!   pub/sub 通知其他实例，其他实例直接应用状态（不再发布事件）；订阅建立或重连后从哈希全量同步
+//! - 用量计数：`UsageRecorded` 事件按天（UTC）累加到 Redis 哈希（合计 / 按模型 / 按 API Key）
+//! - 限流：`rateLimitRequestsPerMinute` / `rateLimitTokensPerMinute` 对整个集群生效，
+//!   按前一分钟与当前分钟的计数加权估算滑动窗口用量；Redis 不可用时退回本实例限流
+//! - Leader 选举：基于租约的锁，只有 leader 执行后台账号信息刷新等周期任务
+//! - Prompt caching 前缀记录（开启 `cluster.sharePromptCache` 时）：每个前缀一个带过期时间的键，
+//!   `SET ... PX ... GET` 原子地检查并刷新
+//! - 租户每月用量（开启 `cluster.shareTenantUsage` 时）：每个租户每月一个哈希，配额检查与请求计数
+//!   由 Lua 脚本原子执行，token 数通过 `HINCRBY` 累加
+//!
+//! API Key 等配置项仍由各实例的配置文件（或 `KIRO_*` 环境变量）提供，需保持一致。
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use futures::future::BoxFuture;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+use crate::anthropic::prompt_cache::{Breakpoint, PrefixStore};
+use crate::common::rate_limit::{LimitStatus, RateLimitStatus};
+use crate::events::{self, AppEvent, TokenUsage};
+use crate::kiro::token_manager::{DisabledState, MultiTokenManager};
+use crate::model::config::{ClusterConfig, TenantConfig};
+use crate::tenants::{QuotaExceeded, TenantUsage, TenantUsageStore};
+
+/// Redis 连接与单次操作的超时
+const REDIS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 断线重连的最大退避间隔（毫秒）
+const RECONNECT_MAX_DELAY_MS: u64 = 1_000;
+
+/// 状态订阅断开后的重连间隔
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// 用量计数的保留时间（天）
+const USAGE_RETENTION_DAYS: i64 = 90;
+
+/// 租户用量的保留时间（天，自最后一次写入起）
+const TENANT_USAGE_RETENTION_DAYS: i64 = 40;
+
+/// 集群状态中返回的用量天数
+const STATUS_USAGE_DAYS: i64 = 7;
+
+/// 限流窗口长度（毫秒）
+const RATE_WINDOW_MS: i64 = 60_000;
+
+/// 获取或续期 leader 租约：没有 leader 或自己就是 leader 时成功
+const LEAD_SCRIPT: &str = r"
+local owner = redis.call('GET', KEYS[1])
+if owner == ARGV[1] then
+  redis.call('PEXPIRE', KEYS[1], ARGV[2])
+  return 1
+elseif not owner then
+  redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+  return 1
+end
+return 0
+";
+
+/// 释放自己持有的 leader 租约
+const RELEASE_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+  return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// 检查租户配额（空字符串表示不限），未超出时计入一次请求；返回 0 放行，1 / 2 为超出请求数 / token 数配额
+const TENANT_ACQUIRE_SCRIPT: &str = r"
+local usage = redis.call('HMGET', KEYS[1], 'requests', 'inputTokens', 'outputTokens')
+local requests = tonumber(usage[1]) or 0
+local tokens = (tonumber(usage[2]) or 0) + (tonumber(usage[3]) or 0)
+if ARGV[1] ~= '' and requests >= tonumber(ARGV[1]) then
+  return 1
+end
+if ARGV[2] ~= '' and tokens >= tonumber(ARGV[2]) then
+  return 2
+end
+redis.call('HINCRBY', KEYS[1], 'requests', 1)
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+return 0
+";
+
+/// 撤销一次租户请求计数（不低于 0）
+const TENANT_RELEASE_SCRIPT: &str = r"
+if (tonumber(redis.call('HGET', KEYS[1], 'requests')) or 0) > 0 then
+  redis.call('HINCRBY', KEYS[1], 'requests', -1)
+end
+return 0
+";
+
+static CLUSTER: OnceLock<Cluster> = OnceLock::new();
+
+struct Cluster {
+    client: redis::Client,
+    conn: ConnectionManager,
+    prefix: String,
+    instance_id: String,
+    lease: Duration,
+    started_at: DateTime<Utc>,
+    leader: AtomicBool,
+}
+
+/// 通过 pub/sub 广播的凭据状态变化
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StateUpdate {
+    instance_id: String,
+    credential_id: u64,
+    state: DisabledState,
+}
+
+/// 集群中的实例（心跳记录）
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceInfo {
+    pub instance_id: String,
+    pub version: String,
+    pub started_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+/// 用量计数
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageCounters {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+}
+
+impl UsageCounters {
+    fn field_mut(&mut self, name: &str) -> Option<&mut u64> {
+        Some(match name {
+            "requests" => &mut self.requests,
+            "inputTokens" => &mut self.input_tokens,
+            "outputTokens" => &mut self.output_tokens,
+            "cacheCreationInputTokens" => &mut self.cache_creation_input_tokens,
+            "cacheReadInputTokens" => &mut self.cache_read_input_tokens,
+            _ => return None,
+        })
+    }
+}
+
+/// 一天内集群所有实例的合计用量
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterUsage {
+    pub total: UsageCounters,
+    pub by_model: BTreeMap<String, UsageCounters>,
+    pub by_api_key: BTreeMap<String, UsageCounters>,
+}
+
+/// 集群状态
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterStatus {
+    /// 是否启用了集群模式
+    pub enabled: bool,
+    /// 本实例标识
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+    /// 本实例是否为 leader
+    pub is_leader: bool,
+    /// 当前 leader 的实例标识
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_id: Option<String>,
+    /// 最近有心跳的实例
+    pub instances: Vec<InstanceInfo>,
+    /// 最近 7 天（UTC）的合计用量，键为日期
+    pub usage: BTreeMap<String, ClusterUsage>,
+}
+
+impl Cluster {
+    fn key(&self, name: &str) -> String {
+        format!("{}:{}", self.prefix, name)
+    }
+
+    fn conn(&self) -> ConnectionManager {
+        self.conn.clone()
+    }
+
+    /// 获取或续期 leader 租约
+    async fn try_lead(&self) -> redis::RedisResult<bool> {
+        redis::Script::new(LEAD_SCRIPT)
+            .key(self.key("leader"))
+            .arg(&self.instance_id)
+            .arg(self.lease.as_millis() as u64)
+            .invoke_async(&mut self.conn())
+            .await
+    }
+
+    /// 写入本实例心跳；leader 同时清理长时间没有心跳的实例
+    async fn heartbeat(&self) -> anyhow::Result<()> {
+        let info = InstanceInfo {
+            instance_id: self.instance_id.clone(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: self.started_at,
+            last_seen_at: Utc::now(),
+        };
+        let key = self.key("instances");
+        let mut conn = self.conn();
+        redis::cmd("HSET")
+            .arg(&key)
+            .arg(&self.instance_id)
+            .arg(serde_json::to_string(&info)?)
+            .query_async::<()>(&mut conn)
+            .await?;
+
+        if self.leader.load(Ordering::Relaxed) {
+            let stale: Vec<String> = self
+                .instances()
+                .await?
+                .into_iter()
+                .filter(|i| !self.is_alive(i))
+                .map(|i| i.instance_id)
+                .collect();
+            if !stale.is_empty() {
+                redis::cmd("HDEL")
+                    .arg(&key)
+                    .arg(&stale)
+                    .query_async::<()>(&mut conn)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 最近 3 个租约周期内有心跳的实例视为存活
+    fn is_alive(&self, instance: &InstanceInfo) -> bool {
+        let timeout = chrono::Duration::from_std(self.lease * 3).unwrap_or_default();
+        Utc::now() - instance.last_seen_at <= timeout
+    }
+
+    async fn instances(&self) -> anyhow::Result<Vec<InstanceInfo>> {
+        let entries: HashMap<String, String> = redis::cmd("HGETALL")
+            .arg(self.key("instances"))
+            .query_async(&mut self.conn())
+            .await?;
+        let mut instances: Vec<InstanceInfo> = entries
+            .values()
+            .filter_map(|v| serde_json::from_str(v).ok())
+            .collect();
+        instances.sort_by(|a, b| a.instance_id.cmp(&b.instance_id));
+        Ok(instances)
+    }
+
+    /// 把本实例的凭据状态写入 Redis 并通知其他实例
+    async fn publish_state(&self, credential_id: u64, state: DisabledState) -> anyhow::Result<()> {
+        let update = StateUpdate {
+            instance_id: self.instance_id.clone(),
+            credential_id,
+            state,
+        };
+        redis::pipe()
+            .cmd("HSET")
+            .arg(self.key("credentials"))
+            .arg(credential_id)
+            .arg(serde_json::to_string(&update.state)?)
+            .ignore()
+            .cmd("PUBLISH")
+            .arg(self.key("credential-events"))
+            .arg(serde_json::to_string(&update)?)
+            .ignore()
+            .query_async::<()>(&mut self.conn())
+            .await?;
+        Ok(())
+    }
+
+    /// 按 Redis 中保存的凭据状态全量同步
+    async fn sync_states(&self, token_manager: &MultiTokenManager) -> anyhow::Result<()> {
+        let entries: HashMap<String, String> = redis::cmd("HGETALL")
+            .arg(self.key("credentials"))
+            .query_async(&mut self.conn())
+            .await?;
+        for (id, state) in entries {
+            if let (Ok(id), Ok(state)) = (id.parse(), serde_json::from_str(&state))
+                && token_manager.apply_disabled_state(id, &state)
+            {
+                tracing::info!("已从集群同步凭据 #{} 的状态", id);
+            }
+        }
+        Ok(())
+    }
+
+    /// 订阅其他实例的凭据状态变化，直到连接断开
+    async fn run_subscription(&self, token_manager: &MultiTokenManager) -> anyhow::Result<()> {
+        let mut pubsub = timed(self.client.get_async_pubsub()).await?;
+        timed(pubsub.subscribe(self.key("credential-events"))).await?;
+        // 订阅之后再全量同步，不会遗漏两者之间的变化
+        timed(self.sync_states(token_manager)).await?;
+
+        let mut messages = pubsub.into_on_message();
+        while let Some(message) = messages.next().await {
+            let Ok(payload) = message.get_payload::<String>() else {
+                continue;
+            };
+            match serde_json::from_str::<StateUpdate>(&payload) {
+                Ok(update) if update.instance_id != self.instance_id => {
+                    if token_manager.apply_disabled_state(update.credential_id, &update.state) {
+                        tracing::info!(
+                            "已同步凭据 #{} 的状态（来自实例 {}）: {}",
+                            update.credential_id,
+                            update.instance_id,
+                            if update.state.disabled {
+                                "禁用"
+                            } else {
+                                "启用"
+                            }
+                        );
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("无法解析集群凭据状态消息: {}", e),
+            }
+        }
+        anyhow::bail!("订阅连接已断开")
+    }
+
+    /// 累加一次响应的用量
+    async fn record_usage(
+        &self,
+        api_key_id: Option<&str>,
+        model: &str,
+        usage: &TokenUsage,
+    ) -> redis::RedisResult<()> {
+        let key = self.key(&format!("usage:{}", Utc::now().format("%Y-%m-%d")));
+        let mut scopes = vec!["total".to_string(), format!("model:{}", model)];
+        if let Some(id) = api_key_id {
+            scopes.push(format!("apiKey:{}", id));
+        }
+        let values = [
+            ("requests", 1),
+            ("inputTokens", i64::from(usage.input_tokens)),
+            ("outputTokens", i64::from(usage.output_tokens)),
+            (
+                "cacheCreationInputTokens",
+                i64::from(usage.cache_creation_input_tokens),
+            ),
+            (
+                "cacheReadInputTokens",
+                i64::from(usage.cache_read_input_tokens),
+            ),
+        ];
+
+        let mut pipe = redis::pipe();
+        for scope in &scopes {
+            for (field, value) in values {
+                pipe.cmd("HINCRBY")
+                    .arg(&key)
+                    .arg(format!("{}:{}", scope, field))
+                    .arg(value)
+                    .ignore();
+            }
+        }
+        pipe.cmd("EXPIRE")
+            .arg(&key)
+            .arg(USAGE_RETENTION_DAYS * 86_400)
+            .ignore();
+        pipe.query_async(&mut self.conn()).await
+    }
+
+    async fn usage(&self, days: i64) -> anyhow::Result<BTreeMap<String, ClusterUsage>> {
+        let today = Utc::now().date_naive();
+        let mut result = BTreeMap::new();
+        for offset in 0..days {
+            let date = (today - chrono::Duration::days(offset))
+                .format("%Y-%m-%d")
+                .to_string();
+            let fields: HashMap<String, i64> = redis::cmd("HGETALL")
+                .arg(self.key(&format!("usage:{}", date)))
+                .query_async(&mut self.conn())
+                .await?;
+            if !fields.is_empty() {
+                result.insert(date, parse_usage(&fields));
+            }
+        }
+        Ok(result)
+    }
+
+    async fn status(&self) -> anyhow::Result<ClusterStatus> {
+        let leader_id: Option<String> = redis::cmd("GET")
+            .arg(self.key("leader"))
+            .query_async(&mut self.conn())
+            .await?;
+        let instances = self
+            .instances()
+            .await?
+            .into_iter()
+            .filter(|i| self.is_alive(i))
+            .collect();
+        Ok(ClusterStatus {
+            enabled: true,
+            instance_id: Some(self.instance_id.clone()),
+            is_leader: self.leader.load(Ordering::Relaxed),
+            leader_id,
+            instances,
+            usage: self.usage(STATUS_USAGE_DAYS).await?,
+        })
+    }
+
+    /// 释放 leader 租约并移除本实例的心跳
+    async fn leave(&self) -> anyhow::Result<()> {
+        let mut conn = self.conn();
+        redis::Script::new(RELEASE_SCRIPT)
+            .key(self.key("leader"))
+            .arg(&self.instance_id)
+            .invoke_async::<i64>(&mut conn)
+            .await?;
+        redis::cmd("HDEL")
+            .arg(self.key("instances"))
+            .arg(&self.instance_id)
+            .query_async::<()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    fn rate_key(&self, kind: &str, window: i64) -> String {
+        self.key(&format!("ratelimit:{}:{}", kind, window))
+    }
+
+    async fn check_rate_limit(
+        &self,
+        requests_per_minute: Option<u32>,
+        tokens_per_minute: Option<u64>,
+    ) -> redis::RedisResult<(Result<(), Duration>, RateLimitStatus)> {
+        let now = Utc::now().timestamp_millis();
+        let window = now.div_euclid(RATE_WINDOW_MS);
+        let elapsed = now.rem_euclid(RATE_WINDOW_MS);
+        let mut conn = self.conn();
+        let counts: Vec<Option<u64>> = redis::cmd("MGET")
+            .arg(self.rate_key("requests", window - 1))
+            .arg(self.rate_key("requests", window))
+            .arg(self.rate_key("tokens", window - 1))
+            .arg(self.rate_key("tokens", window))
+            .query_async(&mut conn)
+            .await?;
+        let count = |i: usize| counts.get(i).copied().flatten().unwrap_or(0);
+        let requests = SlidingWindow {
+            previous: count(0),
+            current: count(1),
+            elapsed_ms: elapsed,
+        };
+        let tokens = SlidingWindow {
+            previous: count(2),
+            current: count(3),
+            elapsed_ms: elapsed,
+        };
+
+        let retry_after = [
+            requests_per_minute.map(|limit| requests.retry_after(u64::from(limit))),
+            tokens_per_minute.map(|limit| tokens.retry_after(limit)),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or_default();
+        let decision = if retry_after.is_zero() {
+            let key = self.rate_key("requests", window);
+            redis::pipe()
+                .cmd("INCR")
+                .arg(&key)
+                .ignore()
+                .cmd("PEXPIRE")
+                .arg(&key)
+                .arg(RATE_WINDOW_MS * 2)
+                .ignore()
+                .query_async::<()>(&mut conn)
+                .await?;
+            Ok(())
+        } else {
+            Err(retry_after)
+        };
+
+        // 放行时把本次请求计入剩余额度
+        let admitted = u64::from(decision.is_ok());
+        let reset = Duration::from_millis((RATE_WINDOW_MS - elapsed) as u64);
+        let status = RateLimitStatus {
+            requests: requests_per_minute.map(|limit| LimitStatus {
+                limit: u64::from(limit),
+                remaining: u64::from(limit).saturating_sub(requests.estimate() + admitted),
+                reset,
+            }),
+            tokens: tokens_per_minute.map(|limit| LimitStatus {
+                limit,
+                remaining: limit.saturating_sub(tokens.estimate()),
+                reset,
+            }),
+        };
+        Ok((decision, status))
+    }
+
+    async fn record_tokens(&self, tokens: u64) -> redis::RedisResult<()> {
+        let window = Utc::now().timestamp_millis().div_euclid(RATE_WINDOW_MS);
+        let key = self.rate_key("tokens", window);
+        redis::pipe()
+            .cmd("INCRBY")
+            .arg(&key)
+            .arg(tokens)
+            .ignore()
+            .cmd("PEXPIRE")
+            .arg(&key)
+            .arg(RATE_WINDOW_MS * 2)
+            .ignore()
+            .query_async(&mut self.conn())
+            .await
+    }
+}
+
+/// 按固定分钟窗口计数估算的滑动窗口用量
+#[derive(Debug, Clone, Copy)]
+struct SlidingWindow {
+    /// 上一分钟的计数
+    previous: u64,
+    /// 当前分钟的计数
+    current: u64,
+    /// 当前分钟已经过的毫秒数
+    elapsed_ms: i64,
+}
+
+impl SlidingWindow {
+    /// 最近 60 秒的估计用量：上一分钟按仍在窗口内的比例计入
+    fn estimate(&self) -> u64 {
+        let remaining = (RATE_WINDOW_MS - self.elapsed_ms) as u128;
+        let previous = u128::from(self.previous) * remaining / RATE_WINDOW_MS as u128;
+        previous as u64 + self.current
+    }
+
+    /// 估计用量降到 `limit` 以下需要等待的时间（未超限时为 0）
+    fn retry_after(&self, limit: u64) -> Duration {
+        if self.estimate() < limit {
+            return Duration::ZERO;
+        }
+        let window = RATE_WINDOW_MS as f64;
+        let until_next_window = window - self.elapsed_ms as f64;
+        let wait_ms = if limit == 0 {
+            until_next_window
+        } else if self.current < limit {
+            // 当前分钟内，上一分钟的计数滑出足够多即可
+            let needed = (limit - self.current) as f64 * window / self.previous as f64;
+            (until_next_window - needed).max(0.0) + 1.0
+        } else {
+            // 等到下一分钟，当前分钟的计数成为“上一分钟”后再滑出足够多
+            until_next_window + window * (1.0 - limit as f64 / self.current as f64) + 1.0
+        };
+        Duration::from_millis(wait_ms.ceil() as u64)
+    }
+}
+
+/// 解析用量哈希：字段为 `total:<计数>`、`model:<模型>:<计数>` 或 `apiKey:<标识>:<计数>`
+fn parse_usage(fields: &HashMap<String, i64>) -> ClusterUsage {
+    let mut usage = ClusterUsage::default();
+    for (field, &value) in fields {
+        let Some((scope, counter)) = field.rsplit_once(':') else {
+            continue;
+        };
+        let counters = match scope.split_once(':') {
+            None if scope == "total" => &mut usage.total,
+            Some(("model", model)) => usage.by_model.entry(model.to_string()).or_default(),
+            Some(("apiKey", id)) => usage.by_api_key.entry(id.to_string()).or_default(),
+            _ => continue,
+        };
+        if let Some(slot) = counters.field_mut(counter) {
+            *slot = value.max(0) as u64;
+        }
+    }
+    usage
+}
+
+/// 存在 Redis 中的 prompt caching 前缀记录
+struct RedisPrefixStore {
+    cluster: &'static Cluster,
+}
+
+impl PrefixStore for RedisPrefixStore {
+    fn touch<'a>(
+        &'a self,
+        breakpoints: &'a [Breakpoint],
+    ) -> BoxFuture<'a, anyhow::Result<Vec<bool>>> {
+        Box::pin(timed(async move {
+            let mut pipe = redis::pipe();
+            for breakpoint in breakpoints {
+                pipe.cmd("SET")
+                    .arg(
+                        self.cluster
+                            .key(&format!("prompt-cache:{:016x}", breakpoint.hash)),
+                    )
+                    .arg(1)
+                    .arg("PX")
+                    .arg(breakpoint.ttl.as_millis() as u64)
+                    .arg("GET");
+            }
+            let previous: Vec<Option<String>> = pipe.query_async(&mut self.cluster.conn()).await?;
+            redis::RedisResult::Ok(previous.into_iter().map(|v| v.is_some()).collect())
+        }))
+    }
+}
+
+/// 存在 Redis 中的租户每月用量：每个租户每月一个哈希
+struct RedisTenantUsageStore {
+    cluster: &'static Cluster,
+}
+
+impl RedisTenantUsageStore {
+    fn key(&self, month: &str, tenant: &str) -> String {
+        self.cluster
+            .key(&format!("tenant-usage:{}:{}", month, tenant))
+    }
+}
+
+impl TenantUsageStore for RedisTenantUsageStore {
+    fn usage<'a>(
+        &'a self,
+        month: &'a str,
+        tenant: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<TenantUsage>> {
+        Box::pin(timed(async move {
+            let values: Vec<Option<u64>> = redis::cmd("HMGET")
+                .arg(self.key(month, tenant))
+                .arg(&["requests", "inputTokens", "outputTokens"])
+                .query_async(&mut self.cluster.conn())
+                .await?;
+            let value = |i: usize| values.get(i).copied().flatten().unwrap_or(0);
+            redis::RedisResult::Ok(TenantUsage {
+                requests: value(0),
+                input_tokens: value(1),
+                output_tokens: value(2),
+            })
+        }))
+    }
+
+    fn acquire<'a>(
+        &'a self,
+        month: &'a str,
+        tenant: &'a str,
+        quota: &'a TenantConfig,
+    ) -> BoxFuture<'a, anyhow::Result<Result<(), QuotaExceeded>>> {
+        let limit = |limit: Option<u64>| limit.map(|l| l.to_string()).unwrap_or_default();
+        Box::pin(timed(async move {
+            let exceeded: i64 = redis::Script::new(TENANT_ACQUIRE_SCRIPT)
+                .key(self.key(month, tenant))
+                .arg(limit(quota.monthly_request_quota))
+                .arg(limit(quota.monthly_token_quota))
+                .arg(TENANT_USAGE_RETENTION_DAYS * 86_400)
+                .invoke_async(&mut self.cluster.conn())
+                .await?;
+            redis::RedisResult::Ok(match exceeded {
+                1 => Err(QuotaExceeded::Requests(
+                    quota.monthly_request_quota.unwrap_or_default(),
+                )),
+                2 => Err(QuotaExceeded::Tokens(
+                    quota.monthly_token_quota.unwrap_or_default(),
+                )),
+                _ => Ok(()),
+            })
+        }))
+    }
+
+    fn release<'a>(&'a self, month: &'a str, tenant: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(timed(async move {
+            redis::Script::new(TENANT_RELEASE_SCRIPT)
+                .key(self.key(month, tenant))
+                .invoke_async::<()>(&mut self.cluster.conn())
+                .await
+        }))
+    }
+
+    fn add_tokens<'a>(
+        &'a self,
+        month: &'a str,
+        tenant: &'a str,
+        input_tokens: u64,
+        output_tokens: u64,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(timed(async move {
+            let key = self.key(month, tenant);
+            redis::pipe()
+                .cmd("HINCRBY")
+                .arg(&key)
+                .arg("inputTokens")
+                .arg(input_tokens)
+                .ignore()
+                .cmd("HINCRBY")
+                .arg(&key)
+                .arg("outputTokens")
+                .arg(output_tokens)
+                .ignore()
+                .cmd("EXPIRE")
+                .arg(&key)
+                .arg(TENANT_USAGE_RETENTION_DAYS * 86_400)
+                .ignore()
+                .query_async::<()>(&mut self.cluster.conn())
+                .await
+        }))
+    }
+}
+
+/// 默认实例标识：主机名-进程 ID
+fn default_instance_id() -> String {
+    let host = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "kiro-rs".to_string());
+    format!("{}-{}", host, std::process::id())
+}
+
+/// 连接 Redis 并启动集群后台任务（leader 选举、心跳、凭据状态同步、用量计数）
+pub async fn init(
+    config: &ClusterConfig,
+    token_manager: Arc<MultiTokenManager>,
+) -> anyhow::Result<()> {
+    let client = redis::Client::open(config.redis_url.as_str()).context("Redis 地址无效")?;
+    let manager_config = ConnectionManagerConfig::new()
+        .set_connection_timeout(REDIS_TIMEOUT)
+        .set_response_timeout(REDIS_TIMEOUT)
+        .set_number_of_retries(2)
+        .set_max_delay(RECONNECT_MAX_DELAY_MS);
+    let conn = ConnectionManager::new_with_config(client.clone(), manager_config)
+        .await
+        .context("连接 Redis 失败")?;
+    let cluster = Cluster {
+        client,
+        conn,
+        prefix: config.key_prefix.clone(),
+        instance_id: config
+            .instance_id
+            .clone()
+            .unwrap_or_else(default_instance_id),
+        lease: Duration::from_secs(config.leader_lease_secs.max(3)),
+        started_at: Utc::now(),
+        leader: AtomicBool::new(false),
+    };
+    if CLUSTER.set(cluster).is_err() {
+        anyhow::bail!("集群模式已初始化");
+    }
+    let Some(cluster) = CLUSTER.get() else {
+        anyhow::bail!("集群模式初始化失败");
+    };
+    // 先完成一次选举与心跳，启动后立即可以判断是否为 leader
+    update_leadership(cluster).await;
+    tracing::info!(
+        "集群模式已启用：实例 {}（{}）",
+        cluster.instance_id,
+        if cluster.leader.load(Ordering::Relaxed) {
+            "leader"
+        } else {
+            "follower"
+        }
+    );
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(cluster.lease / 3);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            update_leadership(cluster).await;
+        }
+    });
+
+    let mut receiver = events::subscribe();
+    tokio::spawn({
+        let token_manager = token_manager.clone();
+        async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(envelope) => {
+                        handle_local_event(cluster, &token_manager, envelope.event).await
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("集群同步处理过慢，跳过了 {} 个事件", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = cluster.run_subscription(&token_manager).await {
+                tracing::warn!(
+                    "集群凭据状态订阅中断: {}，{} 秒后重连",
+                    e,
+                    RESUBSCRIBE_DELAY.as_secs()
+                );
+            }
+            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+        }
+    });
+    Ok(())
+}
+
+async fn update_leadership(cluster: &Cluster) {
+    // 无法确认租约时立即放弃 leader 身份，避免租约过期后与新 leader 同时执行后台任务
+    let leader = match timed(cluster.try_lead()).await {
+        Ok(leader) => leader,
+        Err(e) => {
+            tracing::warn!("集群 leader 选举失败: {}", e);
+            false
+        }
+    };
+    let was_leader = cluster.leader.swap(leader, Ordering::Relaxed);
+    if leader != was_leader {
+        if leader {
+            tracing::info!("本实例 {} 成为集群 leader", cluster.instance_id);
+        } else {
+            tracing::warn!("本实例 {} 不再是集群 leader", cluster.instance_id);
+        }
+    }
+    if let Err(e) = timed(cluster.heartbeat()).await {
+        tracing::warn!("写入集群心跳失败: {}", e);
+    }
+}
+
+async fn handle_local_event(cluster: &Cluster, token_manager: &MultiTokenManager, event: AppEvent) {
+    let result = match event {
+        AppEvent::CredentialDisabled { credential_id, .. }
+        | AppEvent::CredentialEnabled { credential_id } => {
+            match token_manager.disabled_state(credential_id) {
+                Some(state) => timed(cluster.publish_state(credential_id, state)).await,
+                None => Ok(()),
+            }
+        }
+        AppEvent::CredentialDeleted { credential_id } => {
+            let mut conn = cluster.conn();
+            let mut cmd = redis::cmd("HDEL");
+            cmd.arg(cluster.key("credentials")).arg(credential_id);
+            timed(cmd.query_async::<()>(&mut conn)).await
+        }
+        AppEvent::UsageRecorded {
+            api_key_id,
+            model,
+            usage,
+            ..
+        } => timed(cluster.record_usage(api_key_id.as_deref(), &model, &usage)).await,
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        tracing::warn!("同步到集群失败: {}", e);
+    }
+}
+
+/// 本实例是否应执行周期性后台任务：单实例运行时总是，集群模式下仅 leader
+pub fn is_leader() -> bool {
+    CLUSTER
+        .get()
+        .is_none_or(|cluster| cluster.leader.load(Ordering::Relaxed))
+}
+
+/// 集群范围的限流检查，返回检查结果与当前额度；未启用集群或 Redis 出错时返回 None（由调用方退回本实例限流）
+pub async fn check_rate_limit(
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u64>,
+) -> Option<(Result<(), Duration>, RateLimitStatus)> {
+    let cluster = CLUSTER.get()?;
+    match timed(cluster.check_rate_limit(requests_per_minute, tokens_per_minute)).await {
+        Ok(result) => Some(result),
+        Err(e) => {
+            tracing::warn!("集群限流检查失败，使用本实例限流: {}", e);
+            None
+        }
+    }
+}
+
+/// 记录本实例消耗的 token 数（集群范围的每分钟 token 限流）
+pub fn record_tokens(tokens: u64) {
+    if tokens == 0 {
+        return;
+    }
+    if let Some(cluster) = CLUSTER.get() {
+        tokio::spawn(async move {
+            if let Err(e) = timed(cluster.record_tokens(tokens)).await {
+                tracing::warn!("记录集群 token 用量失败: {}", e);
+            }
+        });
+    }
+}
+
+/// 集群共享的 prompt caching 前缀记录（未启用集群模式时为 None）
+pub fn prompt_cache_store() -> Option<Arc<dyn PrefixStore>> {
+    let cluster = CLUSTER.get()?;
+    Some(Arc::new(RedisPrefixStore { cluster }))
+}
+
+/// 集群共享的租户每月用量（未启用集群模式时为 None）
+pub fn tenant_usage_store() -> Option<Arc<dyn TenantUsageStore>> {
+    let cluster = CLUSTER.get()?;
+    Some(Arc::new(RedisTenantUsageStore { cluster }))
+}
+
+/// 集群状态（实例、leader 与最近的合计用量）
+pub async fn status() -> anyhow::Result<ClusterStatus> {
+    let Some(cluster) = CLUSTER.get() else {
+        return Ok(ClusterStatus::default());
+    };
+    timed(cluster.status()).await
+}
+
+/// 退出前释放 leader 租约并移除本实例的心跳，其他实例可立即接替
+pub async fn shutdown() {
+    if let Some(cluster) = CLUSTER.get()
+        && let Err(e) = timed(cluster.leave()).await
+    {
+        tracing::warn!("退出集群失败: {}", e);
+    }
+}
+
+/// 为 Redis 操作加上超时：连接断开后命令会等待重连，不能让请求、选举与退出长时间阻塞
+async fn timed<T, E>(future: impl Future<Output = Result<T, E>>) -> anyhow::Result<T>
+where
+    E: Into<anyhow::Error>,
+{
+    match tokio::time::timeout(REDIS_TIMEOUT, future).await {
+        Ok(result) => result.map_err(Into::into),
+        Err(_) => anyhow::bail!("Redis 操作超时"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sliding_window_estimate() {
+        let window = SlidingWindow {
+            previous: 60,
+            current: 10,
+            elapsed_ms: 15_000,
+        };
+        // 上一分钟仍有 3/4 在窗口内
+        assert_eq!(window.estimate(), 55);
+        assert_eq!(window.retry_after(56), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_sliding_window_retry_after() {
+        // 当前分钟未超限：等上一分钟的计数滑出 5 个（5 秒）
+        let window = SlidingWindow {
+            previous: 60,
+            current: 10,
+            elapsed_ms: 15_000,
+        };
+        let wait = window.retry_after(50);
+        assert!(
+            wait >= Duration::from_secs(5) && wait < Duration::from_millis(5_100),
+            "实际: {:?}",
+            wait
+        );
+
+        // 当前分钟已超限：等到下一分钟后再滑出一半
+        let window = SlidingWindow {
+            previous: 0,
+            current: 20,
+            elapsed_ms: 30_000,
+        };
+        let wait = window.retry_after(10);
+        assert!(
+            wait >= Duration::from_secs(60) && wait < Duration::from_millis(60_100),
+            "实际: {:?}",
+            wait
+        );
+
+        let empty = SlidingWindow {
+            previous: 0,
+            current: 0,
+            elapsed_ms: 0,
+        };
+        assert_eq!(empty.retry_after(0), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_parse_usage() {
+        let fields: HashMap<String, i64> = [
+            ("total:requests", 3),
+            ("total:inputTokens", 300),
+            ("model:claude-sonnet-4:outputTokens", 40),
+            ("model:ns:claude:requests", 1),
+            ("apiKey:sk-a1b2:cacheReadInputTokens", 7),
+            ("unknown:requests", 9),
+            ("total:unknownCounter", 9),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+        let usage = parse_usage(&fields);
+        assert_eq!(usage.total.requests, 3);
+        assert_eq!(usage.total.input_tokens, 300);
+        assert_eq!(usage.by_model["claude-sonnet-4"].output_tokens, 40);
+        // 模型名中的冒号保留
+        assert_eq!(usage.by_model["ns:claude"].requests, 1);
+        assert_eq!(usage.by_api_key["sk-a1b2"].cache_read_input_tokens, 7);
+        assert_eq!(usage.by_model.len(), 2);
+        assert_eq!(usage.by_api_key.len(), 1);
+    }
+}
@@ -4,8 +4,12 @@ use axum::{
     body::Body,
     http::{Request, header},
 };
+use sha2::{Digest, Sha256};
 use subtle::ConstantTimeEq;
 
+/// 哈希存储的 API Key 前缀
+const SHA256_PREFIX: &str = "sha256:";
+
 /// 从请求中提取 API Key
 ///
 /// 支持两种认证方式：
@@ -39,3 +43,93 @@ pub fn extract_api_key(request: &Request<Body>) -> Option<String> {
 pub fn constant_time_eq(a: &str, b: &str) -> bool {
     a.as_bytes().ct_eq(b.as_bytes()).into()
 }
+
+/// 校验客户端提供的 API Key
+///
+/// 配置值支持两种形式：
+/// - 明文：直接常量时间比较
+/// - `sha256:<hex>` 或 `sha256:<标识前缀>:<hex>`：比较 SHA-256 摘要（标识前缀仅用于辨认，不参与校验）
+pub fn verify_api_key(presented: &str, configured: &str) -> bool {
+    let Some(hashed) = configured.strip_prefix(SHA256_PREFIX) else {
+        return constant_time_eq(presented, configured);
+    };
+    let expected = hashed
+        .rsplit_once(':')
+        .map(|(_, hex)| hex)
+        .unwrap_or(hashed);
+    let actual = hex::encode(Sha256::digest(presented.as_bytes()));
+    constant_time_eq(&actual, &expected.to_ascii_lowercase())
+}
+
+/// 生成 API Key 的哈希存储形式：`sha256:<api_key_id>:<hex>`
+///
+/// 标识前缀取摘要的前 8 位（即 `api_key_id`），不包含 Key 本身的任何字符
+pub fn hash_api_key(key: &str) -> String {
+    let digest = hex::encode(Sha256::digest(key.as_bytes()));
+    format!("{}{}:{}", SHA256_PREFIX, &digest[..8], digest)
+}
+
+/// 用于统计展示的 API Key 标识：Key 的 SHA-256 前 8 位 hex（不暴露 Key 本身）
+pub fn api_key_id(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))[..8].to_string()
+}
+
+/// 配置中的 Key（明文或哈希存储形式）对应的 API Key 标识，与 `api_key_id` 的结果一致
+pub fn configured_api_key_id(configured: &str) -> String {
+    match configured.strip_prefix(SHA256_PREFIX) {
+        Some(hashed) => {
+            let hex = hashed
+                .rsplit_once(':')
+                .map(|(_, hex)| hex)
+                .unwrap_or(hashed);
+            hex.get(..8).unwrap_or(hex).to_ascii_lowercase()
+        }
+        None => api_key_id(configured),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_plaintext_api_key() {
+        assert!(verify_api_key("secret", "secret"));
+        assert!(!verify_api_key("secret2", "secret"));
+    }
+
+    #[test]
+    fn test_verify_hashed_api_key() {
+        let stored = hash_api_key("sk-kiro-test-key");
+        assert!(stored.starts_with(&format!("sha256:{}:", api_key_id("sk-kiro-test-key"))));
+        // 标识前缀不泄露明文 Key
+        assert!(!stored.contains("sk-kiro"));
+        assert!(verify_api_key("sk-kiro-test-key", &stored));
+        assert!(!verify_api_key("sk-kiro-other", &stored));
+
+        // 不带标识前缀、大写 hex 也可校验
+        let hex_only = stored.rsplit_once(':').unwrap().1.to_uppercase();
+        assert!(verify_api_key(
+            "sk-kiro-test-key",
+            &format!("sha256:{}", hex_only)
+        ));
+    }
+
+    #[test]
+    fn test_api_key_id() {
+        let id = api_key_id("sk-kiro-test-key");
+        assert_eq!(id.len(), 8);
+        assert!(hash_api_key("sk-kiro-test-key").contains(&format!(":{}", id)));
+        assert_ne!(id, api_key_id("sk-kiro-other"));
+    }
+
+    #[test]
+    fn test_configured_api_key_id() {
+        let id = api_key_id("sk-kiro-test-key");
+        assert_eq!(configured_api_key_id("sk-kiro-test-key"), id);
+        let stored = hash_api_key("sk-kiro-test-key");
+        assert_eq!(configured_api_key_id(&stored), id);
+        let hex_only = stored.rsplit_once(':').unwrap().1.to_uppercase();
+        assert_eq!(configured_api_key_id(&format!("sha256:{}", hex_only)), id);
+    }
+}
@@ -0,0 +1,147 @@
+//! 内容寻址存储
+//!
+//! 大块内容按 SHA-256 存为 `<dir>/<hash>.json`，引用方只保存 `{"$blob": "<hash>"}`。
+//! 相同内容只写一次；引用计数只在内存中维护，由使用方在启动时扫描自己的引用重建
+//! （`retain`），之后调用 `collect_garbage` 删除无人引用的内容。
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+/// 引用对象中保存哈希的字段名
+pub const BLOB_REF_KEY: &str = "$blob";
+
+/// 内容寻址存储
+pub struct BlobStore {
+    dir: PathBuf,
+    /// 哈希 -> 引用计数
+    refs: Mutex<HashMap<String, usize>>,
+}
+
+impl BlobStore {
+    pub fn new(dir: PathBuf) -> Self {
+        if let Err(e) = fs::create_dir_all(&dir) {
+            tracing::warn!("创建内容存储目录失败 {}: {}", dir.display(), e);
+        }
+        Self {
+            dir,
+            refs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 若 `value` 是引用对象，返回其中的哈希
+    pub fn blob_ref(value: &Value) -> Option<&str> {
+        let obj = value.as_object().filter(|obj| obj.len() == 1)?;
+        let hash = obj.get(BLOB_REF_KEY)?.as_str()?;
+        (hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())).then_some(hash)
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", hash))
+    }
+
+    /// 保存内容并增加一次引用，返回引用对象
+    pub fn put(&self, value: &Value) -> io::Result<Value> {
+        let content = serde_json::to_vec(value)?;
+        let hash = hex::encode(Sha256::digest(&content));
+
+        let mut refs = self.refs.lock();
+        let path = self.blob_path(&hash);
+        if !path.exists() {
+            fs::write(&path, &content)?;
+        }
+        *refs.entry(hash.clone()).or_default() += 1;
+        Ok(json!({ BLOB_REF_KEY: hash }))
+    }
+
+    /// 还原引用对象；不是引用对象时原样返回
+    pub fn resolve(&self, value: Value) -> io::Result<Value> {
+        let Some(hash) = Self::blob_ref(&value) else {
+            return Ok(value);
+        };
+        let content = fs::read(self.blob_path(hash))?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    /// 记录一次已有引用（启动时重建引用计数）
+    pub fn retain(&self, reference: &Value) {
+        if let Some(hash) = Self::blob_ref(reference) {
+            *self.refs.lock().entry(hash.to_string()).or_default() += 1;
+        }
+    }
+
+    /// 释放一次引用，引用计数归零时删除内容
+    pub fn release(&self, reference: &Value) {
+        let Some(hash) = Self::blob_ref(reference) else {
+            return;
+        };
+        let mut refs = self.refs.lock();
+        let Some(count) = refs.get_mut(hash) else {
+            return;
+        };
+        *count -= 1;
+        if *count == 0 {
+            refs.remove(hash);
+            let _ = fs::remove_file(self.blob_path(hash));
+        }
+    }
+
+    /// 删除没有任何引用的内容，返回删除的数量
+    pub fn collect_garbage(&self) -> usize {
+        let refs = self.refs.lock();
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return 0;
+        };
+        let mut removed = 0;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let orphan = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .is_some_and(|hash| !refs.contains_key(hash));
+            if orphan && fs::remove_file(&path).is_ok() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_dedupes_and_release_deletes() {
+        let dir = std::env::temp_dir().join(format!("kiro-blobs-{}", uuid::Uuid::new_v4()));
+        let store = BlobStore::new(dir.clone());
+        let content = json!({"text": "You are a helpful assistant.".repeat(100)});
+
+        let first = store.put(&content).unwrap();
+        let second = store.put(&content).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert_eq!(store.resolve(first.clone()).unwrap(), content);
+        assert_eq!(store.resolve(json!("plain")).unwrap(), json!("plain"));
+
+        store.release(&first);
+        assert_eq!(store.resolve(first.clone()).unwrap(), content);
+        store.release(&second);
+        assert!(store.resolve(first.clone()).is_err());
+
+        // 重建引用计数后清理无人引用的内容
+        let kept = store.put(&json!("kept")).unwrap();
+        store.put(&json!("orphan")).unwrap();
+        let restarted = BlobStore::new(dir.clone());
+        restarted.retain(&kept);
+        assert_eq!(restarted.collect_garbage(), 1);
+        assert_eq!(restarted.resolve(kept).unwrap(), json!("kept"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,213 @@
+//! 跨域资源共享（CORS）
+//!
+//! 按配置 `cors` 为 `/v1`、`/cc/v1` 与 Admin API 分别生成 CORS 层，让部署在其他来源上的
+//! 浏览器面板可以直接调用。未配置时 `/v1` 允许任意来源，Admin API 不允许跨域访问。
+
+use std::time::Duration;
+
+use axum::http::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use tower_http::cors::{AllowHeaders, AllowOrigin, Any, CorsLayer};
+
+use super::request_id::REQUEST_ID_HEADER;
+
+/// 允许的来源：`*`，或 `scheme://host[:port]`（如 `https://dash.example.com`）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CorsOrigin(String);
+
+impl TryFrom<String> for CorsOrigin {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        if value == "*" {
+            return Ok(Self(value.to_string()));
+        }
+        let invalid = || format!("无效的 CORS 来源（应为 scheme://host[:port]）: {}", value);
+        let url = reqwest::Url::parse(value).map_err(|_| invalid())?;
+        let origin = url.origin().ascii_serialization();
+        // 只接受来源本身，不带路径、查询参数
+        if !matches!(url.scheme(), "http" | "https")
+            || !value.trim_end_matches('/').eq_ignore_ascii_case(&origin)
+        {
+            return Err(invalid());
+        }
+        Ok(Self(origin))
+    }
+}
+
+impl From<CorsOrigin> for String {
+    fn from(origin: CorsOrigin) -> Self {
+        origin.0
+    }
+}
+
+impl CorsOrigin {
+    fn is_any(&self) -> bool {
+        self.0 == "*"
+    }
+}
+
+/// 允许的请求头名称
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CorsHeader(HeaderName);
+
+impl TryFrom<String> for CorsHeader {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        HeaderName::from_bytes(value.trim().as_bytes())
+            .map(Self)
+            .map_err(|_| format!("无效的请求头名称: {}", value))
+    }
+}
+
+impl From<CorsHeader> for String {
+    fn from(header: CorsHeader) -> Self {
+        header.0.as_str().to_string()
+    }
+}
+
+/// 一组路由的 CORS 规则
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorsPolicy {
+    /// 允许的来源，包含 `*` 时允许任意来源；为空时不允许跨域访问
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_origins: Vec<CorsOrigin>,
+    /// 允许的请求头，为空时允许任意请求头
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_headers: Vec<CorsHeader>,
+    /// 浏览器缓存预检结果的时间（秒），未设置时不缓存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+}
+
+impl CorsPolicy {
+    /// 允许任意来源与请求头（未配置 `cors.anthropic` 时 `/v1` 的规则）
+    pub fn permissive() -> Self {
+        Self {
+            allowed_origins: vec![CorsOrigin("*".to_string())],
+            ..Default::default()
+        }
+    }
+
+    /// 生成 CORS 层：允许任意方法，并向浏览器暴露 `x-request-id` 响应头
+    pub fn layer(&self) -> CorsLayer {
+        let origin = if self.allowed_origins.iter().any(CorsOrigin::is_any) {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(
+                self.allowed_origins
+                    .iter()
+                    .filter_map(|origin| HeaderValue::from_str(&origin.0).ok()),
+            )
+        };
+        let headers = if self.allowed_headers.is_empty() {
+            AllowHeaders::any()
+        } else {
+            AllowHeaders::list(self.allowed_headers.iter().map(|header| header.0.clone()))
+        };
+        let layer = CorsLayer::new()
+            .allow_origin(origin)
+            .allow_methods(Any)
+            .allow_headers(headers)
+            .expose_headers([HeaderName::from_static(REQUEST_ID_HEADER)]);
+        match self.max_age_secs {
+            Some(secs) => layer.max_age(Duration::from_secs(secs)),
+            None => layer,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Router;
+    use axum::routing::get;
+
+    use super::*;
+
+    fn origin(value: &str) -> Result<CorsOrigin, String> {
+        CorsOrigin::try_from(value.to_string())
+    }
+
+    #[test]
+    fn test_parse_origin() {
+        assert_eq!(origin("*").unwrap().0, "*");
+        assert_eq!(
+            origin("https://Dash.Example.com/").unwrap().0,
+            "https://dash.example.com"
+        );
+        assert_eq!(
+            origin("http://localhost:5173").unwrap().0,
+            "http://localhost:5173"
+        );
+        assert!(origin("dash.example.com").is_err());
+        assert!(origin("https://dash.example.com/admin").is_err());
+        assert!(origin("ftp://dash.example.com").is_err());
+
+        assert!(CorsHeader::try_from("x-api-key".to_string()).is_ok());
+        assert!(CorsHeader::try_from("bad header".to_string()).is_err());
+
+        let policy: CorsPolicy = serde_json::from_str(
+            r#"{"allowedOrigins": ["https://a.example.com"], "allowedHeaders": ["X-Api-Key"]}"#,
+        )
+        .unwrap();
+        assert_eq!(policy.allowed_headers[0].0, "x-api-key");
+        assert!(serde_json::from_str::<CorsPolicy>(r#"{"allowedOrigins": ["a.com"]}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_layer_handles_preflight() {
+        let policy = CorsPolicy {
+            allowed_origins: vec![origin("https://dash.example.com").unwrap()],
+            allowed_headers: vec![CorsHeader::try_from("x-api-key".to_string()).unwrap()],
+            max_age_secs: Some(600),
+        };
+        let app = Router::new()
+            .route("/v1/models", get(|| async { "ok" }))
+            .layer(policy.layer());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/models", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = reqwest::Client::new();
+        let preflight = |origin: &'static str| {
+            client
+                .request(reqwest::Method::OPTIONS, &url)
+                .header("origin", origin)
+                .header("access-control-request-method", "POST")
+                .header("access-control-request-headers", "x-api-key")
+                .send()
+        };
+
+        let response = preflight("https://dash.example.com").await.unwrap();
+        let headers = response.headers();
+        assert_eq!(
+            headers["access-control-allow-origin"],
+            "https://dash.example.com"
+        );
+        assert_eq!(headers["access-control-allow-headers"], "x-api-key");
+        assert_eq!(headers["access-control-max-age"], "600");
+
+        let response = preflight("https://other.example.com").await.unwrap();
+        assert!(
+            !response
+                .headers()
+                .contains_key("access-control-allow-origin")
+        );
+
+        let response = client
+            .get(&url)
+            .header("origin", "https://dash.example.com")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()["access-control-expose-headers"],
+            REQUEST_ID_HEADER
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! 口令加密
+//!
+//! 使用 PBKDF2-HMAC-SHA256 从口令派生 256 位密钥，再以 AES-256-GCM 加密。
+//! 需要反复加密的场景（如凭据文件回写）使用 `DerivedKey` 缓存派生结果，只在创建时付出一次 KDF 开销。
+//! 密文以 JSON 信封形式保存，所有二进制字段均为 hex 编码：
+//!
+//! ```json
+//! {"cipher": "aes-256-gcm", "kdf": "pbkdf2-sha256", "iterations": 600000,
+//!  "salt": "...", "nonce": "...", "ciphertext": "..."}
+//! ```
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+pub const CIPHER: &str = "aes-256-gcm";
+pub const KDF: &str = "pbkdf2-sha256";
+
+/// PBKDF2 迭代次数
+const PBKDF2_ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// 加密信封
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct EncryptedEnvelope {
+    pub cipher: String,
+    pub kdf: String,
+    pub iterations: u32,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Key<Aes256Gcm> {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key.into()
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// 使用口令加密
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> anyhow::Result<EncryptedEnvelope> {
+    encrypt_with_iterations(plaintext, passphrase, PBKDF2_ITERATIONS)
+}
+
+fn encrypt_with_iterations(
+    plaintext: &[u8],
+    passphrase: &str,
+    iterations: u32,
+) -> anyhow::Result<EncryptedEnvelope> {
+    let salt: [u8; SALT_LEN] = random_bytes();
+    let nonce: [u8; NONCE_LEN] = random_bytes();
+
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt, iterations));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow::anyhow!("加密失败"))?;
+
+    Ok(EncryptedEnvelope {
+        cipher: CIPHER.to_string(),
+        kdf: KDF.to_string(),
+        iterations,
+        salt: hex::encode(salt),
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    })
+}
+
+/// 已派生的密钥（绑定固定的盐，每次加密使用新的随机 nonce）
+#[derive(Clone)]
+pub struct DerivedKey {
+    salt: [u8; SALT_LEN],
+    iterations: u32,
+    key: Key<Aes256Gcm>,
+}
+
+impl DerivedKey {
+    /// 从口令派生新密钥（随机盐）
+    pub fn new(passphrase: &str) -> Self {
+        Self::with_iterations(passphrase, PBKDF2_ITERATIONS)
+    }
+
+    fn with_iterations(passphrase: &str, iterations: u32) -> Self {
+        let salt: [u8; SALT_LEN] = random_bytes();
+        Self {
+            salt,
+            iterations,
+            key: derive_key(passphrase, &salt, iterations),
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> anyhow::Result<EncryptedEnvelope> {
+        let nonce: [u8; NONCE_LEN] = random_bytes();
+        let ciphertext = Aes256Gcm::new(&self.key)
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| anyhow::anyhow!("加密失败"))?;
+
+        Ok(EncryptedEnvelope {
+            cipher: CIPHER.to_string(),
+            kdf: KDF.to_string(),
+            iterations: self.iterations,
+            salt: hex::encode(self.salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+}
+
+/// 判断 JSON 值是否为加密信封
+pub fn is_envelope(value: &serde_json::Value) -> bool {
+    value.get("cipher").is_some() && value.get("ciphertext").is_some()
+}
+
+/// 使用口令解密
+pub fn decrypt(envelope: &EncryptedEnvelope, passphrase: &str) -> anyhow::Result<Vec<u8>> {
+    if envelope.cipher != CIPHER || envelope.kdf != KDF {
+        anyhow::bail!(
+            "不支持的加密格式: cipher={}, kdf={}",
+            envelope.cipher,
+            envelope.kdf
+        );
+    }
+
+    let salt = hex::decode(&envelope.salt)?;
+    let nonce = hex::decode(&envelope.nonce)?;
+    let ciphertext = hex::decode(&envelope.ciphertext)?;
+    if nonce.len() != NONCE_LEN {
+        anyhow::bail!("nonce 长度无效");
+    }
+
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt, envelope.iterations));
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow::anyhow!("解密失败：口令错误或数据已损坏"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let envelope =
+            encrypt_with_iterations(b"[{\"refreshToken\":\"abc\"}]", "correct horse", 1000)
+                .unwrap();
+        assert_eq!(envelope.cipher, CIPHER);
+        assert_eq!(
+            decrypt(&envelope, "correct horse").unwrap(),
+            b"[{\"refreshToken\":\"abc\"}]"
+        );
+        assert!(decrypt(&envelope, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_derived_key_reuses_salt() {
+        let key = DerivedKey::with_iterations("pw", 1000);
+        let first = key.encrypt(b"first").unwrap();
+        let second = key.encrypt(b"second").unwrap();
+        assert_eq!(first.salt, second.salt);
+        assert_ne!(first.nonce, second.nonce);
+        assert!(is_envelope(&serde_json::to_value(&second).unwrap()));
+        assert_eq!(decrypt(&first, "pw").unwrap(), b"first");
+        assert_eq!(decrypt(&second, "pw").unwrap(), b"second");
+    }
+}
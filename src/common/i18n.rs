@@ -0,0 +1,172 @@
+//! 多语言消息
+//!
+//! 按请求的 `Accept-Language` 选择消息语言（目前支持中文与英文）。消息文本集中在
+//! [`Message`] 中；请求未携带该头或没有受支持的语言时，每条消息使用其原有的默认语言，
+//! 与引入多语言之前的响应保持一致。
+
+use std::future::Future;
+
+use axum::http::{HeaderMap, header};
+
+tokio::task_local! {
+    /// 当前请求协商出的语言（见 `with_locale`）
+    static LOCALE: Option<Locale>;
+}
+
+/// 支持的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    Zh,
+    En,
+}
+
+impl Locale {
+    /// 解析 `Accept-Language`，返回权重最高的受支持语言（权重相同时取先出现的）
+    pub fn negotiate(accept_language: &str) -> Option<Self> {
+        let mut best: Option<(Self, f32)> = None;
+        for item in accept_language.split(',') {
+            let mut parts = item.split(';');
+            let tag = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+            let locale = match tag.split('-').next().unwrap_or_default() {
+                "zh" => Self::Zh,
+                "en" => Self::En,
+                _ => continue,
+            };
+            let quality = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .map_or(1.0, |q| q.trim().parse::<f32>().unwrap_or(0.0));
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((locale, quality));
+            }
+        }
+        best.map(|(locale, _)| locale)
+    }
+
+    /// 从请求头协商语言
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(Self::negotiate)
+    }
+}
+
+/// 在指定语言下执行（期间 [`Message::localized`] 按该语言输出）
+pub async fn with_locale<F: Future>(locale: Option<Locale>, future: F) -> F::Output {
+    LOCALE.scope(locale, future).await
+}
+
+/// 当前请求的语言（不在 `with_locale` 中时为 None）
+pub fn current_locale() -> Option<Locale> {
+    LOCALE.try_with(|locale| *locale).ok().flatten()
+}
+
+/// 消息目录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    AdminAuthenticationFailed,
+    AdminReadonly,
+    IpDenied,
+    SessionTokenRequired,
+    CredentialNotFound,
+    UpstreamError,
+    InternalError,
+    InvalidCredential,
+    ModelRouteNotFound,
+    TenantNotFound,
+    TenantKeyNotFound,
+    LogBufferDisabled,
+    SigningKeyNotConfigured,
+}
+
+impl Message {
+    /// 未协商出语言时使用的语言
+    fn default_locale(self) -> Locale {
+        match self {
+            Self::AdminAuthenticationFailed
+            | Self::AdminReadonly
+            | Self::IpDenied
+            | Self::SessionTokenRequired => Locale::En,
+            _ => Locale::Zh,
+        }
+    }
+
+    fn zh(self) -> &'static str {
+        match self {
+            Self::AdminAuthenticationFailed => "Admin API Key 无效或缺失",
+            Self::AdminReadonly => "只读账号不能修改状态",
+            Self::IpDenied => "不允许从该 IP 地址访问",
+            Self::SessionTokenRequired => "续期需要使用会话 Token",
+            Self::CredentialNotFound => "凭据不存在",
+            Self::UpstreamError => "上游服务错误",
+            Self::InternalError => "内部错误",
+            Self::InvalidCredential => "凭据无效",
+            Self::ModelRouteNotFound => "模型路由不存在",
+            Self::TenantNotFound => "租户不存在",
+            Self::TenantKeyNotFound => "租户的 API Key 不存在",
+            Self::LogBufferDisabled => "日志缓冲未启用",
+            Self::SigningKeyNotConfigured => "未配置 usageSigningKey",
+        }
+    }
+
+    fn en(self) -> &'static str {
+        match self {
+            Self::AdminAuthenticationFailed => "Invalid or missing admin API key",
+            Self::AdminReadonly => "Read-only admin API key cannot modify state",
+            Self::IpDenied => "Access from this IP address is not allowed",
+            Self::SessionTokenRequired => "Session refresh requires a session token",
+            Self::CredentialNotFound => "Credential not found",
+            Self::UpstreamError => "Upstream service error",
+            Self::InternalError => "Internal error",
+            Self::InvalidCredential => "Invalid credential",
+            Self::ModelRouteNotFound => "Model route not found",
+            Self::TenantNotFound => "Tenant not found",
+            Self::TenantKeyNotFound => "Tenant API key not found",
+            Self::LogBufferDisabled => "Log buffer is not enabled",
+            Self::SigningKeyNotConfigured => "usageSigningKey is not configured",
+        }
+    }
+
+    /// 指定语言下的文本
+    pub fn text(self, locale: Option<Locale>) -> &'static str {
+        match locale.unwrap_or(self.default_locale()) {
+            Locale::Zh => self.zh(),
+            Locale::En => self.en(),
+        }
+    }
+
+    /// 当前请求语言下的文本
+    pub fn localized(self) -> &'static str {
+        self.text(current_locale())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(
+            Locale::negotiate("zh-CN,zh;q=0.9,en;q=0.8"),
+            Some(Locale::Zh)
+        );
+        assert_eq!(Locale::negotiate("fr-FR, en-US;q=0.7"), Some(Locale::En));
+        assert_eq!(Locale::negotiate("zh;q=0.5, EN;q=0.6"), Some(Locale::En));
+        assert_eq!(Locale::negotiate("en;q=0, zh;q=0.1"), Some(Locale::Zh));
+        assert_eq!(Locale::negotiate("fr, *"), None);
+        assert_eq!(Locale::negotiate(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_localized_uses_request_locale() {
+        let message = Message::CredentialNotFound;
+        assert_eq!(message.localized(), "凭据不存在");
+        assert_eq!(Message::IpDenied.localized(), Message::IpDenied.en());
+
+        let text = with_locale(Some(Locale::En), async { message.localized() }).await;
+        assert_eq!(text, "Credential not found");
+        let text = with_locale(Some(Locale::Zh), async { Message::IpDenied.localized() }).await;
+        assert_eq!(text, "不允许从该 IP 地址访问");
+    }
+}
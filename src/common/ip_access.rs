@@ -0,0 +1,171 @@
+//! IP 访问控制
+//!
+//! 按 CIDR 白名单 / 黑名单限制客户端来源（配置 `ipAccess`），Anthropic API 与 Admin API
+//! 各自使用独立的规则。规则在每个请求时读取，热重载配置后立即生效。
+
+use std::net::{IpAddr, SocketAddr};
+
+use axum::extract::{ConnectInfo, Request};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+/// 访问规则中的一项：CIDR（如 `10.0.0.0/8`）或单个 IP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IpRule(IpNet);
+
+impl TryFrom<String> for IpRule {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        value
+            .parse::<IpNet>()
+            .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+            .map(Self)
+            .map_err(|_| format!("无效的 IP 或 CIDR: {}", value))
+    }
+}
+
+impl From<IpRule> for String {
+    fn from(rule: IpRule) -> Self {
+        rule.0.to_string()
+    }
+}
+
+impl IpRule {
+    fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.contains(ip)
+    }
+}
+
+/// 一组访问规则：命中 `deny` 的地址总是拒绝；`allow` 非空时只允许其中的地址
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpAccessList {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<IpRule>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<IpRule>,
+}
+
+impl IpAccessList {
+    /// 是否允许该地址访问（地址未知时只有在没有白名单时才允许）
+    pub fn permits(&self, ip: Option<IpAddr>) -> bool {
+        let Some(ip) = ip else {
+            return self.allow.is_empty();
+        };
+        let ip = ip.to_canonical();
+        if self.deny.iter().any(|rule| rule.contains(&ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|rule| rule.contains(&ip))
+    }
+}
+
+/// 请求的客户端地址
+///
+/// `trust_forwarded_for` 为 true 时（服务部署在反向代理之后）取 `X-Forwarded-For` 的最后一项，
+/// 即反向代理追加的对端地址；前面的项由客户端提供、可以伪造，不予采信。
+/// 未开启或最后一项无效时取 TCP 连接的对端地址
+pub fn client_ip(request: &Request, trust_forwarded_for: bool) -> Option<IpAddr> {
+    if trust_forwarded_for
+        && let Some(ip) = request
+            .headers()
+            .get_all("x-forwarded-for")
+            .iter()
+            .next_back()
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit(',').next())
+            .and_then(|v| v.trim().parse::<IpAddr>().ok())
+    {
+        return Some(ip);
+    }
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(allow: &[&str], deny: &[&str]) -> IpAccessList {
+        let rules = |items: &[&str]| {
+            items
+                .iter()
+                .map(|s| IpRule::try_from(s.to_string()).unwrap())
+                .collect()
+        };
+        IpAccessList {
+            allow: rules(allow),
+            deny: rules(deny),
+        }
+    }
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn test_allow_and_deny_rules() {
+        let open = IpAccessList::default();
+        assert!(open.permits(ip("203.0.113.7")));
+        assert!(open.permits(None));
+
+        let admin = list(&["10.0.0.0/8", "192.168.1.20"], &["10.0.0.66"]);
+        assert!(admin.permits(ip("10.1.2.3")));
+        assert!(admin.permits(ip("192.168.1.20")));
+        assert!(!admin.permits(ip("192.168.1.21")));
+        assert!(!admin.permits(ip("10.0.0.66")));
+        // IPv4 映射的 IPv6 地址按 IPv4 匹配
+        assert!(admin.permits(ip("::ffff:10.1.2.3")));
+        assert!(!admin.permits(None));
+
+        let blocked = list(&[], &["2001:db8::/32"]);
+        assert!(!blocked.permits(ip("2001:db8::1")));
+        assert!(blocked.permits(ip("2001:db9::1")));
+    }
+
+    #[test]
+    fn test_client_ip_uses_rightmost_forwarded_for() {
+        let request = |forwarded: &[&str]| {
+            let mut builder = Request::builder();
+            for value in forwarded {
+                builder = builder.header("x-forwarded-for", *value);
+            }
+            let mut request = builder.body(axum::body::Body::empty()).unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 2], 443))));
+            request
+        };
+
+        // 客户端伪造的最左项不被采信
+        let spoofed = request(&["127.0.0.1, 203.0.113.7"]);
+        assert_eq!(client_ip(&spoofed, true), ip("203.0.113.7"));
+        let admin = list(&["127.0.0.1"], &[]);
+        assert!(!admin.permits(client_ip(&spoofed, true)));
+
+        // 客户端自带一个头、代理再追加一个头
+        let appended = request(&["127.0.0.1", "198.51.100.4"]);
+        assert_eq!(client_ip(&appended, true), ip("198.51.100.4"));
+
+        // 未开启或最后一项无效时取连接对端地址
+        assert_eq!(client_ip(&spoofed, false), ip("10.0.0.2"));
+        assert_eq!(
+            client_ip(&request(&["203.0.113.7, unknown"]), true),
+            ip("10.0.0.2")
+        );
+        assert_eq!(client_ip(&request(&[]), true), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn test_rule_parsing() {
+        assert!(IpRule::try_from("10.0.0.0/33".to_string()).is_err());
+        assert!(IpRule::try_from("example.com".to_string()).is_err());
+        let json = serde_json::to_string(&list(&["127.0.0.1"], &[])).unwrap();
+        assert_eq!(json, r#"{"allow":["127.0.0.1/32"]}"#);
+    }
+}
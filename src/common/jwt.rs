@@ -0,0 +1,74 @@
+//! HS256 JWT 签发与校验
+//!
+//! 只支持 Admin 会话需要的最小子集：固定头部 `{"alg":"HS256","typ":"JWT"}`，
+//! 校验签名后返回 claims，过期时间等字段由调用方检查。
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// base64url 编码的固定头部 `{"alg":"HS256","typ":"JWT"}`
+const HEADER: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9";
+
+fn sign(message: &str, secret: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC 接受任意长度的密钥");
+    mac.update(message.as_bytes());
+    mac
+}
+
+/// 签发 JWT
+pub fn encode<T: Serialize>(claims: &T, secret: &[u8]) -> String {
+    let payload = serde_json::to_vec(claims).unwrap_or_default();
+    let message = format!("{}.{}", HEADER, URL_SAFE_NO_PAD.encode(payload));
+    let signature = sign(&message, secret).finalize().into_bytes();
+    format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature))
+}
+
+/// 校验签名并解析 claims（头部不是本模块签发的格式时同样视为无效）
+pub fn decode<T: DeserializeOwned>(token: &str, secret: &[u8]) -> Option<T> {
+    let (message, signature) = token.rsplit_once('.')?;
+    let (header, payload) = message.split_once('.')?;
+    if header != HEADER {
+        return None;
+    }
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    sign(message, secret).verify_slice(&signature).ok()?;
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Claims {
+        sub: String,
+        exp: i64,
+    }
+
+    #[test]
+    fn test_round_trip_and_tampering() {
+        let claims = Claims {
+            sub: "alice".to_string(),
+            exp: 1_700_000_000,
+        };
+        let token = encode(&claims, b"secret");
+        assert_eq!(decode::<Claims>(&token, b"secret"), Some(claims));
+        assert_eq!(decode::<Claims>(&token, b"other"), None);
+
+        // 篡改 payload 后签名失效
+        let (_, rest) = token.split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        let forged_payload = URL_SAFE_NO_PAD.encode(br#"{"sub":"root","exp":1700000000}"#);
+        let forged = format!("{}.{}.{}", HEADER, forged_payload, signature);
+        assert_eq!(decode::<Claims>(&forged, b"secret"), None);
+
+        assert_eq!(decode::<Claims>("not-a-token", b"secret"), None);
+    }
+}
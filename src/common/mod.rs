@@ -1,3 +1,12 @@
 //! 公共工具模块
 
 pub mod auth;
+pub mod blob_store;
+pub mod cors;
+pub mod crypto;
+pub mod i18n;
+pub mod ip_access;
+pub mod jwt;
+pub mod rate_limit;
+pub mod request_id;
+pub mod usage_signing;
@@ -0,0 +1,238 @@
+//! 滑动窗口限流器
+//!
+//! 统计最近 60 秒内的请求数与 token 数，超出配置的上限时拒绝新请求，
+//! 并给出最早一条记录滑出窗口所需的等待时间（用于 `retry-after` 头）。
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+/// 限流窗口长度
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// 单项限额的当前状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitStatus {
+    /// 每分钟上限
+    pub limit: u64,
+    /// 当前窗口内的剩余额度
+    pub remaining: u64,
+    /// 最早一条记录滑出窗口所需的时间（窗口为空时为 0）
+    pub reset: Duration,
+}
+
+/// 限流器的当前状态（未设置的上限为 None）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub requests: Option<LimitStatus>,
+    pub tokens: Option<LimitStatus>,
+}
+
+/// 滑动窗口限流器（每分钟请求数 / 每分钟 token 数）
+pub struct RateLimiter {
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u64>,
+    state: Mutex<WindowState>,
+}
+
+#[derive(Default)]
+struct WindowState {
+    /// 窗口内每个请求的到达时间
+    requests: VecDeque<Instant>,
+    /// 窗口内每次 token 消耗的时间与数量
+    tokens: VecDeque<(Instant, u64)>,
+    /// 窗口内 token 总数
+    token_sum: u64,
+}
+
+impl WindowState {
+    fn prune(&mut self, now: Instant) {
+        while let Some(&t) = self.requests.front() {
+            if now.duration_since(t) < WINDOW {
+                break;
+            }
+            self.requests.pop_front();
+        }
+        while let Some(&(t, n)) = self.tokens.front() {
+            if now.duration_since(t) < WINDOW {
+                break;
+            }
+            self.tokens.pop_front();
+            self.token_sum -= n;
+        }
+    }
+}
+
+impl RateLimiter {
+    /// 创建限流器，两个上限都未设置时返回 None
+    pub fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u64>) -> Option<Self> {
+        if requests_per_minute.is_none() && tokens_per_minute.is_none() {
+            return None;
+        }
+        Some(Self {
+            requests_per_minute,
+            tokens_per_minute,
+            state: Mutex::new(WindowState::default()),
+        })
+    }
+
+    /// 每分钟请求数上限
+    pub fn requests_per_minute(&self) -> Option<u32> {
+        self.requests_per_minute
+    }
+
+    /// 每分钟 token 数上限
+    pub fn tokens_per_minute(&self) -> Option<u64> {
+        self.tokens_per_minute
+    }
+
+    /// 尝试放行一个请求，超限时返回需要等待的时间
+    pub fn check(&self) -> Result<(), Duration> {
+        self.check_at(Instant::now())
+    }
+
+    fn check_at(&self, now: Instant) -> Result<(), Duration> {
+        let mut state = self.state.lock();
+        state.prune(now);
+
+        if let Some(limit) = self.requests_per_minute
+            && state.requests.len() >= limit as usize
+        {
+            let oldest = state.requests.front().copied().unwrap_or(now);
+            return Err(WINDOW.saturating_sub(now.duration_since(oldest)));
+        }
+
+        if let Some(limit) = self.tokens_per_minute
+            && state.token_sum >= limit
+        {
+            let oldest = state.tokens.front().map(|&(t, _)| t).unwrap_or(now);
+            return Err(WINDOW.saturating_sub(now.duration_since(oldest)));
+        }
+
+        state.requests.push_back(now);
+        Ok(())
+    }
+
+    /// 记录一次 token 消耗
+    pub fn record_tokens(&self, tokens: u64) {
+        self.record_tokens_at(Instant::now(), tokens);
+    }
+
+    fn record_tokens_at(&self, now: Instant, tokens: u64) {
+        if self.tokens_per_minute.is_none() || tokens == 0 {
+            return;
+        }
+        let mut state = self.state.lock();
+        state.prune(now);
+        state.tokens.push_back((now, tokens));
+        state.token_sum += tokens;
+    }
+
+    /// 当前窗口内的用量与剩余额度
+    pub fn status(&self) -> RateLimitStatus {
+        self.status_at(Instant::now())
+    }
+
+    fn status_at(&self, now: Instant) -> RateLimitStatus {
+        let mut state = self.state.lock();
+        state.prune(now);
+        let reset = |oldest: Option<Instant>| {
+            oldest.map_or(Duration::ZERO, |t| {
+                WINDOW.saturating_sub(now.duration_since(t))
+            })
+        };
+
+        RateLimitStatus {
+            requests: self.requests_per_minute.map(|limit| LimitStatus {
+                limit: u64::from(limit),
+                remaining: u64::from(limit).saturating_sub(state.requests.len() as u64),
+                reset: reset(state.requests.front().copied()),
+            }),
+            tokens: self.tokens_per_minute.map(|limit| LimitStatus {
+                limit,
+                remaining: limit.saturating_sub(state.token_sum),
+                reset: reset(state.tokens.front().map(|&(t, _)| t)),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_when_no_limits() {
+        assert!(RateLimiter::new(None, None).is_none());
+    }
+
+    #[test]
+    fn test_requests_per_minute() {
+        let limiter = RateLimiter::new(Some(2), None).unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check_at(start).is_ok());
+        assert!(limiter.check_at(start + Duration::from_secs(10)).is_ok());
+
+        let retry = limiter
+            .check_at(start + Duration::from_secs(20))
+            .unwrap_err();
+        assert_eq!(retry, Duration::from_secs(40));
+
+        // 第一个请求滑出窗口后恢复
+        assert!(limiter.check_at(start + Duration::from_secs(61)).is_ok());
+    }
+
+    #[test]
+    fn test_tokens_per_minute() {
+        let limiter = RateLimiter::new(None, Some(1000)).unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check_at(start).is_ok());
+        limiter.record_tokens_at(start, 600);
+        assert!(limiter.check_at(start + Duration::from_secs(1)).is_ok());
+        limiter.record_tokens_at(start + Duration::from_secs(30), 500);
+
+        let retry = limiter
+            .check_at(start + Duration::from_secs(45))
+            .unwrap_err();
+        assert_eq!(retry, Duration::from_secs(15));
+
+        // 600 tokens 滑出窗口后剩余 500，低于上限
+        assert!(limiter.check_at(start + Duration::from_secs(60)).is_ok());
+    }
+
+    #[test]
+    fn test_status_reports_remaining() {
+        let limiter = RateLimiter::new(Some(10), Some(1000)).unwrap();
+        let start = Instant::now();
+
+        let idle = limiter.status_at(start);
+        assert_eq!(idle.requests.unwrap().remaining, 10);
+        assert_eq!(idle.requests.unwrap().reset, Duration::ZERO);
+
+        assert!(limiter.check_at(start).is_ok());
+        limiter.record_tokens_at(start + Duration::from_secs(20), 300);
+        let status = limiter.status_at(start + Duration::from_secs(30));
+        assert_eq!(
+            status.requests,
+            Some(LimitStatus {
+                limit: 10,
+                remaining: 9,
+                reset: Duration::from_secs(30),
+            })
+        );
+        assert_eq!(
+            status.tokens,
+            Some(LimitStatus {
+                limit: 1000,
+                remaining: 700,
+                reset: Duration::from_secs(50),
+            })
+        );
+
+        let requests_only = RateLimiter::new(Some(1), None).unwrap();
+        assert!(requests_only.status().tokens.is_none());
+    }
+}
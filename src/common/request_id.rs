@@ -0,0 +1,94 @@
+//! 请求 ID
+//!
+//! 为每个请求分配 ID（客户端通过 `x-request-id` 传入合法值时沿用），
+//! 处理请求期间（含流式响应体）产生的日志都挂在携带该 ID 的 tracing span 下，
+//! 并在响应头 `x-request-id` 中返回，便于将客户端报错与服务端日志对应起来。
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::Request,
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use http_body::{Frame, SizeHint};
+use tracing::{Instrument, Span};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// 客户端传入的请求 ID 最大长度
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 判断客户端传入的请求 ID 是否可以沿用（非空、长度受限、仅含可见 ASCII 字符）
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// 请求 ID 中间件
+pub async fn request_id_middleware(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(String::from)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+
+    let mut response = next.run(request).instrument(span.clone()).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    // 流式响应的大部分处理发生在响应体被轮询时，同样需要进入 span
+    response.map(|body| Body::new(InstrumentedBody { inner: body, span }))
+}
+
+/// 轮询时进入指定 span 的响应体
+struct InstrumentedBody {
+    inner: Body,
+    span: Span,
+}
+
+impl HttpBody for InstrumentedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let _enter = this.span.enter();
+        Pin::new(&mut this.inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_request_id() {
+        assert!(is_valid_request_id("req-123_abc"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("has space"));
+        assert!(!is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+    }
+}
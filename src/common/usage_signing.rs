@@ -0,0 +1,156 @@
+//! 请求记录签名
+//!
+//! 配置 `usageSigningKey`（base64 编码的 32 字节 Ed25519 私钥种子）后，每条请求记录在生成时
+//! 附带签名。下游计费系统用 `GET /api/admin/token-usage/signing-key` 返回的公钥校验，
+//! 汇总多个代理导出的用量时可以确认记录未被篡改。
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// 签名算法名称
+pub const ALGORITHM: &str = "ed25519";
+
+/// Ed25519 签名私钥
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct UsageSigningKey(SigningKey);
+
+impl TryFrom<String> for UsageSigningKey {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let seed: [u8; 32] = STANDARD
+            .decode(value.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| "usageSigningKey 必须是 base64 编码的 32 字节私钥".to_string())?;
+        Ok(Self(SigningKey::from_bytes(&seed)))
+    }
+}
+
+impl From<UsageSigningKey> for String {
+    fn from(key: UsageSigningKey) -> Self {
+        key.secret()
+    }
+}
+
+impl std::fmt::Debug for UsageSigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("UsageSigningKey")
+            .field(&self.key_id())
+            .finish()
+    }
+}
+
+impl UsageSigningKey {
+    /// base64 编码的私钥（用于密钥屏蔽）
+    pub fn secret(&self) -> String {
+        STANDARD.encode(self.0.to_bytes())
+    }
+
+    /// base64 编码的公钥
+    pub fn public_key(&self) -> String {
+        STANDARD.encode(self.0.verifying_key().as_bytes())
+    }
+
+    /// 密钥标识（公钥的 SHA-256 前 8 位），轮换密钥后用于区分签名所用的公钥
+    pub fn key_id(&self) -> String {
+        hex::encode(Sha256::digest(self.0.verifying_key().as_bytes()))[..8].to_string()
+    }
+
+    /// 对规范化的记录内容签名，返回 base64 编码的签名
+    pub fn sign(&self, payload: &str) -> String {
+        STANDARD.encode(self.0.sign(payload.as_bytes()).to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    /// 按下游的方式用 base64 编码的公钥校验签名
+    fn verify(public_key: &str, payload: &str, signature: &str) -> bool {
+        let key = STANDARD
+            .decode(public_key)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok());
+        let signature = STANDARD
+            .decode(signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok());
+        match (key, signature) {
+            (Some(key), Some(signature)) => key.verify(payload.as_bytes(), &signature).is_ok(),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = UsageSigningKey::try_from(STANDARD.encode([7u8; 32])).unwrap();
+        let signature = key.sign("payload");
+        assert!(verify(&key.public_key(), "payload", &signature));
+        assert!(!verify(&key.public_key(), "payload2", &signature));
+        assert!(!verify(&key.public_key(), "payload", "bad"));
+
+        // 序列化保持原始私钥，Debug 不输出私钥
+        let json = serde_json::to_string(&key).unwrap();
+        assert_eq!(json, format!("\"{}\"", STANDARD.encode([7u8; 32])));
+        assert!(!format!("{:?}", key).contains(&key.secret()));
+
+        assert!(UsageSigningKey::try_from("c2hvcnQ=".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_record_signature_covers_final_usage() {
+        use crate::admin::types::RequestRecord;
+
+        let key = UsageSigningKey::try_from(STANDARD.encode([9u8; 32])).unwrap();
+        let record = RequestRecord {
+            timestamp: "2026-10-14T08:00:00Z".parse().unwrap(),
+            api_key_id: Some("1a2b3c4d".to_string()),
+            credential_id: Some(3),
+            model: Some("claude-sonnet-4".to_string()),
+            stream: true,
+            status: Some(200),
+            attempts: 1,
+            duration_ms: 1200,
+            input_tokens: Some(1000),
+            output_tokens: Some(250),
+            cache_read_input_tokens: Some(800),
+            cache_creation_input_tokens: Some(0),
+            key_id: None,
+            signature_version: None,
+            signature: None,
+        };
+        let payload = record.signed_payload();
+        assert!(payload.starts_with("v2\n2026-10-14T08:00:00Z\n1a2b3c4d\n3\n"));
+        assert!(payload.ends_with("\n1000\n250\n800\n0"));
+        let signature = key.sign(&payload);
+        assert!(verify(
+            &key.public_key(),
+            &record.signed_payload(),
+            &signature
+        ));
+
+        // 篡改输出或缓存 tokens 后签名不再通过校验
+        let mut tampered = record.clone();
+        tampered.output_tokens = Some(25);
+        assert!(!verify(
+            &key.public_key(),
+            &tampered.signed_payload(),
+            &signature
+        ));
+        let mut tampered = record;
+        tampered.cache_read_input_tokens = Some(0);
+        assert!(!verify(
+            &key.public_key(),
+            &tampered.signed_payload(),
+            &signature
+        ));
+    }
+}
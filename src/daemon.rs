@@ -0,0 +1,241 @@
+//! 守护进程模式、PID 文件与 systemd 通知
+//!
+//! - `--daemon`：两次 fork 并 `setsid` 脱离终端在后台运行（仅 Unix），标准输出 / 错误重定向到
+//!   `--log-file`（未指定时丢弃），必须在启动 tokio 运行时之前调用
+//! - `--pid-file`：启动时写入 PID、正常退出时删除；文件已存在且对应进程仍在运行时拒绝启动
+//! - sd_notify：设置了 `NOTIFY_SOCKET`（systemd `Type=notify`）时在开始监听后发送 `READY=1`，
+//!   退出时发送 `STOPPING=1`；设置了 `WATCHDOG_USEC` 时按其一半的间隔发送 `WATCHDOG=1`
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+static PID_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// 转入后台运行（必须在创建任何线程之前调用）
+#[cfg(unix)]
+pub fn daemonize(log_file: Option<&Path>) -> io::Result<()> {
+    use std::fs::{File, OpenOptions};
+    use std::os::fd::AsRawFd;
+
+    // 先打开文件，出错时还能在终端上报告
+    let log = match log_file {
+        Some(path) => OpenOptions::new().create(true).append(true).open(path)?,
+        None => OpenOptions::new().write(true).open("/dev/null")?,
+    };
+    let null = File::open("/dev/null")?;
+
+    fn fork_and_exit_parent() -> io::Result<()> {
+        // SAFETY: 调用时进程只有一个线程；父进程立即 _exit，不运行任何析构
+        match unsafe { libc::fork() } {
+            -1 => Err(io::Error::last_os_error()),
+            0 => Ok(()),
+            _ => unsafe { libc::_exit(0) },
+        }
+    }
+
+    fork_and_exit_parent()?;
+    // SAFETY: setsid 没有内存安全方面的前置条件
+    if unsafe { libc::setsid() } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // 第二次 fork 后不再是会话首进程，不会重新获得控制终端
+    fork_and_exit_parent()?;
+
+    for (from, to) in [
+        (null.as_raw_fd(), libc::STDIN_FILENO),
+        (log.as_raw_fd(), libc::STDOUT_FILENO),
+        (log.as_raw_fd(), libc::STDERR_FILENO),
+    ] {
+        // SAFETY: 两个文件描述符在调用期间都有效
+        if unsafe { libc::dup2(from, to) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn daemonize(_log_file: Option<&Path>) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "当前平台不支持 --daemon，请使用系统的服务管理器",
+    ))
+}
+
+/// PID 文件中记录的、仍在运行的进程（文件不存在、内容无效或进程已退出时为 None）
+fn running_pid(path: &Path) -> Option<u32> {
+    let pid: u32 = std::fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    (pid != std::process::id() && process_alive(pid)).then_some(pid)
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: 信号 0 只检查进程是否存在，不发送信号
+    let result = unsafe { libc::kill(pid, 0) };
+    // EPERM 表示进程存在但属于其他用户
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    // 无法检查时按已退出处理，覆盖残留的 PID 文件
+    false
+}
+
+/// 写入 PID 文件（只能调用一次）；文件对应的进程仍在运行时返回错误
+pub fn write_pid_file(path: PathBuf) -> io::Result<()> {
+    if let Some(pid) = running_pid(&path) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("PID 文件 {} 对应的进程 {} 仍在运行", path.display(), pid),
+        ));
+    }
+    std::fs::write(&path, format!("{}\n", std::process::id()))?;
+    let _ = PID_FILE.set(path);
+    Ok(())
+}
+
+/// 删除 PID 文件（退出前调用）
+pub fn remove_pid_file() {
+    if let Some(path) = PID_FILE.get()
+        && let Err(e) = std::fs::remove_file(path)
+        && e.kind() != io::ErrorKind::NotFound
+    {
+        tracing::warn!("删除 PID 文件失败 {}: {}", path.display(), e);
+    }
+}
+
+/// 向 `socket`（路径或以 `@` 开头的抽象命名空间地址）发送 sd_notify 消息
+#[cfg(unix)]
+fn send_notify(socket: &str, message: &str) -> io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let datagram = UnixDatagram::unbound()?;
+    #[cfg(target_os = "linux")]
+    if let Some(name) = socket.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        datagram.send_to_addr(message.as_bytes(), &addr)?;
+        return Ok(());
+    }
+    datagram.send_to(message.as_bytes(), socket)?;
+    Ok(())
+}
+
+/// 发送 sd_notify 消息（未设置 `NOTIFY_SOCKET` 时不做任何事）
+fn notify(message: &str) {
+    #[cfg(unix)]
+    if let Ok(socket) = std::env::var("NOTIFY_SOCKET")
+        && let Err(e) = send_notify(&socket, message)
+    {
+        tracing::warn!("发送 systemd 通知失败: {}", e);
+    }
+    #[cfg(not(unix))]
+    let _ = message;
+}
+
+/// 通知 systemd 服务已就绪，并按 `WATCHDOG_USEC` 启动看门狗心跳
+pub fn notify_ready(status: &str) {
+    notify(&format!(
+        "READY=1\nSTATUS={}\nMAINPID={}",
+        status,
+        std::process::id()
+    ));
+    if let Some(interval) = watchdog_interval(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+    ) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                notify("WATCHDOG=1");
+            }
+        });
+    }
+}
+
+/// 通知 systemd 服务正在退出
+pub fn notify_stopping() {
+    notify("STOPPING=1");
+}
+
+/// 看门狗心跳间隔：`WATCHDOG_USEC` 的一半；`WATCHDOG_PID` 指向其他进程时不启用
+fn watchdog_interval(usec: Option<&str>, pid: Option<&str>) -> Option<Duration> {
+    if let Some(pid) = pid
+        && pid.trim().parse::<u32>().ok() != Some(std::process::id())
+    {
+        return None;
+    }
+    let usec: u64 = usec?.trim().parse().ok().filter(|&usec| usec > 0)?;
+    Some(Duration::from_micros(usec / 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_interval() {
+        let own_pid = std::process::id().to_string();
+        assert_eq!(
+            watchdog_interval(Some("10000000"), None),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            watchdog_interval(Some("10000000"), Some(&own_pid)),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(watchdog_interval(Some("10000000"), Some("1")), None);
+        assert_eq!(watchdog_interval(Some("0"), None), None);
+        assert_eq!(watchdog_interval(None, None), None);
+    }
+
+    #[test]
+    fn test_running_pid() {
+        let dir = std::env::temp_dir().join(format!("kiro-daemon-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("kiro.pid");
+
+        assert_eq!(running_pid(&path), None);
+        std::fs::write(&path, "not a pid").unwrap();
+        assert_eq!(running_pid(&path), None);
+        // 自身的 PID（如重启后 PID 被复用）不视为其他实例
+        std::fs::write(&path, format!("{}\n", std::process::id())).unwrap();
+        assert_eq!(running_pid(&path), None);
+        #[cfg(unix)]
+        {
+            let child = std::process::Command::new("sleep").arg("5").spawn();
+            if let Ok(mut child) = child {
+                std::fs::write(&path, child.id().to_string()).unwrap();
+                assert_eq!(running_pid(&path), Some(child.id()));
+                child.kill().unwrap();
+                child.wait().unwrap();
+                assert_eq!(running_pid(&path), None);
+            }
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_notify() {
+        let dir = std::env::temp_dir().join(format!("kiro-notify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notify.sock");
+        let receiver = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+
+        send_notify(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buf = [0u8; 64];
+        let n = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+//! 数据目录磁盘空间监控
+//!
+//! 后台定期检查数据目录（凭据所在目录，保存统计、余额缓存、批次等文件）所在文件系统的
+//! 可用空间。低于 `minFreeDiskMb` 时：
+//! - 停止写入可选数据（凭据统计、余额缓存），空间恢复后再补写
+//! - 发布 `DiskSpaceChanged` 事件（写入审计日志，可通过 Admin API 事件流订阅）
+//! - `/readyz` 返回 503
+//!
+//! 凭据文件、配置文件等必要数据仍照常写入。
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::events::{self, AppEvent};
+
+/// 检查间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 磁盘空间状态
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskStatus {
+    pub path: PathBuf,
+    /// 最近一次检查到的可用空间（字节）
+    pub free_bytes: u64,
+    /// 可用空间下限（字节）
+    pub min_free_bytes: u64,
+    /// 可用空间是否低于下限
+    pub low: bool,
+}
+
+struct DiskMonitor {
+    path: PathBuf,
+    min_free_bytes: u64,
+    free_bytes: AtomicU64,
+    low: AtomicBool,
+}
+
+impl DiskMonitor {
+    /// 记录一次检查结果，返回低空间状态是否发生了变化
+    fn update(&self, free_bytes: u64) -> bool {
+        self.free_bytes.store(free_bytes, Ordering::Relaxed);
+        let low = free_bytes < self.min_free_bytes;
+        self.low.swap(low, Ordering::Relaxed) != low
+    }
+
+    fn status(&self) -> DiskStatus {
+        DiskStatus {
+            path: self.path.clone(),
+            free_bytes: self.free_bytes.load(Ordering::Relaxed),
+            min_free_bytes: self.min_free_bytes,
+            low: self.low.load(Ordering::Relaxed),
+        }
+    }
+}
+
+static MONITOR: OnceLock<DiskMonitor> = OnceLock::new();
+
+/// 查询路径所在文件系统的可用空间（非 root 用户可用部分）
+#[cfg(unix)]
+fn free_bytes(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs 是纯 C 结构体，全零是合法的初始值
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path 是以 NUL 结尾的有效字符串，stat 指向可写的 statvfs
+    let ret = unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) };
+    #[allow(clippy::unnecessary_cast)]
+    (ret == 0).then(|| stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_bytes(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// 启动磁盘空间监控（只能启动一次；无法获取可用空间的平台上不启动）
+pub fn spawn(path: PathBuf, min_free_bytes: u64) {
+    let Some(free) = free_bytes(&path) else {
+        tracing::warn!("无法获取 {} 的可用磁盘空间，磁盘监控未启用", path.display());
+        return;
+    };
+    let monitor = DiskMonitor {
+        path,
+        min_free_bytes,
+        free_bytes: AtomicU64::new(u64::MAX),
+        low: AtomicBool::new(false),
+    };
+    if MONITOR.set(monitor).is_err() {
+        return;
+    }
+    let monitor = MONITOR.get().expect("monitor just set");
+    check(monitor, free);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match free_bytes(&monitor.path) {
+                Some(free) => check(monitor, free),
+                None => tracing::warn!("获取 {} 的可用磁盘空间失败", monitor.path.display()),
+            }
+        }
+    });
+}
+
+fn check(monitor: &DiskMonitor, free: u64) {
+    if !monitor.update(free) {
+        return;
+    }
+    let status = monitor.status();
+    if status.low {
+        tracing::error!(
+            "数据目录 {} 可用磁盘空间不足：剩余 {} MB，低于下限 {} MB，已暂停写入可选数据",
+            status.path.display(),
+            status.free_bytes / 1024 / 1024,
+            status.min_free_bytes / 1024 / 1024
+        );
+    } else {
+        tracing::info!(
+            "数据目录 {} 可用磁盘空间已恢复：剩余 {} MB",
+            status.path.display(),
+            status.free_bytes / 1024 / 1024
+        );
+    }
+    events::publish(AppEvent::DiskSpaceChanged {
+        low: status.low,
+        free_bytes: status.free_bytes,
+        min_free_bytes: status.min_free_bytes,
+    });
+}
+
+/// 当前磁盘空间状态（未启动监控时为 None）
+pub fn status() -> Option<DiskStatus> {
+    MONITOR.get().map(DiskMonitor::status)
+}
+
+/// 是否允许写入可选数据（未启动监控时总是允许）
+pub fn optional_writes_allowed() -> bool {
+    MONITOR.get().is_none_or(|m| !m.low.load(Ordering::Relaxed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_reports_transitions() {
+        let monitor = DiskMonitor {
+            path: PathBuf::from("/data"),
+            min_free_bytes: 100,
+            free_bytes: AtomicU64::new(u64::MAX),
+            low: AtomicBool::new(false),
+        };
+        assert!(!monitor.update(500));
+        assert!(monitor.update(50));
+        assert!(monitor.status().low);
+        assert!(!monitor.update(60));
+        assert!(monitor.update(100));
+        assert!(!monitor.status().low);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_free_bytes_of_temp_dir() {
+        assert!(free_bytes(&std::env::temp_dir()).is_some());
+    }
+}
@@ -0,0 +1,281 @@
+//! DNS 解析控制模块
+//!
+//! 为所有出站 HTTP Client 提供统一的解析策略：
+//! - 域名覆盖（host → IP），直接写入 reqwest 的解析覆盖表
+//! - DNS over HTTPS（JSON 格式接口，`?name=<host>&type=A|AAAA`），代替系统解析器
+//! - IPv4 / IPv6 偏好：对解析结果重新排序或过滤，规避不可用的 IPv6 线路
+//!
+//! 未配置任何项时保持 reqwest 默认的系统解析行为。
+//! 使用 http/https/socks5h 代理时目标域名由代理服务器解析，这里只影响代理服务器自身地址的解析；
+//! socks5 代理仍在本机解析目标域名。
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{Client, ClientBuilder};
+
+use crate::model::config::{Config, IpPreference, TlsBackend};
+
+/// DoH 请求超时（秒）
+const DOH_TIMEOUT_SECS: u64 = 10;
+
+/// DNS 记录类型：A
+const RECORD_A: u16 = 1;
+/// DNS 记录类型：AAAA
+const RECORD_AAAA: u16 = 28;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// DNS 配置
+#[derive(Default)]
+pub struct DnsConfig {
+    /// 域名覆盖（小写域名 → 地址，端口为 0 表示使用 URL 中的端口）
+    overrides: HashMap<String, Vec<SocketAddr>>,
+    /// 自定义解析器（配置了 DoH 或 IP 偏好时创建）
+    resolver: Option<Arc<ConfiguredResolver>>,
+}
+
+impl DnsConfig {
+    /// 从应用配置构建，无效的覆盖项会被忽略并记录警告
+    pub fn from_config(config: &Config) -> Self {
+        let mut overrides = HashMap::new();
+        for (host, ips) in &config.dns_overrides {
+            let addrs: Vec<SocketAddr> = ips
+                .split(',')
+                .filter_map(|ip| match ip.trim().parse::<IpAddr>() {
+                    Ok(ip) => Some(SocketAddr::new(ip, 0)),
+                    Err(_) => {
+                        tracing::warn!("忽略无效的 DNS 覆盖: {} -> {}", host, ip.trim());
+                        None
+                    }
+                })
+                .collect();
+            if !addrs.is_empty() {
+                overrides.insert(host.to_ascii_lowercase(), addrs);
+            }
+        }
+
+        let doh = config
+            .dns_over_https_url
+            .as_ref()
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| (url.clone(), config.tls_backend));
+
+        let resolver = (doh.is_some() || config.ip_preference != IpPreference::Auto)
+            .then(|| Arc::new(ConfiguredResolver::new(doh, config.ip_preference)));
+
+        Self {
+            overrides,
+            resolver,
+        }
+    }
+
+    fn apply(&self, mut builder: ClientBuilder) -> ClientBuilder {
+        for (host, addrs) in &self.overrides {
+            builder = builder.resolve_to_addrs(host, addrs);
+        }
+        if let Some(resolver) = &self.resolver {
+            builder = builder.dns_resolver(resolver.clone());
+        }
+        builder
+    }
+}
+
+/// 全局配置存储
+static DNS_CONFIG: OnceLock<DnsConfig> = OnceLock::new();
+
+/// 初始化 DNS 配置
+///
+/// 应在应用启动时、创建任何 HTTP Client 之前调用一次
+pub fn init_config(config: DnsConfig) {
+    let _ = DNS_CONFIG.set(config);
+}
+
+/// 将 DNS 配置应用到 ClientBuilder（未初始化时原样返回）
+pub(crate) fn apply(builder: ClientBuilder) -> ClientBuilder {
+    match DNS_CONFIG.get() {
+        Some(config) => config.apply(builder),
+        None => builder,
+    }
+}
+
+/// 按 DoH / IP 偏好解析的解析器
+struct ConfiguredResolver {
+    /// DoH 客户端与接口地址
+    doh: Option<(Client, String)>,
+    preference: IpPreference,
+}
+
+impl ConfiguredResolver {
+    fn new(doh: Option<(String, TlsBackend)>, preference: IpPreference) -> Self {
+        let doh = doh.and_then(|(url, tls_backend)| {
+            // DoH 客户端本身使用系统解析器，避免递归
+            let mut builder = Client::builder().timeout(Duration::from_secs(DOH_TIMEOUT_SECS));
+            if tls_backend == TlsBackend::Rustls {
+                builder = builder.use_rustls_tls();
+            }
+            match builder.build() {
+                Ok(client) => Some((client, url)),
+                Err(e) => {
+                    tracing::warn!("创建 DoH 客户端失败，回退到系统解析器: {}", e);
+                    None
+                }
+            }
+        });
+        Self { doh, preference }
+    }
+
+    async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, BoxError> {
+        let ips = match &self.doh {
+            Some((client, url)) => self.lookup_doh(client, url, host).await?,
+            None => tokio::net::lookup_host((host, 0))
+                .await?
+                .map(|addr| addr.ip())
+                .collect(),
+        };
+
+        let ips = apply_preference(ips, self.preference);
+        if ips.is_empty() {
+            return Err(format!(
+                "DNS 解析 {} 未得到符合偏好 {:?} 的地址",
+                host, self.preference
+            )
+            .into());
+        }
+        Ok(ips)
+    }
+
+    async fn lookup_doh(
+        &self,
+        client: &Client,
+        url: &str,
+        host: &str,
+    ) -> Result<Vec<IpAddr>, BoxError> {
+        let record_types: &[u16] = match self.preference {
+            IpPreference::Ipv4Only => &[RECORD_A],
+            IpPreference::Ipv6Only => &[RECORD_AAAA],
+            _ => &[RECORD_A, RECORD_AAAA],
+        };
+
+        let queries = record_types.iter().map(|&record_type| async move {
+            let body: serde_json::Value = client
+                .get(url)
+                .query(&[("name", host), ("type", &record_type.to_string())])
+                .header("accept", "application/dns-json")
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            Ok::<_, reqwest::Error>(parse_doh_answer(&body, record_type))
+        });
+
+        let mut ips = Vec::new();
+        let mut last_error = None;
+        for result in futures::future::join_all(queries).await {
+            match result {
+                Ok(found) => ips.extend(found),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        match (ips.is_empty(), last_error) {
+            (true, Some(e)) => Err(format!("DoH 解析 {} 失败: {}", host, e).into()),
+            _ => Ok(ips),
+        }
+    }
+}
+
+impl Resolve for ConfiguredResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = ConfiguredResolver {
+            doh: self.doh.clone(),
+            preference: self.preference,
+        };
+        Box::pin(async move {
+            let ips = resolver.lookup(name.as_str()).await?;
+            let addrs: Addrs = Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// 按 IP 版本偏好排序或过滤（排序是稳定的，同版本地址保持原顺序）
+fn apply_preference(mut ips: Vec<IpAddr>, preference: IpPreference) -> Vec<IpAddr> {
+    match preference {
+        IpPreference::Auto => {}
+        IpPreference::Ipv4First => ips.sort_by_key(|ip| !ip.is_ipv4()),
+        IpPreference::Ipv6First => ips.sort_by_key(|ip| !ip.is_ipv6()),
+        IpPreference::Ipv4Only => ips.retain(|ip| ip.is_ipv4()),
+        IpPreference::Ipv6Only => ips.retain(|ip| ip.is_ipv6()),
+    }
+    ips
+}
+
+/// 提取 DoH JSON 响应中指定类型的地址（忽略 CNAME 等其他记录）
+fn parse_doh_answer(body: &serde_json::Value, record_type: u16) -> Vec<IpAddr> {
+    body["Answer"]
+        .as_array()
+        .map(|answers| {
+            answers
+                .iter()
+                .filter(|a| a["type"].as_u64() == Some(record_type as u64))
+                .filter_map(|a| a["data"].as_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ips(list: &[&str]) -> Vec<IpAddr> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_apply_preference() {
+        let all = ips(&["2001:db8::1", "1.1.1.1", "2001:db8::2", "8.8.8.8"]);
+        assert_eq!(
+            apply_preference(all.clone(), IpPreference::Ipv4First),
+            ips(&["1.1.1.1", "8.8.8.8", "2001:db8::1", "2001:db8::2"])
+        );
+        assert_eq!(
+            apply_preference(all.clone(), IpPreference::Ipv6Only),
+            ips(&["2001:db8::1", "2001:db8::2"])
+        );
+        assert_eq!(apply_preference(all.clone(), IpPreference::Auto), all);
+    }
+
+    #[test]
+    fn test_parse_doh_answer_skips_cname() {
+        let body = json!({
+            "Status": 0,
+            "Answer": [
+                {"name": "q.us-east-1.amazonaws.com", "type": 5, "data": "alias.amazonaws.com."},
+                {"name": "alias.amazonaws.com", "type": 1, "data": "52.1.2.3"}
+            ]
+        });
+        assert_eq!(parse_doh_answer(&body, RECORD_A), ips(&["52.1.2.3"]));
+        assert!(parse_doh_answer(&body, RECORD_AAAA).is_empty());
+    }
+
+    #[test]
+    fn test_dns_config_ignores_invalid_overrides() {
+        let mut config = Config::default();
+        config
+            .dns_overrides
+            .insert("Example.com".to_string(), "1.2.3.4, bad".to_string());
+        let dns = DnsConfig::from_config(&config);
+        assert_eq!(
+            dns.overrides["example.com"],
+            vec![SocketAddr::new(ips(&["1.2.3.4"])[0], 0)]
+        );
+        assert!(dns.resolver.is_none());
+    }
+}
@@ -0,0 +1,340 @@
+//! 进程内事件总线
+//!
+//! 各模块通过 `publish` 发布类型化事件，关心这些事件的模块（余额缓存、审计日志、
+//! Admin API 的事件推送等）各自 `subscribe`，发布方无需知道有哪些消费者。
+//! 基于 tokio broadcast：没有订阅者时事件直接丢弃，消费过慢的订阅者会跳过最旧的事件。
+
+use std::future::Future;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::kiro::token_manager::DisabledReason;
+
+/// 事件通道容量
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 进行中的流式响应数（`StreamEndGuard` 存活数）
+static ACTIVE_STREAMS: AtomicU64 = AtomicU64::new(0);
+
+tokio::task_local! {
+    /// 触发当前操作的 Admin 账号名（见 `with_actor`）
+    static ACTOR: String;
+}
+
+/// 进程内事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AppEvent {
+    /// 一次上游 API 调用结束（含重试，status 为最终结果；未拿到响应时为 None）
+    #[serde(rename_all = "camelCase")]
+    RequestCompleted {
+        /// 发起请求的 API Key 标识（见 `common::auth::api_key_id`）
+        api_key_id: Option<String>,
+        /// 估算的输入 tokens
+        input_tokens: Option<i32>,
+        credential_id: Option<u64>,
+        model: Option<String>,
+        stream: bool,
+        status: Option<u16>,
+        attempts: usize,
+        duration_ms: u64,
+    },
+    /// 一次流式响应结束
+    #[serde(rename_all = "camelCase")]
+    StreamEnded {
+        /// 发起请求的 API Key 标识（见 `common::auth::api_key_id`）
+        api_key_id: Option<String>,
+        model: String,
+        outcome: StreamOutcome,
+    },
+    /// 一次响应的最终 token 用量（流式响应在结束或客户端断开时发布）
+    #[serde(rename_all = "camelCase")]
+    UsageRecorded {
+        /// 发起请求的 API Key 标识（见 `common::auth::api_key_id`）
+        api_key_id: Option<String>,
+        credential_id: Option<u64>,
+        model: String,
+        #[serde(flatten)]
+        usage: TokenUsage,
+    },
+    /// 凭据被禁用（手动或自动）
+    #[serde(rename_all = "camelCase")]
+    CredentialDisabled {
+        credential_id: u64,
+        reason: DisabledReason,
+    },
+    /// 凭据被重新启用
+    #[serde(rename_all = "camelCase")]
+    CredentialEnabled { credential_id: u64 },
+    /// 新增凭据
+    #[serde(rename_all = "camelCase")]
+    CredentialAdded { credential_id: u64 },
+    /// 删除凭据
+    #[serde(rename_all = "camelCase")]
+    CredentialDeleted { credential_id: u64 },
+    /// 数据目录可用磁盘空间低于 / 恢复到下限以上
+    #[serde(rename_all = "camelCase")]
+    DiskSpaceChanged {
+        low: bool,
+        free_bytes: u64,
+        min_free_bytes: u64,
+    },
+    /// 凭据使用的代理不可用 / 恢复可用（`url` 中的密码已隐藏）
+    #[serde(rename_all = "camelCase")]
+    ProxyHealthChanged {
+        url: String,
+        healthy: bool,
+        error: Option<String>,
+    },
+    /// 配置已热重载
+    #[serde(rename_all = "camelCase")]
+    ConfigReloaded { requires_restart: Vec<String> },
+}
+
+/// 流式响应的结束方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StreamOutcome {
+    /// 上游正常结束
+    Completed,
+    /// 上游响应流中途出错
+    UpstreamError,
+    /// 客户端在响应结束前断开连接
+    ClientDisconnected,
+}
+
+/// 响应的 token 用量（与 Anthropic usage 字段一致，input_tokens 不含缓存部分）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub cache_creation_input_tokens: i32,
+    pub cache_read_input_tokens: i32,
+}
+
+/// 流式响应结束跟踪：随响应流一起存放，流结束时记录结束方式，
+/// 未记录就被丢弃说明客户端提前断开了连接；丢弃时发布 `StreamEnded` 事件，
+/// 记录过用量时还会按最后一次记录的用量发布 `UsageRecorded` 事件
+pub struct StreamEndGuard {
+    api_key_id: Option<String>,
+    credential_id: Option<u64>,
+    model: String,
+    outcome: Option<StreamOutcome>,
+    usage: Option<TokenUsage>,
+}
+
+impl StreamEndGuard {
+    pub fn new(api_key_id: Option<String>, model: impl Into<String>) -> Self {
+        ACTIVE_STREAMS.fetch_add(1, Ordering::Relaxed);
+        Self {
+            api_key_id,
+            credential_id: None,
+            model: model.into(),
+            outcome: None,
+            usage: None,
+        }
+    }
+
+    /// 设置处理该响应的凭据 ID
+    pub fn with_credential_id(mut self, credential_id: Option<u64>) -> Self {
+        self.credential_id = credential_id;
+        self
+    }
+
+    /// 记录结束方式（只记录第一次）
+    pub fn finish(&mut self, outcome: StreamOutcome) {
+        self.outcome.get_or_insert(outcome);
+    }
+
+    /// 记录目前为止的 token 用量（客户端中途断开时按最后一次记录的用量计费）
+    pub fn record_usage(&mut self, usage: TokenUsage) {
+        self.usage = Some(usage);
+    }
+}
+
+impl Drop for StreamEndGuard {
+    fn drop(&mut self) {
+        ACTIVE_STREAMS.fetch_sub(1, Ordering::Relaxed);
+        if let Some(usage) = self.usage {
+            publish(AppEvent::UsageRecorded {
+                api_key_id: self.api_key_id.clone(),
+                credential_id: self.credential_id,
+                model: self.model.clone(),
+                usage,
+            });
+        }
+        publish(AppEvent::StreamEnded {
+            api_key_id: self.api_key_id.take(),
+            model: std::mem::take(&mut self.model),
+            outcome: self.outcome.unwrap_or(StreamOutcome::ClientDisconnected),
+        });
+    }
+}
+
+/// 进行中的流式响应数
+pub fn active_streams() -> u64 {
+    ACTIVE_STREAMS.load(Ordering::Relaxed)
+}
+
+/// 带时间戳的事件
+#[derive(Debug, Clone, Serialize)]
+pub struct EventEnvelope {
+    /// 事件时间（RFC3339 格式）
+    pub timestamp: String,
+    /// 触发事件的 Admin 账号名（由 Admin API 操作引起时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    #[serde(flatten)]
+    pub event: AppEvent,
+}
+
+static EVENT_BUS: OnceLock<broadcast::Sender<EventEnvelope>> = OnceLock::new();
+
+fn sender() -> &'static broadcast::Sender<EventEnvelope> {
+    EVENT_BUS.get_or_init(|| broadcast::channel(EVENT_CHANNEL_CAPACITY).0)
+}
+
+/// 发布事件
+pub fn publish(event: AppEvent) {
+    // 没有订阅者时发送失败是正常情况
+    let _ = sender().send(EventEnvelope {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        actor: ACTOR.try_with(Clone::clone).ok(),
+        event,
+    });
+}
+
+/// 在 `actor` 的名义下执行 `future`：期间发布的事件都带上该 Admin 账号名
+pub async fn with_actor<F: Future>(actor: String, future: F) -> F::Output {
+    ACTOR.scope(actor, future).await
+}
+
+/// 订阅之后发布的事件
+pub fn subscribe() -> broadcast::Receiver<EventEnvelope> {
+    sender().subscribe()
+}
+
+/// 审计日志：将凭据与配置相关的事件写入 `audit` 目标的日志
+pub fn spawn_audit_logger() {
+    let mut receiver = subscribe();
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(envelope) => {
+                    if matches!(
+                        envelope.event,
+                        AppEvent::RequestCompleted { .. }
+                            | AppEvent::StreamEnded { .. }
+                            | AppEvent::UsageRecorded { .. }
+                    ) {
+                        continue;
+                    }
+                    let event = serde_json::to_string(&envelope.event).unwrap_or_default();
+                    let actor = envelope.actor.as_deref().unwrap_or("-");
+                    tracing::info!(target: "audit", event = %event, actor = %actor, "审计事件");
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("审计日志处理过慢，跳过了 {} 个事件", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_reaches_subscribers() {
+        const ID: u64 = 4_000_000_007;
+        let mut receiver = subscribe();
+        publish(AppEvent::CredentialDisabled {
+            credential_id: ID,
+            reason: DisabledReason::QuotaExceeded,
+        });
+
+        // 其他测试可能并发发布事件，只检查本测试发布的事件
+        let json = loop {
+            let json = serde_json::to_value(receiver.recv().await.unwrap()).unwrap();
+            if json["credentialId"] == ID {
+                break json;
+            }
+        };
+        assert_eq!(json["type"], "credentialDisabled");
+        assert_eq!(json["reason"], "quota-exceeded");
+        assert!(json["timestamp"].is_string());
+        assert!(json.get("actor").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_with_actor_attributes_events() {
+        const ID: u64 = 4_000_000_011;
+        let mut receiver = subscribe();
+        with_actor("alice".to_string(), async {
+            publish(AppEvent::CredentialEnabled { credential_id: ID });
+        })
+        .await;
+
+        let json = loop {
+            let json = serde_json::to_value(receiver.recv().await.unwrap()).unwrap();
+            if json["credentialId"] == ID {
+                break json;
+            }
+        };
+        assert_eq!(json["type"], "credentialEnabled");
+        assert_eq!(json["actor"], "alice");
+    }
+
+    #[tokio::test]
+    async fn test_stream_end_guard_reports_disconnect() {
+        const MODEL: &str = "stream-end-guard-test";
+        let mut receiver = subscribe();
+
+        let mut completed = StreamEndGuard::new(Some("abcd1234".to_string()), MODEL);
+        completed.finish(StreamOutcome::Completed);
+        completed.finish(StreamOutcome::UpstreamError);
+        drop(completed);
+        drop(StreamEndGuard::new(None, MODEL));
+
+        let mut outcomes = Vec::new();
+        while outcomes.len() < 2 {
+            let json = serde_json::to_value(receiver.recv().await.unwrap()).unwrap();
+            if json["model"] == MODEL {
+                outcomes.push(json["outcome"].clone());
+            }
+        }
+        assert_eq!(outcomes, vec!["completed", "clientDisconnected"]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_end_guard_reports_last_usage() {
+        const MODEL: &str = "stream-usage-test";
+        let mut receiver = subscribe();
+
+        let mut guard = StreamEndGuard::new(None, MODEL).with_credential_id(Some(3));
+        for output_tokens in [5, 12] {
+            guard.record_usage(TokenUsage {
+                input_tokens: 100,
+                output_tokens,
+                ..Default::default()
+            });
+        }
+        drop(guard);
+
+        let json = loop {
+            let json = serde_json::to_value(receiver.recv().await.unwrap()).unwrap();
+            if json["model"] == MODEL && json["type"] == "usageRecorded" {
+                break json;
+            }
+        };
+        assert_eq!(json["credentialId"], 3);
+        assert_eq!(json["inputTokens"], 100);
+        assert_eq!(json["outputTokens"], 12);
+    }
+}
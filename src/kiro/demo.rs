@@ -0,0 +1,123 @@
+//! 演示模式
+//!
+//! 配置 `demoMode` 后 `KiroProvider` 不访问上游、也不需要凭据，按请求生成模拟响应。
+//! 模拟响应与上游一样是 AWS Event Stream 编码，后续的转换、流式输出、统计与事件
+//! 都按真实请求的流程处理，便于在导入真实账号之前对接 API 或体验 Admin UI。
+
+use std::convert::Infallible;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::{Value, json};
+
+use crate::kiro::parser::frame::encode_event_frame;
+
+/// 流式响应中相邻两个文本片段的发送间隔
+const STREAM_CHUNK_DELAY: Duration = Duration::from_millis(30);
+
+/// 每个文本片段的字符数
+const CHUNK_CHARS: usize = 8;
+
+/// 回复中引用用户消息的最大字符数
+const MAX_ECHO_CHARS: usize = 200;
+
+/// 根据 Kiro 请求体生成模拟回复文本（启用 thinking 时带一段模拟的思考内容）
+fn reply_text(request_body: &str) -> String {
+    let json: Value = serde_json::from_str(request_body).unwrap_or_default();
+    let prompt = json
+        .pointer("/conversationState/currentMessage/userInputMessage/content")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    // 转换器会把 thinking 配置标签放在消息内容前面，引用时去掉
+    let prompt = prompt.rsplit("</thinking_effort>").next().unwrap_or(prompt);
+    let prompt = prompt
+        .rsplit("</max_thinking_length>")
+        .next()
+        .unwrap_or(prompt);
+    let echo: String = prompt.trim().chars().take(MAX_ECHO_CHARS).collect();
+
+    let mut text = String::new();
+    if request_body.contains("<thinking_mode>") {
+        text.push_str("<thinking>\n这是演示模式生成的模拟思考内容。\n</thinking>\n\n");
+    }
+    text.push_str("这是 kiro-rs 演示模式的模拟回复，未调用上游 API。");
+    if !echo.is_empty() {
+        text.push_str(&format!("\n\n你发送的内容：{}", echo));
+    }
+    text
+}
+
+/// 把文本切成事件流帧
+fn reply_frames(request_body: &str) -> Vec<Vec<u8>> {
+    let chars: Vec<char> = reply_text(request_body).chars().collect();
+    chars
+        .chunks(CHUNK_CHARS)
+        .map(|chunk| {
+            let content: String = chunk.iter().collect();
+            encode_event_frame("assistantResponseEvent", &json!({ "content": content }))
+        })
+        .collect()
+}
+
+/// 生成模拟的上游响应：流式请求逐段发送，非流式请求一次性返回
+pub fn response(request_body: &str, is_stream: bool) -> reqwest::Response {
+    let frames = reply_frames(request_body);
+    let body = if is_stream {
+        let chunks = futures::stream::iter(frames.into_iter().enumerate()).then(
+            |(index, frame)| async move {
+                if index > 0 {
+                    tokio::time::sleep(STREAM_CHUNK_DELAY).await;
+                }
+                Ok::<_, Infallible>(Bytes::from(frame))
+            },
+        );
+        reqwest::Body::wrap_stream(chunks)
+    } else {
+        reqwest::Body::from(frames.concat())
+    };
+
+    let mut response = http::Response::new(body);
+    response.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("application/vnd.amazon.eventstream"),
+    );
+    reqwest::Response::from(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiro::model::events::Event;
+    use crate::kiro::parser::decoder::EventStreamDecoder;
+
+    fn decode_text(body: &[u8]) -> String {
+        let mut decoder = EventStreamDecoder::new();
+        decoder.feed(body).unwrap();
+        decoder
+            .decode_iter()
+            .filter_map(|frame| match Event::from_frame(frame.ok()?) {
+                Ok(Event::AssistantResponse(resp)) => Some(resp.content),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_demo_response_echoes_prompt() {
+        let body = json!({
+            "conversationState": {
+                "currentMessage": {"userInputMessage": {"content": "<thinking_mode>enabled</thinking_mode><max_thinking_length>1024</max_thinking_length>你好"}}
+            }
+        })
+        .to_string();
+
+        let full = response(&body, false).bytes().await.unwrap();
+        let streamed = response(&body, true).bytes().await.unwrap();
+        assert_eq!(full, streamed);
+
+        let text = decode_text(&full);
+        assert!(text.starts_with("<thinking>"));
+        assert!(text.ends_with("你发送的内容：你好"));
+    }
+}
@@ -1,7 +1,11 @@
 //! Kiro API 客户端模块
 
+pub mod demo;
 pub mod machine_id;
 pub mod model;
 pub mod parser;
 pub mod provider;
+pub mod regions;
+pub mod schema_drift;
+pub mod selection;
 pub mod token_manager;
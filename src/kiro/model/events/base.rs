@@ -4,6 +4,7 @@
 
 use crate::kiro::parser::error::{ParseError, ParseResult};
 use crate::kiro::parser::frame::Frame;
+use crate::kiro::schema_drift;
 
 /// 事件类型枚举
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -98,7 +99,10 @@ impl Event {
             "event" => Self::parse_event(frame),
             "error" => Self::parse_error(frame),
             "exception" => Self::parse_exception(frame),
-            other => Err(ParseError::InvalidMessageType(other.to_string())),
+            other => {
+                schema_drift::observe_message_type(other, &frame);
+                Err(ParseError::InvalidMessageType(other.to_string()))
+            }
         }
     }
 
@@ -106,6 +110,7 @@ impl Event {
     fn parse_event(frame: Frame) -> ParseResult<Self> {
         let event_type_str = frame.event_type().unwrap_or("unknown");
         let event_type = EventType::from_str(event_type_str);
+        schema_drift::observe_event(event_type_str, &frame);
 
         match event_type {
             EventType::AssistantResponse => {
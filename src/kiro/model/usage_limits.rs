@@ -19,6 +19,23 @@ pub struct UsageLimitsResponse {
     /// 使用量明细列表
     #[serde(default)]
     pub usage_breakdown_list: Vec<UsageBreakdown>,
+
+    /// 账号信息（请求携带 isEmailRequired=true 时返回）
+    #[serde(default)]
+    pub user_info: Option<UserInfo>,
+}
+
+/// 账号信息
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserInfo {
+    /// 账号邮箱
+    #[serde(default)]
+    pub email: Option<String>,
+
+    /// 账号 ID
+    #[serde(default)]
+    pub user_id: Option<String>,
 }
 
 /// 订阅信息
@@ -139,6 +156,22 @@ impl UsageLimitsResponse {
             .and_then(|info| info.subscription_title.as_deref())
     }
 
+    /// 获取账号邮箱
+    pub fn email(&self) -> Option<&str> {
+        self.user_info
+            .as_ref()
+            .and_then(|info| info.email.as_deref())
+            .filter(|email| !email.is_empty())
+    }
+
+    /// 获取账号 ID
+    pub fn account_id(&self) -> Option<&str> {
+        self.user_info
+            .as_ref()
+            .and_then(|info| info.user_id.as_deref())
+            .filter(|id| !id.is_empty())
+    }
+
     /// 获取第一个使用量明细
     fn primary_breakdown(&self) -> Option<&UsageBreakdown> {
         self.usage_breakdown_list.first()
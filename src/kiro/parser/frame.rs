@@ -153,10 +153,61 @@ pub fn parse_frame(buffer: &[u8]) -> ParseResult<Option<(Frame, usize)>> {
     Ok(Some((Frame { headers, payload }, total_length)))
 }
 
+/// 将事件编码为完整的消息帧（仅支持字符串类型头部）
+///
+/// 与 `parse_frame` 互逆，用于构造测试数据与演示模式的模拟响应
+pub(crate) fn encode_frame(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+    let mut header_bytes = Vec::new();
+    for (name, value) in headers {
+        header_bytes.push(name.len() as u8);
+        header_bytes.extend_from_slice(name.as_bytes());
+        header_bytes.push(7); // String
+        header_bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        header_bytes.extend_from_slice(value.as_bytes());
+    }
+
+    let total_length = (PRELUDE_SIZE + header_bytes.len() + payload.len() + 4) as u32;
+    let mut buffer = Vec::with_capacity(total_length as usize);
+    buffer.extend_from_slice(&total_length.to_be_bytes());
+    buffer.extend_from_slice(&(header_bytes.len() as u32).to_be_bytes());
+    let prelude_crc = crc32(&buffer[..8]);
+    buffer.extend_from_slice(&prelude_crc.to_be_bytes());
+    buffer.extend_from_slice(&header_bytes);
+    buffer.extend_from_slice(payload);
+    let message_crc = crc32(&buffer);
+    buffer.extend_from_slice(&message_crc.to_be_bytes());
+    buffer
+}
+
+/// 编码一个 `:message-type = event` 的 JSON 事件帧
+pub(crate) fn encode_event_frame(event_type: &str, payload: &serde_json::Value) -> Vec<u8> {
+    encode_frame(
+        &[
+            (":message-type", "event"),
+            (":event-type", event_type),
+            (":content-type", "application/json"),
+        ],
+        payload.to_string().as_bytes(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_encode_frame_roundtrip() {
+        let buffer = encode_event_frame(
+            "assistantResponseEvent",
+            &serde_json::json!({"content": "hi"}),
+        );
+        let (frame, consumed) = parse_frame(&buffer).unwrap().unwrap();
+        assert_eq!(consumed, buffer.len());
+        assert_eq!(frame.message_type(), Some("event"));
+        assert_eq!(frame.event_type(), Some("assistantResponseEvent"));
+        assert_eq!(frame.payload_as_str(), r#"{"content":"hi"}"#);
+    }
+
     #[test]
     fn test_frame_insufficient_data() {
         let buffer = [0u8; 10]; // 小于 PRELUDE_SIZE
@@ -4,19 +4,30 @@
 //! 支持流式和非流式请求
 //! 支持多凭据故障转移和重试
 
+use futures::StreamExt;
 use reqwest::Client;
-use reqwest::header::{AUTHORIZATION, CONNECTION, CONTENT_TYPE, HOST, HeaderMap, HeaderValue};
+use reqwest::header::{
+    AUTHORIZATION, CONNECTION, CONTENT_ENCODING, CONTENT_TYPE, HOST, HeaderMap, HeaderValue,
+};
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 use uuid::Uuid;
 
-use crate::http_client::{ProxyConfig, build_client};
+use crate::events::{self, AppEvent};
+use crate::http_client::{ProxyConfig, build_client, merge_extra_headers};
+use crate::kiro::demo;
 use crate::kiro::machine_id;
 use crate::kiro::model::credentials::KiroCredentials;
-use crate::kiro::token_manager::{CallContext, MultiTokenManager};
+use crate::kiro::model::events::Event;
+use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::kiro::regions::ApiEndpoints;
+use crate::kiro::token_manager::{CallContext, FailureClass, MultiTokenManager};
 use crate::model::config::TlsBackend;
+use crate::proxy_health;
+use crate::tls::Destination;
 use parking_lot::Mutex;
 
 /// 每个凭据的最大重试次数
@@ -25,121 +36,224 @@ const MAX_RETRIES_PER_CREDENTIAL: usize = 3;
 /// 总重试次数硬上限（避免无限重试）
 const MAX_TOTAL_RETRIES: usize = 9;
 
+/// 请求体超过该大小时才进行 gzip 压缩
+const COMPRESSION_MIN_BYTES: usize = 8 * 1024;
+
+tokio::task_local! {
+    /// 当前请求固定使用的凭据 ID（见 `KiroProvider::with_pinned_credential`）
+    static PINNED_CREDENTIAL: u64;
+    /// 当前请求的附加信息（见 `KiroProvider::with_request_tags`）
+    static REQUEST_TAGS: parking_lot::Mutex<RequestTags>;
+}
+
+/// 附加在 `RequestCompleted` 事件上的请求信息（由接收客户端请求的一侧提供）
+#[derive(Debug, Clone, Default)]
+struct RequestTags {
+    api_key_id: Option<String>,
+    input_tokens: Option<i32>,
+    /// 最近一次成功的 API 调用所用的凭据（见 `served_credential`）
+    credential_id: Option<u64>,
+}
+
+/// 一次 API 调用（含重试）的结果摘要，用于发布 `RequestCompleted` 事件
+#[derive(Default)]
+struct RequestOutcome {
+    credential_id: Option<u64>,
+    model: Option<String>,
+    status: Option<u16>,
+    attempts: usize,
+}
+
 /// Kiro API Provider
 ///
 /// 核心组件，负责与 Kiro API 通信
 /// 支持多凭据故障转移和重试机制
 pub struct KiroProvider {
     token_manager: Arc<MultiTokenManager>,
-    /// 全局代理配置（用于凭据无自定义代理时的回退）
-    global_proxy: Option<ProxyConfig>,
     /// Client 缓存：key = effective proxy config, value = reqwest::Client
     /// 不同代理配置的凭据使用不同的 Client，共享相同代理的凭据复用 Client
     client_cache: Mutex<HashMap<Option<ProxyConfig>, Client>>,
     /// TLS 后端配置
     tls_backend: TlsBackend,
+    /// 累计收到的空响应次数（用于观察上游是否频繁返回空内容）
+    empty_response_count: AtomicU64,
+    /// 上游是否接受 gzip 压缩的请求体（收到拒绝后置为 false，不再压缩）
+    compression_supported: AtomicBool,
 }
 
 impl KiroProvider {
     /// 创建新的 KiroProvider 实例
+    ///
+    /// 全局代理（凭据无自定义代理时的回退）取自 token_manager，配置热更新后随之生效
     pub fn new(token_manager: Arc<MultiTokenManager>) -> Self {
-        Self::with_proxy(token_manager, None)
-    }
-
-    /// 创建带代理配置的 KiroProvider 实例
-    pub fn with_proxy(token_manager: Arc<MultiTokenManager>, proxy: Option<ProxyConfig>) -> Self {
         let tls_backend = token_manager.config().tls_backend;
+        let proxy = token_manager.proxy();
         // 预热：构建全局代理对应的 Client
-        let initial_client = build_client(proxy.as_ref(), 720, tls_backend)
+        let initial_client = build_client(proxy.as_ref(), 720, tls_backend, Destination::Kiro)
             .expect("创建 HTTP 客户端失败");
         let mut cache = HashMap::new();
         cache.insert(proxy.clone(), initial_client);
 
         Self {
             token_manager,
-            global_proxy: proxy,
             client_cache: Mutex::new(cache),
             tls_backend,
+            empty_response_count: AtomicU64::new(0),
+            compression_supported: AtomicBool::new(true),
         }
     }
 
-    /// 根据凭据的代理配置获取（或创建并缓存）对应的 reqwest::Client
-    fn client_for(&self, credentials: &KiroCredentials) -> anyhow::Result<Client> {
-        let effective = credentials.effective_proxy(self.global_proxy.as_ref());
+    /// 按配置压缩请求体，返回 (gzip 后的请求体, 是否已压缩)
+    fn encode_request_body(&self, request_body: &str) -> (bytes::Bytes, bool) {
+        let plain = || bytes::Bytes::copy_from_slice(request_body.as_bytes());
+
+        if !self.token_manager.config().upstream_request_compression
+            || !self.compression_supported.load(Ordering::Relaxed)
+            || request_body.len() < COMPRESSION_MIN_BYTES
+        {
+            return (plain(), false);
+        }
+
+        match gzip(request_body.as_bytes()) {
+            Ok(compressed) => (compressed.into(), true),
+            Err(e) => {
+                tracing::warn!("请求体 gzip 压缩失败，使用原始请求体: {}", e);
+                (plain(), false)
+            }
+        }
+    }
+
+    /// 判断失败响应是否可能表示上游不接受压缩请求体：415，或明确提到 Content-Encoding 的 400
+    fn is_compression_rejected(status: u16, body: &str) -> bool {
+        status == 415 || (status == 400 && body.to_ascii_lowercase().contains("content-encoding"))
+    }
+
+    /// 获取（或创建并缓存）代理配置对应的 reqwest::Client
+    fn client_with_proxy(&self, proxy: Option<ProxyConfig>) -> anyhow::Result<Client> {
         let mut cache = self.client_cache.lock();
-        if let Some(client) = cache.get(&effective) {
+        if let Some(client) = cache.get(&proxy) {
             return Ok(client.clone());
         }
-        let client = build_client(effective.as_ref(), 720, self.tls_backend)?;
-        cache.insert(effective, client.clone());
+        let client = build_client(proxy.as_ref(), 720, self.tls_backend, Destination::Kiro)?;
+        cache.insert(proxy, client.clone());
         Ok(client)
     }
 
+    /// 获取凭据当前应使用的代理及其对应的 reqwest::Client（代理不可用时自动换用备用代理）
+    fn client_for(
+        &self,
+        credentials: &KiroCredentials,
+    ) -> anyhow::Result<(Client, Option<ProxyConfig>)> {
+        let proxy = self.token_manager.proxy_for(credentials);
+        Ok((self.client_with_proxy(proxy.clone())?, proxy))
+    }
+
+    /// 请求发送失败：经代理连接失败时标记代理不可用，返回是否归因于代理
+    fn report_proxy_failure(proxy: Option<&ProxyConfig>, error: &reqwest::Error) -> bool {
+        match proxy {
+            Some(proxy) if error.is_connect() => {
+                proxy_health::report_failure(proxy, &error.to_string());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 获取全局代理对应的 reqwest::Client（用于与凭据无关的外部请求）
+    pub fn default_client(&self) -> anyhow::Result<Client> {
+        self.client_with_proxy(self.token_manager.proxy())
+    }
+
+    /// 凭据额度用尽：禁用凭据，并在后台查询余额接口获取实际的额度重置时间
+    ///
+    /// 返回是否还有可用凭据
+    fn handle_quota_exhausted(&self, id: u64) -> bool {
+        let has_available = self.token_manager.report_quota_exhausted(id);
+
+        let token_manager = self.token_manager.clone();
+        tokio::spawn(async move {
+            match token_manager.get_usage_limits_for(id).await {
+                Ok(usage) => {
+                    if let Some(until) = usage
+                        .next_date_reset
+                        .and_then(|ts| chrono::DateTime::from_timestamp(ts as i64, 0))
+                    {
+                        token_manager.set_exhausted_until(id, until);
+                    }
+                }
+                Err(e) => tracing::warn!("查询凭据 #{} 额度重置时间失败，使用默认值: {}", id, e),
+            }
+        });
+
+        has_available
+    }
+
     /// 获取 token_manager 的引用
     pub fn token_manager(&self) -> &MultiTokenManager {
         &self.token_manager
     }
 
+    /// 获取 config 级 API 端点（使用 config 级 api_region）
+    fn endpoints(&self) -> ApiEndpoints {
+        let config = self.token_manager.config();
+        ApiEndpoints::for_region(config.effective_api_region(), &config)
+    }
+
+    /// 获取凭据级 API 端点
+    fn endpoints_for(&self, credentials: &KiroCredentials) -> ApiEndpoints {
+        let config = self.token_manager.config();
+        ApiEndpoints::for_region(credentials.effective_api_region(&config), &config)
+    }
+
     /// 获取 API 基础 URL（使用 config 级 api_region）
     pub fn base_url(&self) -> String {
-        format!(
-            "https://q.{}.amazonaws.com/generateAssistantResponse",
-            self.token_manager.config().effective_api_region()
-        )
+        self.endpoints().generate_url()
     }
 
     /// 获取 MCP API URL（使用 config 级 api_region）
     pub fn mcp_url(&self) -> String {
-        format!(
-            "https://q.{}.amazonaws.com/mcp",
-            self.token_manager.config().effective_api_region()
-        )
+        self.endpoints().mcp_url()
     }
 
     /// 获取 API 基础域名（使用 config 级 api_region）
     pub fn base_domain(&self) -> String {
-        format!("q.{}.amazonaws.com", self.token_manager.config().effective_api_region())
+        self.endpoints().api_host
     }
 
     /// 获取凭据级 API 基础 URL
     fn base_url_for(&self, credentials: &KiroCredentials) -> String {
-        format!(
-            "https://q.{}.amazonaws.com/generateAssistantResponse",
-            credentials.effective_api_region(self.token_manager.config())
-        )
+        self.endpoints_for(credentials).generate_url()
     }
 
     /// 获取凭据级 MCP API URL
     fn mcp_url_for(&self, credentials: &KiroCredentials) -> String {
-        format!(
-            "https://q.{}.amazonaws.com/mcp",
-            credentials.effective_api_region(self.token_manager.config())
-        )
+        self.endpoints_for(credentials).mcp_url()
     }
 
     /// 获取凭据级 API 基础域名
     fn base_domain_for(&self, credentials: &KiroCredentials) -> String {
-        format!(
-            "q.{}.amazonaws.com",
-            credentials.effective_api_region(self.token_manager.config())
-        )
+        self.endpoints_for(credentials).api_host
     }
 
-    /// 从请求体中提取模型信息
+    /// 从请求体中提取路由信息
     ///
-    /// 尝试解析 JSON 请求体，提取 conversationState.currentMessage.userInputMessage.modelId
-    fn extract_model_from_request(request_body: &str) -> Option<String> {
+    /// 解析 JSON 请求体，返回 (conversationState.currentMessage.userInputMessage.modelId,
+    /// conversationState.conversationId)，后者用于 sticky 负载均衡的会话亲和
+    fn extract_routing_info(request_body: &str) -> (Option<String>, Option<String>) {
         use serde_json::Value;
 
-        let json: Value = serde_json::from_str(request_body).ok()?;
+        let Ok(json) = serde_json::from_str::<Value>(request_body) else {
+            return (None, None);
+        };
+        let state = &json["conversationState"];
 
-        // 尝试提取 conversationState.currentMessage.userInputMessage.modelId
-        json.get("conversationState")?
-            .get("currentMessage")?
-            .get("userInputMessage")?
-            .get("modelId")?
-            .as_str()
-            .map(|s| s.to_string())
+        let model = state
+            .pointer("/currentMessage/userInputMessage/modelId")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let conversation_id = state["conversationId"].as_str().map(|s| s.to_string());
+
+        (model, conversation_id)
     }
 
     /// 构建请求头
@@ -149,7 +263,7 @@ impl KiroProvider {
     fn build_headers(&self, ctx: &CallContext) -> anyhow::Result<HeaderMap> {
         let config = self.token_manager.config();
 
-        let machine_id = machine_id::generate_from_credentials(&ctx.credentials, config)
+        let machine_id = machine_id::generate_from_credentials(&ctx.credentials, &config)
             .ok_or_else(|| anyhow::anyhow!("无法生成 machine_id，请检查凭证配置"))?;
 
         let kiro_version = &config.kiro_version;
@@ -193,6 +307,7 @@ impl KiroProvider {
             HeaderValue::from_str(&format!("Bearer {}", ctx.token)).unwrap(),
         );
         headers.insert(CONNECTION, HeaderValue::from_static("close"));
+        merge_extra_headers(&mut headers, &config);
 
         Ok(headers)
     }
@@ -201,7 +316,7 @@ impl KiroProvider {
     fn build_mcp_headers(&self, ctx: &CallContext) -> anyhow::Result<HeaderMap> {
         let config = self.token_manager.config();
 
-        let machine_id = machine_id::generate_from_credentials(&ctx.credentials, config)
+        let machine_id = machine_id::generate_from_credentials(&ctx.credentials, &config)
             .ok_or_else(|| anyhow::anyhow!("无法生成 machine_id，请检查凭证配置"))?;
 
         let kiro_version = &config.kiro_version;
@@ -238,6 +353,7 @@ impl KiroProvider {
             HeaderValue::from_str(&format!("Bearer {}", ctx.token)).unwrap(),
         );
         headers.insert("Connection", HeaderValue::from_static("close"));
+        merge_extra_headers(&mut headers, &config);
 
         Ok(headers)
     }
@@ -248,15 +364,140 @@ impl KiroProvider {
     /// - 400 Bad Request: 直接返回错误，不计入凭据失败
     /// - 401/403: 视为凭据/权限问题，计入失败次数并允许故障转移
     /// - 402 MONTHLY_REQUEST_COUNT: 视为额度用尽，禁用凭据并切换
-    /// - 429/5xx/网络等瞬态错误: 重试但不禁用或切换凭据（避免误把所有凭据锁死）
+    /// - 429/408/5xx 等瞬态错误: 指数退避后换凭据重试，但不计入失败、不禁用凭据
+    /// - 网络错误: 退避后重试，不切换凭据
     ///
     /// # Arguments
     /// * `request_body` - JSON 格式的请求体字符串
     ///
+    /// 空响应（无文本、无工具调用）时会避开返回空响应的凭据重试一次，
+    /// 可通过 `emptyResponseRetry` 配置关闭。流式请求不做此重试（需缓冲完整响应才能判断）
+    ///
     /// # Returns
     /// 返回原始的 HTTP Response，不做解析
     pub async fn call_api(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, false).await
+        // 固定凭据的请求无法换凭据重试
+        let retry_empty =
+            self.token_manager.config().empty_response_retry && Self::pinned_credential().is_none();
+        self.retry_on_empty(retry_empty, |exclude| async move {
+            self.call_api_with_retry(request_body, false, &exclude)
+                .await
+        })
+        .await
+    }
+
+    /// 发送请求，空响应时把返回空响应的凭据加入排除列表再发送一次
+    ///
+    /// `send` 接收需要避开的凭据列表，返回响应与实际使用的凭据；只有一个可用凭据时不重试
+    async fn retry_on_empty<F, Fut>(
+        &self,
+        enabled: bool,
+        send: F,
+    ) -> anyhow::Result<reqwest::Response>
+    where
+        F: Fn(Vec<u64>) -> Fut,
+        Fut: Future<Output = anyhow::Result<(reqwest::Response, Option<u64>)>>,
+    {
+        let (response, credential_id) = send(Vec::new()).await?;
+        if !enabled {
+            return Ok(response);
+        }
+
+        let (response, is_empty) = Self::buffer_and_inspect(response).await?;
+        if !is_empty {
+            return Ok(response);
+        }
+
+        let count = self.empty_response_count.fetch_add(1, Ordering::Relaxed) + 1;
+        let Some(credential_id) =
+            credential_id.filter(|_| self.token_manager.available_count() > 1)
+        else {
+            tracing::warn!(
+                "上游返回空响应（累计 {} 次），没有其他可用凭据，不再重试",
+                count
+            );
+            return Ok(response);
+        };
+        tracing::warn!(
+            "上游返回空响应（累计 {} 次，凭据 #{}），换凭据重试一次",
+            count,
+            credential_id
+        );
+
+        let (retry, _) = send(vec![credential_id]).await?;
+        let (retry, is_empty) = Self::buffer_and_inspect(retry).await?;
+        if is_empty {
+            let count = self.empty_response_count.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::warn!("重试后上游仍返回空响应（累计 {} 次）", count);
+        }
+        Ok(retry)
+    }
+
+    /// 读取完整响应体并判断是否为空响应
+    ///
+    /// 返回以相同状态码、响应头和响应体重建的 Response，供调用方继续按原样处理
+    async fn buffer_and_inspect(
+        response: reqwest::Response,
+    ) -> anyhow::Result<(reqwest::Response, bool)> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+        let is_empty = Self::is_empty_completion(&body);
+
+        let mut rebuilt = http::Response::new(body);
+        *rebuilt.status_mut() = status;
+        *rebuilt.headers_mut() = headers;
+        Ok((reqwest::Response::from(rebuilt), is_empty))
+    }
+
+    /// 等待流式响应的第一个非空数据块
+    ///
+    /// 上游在返回任何数据前断开（读取出错或响应体为空）时返回错误；否则以相同状态码、
+    /// 响应头和“首个数据块 + 剩余数据流”重建 Response，调用方可照常按流读取
+    async fn peek_first_chunk(response: reqwest::Response) -> anyhow::Result<reqwest::Response> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let mut stream = response.bytes_stream();
+
+        let first = loop {
+            match stream.next().await {
+                Some(Ok(chunk)) if chunk.is_empty() => continue,
+                Some(Ok(chunk)) => break chunk,
+                Some(Err(e)) => anyhow::bail!("读取流式响应失败: {}", e),
+                None => anyhow::bail!("流式响应在返回数据前结束"),
+            }
+        };
+
+        let body = futures::stream::once(async { Ok(first) }).chain(stream);
+        let mut rebuilt = http::Response::new(reqwest::Body::wrap_stream(body));
+        *rebuilt.status_mut() = status;
+        *rebuilt.headers_mut() = headers;
+        Ok(reqwest::Response::from(rebuilt))
+    }
+
+    /// 判断事件流是否为空响应：既没有文本内容，也没有工具调用
+    ///
+    /// 上游错误/异常事件不算空响应，交由调用方按原有逻辑处理
+    fn is_empty_completion(body: &[u8]) -> bool {
+        let mut decoder = EventStreamDecoder::new();
+        if decoder.feed(body).is_err() {
+            return false;
+        }
+
+        for result in decoder.decode_iter() {
+            let Ok(frame) = result else {
+                return false;
+            };
+            match Event::from_frame(frame) {
+                Ok(Event::AssistantResponse(resp)) if !resp.content.is_empty() => return false,
+                Ok(Event::ToolUse(_))
+                | Ok(Event::Error { .. })
+                | Ok(Event::Exception { .. })
+                | Err(_) => return false,
+                Ok(_) => {}
+            }
+        }
+        true
     }
 
     /// 发送流式 API 请求
@@ -265,15 +506,17 @@ impl KiroProvider {
     /// - 400 Bad Request: 直接返回错误，不计入凭据失败
     /// - 401/403: 视为凭据/权限问题，计入失败次数并允许故障转移
     /// - 402 MONTHLY_REQUEST_COUNT: 视为额度用尽，禁用凭据并切换
-    /// - 429/5xx/网络等瞬态错误: 重试但不禁用或切换凭据（避免误把所有凭据锁死）
+    /// - 429/408/5xx 等瞬态错误: 指数退避后换凭据重试，但不计入失败、不禁用凭据
+    /// - 网络错误: 退避后重试，不切换凭据
+    /// - 200 但在返回任何数据前中断: 视为瞬态错误，换凭据重试
     ///
     /// # Arguments
     /// * `request_body` - JSON 格式的请求体字符串
     ///
     /// # Returns
-    /// 返回原始的 HTTP Response，调用方负责处理流式数据
+    /// 返回原始的 HTTP Response（已读取首个数据块），调用方负责处理流式数据
     pub async fn call_api_stream(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, true).await
+        Ok(self.call_api_with_retry(request_body, true, &[]).await?.0)
     }
 
     /// 发送 MCP API 请求
@@ -286,6 +529,9 @@ impl KiroProvider {
     /// # Returns
     /// 返回原始的 HTTP Response
     pub async fn call_mcp(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
+        if self.token_manager.config().demo_mode {
+            anyhow::bail!("演示模式不支持 WebSearch 等 MCP 工具调用");
+        }
         self.call_mcp_with_retry(request_body).await
     }
 
@@ -297,8 +543,12 @@ impl KiroProvider {
 
         for attempt in 0..max_retries {
             // 获取调用上下文
-            // MCP 调用（WebSearch 等工具）不涉及模型选择，无需按模型过滤凭据
-            let ctx = match self.token_manager.acquire_context(None).await {
+            // MCP 调用（WebSearch 等工具）不涉及模型选择，无需按模型过滤凭据，也没有会话亲和
+            let ctx = match Self::pinned_credential() {
+                Some(id) => self.token_manager.acquire_context_for(id).await,
+                None => self.token_manager.acquire_context(None).await,
+            };
+            let ctx = match ctx {
                 Ok(c) => c,
                 Err(e) => {
                     last_error = Some(e);
@@ -316,8 +566,8 @@ impl KiroProvider {
             };
 
             // 发送请求
-            let response = match self
-                .client_for(&ctx.credentials)?
+            let (client, proxy) = self.client_for(&ctx.credentials)?;
+            let response = match client
                 .post(&url)
                 .headers(headers)
                 .body(request_body.to_string())
@@ -332,6 +582,7 @@ impl KiroProvider {
                         max_retries,
                         e
                     );
+                    Self::report_proxy_failure(proxy.as_ref(), &e);
                     last_error = Some(e.into());
                     if attempt + 1 < max_retries {
                         sleep(Self::retry_delay(attempt)).await;
@@ -353,7 +604,7 @@ impl KiroProvider {
 
             // 402 额度用尽
             if status.as_u16() == 402 && Self::is_monthly_request_limit(&body) {
-                let has_available = self.token_manager.report_quota_exhausted(ctx.id);
+                let has_available = self.handle_quota_exhausted(ctx.id);
                 if !has_available {
                     anyhow::bail!("MCP 请求失败（所有凭据已用尽）: {} {}", status, body);
                 }
@@ -409,50 +660,188 @@ impl KiroProvider {
         }))
     }
 
-    /// 内部方法：带重试逻辑的 API 调用
+    /// 在固定凭据的作用域内执行 `fut`
+    ///
+    /// 作用域内发出的 API / MCP 请求只使用该凭据，不经过负载均衡，也不切换到其他凭据；
+    /// `credential_id` 为 None 时按正常流程选择凭据。已处于固定凭据的作用域内时
+    /// 保持外层的凭据（请求头指定的凭据优先于模型路由）
+    pub async fn with_pinned_credential<F: Future>(
+        credential_id: Option<u64>,
+        fut: F,
+    ) -> F::Output {
+        match credential_id.filter(|_| Self::pinned_credential().is_none()) {
+            Some(id) => PINNED_CREDENTIAL.scope(id, fut).await,
+            None => fut.await,
+        }
+    }
+
+    /// 在带请求信息的作用域内执行 `fut`：作用域内的 API 调用发布的 `RequestCompleted`
+    /// 事件会带上发起请求的 API Key 标识与输入 tokens（见 `tag_input_tokens`）
+    pub async fn with_request_tags<F: Future>(api_key_id: Option<String>, fut: F) -> F::Output {
+        let tags = RequestTags {
+            api_key_id,
+            ..Default::default()
+        };
+        REQUEST_TAGS.scope(parking_lot::Mutex::new(tags), fut).await
+    }
+
+    /// 记录当前请求估算的输入 tokens（不在 `with_request_tags` 作用域内时忽略）
+    pub fn tag_input_tokens(tokens: i32) {
+        let _ = REQUEST_TAGS.try_with(|tags| tags.lock().input_tokens = Some(tokens));
+    }
+
+    /// 当前请求最近一次成功的 API 调用所用的凭据（不在 `with_request_tags` 作用域内时为 None）
+    pub fn served_credential() -> Option<u64> {
+        REQUEST_TAGS
+            .try_with(|tags| tags.lock().credential_id)
+            .ok()
+            .flatten()
+    }
+
+    fn request_tags() -> RequestTags {
+        REQUEST_TAGS
+            .try_with(|tags| tags.lock().clone())
+            .unwrap_or_default()
+    }
+
+    /// 当前作用域固定的凭据 ID
+    pub fn pinned_credential() -> Option<u64> {
+        PINNED_CREDENTIAL.try_with(|id| *id).ok()
+    }
+
+    /// 预测该请求会使用的凭据（固定凭据优先），不发送请求；无可用凭据时返回 None
+    pub fn preview_credential(&self, request_body: &str) -> Option<u64> {
+        if let Some(id) = Self::pinned_credential() {
+            return Some(id);
+        }
+        let (model, conversation_id) = Self::extract_routing_info(request_body);
+        self.token_manager
+            .preview_credential(model.as_deref(), conversation_id.as_deref())
+    }
+
+    /// 获取本次请求的调用上下文：有固定凭据时使用该凭据，否则按负载均衡策略选择，
+    /// 并尽量避开本次请求中已遇到瞬态错误的凭据
+    async fn acquire_context_for_request(
+        &self,
+        model: Option<&str>,
+        affinity: Option<&str>,
+        exclude: &[u64],
+    ) -> anyhow::Result<CallContext> {
+        match Self::pinned_credential() {
+            Some(id) => self.token_manager.acquire_context_for(id).await,
+            None => {
+                self.token_manager
+                    .acquire_context_excluding(model, affinity, exclude)
+                    .await
+            }
+        }
+    }
+
+    /// 内部方法：带重试逻辑的 API 调用，结束后发布 `RequestCompleted` 事件
+    ///
+    /// `exclude` 中的凭据会尽量避开；返回响应与最后一次尝试使用的凭据
+    async fn call_api_with_retry(
+        &self,
+        request_body: &str,
+        is_stream: bool,
+        exclude: &[u64],
+    ) -> anyhow::Result<(reqwest::Response, Option<u64>)> {
+        let started = Instant::now();
+        let mut outcome = RequestOutcome::default();
+        let result = if self.token_manager.config().demo_mode {
+            outcome.model = Self::extract_routing_info(request_body).0;
+            outcome.status = Some(200);
+            outcome.attempts = 1;
+            Ok(demo::response(request_body, is_stream))
+        } else {
+            self.send_with_retry(request_body, is_stream, exclude, &mut outcome)
+                .await
+        };
+        if result.is_ok() {
+            let _ = REQUEST_TAGS.try_with(|tags| tags.lock().credential_id = outcome.credential_id);
+        }
+        let tags = Self::request_tags();
+        events::publish(AppEvent::RequestCompleted {
+            api_key_id: tags.api_key_id,
+            input_tokens: tags.input_tokens,
+            credential_id: outcome.credential_id,
+            model: outcome.model,
+            stream: is_stream,
+            status: outcome.status,
+            attempts: outcome.attempts,
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+        result.map(|response| (response, outcome.credential_id))
+    }
+
+    /// 发送 API 请求并按需重试，过程中记录最后一次尝试的凭据与状态码
     ///
     /// 重试策略：
     /// - 每个凭据最多重试 MAX_RETRIES_PER_CREDENTIAL 次
     /// - 总重试次数 = min(凭据数量 × 每凭据重试次数, MAX_TOTAL_RETRIES)
     /// - 硬上限 9 次，避免无限重试
-    async fn call_api_with_retry(
+    async fn send_with_retry(
         &self,
         request_body: &str,
         is_stream: bool,
+        exclude: &[u64],
+        outcome: &mut RequestOutcome,
     ) -> anyhow::Result<reqwest::Response> {
         let total_credentials = self.token_manager.total_count();
         let max_retries = (total_credentials * MAX_RETRIES_PER_CREDENTIAL).min(MAX_TOTAL_RETRIES);
         let mut last_error: Option<anyhow::Error> = None;
         let api_type = if is_stream { "流式" } else { "非流式" };
 
-        // 尝试从请求体中提取模型信息
-        let model = Self::extract_model_from_request(request_body);
+        // 尝试从请求体中提取模型与会话信息
+        let (model, conversation_id) = Self::extract_routing_info(request_body);
+        outcome.model = model.clone();
+
+        let (mut body_bytes, mut compressed) = self.encode_request_body(request_body);
+        // 压缩请求被拒绝后以原始请求体重试一次，重试成功才确认上游不接受压缩
+        let mut compression_probe = false;
+        // 本次请求中遇到瞬态错误（或调用方要求避开）的凭据，后续重试优先换用其他凭据
+        let mut transient_failed: Vec<u64> = exclude.to_vec();
 
         for attempt in 0..max_retries {
+            outcome.attempts = attempt + 1;
+
             // 获取调用上下文（绑定 index、credentials、token）
-            let ctx = match self.token_manager.acquire_context(model.as_deref()).await {
+            let ctx = match self
+                .acquire_context_for_request(
+                    model.as_deref(),
+                    conversation_id.as_deref(),
+                    &transient_failed,
+                )
+                .await
+            {
                 Ok(c) => c,
                 Err(e) => {
                     last_error = Some(e);
                     continue;
                 }
             };
+            outcome.credential_id = Some(ctx.id);
+            outcome.status = None;
 
             let url = self.base_url_for(&ctx.credentials);
-            let headers = match self.build_headers(&ctx) {
+            let mut headers = match self.build_headers(&ctx) {
                 Ok(h) => h,
                 Err(e) => {
                     last_error = Some(e);
                     continue;
                 }
             };
+            if compressed {
+                headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            }
 
             // 发送请求
-            let response = match self
-                .client_for(&ctx.credentials)?
+            let started_at = Instant::now();
+            let (client, proxy) = self.client_for(&ctx.credentials)?;
+            let response = match client
                 .post(&url)
                 .headers(headers)
-                .body(request_body.to_string())
+                .body(body_bytes.clone())
                 .send()
                 .await
             {
@@ -466,6 +855,14 @@ impl KiroProvider {
                     );
                     // 网络错误通常是上游/链路瞬态问题，不应导致"禁用凭据"或"切换凭据"
                     // （否则一段时间网络抖动会把所有凭据都误禁用，需要重启才能恢复）
+                    // 代理连接失败归因于代理：下次尝试换用备用代理，不计入凭据的失败统计
+                    if !Self::report_proxy_failure(proxy.as_ref(), &e) {
+                        self.token_manager.record_error(
+                            ctx.id,
+                            FailureClass::Network,
+                            &e.to_string(),
+                        );
+                    }
                     last_error = Some(e.into());
                     if attempt + 1 < max_retries {
                         sleep(Self::retry_delay(attempt)).await;
@@ -475,15 +872,82 @@ impl KiroProvider {
             };
 
             let status = response.status();
+            outcome.status = Some(status.as_u16());
+            let probing = std::mem::take(&mut compression_probe);
 
             // 成功响应
             if status.is_success() {
+                if probing {
+                    tracing::warn!("上游不接受 gzip 压缩的请求体，后续请求不再压缩");
+                    self.compression_supported.store(false, Ordering::Relaxed);
+                }
+                // 流式响应在返回任何数据前中断时，客户端尚未收到内容，可以换凭据透明重试
+                let response = if is_stream {
+                    match Self::peek_first_chunk(response).await {
+                        Ok(resp) => resp,
+                        Err(e) => {
+                            tracing::warn!(
+                                "流式响应在返回数据前中断，换凭据重试（尝试 {}/{}）: {}",
+                                attempt + 1,
+                                max_retries,
+                                e
+                            );
+                            self.token_manager.record_error(
+                                ctx.id,
+                                FailureClass::Transient,
+                                &e.to_string(),
+                            );
+                            if !transient_failed.contains(&ctx.id) {
+                                transient_failed.push(ctx.id);
+                            }
+                            last_error = Some(e);
+                            if attempt + 1 < max_retries {
+                                sleep(Self::retry_delay(attempt)).await;
+                            }
+                            continue;
+                        }
+                    }
+                } else {
+                    response
+                };
+                self.token_manager
+                    .record_latency(ctx.id, started_at.elapsed());
                 self.token_manager.report_success(ctx.id);
                 return Ok(response);
             }
 
             // 失败响应：读取 body 用于日志/错误信息
             let body = response.text().await.unwrap_or_default();
+            let failure_class = match status.as_u16() {
+                401 | 403 => FailureClass::Auth,
+                402 if Self::is_monthly_request_limit(&body) => FailureClass::Quota,
+                408 | 429 => FailureClass::Transient,
+                _ if status.is_client_error() => FailureClass::Client,
+                _ => FailureClass::Transient,
+            };
+
+            // 上游可能不接受压缩请求体：立即以原始请求体重试一次（不计入凭据失败），
+            // 重试成功后才在本进程内关闭压缩
+            if compressed && Self::is_compression_rejected(status.as_u16(), &body) {
+                tracing::warn!(
+                    "上游拒绝 gzip 压缩的请求体，以原始请求体重试: {} {}",
+                    status,
+                    body
+                );
+                body_bytes = bytes::Bytes::copy_from_slice(request_body.as_bytes());
+                compressed = false;
+                compression_probe = true;
+                last_error = Some(anyhow::anyhow!(
+                    "{} API 请求失败: {} {}",
+                    api_type,
+                    status,
+                    body
+                ));
+                continue;
+            }
+
+            self.token_manager
+                .record_error(ctx.id, failure_class, &format!("{} {}", status, body));
 
             // 402 Payment Required 且额度用尽：禁用凭据并故障转移
             if status.as_u16() == 402 && Self::is_monthly_request_limit(&body) {
@@ -495,7 +959,7 @@ impl KiroProvider {
                     body
                 );
 
-                let has_available = self.token_manager.report_quota_exhausted(ctx.id);
+                let has_available = self.handle_quota_exhausted(ctx.id);
                 if !has_available {
                     anyhow::bail!(
                         "{} API 请求失败（所有凭据已用尽）: {} {}",
@@ -548,16 +1012,19 @@ impl KiroProvider {
                 continue;
             }
 
-            // 429/408/5xx - 瞬态上游错误：重试但不禁用或切换凭据
+            // 429/408/5xx - 瞬态上游错误：指数退避后换一个凭据重试，但不计入失败、不禁用凭据
             // （避免 429 high traffic / 502 high load 等瞬态错误把所有凭据锁死）
             if matches!(status.as_u16(), 408 | 429) || status.is_server_error() {
                 tracing::warn!(
-                    "API 请求失败（上游瞬态错误，尝试 {}/{}）: {} {}",
+                    "API 请求失败（上游瞬态错误，换凭据重试，尝试 {}/{}）: {} {}",
                     attempt + 1,
                     max_retries,
                     status,
                     body
                 );
+                if !transient_failed.contains(&ctx.id) {
+                    transient_failed.push(ctx.id);
+                }
                 last_error = Some(anyhow::anyhow!(
                     "{} API 请求失败: {} {}",
                     api_type,
@@ -639,6 +1106,15 @@ impl KiroProvider {
     }
 }
 
+/// gzip 压缩
+fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -659,6 +1135,42 @@ mod tests {
         assert!(provider.base_url().contains("generateAssistantResponse"));
     }
 
+    #[tokio::test]
+    async fn test_peek_first_chunk() {
+        let empty = reqwest::Response::from(http::Response::new(reqwest::Body::from("")));
+        assert!(KiroProvider::peek_first_chunk(empty).await.is_err());
+
+        let mut source = http::Response::new(reqwest::Body::from("event-data"));
+        *source.status_mut() = reqwest::StatusCode::ACCEPTED;
+        let peeked = KiroProvider::peek_first_chunk(reqwest::Response::from(source))
+            .await
+            .unwrap();
+        assert_eq!(peeked.status(), reqwest::StatusCode::ACCEPTED);
+        assert_eq!(peeked.bytes().await.unwrap(), "event-data");
+    }
+
+    #[tokio::test]
+    async fn test_with_pinned_credential_keeps_outer_pin() {
+        assert_eq!(KiroProvider::pinned_credential(), None);
+        let inner = KiroProvider::with_pinned_credential(Some(1), async {
+            KiroProvider::with_pinned_credential(Some(2), async {
+                KiroProvider::pinned_credential()
+            })
+            .await
+        })
+        .await;
+        assert_eq!(inner, Some(1));
+
+        let routed = KiroProvider::with_pinned_credential(None, async {
+            KiroProvider::with_pinned_credential(Some(2), async {
+                KiroProvider::pinned_credential()
+            })
+            .await
+        })
+        .await;
+        assert_eq!(routed, Some(2));
+    }
+
     #[test]
     fn test_base_domain() {
         let mut config = Config::default();
@@ -668,6 +1180,27 @@ mod tests {
         assert_eq!(provider.base_domain(), "q.us-east-1.amazonaws.com");
     }
 
+    #[test]
+    fn test_region_endpoint_override_urls() {
+        let mut config = Config::default();
+        config.region = "us-east-1".to_string();
+        config.region_endpoints.insert(
+            "us-east-1".to_string(),
+            crate::model::config::RegionEndpointOverride {
+                api_host: Some("kiro-gw.internal".to_string()),
+                ..Default::default()
+            },
+        );
+        let credentials = KiroCredentials::default();
+        let provider = create_test_provider(config, credentials.clone());
+        assert_eq!(provider.base_domain(), "kiro-gw.internal");
+        assert_eq!(provider.mcp_url(), "https://kiro-gw.internal/mcp");
+        assert_eq!(
+            provider.base_url_for(&credentials),
+            "https://kiro-gw.internal/generateAssistantResponse"
+        );
+    }
+
     #[test]
     fn test_build_headers() {
         let mut config = Config::default();
@@ -700,6 +1233,46 @@ mod tests {
         assert_eq!(headers.get(CONNECTION).unwrap(), "close");
     }
 
+    #[test]
+    fn test_encode_request_body_compression() {
+        use std::io::Read;
+
+        let large = "x".repeat(COMPRESSION_MIN_BYTES);
+        let mut config = Config::default();
+        config.upstream_request_compression = true;
+        let provider = create_test_provider(config, KiroCredentials::default());
+
+        // 小请求体不压缩
+        assert!(!provider.encode_request_body("{}").1);
+
+        let (body, compressed) = provider.encode_request_body(&large);
+        assert!(compressed);
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(body.as_ref())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, large);
+
+        // 415 或明确提到 Content-Encoding 的 400 视为拒绝压缩
+        assert!(KiroProvider::is_compression_rejected(415, ""));
+        assert!(KiroProvider::is_compression_rejected(
+            400,
+            "Unsupported Content-Encoding: gzip"
+        ));
+        // 普通的请求格式错误不视为拒绝压缩
+        assert!(!KiroProvider::is_compression_rejected(
+            400,
+            r#"{"__type":"SerializationException"}"#
+        ));
+        assert!(!KiroProvider::is_compression_rejected(
+            400,
+            "Invalid character encoding in field"
+        ));
+        // 确认上游拒绝后不再压缩
+        provider.compression_supported.store(false, Ordering::Relaxed);
+        assert!(!provider.encode_request_body(&large).1);
+    }
+
     #[test]
     fn test_is_monthly_request_limit_detects_reason() {
         let body = r#"{"message":"You have reached the limit.","reason":"MONTHLY_REQUEST_COUNT"}"#;
@@ -717,4 +1290,101 @@ mod tests {
         let body = r#"{"message":"nope","reason":"DAILY_REQUEST_COUNT"}"#;
         assert!(!KiroProvider::is_monthly_request_limit(body));
     }
+
+    #[test]
+    fn test_is_empty_completion() {
+        use crate::kiro::parser::frame::encode_event_frame;
+
+        let context = encode_event_frame(
+            "contextUsageEvent",
+            &serde_json::json!({"contextUsagePercentage": 1.5}),
+        );
+        let empty_text =
+            encode_event_frame("assistantResponseEvent", &serde_json::json!({"content": ""}));
+        assert!(KiroProvider::is_empty_completion(&[]));
+        assert!(KiroProvider::is_empty_completion(
+            &[empty_text.clone(), context.clone()].concat()
+        ));
+
+        let text =
+            encode_event_frame("assistantResponseEvent", &serde_json::json!({"content": "hi"}));
+        assert!(!KiroProvider::is_empty_completion(&[empty_text, text, context].concat()));
+
+        let tool_use = encode_event_frame(
+            "toolUseEvent",
+            &serde_json::json!({"toolUseId": "t1", "name": "read", "input": "", "stop": true}),
+        );
+        assert!(!KiroProvider::is_empty_completion(&tool_use));
+    }
+
+    #[tokio::test]
+    async fn test_empty_response_retry_uses_other_credential() {
+        use crate::kiro::parser::frame::encode_event_frame;
+
+        let mut config = Config::default();
+        config.load_balancing_mode = "balanced".to_string();
+        let creds: Vec<KiroCredentials> = (1..=2)
+            .map(|i| KiroCredentials {
+                access_token: Some(format!("t{}", i)),
+                expires_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+                ..Default::default()
+            })
+            .collect();
+        let tm = MultiTokenManager::new(config, creds, None, None, false).unwrap();
+        let provider = KiroProvider::new(Arc::new(tm));
+
+        let empty = encode_event_frame(
+            "assistantResponseEvent",
+            &serde_json::json!({"content": ""}),
+        );
+        let text = encode_event_frame(
+            "assistantResponseEvent",
+            &serde_json::json!({"content": "hi"}),
+        );
+        for _ in 0..10 {
+            // 每次发送按排除列表选择凭据（与 send_with_retry 相同），第一次返回空响应
+            let used = std::sync::Mutex::new(Vec::new());
+            let send = |exclude: Vec<u64>| {
+                let (provider, used) = (&provider, &used);
+                let (empty, text) = (empty.clone(), text.clone());
+                async move {
+                    let ctx = provider
+                        .acquire_context_for_request(None, None, &exclude)
+                        .await?;
+                    let mut used = used.lock().unwrap();
+                    let body = if used.is_empty() { empty } else { text };
+                    used.push(ctx.id);
+                    let response = reqwest::Response::from(http::Response::new(body));
+                    Ok((response, Some(ctx.id)))
+                }
+            };
+            let response = provider.retry_on_empty(true, send).await.unwrap();
+            assert!(!KiroProvider::is_empty_completion(
+                &response.bytes().await.unwrap()
+            ));
+
+            let used = used.into_inner().unwrap();
+            assert_eq!(used.len(), 2);
+            assert_ne!(used[0], used[1]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_buffer_and_inspect_preserves_response() {
+        let body = crate::kiro::parser::frame::encode_event_frame(
+            "assistantResponseEvent",
+            &serde_json::json!({"content": "hi"}),
+        );
+        let mut original = http::Response::new(body.clone());
+        original
+            .headers_mut()
+            .insert("x-test", HeaderValue::from_static("1"));
+        let response = reqwest::Response::from(original);
+
+        let (rebuilt, is_empty) = KiroProvider::buffer_and_inspect(response).await.unwrap();
+        assert!(!is_empty);
+        assert_eq!(rebuilt.status(), reqwest::StatusCode::OK);
+        assert_eq!(rebuilt.headers().get("x-test").unwrap(), "1");
+        assert_eq!(rebuilt.bytes().await.unwrap().as_ref(), body.as_slice());
+    }
 }
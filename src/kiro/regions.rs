@@ -0,0 +1,197 @@
+//! Region 端点目录
+//!
+//! 集中维护 Region → 具体端点的映射：
+//! - Social Token 刷新：`prod.{region}.auth.desktop.kiro.dev`
+//! - IdC Token 刷新：`oidc.{region}.amazonaws.com`
+//! - Kiro API / MCP / 额度查询：`q.{region}.amazonaws.com`
+//!
+//! 可通过 config.json 的 `regionEndpoints` 按 Region 覆盖任一域名（如私有网关、测试环境）。
+
+use crate::kiro::model::credentials::KiroCredentials;
+use crate::model::config::Config;
+
+/// 已知提供 Kiro 服务的 Region
+pub const KNOWN_REGIONS: &[&str] = &["us-east-1", "eu-central-1"];
+
+/// Social / IdC 刷新使用的认证端点（依赖 auth region）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthEndpoints {
+    pub auth_host: String,
+    pub oidc_host: String,
+}
+
+impl AuthEndpoints {
+    pub fn for_region(region: &str, config: &Config) -> Self {
+        let overrides = config.region_endpoints.get(region);
+        Self {
+            auth_host: overrides
+                .and_then(|o| o.auth_host.clone())
+                .unwrap_or_else(|| format!("prod.{}.auth.desktop.kiro.dev", region)),
+            oidc_host: overrides
+                .and_then(|o| o.oidc_host.clone())
+                .unwrap_or_else(|| format!("oidc.{}.amazonaws.com", region)),
+        }
+    }
+
+    pub fn social_refresh_url(&self) -> String {
+        format!("https://{}/refreshToken", self.auth_host)
+    }
+
+    pub fn idc_token_url(&self) -> String {
+        format!("https://{}/token", self.oidc_host)
+    }
+}
+
+/// Kiro API 端点（依赖 api region）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiEndpoints {
+    pub api_host: String,
+}
+
+impl ApiEndpoints {
+    pub fn for_region(region: &str, config: &Config) -> Self {
+        Self {
+            api_host: config
+                .region_endpoints
+                .get(region)
+                .and_then(|o| o.api_host.clone())
+                .unwrap_or_else(|| format!("q.{}.amazonaws.com", region)),
+        }
+    }
+
+    pub fn generate_url(&self) -> String {
+        format!("https://{}/generateAssistantResponse", self.api_host)
+    }
+
+    pub fn mcp_url(&self) -> String {
+        format!("https://{}/mcp", self.api_host)
+    }
+
+    pub fn usage_limits_url(&self) -> String {
+        format!("https://{}/getUsageLimits", self.api_host)
+    }
+}
+
+/// 校验 Region 名称
+///
+/// 格式不合法时返回错误；格式合法但不在已知列表且未配置覆盖时返回警告信息
+pub fn validate_region(region: &str, config: &Config) -> Result<Option<String>, String> {
+    let parts: Vec<&str> = region.split('-').collect();
+    let well_formed = parts.len() >= 3
+        && parts[0].len() == 2
+        && parts.iter().all(|p| !p.is_empty())
+        && parts[..parts.len() - 1]
+            .iter()
+            .all(|p| p.chars().all(|c| c.is_ascii_lowercase()))
+        && parts[parts.len() - 1].chars().all(|c| c.is_ascii_digit());
+    if !well_formed {
+        return Err(format!("无效的 Region 名称: {:?}", region));
+    }
+
+    if KNOWN_REGIONS.contains(&region) || config.region_endpoints.contains_key(region) {
+        Ok(None)
+    } else {
+        Ok(Some(format!(
+            "Region {} 不在已知的 Kiro Region 列表中（{}），请确认端点可用",
+            region,
+            KNOWN_REGIONS.join(", ")
+        )))
+    }
+}
+
+/// 校验配置与凭据中出现的所有 Region，返回错误与警告信息
+pub fn validate_all(config: &Config, credentials: &[KiroCredentials]) -> Vec<String> {
+    let mut regions: Vec<(String, &str)> =
+        vec![("config.region".to_string(), config.region.as_str())];
+    if let Some(r) = &config.auth_region {
+        regions.push(("config.authRegion".to_string(), r));
+    }
+    if let Some(r) = &config.api_region {
+        regions.push(("config.apiRegion".to_string(), r));
+    }
+    for (index, cred) in credentials.iter().enumerate() {
+        let label = cred
+            .id
+            .map(|id| format!("凭据 #{}", id))
+            .unwrap_or_else(|| format!("凭据[{}]", index));
+        for (field, value) in [
+            ("region", &cred.region),
+            ("authRegion", &cred.auth_region),
+            ("apiRegion", &cred.api_region),
+        ] {
+            if let Some(r) = value {
+                regions.push((format!("{}.{}", label, field), r));
+            }
+        }
+    }
+
+    regions
+        .into_iter()
+        .filter_map(|(source, region)| match validate_region(region, config) {
+            Ok(None) => None,
+            Ok(Some(warning)) => Some(format!("{}: {}", source, warning)),
+            Err(e) => Some(format!("{}: {}", source, e)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::config::RegionEndpointOverride;
+
+    #[test]
+    fn test_default_endpoints() {
+        let config = Config::default();
+        let auth = AuthEndpoints::for_region("eu-central-1", &config);
+        assert_eq!(
+            auth.social_refresh_url(),
+            "https://prod.eu-central-1.auth.desktop.kiro.dev/refreshToken"
+        );
+        assert_eq!(
+            auth.idc_token_url(),
+            "https://oidc.eu-central-1.amazonaws.com/token"
+        );
+
+        let api = ApiEndpoints::for_region("us-east-1", &config);
+        assert_eq!(
+            api.generate_url(),
+            "https://q.us-east-1.amazonaws.com/generateAssistantResponse"
+        );
+        assert_eq!(api.mcp_url(), "https://q.us-east-1.amazonaws.com/mcp");
+    }
+
+    #[test]
+    fn test_endpoint_overrides() {
+        let mut config = Config::default();
+        config.region_endpoints.insert(
+            "us-east-1".to_string(),
+            RegionEndpointOverride {
+                api_host: Some("kiro-gw.internal".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            ApiEndpoints::for_region("us-east-1", &config).api_host,
+            "kiro-gw.internal"
+        );
+        // 未覆盖的域名保持默认
+        assert_eq!(
+            AuthEndpoints::for_region("us-east-1", &config).oidc_host,
+            "oidc.us-east-1.amazonaws.com"
+        );
+    }
+
+    #[test]
+    fn test_validate_region() {
+        let config = Config::default();
+        assert_eq!(validate_region("us-east-1", &config), Ok(None));
+        assert!(matches!(
+            validate_region("ap-southeast-1", &config),
+            Ok(Some(_))
+        ));
+        assert!(validate_region("useast1", &config).is_err());
+        assert!(validate_region("US-EAST-1", &config).is_err());
+        assert!(validate_region("us-east-", &config).is_err());
+    }
+}
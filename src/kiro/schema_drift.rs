@@ -0,0 +1,242 @@
+//! 上游事件流结构变化检测
+//!
+//! 解析 Kiro 事件流时记录未识别的消息类型、事件类型，以及已知事件中未识别的字段（按出现次数
+//! 统计，仅内存），首次出现时输出警告日志，可通过 `GET /api/admin/diagnostics/unknown-events`
+//! 查询。配置 `unknownEventSamples` 后，每种未识别项的前若干个原始帧保存到数据目录的
+//! `unknown-events/` 下，便于在上游协议变更导致解析失败之前分析新格式。
+//!
+//! 样本包含完整的 payload（可能含有模型输出内容），请按对话内容的要求保管。
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde::de::IgnoredAny;
+use utoipa::ToSchema;
+
+use crate::kiro::parser::frame::Frame;
+
+/// 已识别事件的字段（含已知但不使用的字段）；不在此表中的事件类型不检查字段
+const KNOWN_FIELDS: &[(&str, &[&str])] = &[
+    (
+        "assistantResponseEvent",
+        &[
+            "content",
+            "conversationId",
+            "messageId",
+            "messageStatus",
+            "followupPrompt",
+        ],
+    ),
+    ("toolUseEvent", &["name", "toolUseId", "input", "stop"]),
+    ("contextUsageEvent", &["contextUsagePercentage"]),
+];
+
+/// 已识别的事件类型
+const KNOWN_EVENT_TYPES: &[&str] = &[
+    "assistantResponseEvent",
+    "toolUseEvent",
+    "meteringEvent",
+    "contextUsageEvent",
+];
+
+/// 未识别项的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    MessageType,
+    EventType,
+    Field,
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Self::MessageType => "消息类型",
+            Self::EventType => "事件类型",
+            Self::Field => "字段",
+        }
+    }
+}
+
+/// 单个未识别项的出现统计
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UnknownOccurrence {
+    pub count: u64,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    /// 已保存的原始帧样本数
+    pub samples: usize,
+}
+
+/// 未识别项报告（进程启动以来，仅内存）
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UnknownEventsReport {
+    /// 未识别的消息类型（`:message-type` 头）
+    pub message_types: BTreeMap<String, UnknownOccurrence>,
+    /// 未识别的事件类型（`:event-type` 头）
+    pub event_types: BTreeMap<String, UnknownOccurrence>,
+    /// 已知事件中未识别的字段（`<事件类型>.<字段名>`）
+    pub fields: BTreeMap<String, UnknownOccurrence>,
+    /// 样本保存目录（未配置 `unknownEventSamples` 时为 None）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub samples_dir: Option<PathBuf>,
+}
+
+struct Sampling {
+    dir: PathBuf,
+    per_key: usize,
+}
+
+static STATE: Mutex<UnknownEventsReport> = Mutex::new(UnknownEventsReport {
+    message_types: BTreeMap::new(),
+    event_types: BTreeMap::new(),
+    fields: BTreeMap::new(),
+    samples_dir: None,
+});
+static SAMPLING: OnceLock<Sampling> = OnceLock::new();
+
+/// 启用原始帧采样（只能设置一次；`per_key` 为 0 时不启用）
+pub fn init_sampling(dir: PathBuf, per_key: usize) {
+    if per_key == 0 {
+        return;
+    }
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        tracing::warn!("创建未知事件样本目录失败 {}: {}", dir.display(), e);
+        return;
+    }
+    if SAMPLING.set(Sampling { dir, per_key }).is_ok() {
+        STATE.lock().samples_dir = SAMPLING.get().map(|s| s.dir.clone());
+    }
+}
+
+/// 记录未识别的消息类型
+pub fn observe_message_type(message_type: &str, frame: &Frame) {
+    record(Kind::MessageType, message_type, message_type, "", frame);
+}
+
+/// 检查事件类型与字段是否都能识别
+pub fn observe_event(event_type: &str, frame: &Frame) {
+    if !KNOWN_EVENT_TYPES.contains(&event_type) {
+        record(Kind::EventType, event_type, "event", event_type, frame);
+        return;
+    }
+    for field in unknown_fields(event_type, &frame.payload) {
+        let key = format!("{}.{}", event_type, field);
+        record(Kind::Field, &key, "event", event_type, frame);
+    }
+}
+
+/// payload 中不在已知字段表里的字段名（payload 不是 JSON 对象时视为没有）
+fn unknown_fields(event_type: &str, payload: &[u8]) -> Vec<String> {
+    let Some((_, known)) = KNOWN_FIELDS.iter().find(|(t, _)| *t == event_type) else {
+        return Vec::new();
+    };
+    let Ok(object) = serde_json::from_slice::<HashMap<String, IgnoredAny>>(payload) else {
+        return Vec::new();
+    };
+    let mut fields: Vec<String> = object
+        .into_keys()
+        .filter(|key| !known.contains(&key.as_str()))
+        .collect();
+    fields.sort();
+    fields
+}
+
+fn record(kind: Kind, key: &str, message_type: &str, event_type: &str, frame: &Frame) {
+    let now = Utc::now();
+    let sample_index = {
+        let mut state = STATE.lock();
+        let map = match kind {
+            Kind::MessageType => &mut state.message_types,
+            Kind::EventType => &mut state.event_types,
+            Kind::Field => &mut state.fields,
+        };
+        let occurrence = map.entry(key.to_string()).or_insert_with(|| {
+            tracing::warn!(
+                "Kiro 事件流中出现未识别的{} {}，上游协议可能已变更",
+                kind.label(),
+                key
+            );
+            UnknownOccurrence {
+                count: 0,
+                first_seen_at: now,
+                last_seen_at: now,
+                samples: 0,
+            }
+        });
+        occurrence.count += 1;
+        occurrence.last_seen_at = now;
+        match SAMPLING.get() {
+            Some(sampling)
+                if occurrence.samples < sampling.per_key
+                    && crate::disk_monitor::optional_writes_allowed() =>
+            {
+                occurrence.samples += 1;
+                Some(occurrence.samples)
+            }
+            _ => None,
+        }
+    };
+    if let (Some(index), Some(sampling)) = (sample_index, SAMPLING.get()) {
+        write_sample(sampling, key, index, message_type, event_type, frame, now);
+    }
+}
+
+fn write_sample(
+    sampling: &Sampling,
+    key: &str,
+    index: usize,
+    message_type: &str,
+    event_type: &str,
+    frame: &Frame,
+    captured_at: DateTime<Utc>,
+) {
+    let name: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let path = sampling.dir.join(format!("{}-{}.json", name, index));
+    let sample = serde_json::json!({
+        "key": key,
+        "messageType": message_type,
+        "eventType": event_type,
+        "capturedAt": captured_at,
+        "payload": frame.payload_as_str(),
+    });
+    let result = serde_json::to_string_pretty(&sample)
+        .map_err(std::io::Error::other)
+        .and_then(|json| std::fs::write(&path, json));
+    if let Err(e) = result {
+        tracing::warn!("保存未知事件样本失败 {}: {}", path.display(), e);
+    }
+}
+
+/// 当前的未识别项报告
+pub fn report() -> UnknownEventsReport {
+    STATE.lock().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_fields() {
+        let payload =
+            br#"{"content":"hi","messageId":"m","reasoningSignature":"x","citations":[]}"#;
+        assert_eq!(
+            unknown_fields("assistantResponseEvent", payload),
+            vec!["citations".to_string(), "reasoningSignature".to_string()]
+        );
+        assert!(unknown_fields("toolUseEvent", br#"{"name":"a","toolUseId":"b"}"#).is_empty());
+        // 不检查字段的事件与非 JSON payload
+        assert!(unknown_fields("meteringEvent", br#"{"usage":1}"#).is_empty());
+        assert!(unknown_fields("assistantResponseEvent", b"not json").is_empty());
+    }
+}
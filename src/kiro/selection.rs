@@ -0,0 +1,318 @@
+//! 凭据选择策略
+//!
+//! 每种负载均衡模式对应一个 `SelectionStrategy` 实现，`StrategyRegistry` 按
+//! `loadBalancingMode` 字符串查找策略。新增模式只需实现 trait 并注册：
+//!
+//! - `priority`：优先级最高的凭据，沿用当前凭据直到其不可用
+//! - `balanced`：成功次数最少的凭据
+//! - `weighted`：按凭据 `weight` 加权随机
+//! - `least-usage`：最久未使用的凭据
+//! - `sticky`：按会话 ID 做 rendezvous 哈希，同一会话固定到同一凭据
+
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+/// 选择策略看到的候选凭据（已按禁用状态、模型订阅要求过滤）
+#[derive(Debug, Clone)]
+pub struct Candidate<'a> {
+    pub id: u64,
+    /// 优先级（数字越小优先级越高）
+    pub priority: u32,
+    /// 权重（weighted 模式使用，未配置时为 1）
+    pub weight: u32,
+    /// API 调用成功次数
+    pub success_count: u64,
+    /// 最后一次 API 调用时间（RFC3339 格式）
+    pub last_used_at: Option<&'a str>,
+}
+
+/// 单次选择的请求信息
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SelectionContext<'a> {
+    /// 会话亲和键（如 conversationId）
+    pub affinity: Option<&'a str>,
+}
+
+/// 凭据选择策略
+pub trait SelectionStrategy: Send + Sync {
+    /// 模式名称（即 `loadBalancingMode` 的取值）
+    fn name(&self) -> &'static str;
+
+    /// 是否每次请求都重新选择；返回 false 时优先沿用当前凭据，直到其不可用
+    fn select_per_request(&self) -> bool {
+        true
+    }
+
+    /// 从非空候选列表中选择凭据，返回凭据 ID
+    fn select(&self, candidates: &[Candidate<'_>], ctx: &SelectionContext<'_>) -> Option<u64>;
+}
+
+/// 选择优先级最高的候选凭据
+fn highest_priority(candidates: &[Candidate<'_>]) -> Option<u64> {
+    candidates.iter().min_by_key(|c| c.priority).map(|c| c.id)
+}
+
+/// 优先级模式（默认）
+pub struct PriorityStrategy;
+
+impl SelectionStrategy for PriorityStrategy {
+    fn name(&self) -> &'static str {
+        "priority"
+    }
+
+    fn select_per_request(&self) -> bool {
+        false
+    }
+
+    fn select(&self, candidates: &[Candidate<'_>], _ctx: &SelectionContext<'_>) -> Option<u64> {
+        highest_priority(candidates)
+    }
+}
+
+/// 均衡模式：选择成功次数最少的凭据，平局时按优先级
+pub struct BalancedStrategy;
+
+impl SelectionStrategy for BalancedStrategy {
+    fn name(&self) -> &'static str {
+        "balanced"
+    }
+
+    fn select(&self, candidates: &[Candidate<'_>], _ctx: &SelectionContext<'_>) -> Option<u64> {
+        candidates
+            .iter()
+            .min_by_key(|c| (c.success_count, c.priority))
+            .map(|c| c.id)
+    }
+}
+
+/// 加权随机模式：按权重比例随机选择，权重为 0 的凭据仅在所有候选权重均为 0 时参与
+pub struct WeightedStrategy;
+
+impl SelectionStrategy for WeightedStrategy {
+    fn name(&self) -> &'static str {
+        "weighted"
+    }
+
+    fn select(&self, candidates: &[Candidate<'_>], _ctx: &SelectionContext<'_>) -> Option<u64> {
+        let total: u64 = candidates.iter().map(|c| c.weight as u64).sum();
+        if total == 0 {
+            return highest_priority(candidates);
+        }
+
+        let mut point = fastrand::u64(..total);
+        for candidate in candidates {
+            let weight = candidate.weight as u64;
+            if point < weight {
+                return Some(candidate.id);
+            }
+            point -= weight;
+        }
+        None
+    }
+}
+
+/// 最久未使用模式：选择最后使用时间最早的凭据（从未使用的最先），平局时按优先级
+pub struct LeastUsageStrategy;
+
+impl SelectionStrategy for LeastUsageStrategy {
+    fn name(&self) -> &'static str {
+        "least-usage"
+    }
+
+    fn select(&self, candidates: &[Candidate<'_>], _ctx: &SelectionContext<'_>) -> Option<u64> {
+        // RFC3339 时间按字典序即时间序，None 排在最前
+        candidates
+            .iter()
+            .min_by_key(|c| (c.last_used_at, c.priority))
+            .map(|c| c.id)
+    }
+}
+
+/// 会话粘滞模式：最高随机权重（rendezvous）哈希，同一会话始终落在同一凭据上，
+/// 凭据增减或被禁用时只影响原本映射到该凭据的会话；无会话 ID 时按优先级选择
+pub struct StickyStrategy;
+
+impl StickyStrategy {
+    /// 会话亲和键与凭据 ID 的组合哈希
+    fn score(affinity: &str, id: u64) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        affinity.hash(&mut hasher);
+        id.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl SelectionStrategy for StickyStrategy {
+    fn name(&self) -> &'static str {
+        "sticky"
+    }
+
+    fn select(&self, candidates: &[Candidate<'_>], ctx: &SelectionContext<'_>) -> Option<u64> {
+        match ctx.affinity {
+            Some(affinity) => candidates
+                .iter()
+                .max_by_key(|c| Self::score(affinity, c.id))
+                .map(|c| c.id),
+            None => highest_priority(candidates),
+        }
+    }
+}
+
+/// 按模式名称索引的策略注册表
+#[derive(Clone)]
+pub struct StrategyRegistry {
+    strategies: BTreeMap<&'static str, Arc<dyn SelectionStrategy>>,
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            strategies: BTreeMap::new(),
+        };
+        registry.register(Arc::new(PriorityStrategy));
+        registry.register(Arc::new(BalancedStrategy));
+        registry.register(Arc::new(WeightedStrategy));
+        registry.register(Arc::new(LeastUsageStrategy));
+        registry.register(Arc::new(StickyStrategy));
+        registry
+    }
+}
+
+impl StrategyRegistry {
+    /// 注册策略（同名策略会被替换）
+    pub fn register(&mut self, strategy: Arc<dyn SelectionStrategy>) {
+        self.strategies.insert(strategy.name(), strategy);
+    }
+
+    pub fn get(&self, mode: &str) -> Option<Arc<dyn SelectionStrategy>> {
+        self.strategies.get(mode).cloned()
+    }
+
+    pub fn contains(&self, mode: &str) -> bool {
+        self.strategies.contains_key(mode)
+    }
+
+    /// 所有已注册的模式名称
+    pub fn modes(&self) -> Vec<&'static str> {
+        self.strategies.keys().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: u64, priority: u32) -> Candidate<'static> {
+        Candidate {
+            id,
+            priority,
+            weight: 1,
+            success_count: 0,
+            last_used_at: None,
+        }
+    }
+
+    const CTX: SelectionContext<'static> = SelectionContext { affinity: None };
+
+    #[test]
+    fn test_priority_strategy() {
+        let candidates = [candidate(1, 2), candidate(2, 0), candidate(3, 1)];
+        assert_eq!(PriorityStrategy.select(&candidates, &CTX), Some(2));
+        assert!(!PriorityStrategy.select_per_request());
+    }
+
+    #[test]
+    fn test_balanced_strategy() {
+        let mut candidates = [candidate(1, 0), candidate(2, 1), candidate(3, 2)];
+        candidates[0].success_count = 5;
+        candidates[1].success_count = 2;
+        candidates[2].success_count = 2;
+        // 成功次数相同时按优先级
+        assert_eq!(BalancedStrategy.select(&candidates, &CTX), Some(2));
+    }
+
+    #[test]
+    fn test_weighted_strategy() {
+        let mut candidates = [candidate(1, 0), candidate(2, 1)];
+        candidates[0].weight = 0;
+        candidates[1].weight = 3;
+        for _ in 0..100 {
+            assert_eq!(WeightedStrategy.select(&candidates, &CTX), Some(2));
+        }
+
+        // 全部权重为 0 时回退到优先级
+        candidates[1].weight = 0;
+        assert_eq!(WeightedStrategy.select(&candidates, &CTX), Some(1));
+    }
+
+    #[test]
+    fn test_least_usage_strategy() {
+        let mut candidates = [candidate(1, 0), candidate(2, 1), candidate(3, 2)];
+        candidates[0].last_used_at = Some("2026-01-02T00:00:00Z");
+        candidates[1].last_used_at = Some("2026-01-01T00:00:00Z");
+        assert_eq!(LeastUsageStrategy.select(&candidates, &CTX), Some(3));
+
+        candidates[2].last_used_at = Some("2026-01-03T00:00:00Z");
+        assert_eq!(LeastUsageStrategy.select(&candidates, &CTX), Some(2));
+    }
+
+    #[test]
+    fn test_sticky_strategy() {
+        let candidates = [candidate(1, 1), candidate(2, 0), candidate(3, 2)];
+        let ctx = SelectionContext {
+            affinity: Some("conversation-a"),
+        };
+        let first = StickyStrategy.select(&candidates, &ctx);
+        assert!(first.is_some());
+        assert_eq!(StickyStrategy.select(&candidates, &ctx), first);
+
+        // 移除其他凭据不影响已映射的会话
+        let remaining: Vec<_> = candidates
+            .iter()
+            .filter(|c| Some(c.id) == first || c.id == 1)
+            .cloned()
+            .collect();
+        assert_eq!(StickyStrategy.select(&remaining, &ctx), first);
+
+        // 无会话 ID 时按优先级
+        assert_eq!(StickyStrategy.select(&candidates, &CTX), Some(2));
+    }
+
+    struct AlwaysLast;
+
+    impl SelectionStrategy for AlwaysLast {
+        fn name(&self) -> &'static str {
+            "always-last"
+        }
+
+        fn select(&self, candidates: &[Candidate<'_>], _ctx: &SelectionContext<'_>) -> Option<u64> {
+            candidates.last().map(|c| c.id)
+        }
+    }
+
+    #[test]
+    fn test_registry_custom_strategy() {
+        let mut registry = StrategyRegistry::default();
+        assert!(registry.contains("priority"));
+        assert!(!registry.contains("always-last"));
+
+        registry.register(Arc::new(AlwaysLast));
+        let strategy = registry.get("always-last").unwrap();
+        assert_eq!(
+            strategy.select(&[candidate(1, 0), candidate(2, 1)], &CTX),
+            Some(2)
+        );
+        assert_eq!(
+            registry.modes(),
+            vec![
+                "always-last",
+                "balanced",
+                "least-usage",
+                "priority",
+                "sticky",
+                "weighted"
+            ]
+        );
+    }
+}
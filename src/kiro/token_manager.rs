@@ -5,24 +5,58 @@
 
 use anyhow::bail;
 use chrono::{DateTime, Duration, Utc};
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tokio::sync::Mutex as TokioMutex;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration as StdDuration, Instant};
 
-use crate::http_client::{ProxyConfig, build_client};
+use crate::common::crypto::DerivedKey;
+use crate::events::{self, AppEvent};
+use crate::http_client::{ProxyConfig, build_client, extra_headers};
 use crate::kiro::machine_id;
-use crate::kiro::model::credentials::KiroCredentials;
+use crate::kiro::model::credentials::{CredentialsConfig, KiroCredentials, credentials_passphrase};
 use crate::kiro::model::token_refresh::{
     IdcRefreshRequest, IdcRefreshResponse, RefreshRequest, RefreshResponse,
 };
 use crate::kiro::model::usage_limits::UsageLimitsResponse;
-use crate::model::config::Config;
+use crate::kiro::regions::{ApiEndpoints, AuthEndpoints};
+use crate::kiro::selection::{
+    Candidate, PriorityStrategy, SelectionContext, SelectionStrategy, StrategyRegistry,
+};
+use crate::model::config::{Config, CredentialFallback, ModelRoute, TenantConfig};
+use crate::proxy_health;
+use crate::tls::Destination;
+
+/// 当前请求允许使用的凭据范围（见 `MultiTokenManager::with_credential_scope`）
+#[derive(Clone)]
+struct CredentialScope {
+    ids: Arc<[u64]>,
+    fallback: CredentialFallback,
+}
+
+tokio::task_local! {
+    static CREDENTIAL_SCOPE: CredentialScope;
+}
+
+/// 凭据是否在当前请求允许使用的范围内（不在 `with_credential_scope` 作用域内时总是）
+fn in_credential_scope(id: u64) -> bool {
+    CREDENTIAL_SCOPE
+        .try_with(|scope| scope.ids.contains(&id))
+        .unwrap_or(true)
+}
+
+/// 当前作用域的凭据均不可用时是否可以改用全部凭据
+fn scope_falls_back_to_pool() -> bool {
+    CREDENTIAL_SCOPE
+        .try_with(|scope| scope.fallback == CredentialFallback::Pool)
+        .unwrap_or(false)
+}
 
 /// Token 管理器
 ///
@@ -142,8 +176,17 @@ pub(crate) async fn refresh_token(
 ) -> anyhow::Result<KiroCredentials> {
     validate_refresh_token(credentials)?;
 
-    // 根据 auth_method 选择刷新方式
-    // 如果未指定 auth_method，根据是否有 clientId/clientSecret 自动判断
+    if uses_idc_refresh(credentials) {
+        refresh_idc_token(credentials, config, proxy).await
+    } else {
+        refresh_social_token(credentials, config, proxy).await
+    }
+}
+
+/// 根据 auth_method 判断是否使用 IdC 方式刷新
+///
+/// 未指定 auth_method 时，根据是否有 clientId/clientSecret 自动判断
+pub(crate) fn uses_idc_refresh(credentials: &KiroCredentials) -> bool {
     let auth_method = credentials.auth_method.as_deref().unwrap_or_else(|| {
         if credentials.client_id.is_some() && credentials.client_secret.is_some() {
             "idc"
@@ -152,14 +195,9 @@ pub(crate) async fn refresh_token(
         }
     });
 
-    if auth_method.eq_ignore_ascii_case("idc")
+    auth_method.eq_ignore_ascii_case("idc")
         || auth_method.eq_ignore_ascii_case("builder-id")
         || auth_method.eq_ignore_ascii_case("iam")
-    {
-        refresh_idc_token(credentials, config, proxy).await
-    } else {
-        refresh_social_token(credentials, config, proxy).await
-    }
 }
 
 /// 刷新 Social Token
@@ -174,19 +212,21 @@ async fn refresh_social_token(
     // 优先级：凭据.auth_region > 凭据.region > config.auth_region > config.region
     let region = credentials.effective_auth_region(config);
 
-    let refresh_url = format!("https://prod.{}.auth.desktop.kiro.dev/refreshToken", region);
-    let refresh_domain = format!("prod.{}.auth.desktop.kiro.dev", region);
+    let endpoints = AuthEndpoints::for_region(region, config);
+    let refresh_url = endpoints.social_refresh_url();
+    let refresh_domain = endpoints.auth_host;
     let machine_id = machine_id::generate_from_credentials(credentials, config)
         .ok_or_else(|| anyhow::anyhow!("无法生成 machineId"))?;
     let kiro_version = &config.kiro_version;
 
-    let client = build_client(proxy, 60, config.tls_backend)?;
+    let client = build_client(proxy, 60, config.tls_backend, Destination::Kiro)?;
     let body = RefreshRequest {
         refresh_token: refresh_token.to_string(),
     };
 
     let response = client
         .post(&refresh_url)
+        .headers(extra_headers(config))
         .header("Accept", "application/json, text/plain, */*")
         .header("Content-Type", "application/json")
         .header(
@@ -257,9 +297,10 @@ async fn refresh_idc_token(
 
     // 优先级：凭据.auth_region > 凭据.region > config.auth_region > config.region
     let region = credentials.effective_auth_region(config);
-    let refresh_url = format!("https://oidc.{}.amazonaws.com/token", region);
+    let endpoints = AuthEndpoints::for_region(region, config);
+    let refresh_url = endpoints.idc_token_url();
 
-    let client = build_client(proxy, 60, config.tls_backend)?;
+    let client = build_client(proxy, 60, config.tls_backend, Destination::Kiro)?;
     let body = IdcRefreshRequest {
         client_id: client_id.to_string(),
         client_secret: client_secret.to_string(),
@@ -269,8 +310,9 @@ async fn refresh_idc_token(
 
     let response = client
         .post(&refresh_url)
+        .headers(extra_headers(config))
         .header("Content-Type", "application/json")
-        .header("Host", format!("oidc.{}.amazonaws.com", region))
+        .header("Host", endpoints.oidc_host.as_str())
         .header("Connection", "keep-alive")
         .header("x-amz-user-agent", IDC_AMZ_USER_AGENT)
         .header("Accept", "*/*")
@@ -326,15 +368,15 @@ pub(crate) async fn get_usage_limits(
 
     // 优先级：凭据.api_region > config.api_region > config.region
     let region = credentials.effective_api_region(config);
-    let host = format!("q.{}.amazonaws.com", region);
+    let endpoints = ApiEndpoints::for_region(region, config);
     let machine_id = machine_id::generate_from_credentials(credentials, config)
         .ok_or_else(|| anyhow::anyhow!("无法生成 machineId"))?;
     let kiro_version = &config.kiro_version;
 
     // 构建 URL
     let mut url = format!(
-        "https://{}/getUsageLimits?origin=AI_EDITOR&resourceType=AGENTIC_REQUEST",
-        host
+        "{}?origin=AI_EDITOR&resourceType=AGENTIC_REQUEST&isEmailRequired=true",
+        endpoints.usage_limits_url()
     );
 
     // profileArn 是可选的
@@ -353,13 +395,14 @@ pub(crate) async fn get_usage_limits(
         USAGE_LIMITS_AMZ_USER_AGENT_PREFIX, kiro_version, machine_id
     );
 
-    let client = build_client(proxy, 60, config.tls_backend)?;
+    let client = build_client(proxy, 60, config.tls_backend, Destination::Kiro)?;
 
     let response = client
         .get(&url)
+        .headers(extra_headers(config))
         .header("x-amz-user-agent", &amz_user_agent)
         .header("User-Agent", &user_agent)
-        .header("host", &host)
+        .header("host", &endpoints.api_host)
         .header("amz-sdk-invocation-id", uuid::Uuid::new_v4().to_string())
         .header("amz-sdk-request", "attempt=1; max=1")
         .header("Authorization", format!("Bearer {}", token))
@@ -372,6 +415,7 @@ pub(crate) async fn get_usage_limits(
         let body_text = response.text().await.unwrap_or_default();
         let error_msg = match status.as_u16() {
             401 => "认证失败，Token 无效或已过期",
+            403 if is_account_suspended(&body_text) => ACCOUNT_SUSPENDED_MESSAGE,
             403 => "权限不足，无法获取使用额度",
             429 => "请求过于频繁，已被限流",
             500..=599 => "服务器错误，AWS 服务暂时不可用",
@@ -384,6 +428,14 @@ pub(crate) async fn get_usage_limits(
     Ok(data)
 }
 
+/// 账号被暂停时的错误信息前缀
+const ACCOUNT_SUSPENDED_MESSAGE: &str = "账号已被暂停";
+
+/// 判断 403 响应体是否表示账号被暂停（如 `"reason": "TEMPORARILY_SUSPENDED"`）
+fn is_account_suspended(body: &str) -> bool {
+    body.to_ascii_uppercase().contains("SUSPENDED")
+}
+
 // ============================================================================
 // 多凭据 Token 管理器
 // ============================================================================
@@ -404,17 +456,36 @@ struct CredentialEntry {
     success_count: u64,
     /// 最后一次 API 调用时间（RFC3339 格式）
     last_used_at: Option<String>,
+    /// 额度用尽后的恢复时间（到达后自动重新启用）
+    exhausted_until: Option<DateTime<Utc>>,
+    /// 长期调用质量统计（随统计缓存持久化）
+    stats: CredentialStats,
 }
 
 /// 禁用原因
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum DisabledReason {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DisabledReason {
     /// Admin API 手动禁用
     Manual,
     /// 连续失败达到阈值后自动禁用
     TooManyFailures,
     /// 额度已用尽（如 MONTHLY_REQUEST_COUNT）
     QuotaExceeded,
+    /// 账号被暂停（账号信息刷新时检测到）
+    Suspended,
+}
+
+/// 凭据的禁用状态（集群模式下在实例间同步）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisabledState {
+    pub disabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<DisabledReason>,
+    /// 额度用尽凭据的恢复时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exhausted_until: Option<DateTime<Utc>>,
 }
 
 /// 统计数据持久化条目
@@ -422,6 +493,95 @@ enum DisabledReason {
 struct StatsEntry {
     success_count: u64,
     last_used_at: Option<String>,
+    /// 旧版本的统计缓存没有该字段
+    #[serde(default)]
+    quality: CredentialStats,
+}
+
+/// 延迟滑动平均的平滑系数（越大越偏向最近的请求）
+const LATENCY_EWMA_ALPHA: f64 = 0.2;
+
+/// 最近错误信息的最大保存长度（字符数）
+const LAST_ERROR_MAX_CHARS: usize = 500;
+
+/// 失败类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureClass {
+    /// 认证失败（401/403）
+    Auth,
+    /// 额度用尽（402）
+    Quota,
+    /// 上游临时错误（408/429/5xx、流式响应提前断开）
+    Transient,
+    /// 网络错误（连接失败、超时等，未拿到响应）
+    Network,
+    /// 请求本身的问题（400 等其他 4xx）
+    Client,
+}
+
+/// 按类别累计的失败次数
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FailureCounts {
+    pub auth: u64,
+    pub quota: u64,
+    pub transient: u64,
+    pub network: u64,
+    pub client: u64,
+}
+
+/// 凭据的长期调用质量统计（重启后保留）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CredentialStats {
+    /// 按类别累计的失败次数
+    pub failures: FailureCounts,
+    /// 成功请求的平均延迟（毫秒，指数滑动平均；流式请求计到首个数据块）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_latency_ms: Option<f64>,
+    /// 最近一次错误信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// 最近一次错误时间（RFC3339 格式）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error_at: Option<String>,
+}
+
+impl CredentialStats {
+    fn record_latency(&mut self, latency: StdDuration) {
+        let ms = latency.as_secs_f64() * 1000.0;
+        self.avg_latency_ms = Some(match self.avg_latency_ms {
+            Some(avg) => avg + (ms - avg) * LATENCY_EWMA_ALPHA,
+            None => ms,
+        });
+    }
+
+    fn record_error(&mut self, class: FailureClass, message: &str) {
+        let counter = match class {
+            FailureClass::Auth => &mut self.failures.auth,
+            FailureClass::Quota => &mut self.failures.quota,
+            FailureClass::Transient => &mut self.failures.transient,
+            FailureClass::Network => &mut self.failures.network,
+            FailureClass::Client => &mut self.failures.client,
+        };
+        *counter += 1;
+        self.last_error = Some(message.chars().take(LAST_ERROR_MAX_CHARS).collect());
+        self.last_error_at = Some(Utc::now().to_rfc3339());
+    }
+}
+
+/// 下个自然月 1 日 00:00 UTC（月度额度的默认重置时间）
+fn next_month_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    use chrono::{Datelike, TimeZone};
+
+    let (year, month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now)
 }
 
 // ============================================================================
@@ -459,6 +619,94 @@ pub struct CredentialEntrySnapshot {
     /// 代理 URL（用于前端展示）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub proxy_url: Option<String>,
+    /// 备用代理 URL
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub proxy_fallback_urls: Vec<String>,
+    /// 额度用尽后的恢复时间（RFC3339 格式，仅额度用尽禁用时存在）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exhausted_until: Option<String>,
+    /// 长期调用质量统计
+    pub stats: CredentialStats,
+    /// 备注
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// 账号负责人的联系方式
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner_contact: Option<String>,
+    /// 账号来源
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// 疑似重复的凭据分组（同一账号通过不同认证方式添加）
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    /// 匹配依据（accountId / email）
+    pub matched_by: &'static str,
+    /// 匹配的值（邮箱已转为小写）
+    pub value: String,
+    pub credential_ids: Vec<u64>,
+}
+
+/// 按账号 ID 与邮箱分组查找疑似重复的凭据
+///
+/// 账号 ID 相同的凭据归为一组；邮箱相同但已按账号 ID 归组的凭据不再重复报告
+fn group_duplicates(credentials: &[&KiroCredentials]) -> Vec<DuplicateGroup> {
+    let mut by_account: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+    let mut by_email: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+    for cred in credentials {
+        let Some(id) = cred.id else { continue };
+        if let Some(account_id) = cred.account_id.as_deref().filter(|s| !s.is_empty()) {
+            by_account.entry(account_id).or_default().push(id);
+        }
+        if let Some(email) = cred
+            .email
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            by_email.entry(email.to_lowercase()).or_default().push(id);
+        }
+    }
+
+    let account_groups: Vec<DuplicateGroup> = by_account
+        .into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|(value, credential_ids)| DuplicateGroup {
+            matched_by: "accountId",
+            value: value.to_string(),
+            credential_ids,
+        })
+        .collect();
+
+    let email_groups: Vec<DuplicateGroup> = by_email
+        .into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .filter(|(_, ids)| {
+            !account_groups
+                .iter()
+                .any(|g| ids.iter().all(|id| g.credential_ids.contains(id)))
+        })
+        .map(|(value, credential_ids)| DuplicateGroup {
+            matched_by: "email",
+            value,
+            credential_ids,
+        })
+        .collect();
+
+    account_groups.into_iter().chain(email_groups).collect()
+}
+
+/// 账号信息刷新结果
+#[derive(Debug, Clone)]
+pub struct AccountStatus {
+    /// 账号邮箱
+    pub email: Option<String>,
+    /// 账号 ID
+    pub user_id: Option<String>,
+    /// 账号是否已被暂停（已自动禁用该凭据）
+    pub suspended: bool,
 }
 
 /// 凭据管理器状态快照
@@ -480,8 +728,10 @@ pub struct ManagerSnapshot {
 /// 支持多个凭据的管理，实现固定优先级 + 故障转移策略
 /// 故障统计基于 API 调用结果，而非 Token 刷新结果
 pub struct MultiTokenManager {
-    config: Config,
-    proxy: Option<ProxyConfig>,
+    /// 应用配置（可通过 reload_config 热更新）
+    config: RwLock<Arc<Config>>,
+    /// 全局代理配置（可通过 reload_config 热更新）
+    proxy: RwLock<Option<ProxyConfig>>,
     /// 凭据条目列表
     entries: Mutex<Vec<CredentialEntry>>,
     /// 当前活动凭据 ID
@@ -490,10 +740,16 @@ pub struct MultiTokenManager {
     refresh_lock: TokioMutex<()>,
     /// 凭据文件路径（用于回写）
     credentials_path: Option<PathBuf>,
+    /// 数据目录（配置了 `dataDir` 时，启动时确定，不随热重载变化）
+    data_dir: Option<PathBuf>,
     /// 是否为多凭据格式（数组格式才回写）
     is_multiple_format: bool,
+    /// 凭据文件加密密钥（设置了 KIRO_CREDENTIALS_KEY 时回写为加密格式）
+    encryption_key: Option<DerivedKey>,
     /// 负载均衡模式（运行时可修改）
     load_balancing_mode: Mutex<String>,
+    /// 负载均衡模式 → 选择策略
+    strategies: RwLock<StrategyRegistry>,
     /// 最近一次统计持久化时间（用于 debounce）
     last_stats_save_at: Mutex<Option<Instant>>,
     /// 统计数据是否有未落盘更新
@@ -573,6 +829,8 @@ impl MultiTokenManager {
                     },
                     success_count: 0,
                     last_used_at: None,
+                    exhausted_until: None,
+                    stats: CredentialStats::default(),
                 }
             })
             .collect();
@@ -596,16 +854,34 @@ impl MultiTokenManager {
             .map(|e| e.id)
             .unwrap_or(0);
 
+        // 设置了加密口令时，明文凭据文件在首次回写（含下方的迁移）后即转为加密格式
+        let encryption_key = credentials_passphrase().map(|p| DerivedKey::new(&p));
+        let needs_encryption_migration = encryption_key.is_some()
+            && is_multiple_format
+            && credentials_path
+                .as_ref()
+                .is_some_and(|p| p.exists() && !CredentialsConfig::is_encrypted_file(p));
+
+        let data_dir = config.resolved_data_dir();
+        if let Some(dir) = &data_dir
+            && let Err(e) = std::fs::create_dir_all(dir)
+        {
+            tracing::warn!("创建数据目录失败 {}: {}", dir.display(), e);
+        }
+
         let load_balancing_mode = config.load_balancing_mode.clone();
         let manager = Self {
-            config,
-            proxy,
+            config: RwLock::new(Arc::new(config)),
+            proxy: RwLock::new(proxy),
             entries: Mutex::new(entries),
             current_id: Mutex::new(initial_id),
             refresh_lock: TokioMutex::new(()),
             credentials_path,
+            data_dir,
             is_multiple_format,
+            encryption_key,
             load_balancing_mode: Mutex::new(load_balancing_mode),
+            strategies: RwLock::new(StrategyRegistry::default()),
             last_stats_save_at: Mutex::new(None),
             stats_dirty: AtomicBool::new(false),
         };
@@ -617,6 +893,11 @@ impl MultiTokenManager {
             } else {
                 tracing::info!("已补全凭据 ID/machineId 并写回配置文件");
             }
+        } else if needs_encryption_migration {
+            match manager.persist_credentials() {
+                Ok(_) => tracing::info!("已将明文凭据文件迁移为加密格式"),
+                Err(e) => tracing::warn!("凭据文件加密迁移失败: {}", e),
+            }
         }
 
         // 加载持久化的统计数据（success_count, last_used_at）
@@ -625,9 +906,50 @@ impl MultiTokenManager {
         Ok(manager)
     }
 
-    /// 获取配置的引用
-    pub fn config(&self) -> &Config {
-        &self.config
+    /// 获取当前配置
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().clone()
+    }
+
+    /// 获取当前全局代理配置
+    pub fn proxy(&self) -> Option<ProxyConfig> {
+        self.proxy.read().clone()
+    }
+
+    /// 凭据当前应使用的代理：候选代理中第一个可用的（见 `proxy_health`）
+    pub fn proxy_for(&self, credentials: &KiroCredentials) -> Option<ProxyConfig> {
+        proxy_health::select(&credentials.proxy_candidates(self.proxy().as_ref()))
+    }
+
+    /// 各凭据的候选代理（凭据 ID → 按使用顺序的候选代理，None 表示直连）
+    pub fn proxy_candidates(&self) -> BTreeMap<u64, Vec<Option<ProxyConfig>>> {
+        let global = self.proxy();
+        self.entries
+            .lock()
+            .iter()
+            .map(|e| (e.id, e.credentials.proxy_candidates(global.as_ref())))
+            .collect()
+    }
+
+    /// 刷新 Token；经代理连接失败时标记该代理不可用，并换用下一个可用的候选代理重试
+    async fn refresh_token_with_failover(
+        &self,
+        credentials: &KiroCredentials,
+    ) -> anyhow::Result<KiroCredentials> {
+        loop {
+            let proxy = self.proxy_for(credentials);
+            let result = refresh_token(credentials, &self.config(), proxy.as_ref()).await;
+            match (&result, &proxy) {
+                (Err(e), Some(failed)) if proxy_health::is_connect_error(e) => {
+                    proxy_health::report_failure(failed, &e.to_string());
+                    // 没有其他可用的候选代理
+                    if self.proxy_for(credentials).as_ref() == Some(failed) {
+                        return result;
+                    }
+                }
+                _ => return result,
+            }
+        }
     }
 
     /// 获取当前活动凭据的克隆
@@ -641,6 +963,99 @@ impl MultiTokenManager {
             .unwrap_or_default()
     }
 
+    /// 获取所有凭据中的敏感值（accessToken / refreshToken / clientSecret / 代理密码），用于输出密钥扫描
+    pub fn secret_values(&self) -> Vec<String> {
+        let entries = self.entries.lock();
+        entries
+            .iter()
+            .flat_map(|e| {
+                let c = &e.credentials;
+                [
+                    c.access_token.clone(),
+                    c.refresh_token.clone(),
+                    c.client_secret.clone(),
+                    c.proxy_password.clone(),
+                ]
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// 设置额度用尽凭据的恢复时间（仅对因额度用尽而禁用的凭据生效）
+    pub fn set_exhausted_until(&self, id: u64, until: DateTime<Utc>) {
+        let mut entries = self.entries.lock();
+        if let Some(entry) = entries
+            .iter_mut()
+            .find(|e| e.id == id && e.disabled_reason == Some(DisabledReason::QuotaExceeded))
+        {
+            tracing::info!("凭据 #{} 额度将于 {} 重置", id, until.to_rfc3339());
+            entry.exhausted_until = Some(until);
+        }
+    }
+
+    /// 凭据当前的禁用状态
+    pub fn disabled_state(&self, id: u64) -> Option<DisabledState> {
+        let entries = self.entries.lock();
+        entries.iter().find(|e| e.id == id).map(|e| DisabledState {
+            disabled: e.disabled,
+            reason: e.disabled_reason,
+            exhausted_until: e.exhausted_until,
+        })
+    }
+
+    /// 应用其他实例同步来的禁用状态（集群模式）
+    ///
+    /// 不发布事件（避免再次同步回集群）、不写凭据文件；返回状态是否有变化
+    pub fn apply_disabled_state(&self, id: u64, state: &DisabledState) -> bool {
+        let current_disabled = {
+            let mut entries = self.entries.lock();
+            let Some(entry) = entries.iter_mut().find(|e| e.id == id) else {
+                return false;
+            };
+            if entry.disabled == state.disabled
+                && entry.disabled_reason == state.reason
+                && entry.exhausted_until == state.exhausted_until
+            {
+                return false;
+            }
+            entry.disabled = state.disabled;
+            entry.disabled_reason = state.reason;
+            entry.exhausted_until = state.exhausted_until;
+            entry.failure_count = match state.reason {
+                _ if !state.disabled => 0,
+                Some(DisabledReason::QuotaExceeded | DisabledReason::TooManyFailures) => {
+                    MAX_FAILURES_PER_CREDENTIAL
+                }
+                _ => entry.failure_count,
+            };
+            state.disabled && *self.current_id.lock() == id
+        };
+        if current_disabled {
+            self.select_highest_priority();
+        }
+        true
+    }
+
+    /// 重新启用额度重置时间已到的凭据
+    fn recover_exhausted_credentials(&self) {
+        let now = Utc::now();
+        let mut entries = self.entries.lock();
+        for entry in entries.iter_mut() {
+            if entry.disabled_reason == Some(DisabledReason::QuotaExceeded)
+                && entry.exhausted_until.is_some_and(|until| until <= now)
+            {
+                tracing::info!("凭据 #{} 额度已重置，重新启用", entry.id);
+                events::publish(AppEvent::CredentialEnabled {
+                    credential_id: entry.id,
+                });
+                entry.disabled = false;
+                entry.disabled_reason = None;
+                entry.failure_count = 0;
+                entry.exhausted_until = None;
+            }
+        }
+    }
+
     /// 获取凭据总数
     pub fn total_count(&self) -> usize {
         self.entries.lock().len()
@@ -655,10 +1070,21 @@ impl MultiTokenManager {
     ///
     /// - priority 模式：选择优先级最高（priority 最小）的可用凭据
     /// - balanced 模式：轮询选择可用凭据
+    /// - sticky 模式：按会话亲和键哈希选择凭据（无亲和键时同 priority）
     ///
     /// # 参数
     /// - `model`: 可选的模型名称，用于过滤支持该模型的凭据（如 opus 模型需要付费订阅）
-    fn select_next_credential(&self, model: Option<&str>) -> Option<(u64, KiroCredentials)> {
+    /// - `affinity`: 可选的会话亲和键（如 conversationId）
+    /// - `exclude`: 本次请求中已失败、应尽量避开的凭据；排除后无候选时忽略该条件
+    ///
+    /// 处于 `with_credential_scope` 作用域内时只考虑范围内的凭据；范围内没有可用凭据且
+    /// 回退方式为 `pool` 时改为考虑全部凭据
+    fn select_next_credential(
+        &self,
+        model: Option<&str>,
+        affinity: Option<&str>,
+        exclude: &[u64],
+    ) -> Option<(u64, KiroCredentials)> {
         let entries = self.entries.lock();
 
         // 检查是否是 opus 模型
@@ -667,43 +1093,114 @@ impl MultiTokenManager {
             .unwrap_or(false);
 
         // 过滤可用凭据
-        let available: Vec<_> = entries
-            .iter()
-            .filter(|e| {
-                if e.disabled {
-                    return false;
-                }
-                // 如果是 opus 模型，需要检查订阅等级
-                if is_opus && !e.credentials.supports_opus() {
-                    return false;
-                }
-                true
-            })
-            .collect();
+        let usable = |e: &&CredentialEntry, scoped: bool| {
+            if e.disabled || (scoped && !in_credential_scope(e.id)) {
+                return false;
+            }
+            // 如果是 opus 模型，需要检查订阅等级
+            if is_opus && !e.credentials.supports_opus() {
+                return false;
+            }
+            true
+        };
+        let mut available: Vec<_> = entries.iter().filter(|e| usable(e, true)).collect();
+
+        if available.is_empty() && scope_falls_back_to_pool() {
+            available = entries.iter().filter(|e| usable(e, false)).collect();
+            if !available.is_empty() {
+                tracing::info!("API Key 绑定的凭据均不可用，改为从全部凭据中选择");
+            }
+        }
 
         if available.is_empty() {
             return None;
         }
 
-        let mode = self.load_balancing_mode.lock().clone();
-        let mode = mode.as_str();
+        if available.iter().any(|e| !exclude.contains(&e.id)) {
+            available.retain(|e| !exclude.contains(&e.id));
+        }
 
-        match mode {
-            "balanced" => {
-                // Least-Used 策略：选择成功次数最少的凭据
-                // 平局时按优先级排序（数字越小优先级越高）
-                let entry = available
-                    .iter()
-                    .min_by_key(|e| (e.success_count, e.credentials.priority))?;
+        let candidates: Vec<Candidate<'_>> = available
+            .iter()
+            .map(|e| Candidate {
+                id: e.id,
+                priority: e.credentials.priority,
+                weight: e.credentials.weight.unwrap_or(1),
+                success_count: e.success_count,
+                last_used_at: e.last_used_at.as_deref(),
+            })
+            .collect();
+        let ctx = SelectionContext { affinity };
+        let id = self.strategy().select(&candidates, &ctx)?;
+        available
+            .iter()
+            .find(|e| e.id == id)
+            .map(|e| (e.id, e.credentials.clone()))
+    }
 
-                Some((entry.id, entry.credentials.clone()))
-            }
-            _ => {
-                // priority 模式（默认）：选择优先级最高的
-                let entry = available.iter().min_by_key(|e| e.credentials.priority)?;
-                Some((entry.id, entry.credentials.clone()))
+    /// 按当前负载均衡状态预测下一次请求会选中的凭据
+    ///
+    /// 只读：不更新 current_id、不刷新 Token，也不触发全部禁用后的自愈；无可用凭据时返回 None
+    pub fn preview_credential(&self, model: Option<&str>, affinity: Option<&str>) -> Option<u64> {
+        if !self.strategy().select_per_request() {
+            let entries = self.entries.lock();
+            let current_id = *self.current_id.lock();
+            if entries
+                .iter()
+                .any(|e| e.id == current_id && !e.disabled && in_credential_scope(e.id))
+            {
+                return Some(current_id);
             }
         }
+        self.select_next_credential(model, affinity, &[])
+            .map(|(id, _)| id)
+    }
+
+    /// 当前负载均衡模式对应的选择策略（未注册的模式按 priority 处理）
+    fn strategy(&self) -> Arc<dyn SelectionStrategy> {
+        let mode = self.load_balancing_mode.lock().clone();
+        self.strategies
+            .read()
+            .get(&mode)
+            .unwrap_or_else(|| Arc::new(PriorityStrategy))
+    }
+
+    /// 注册自定义选择策略，注册后即可通过同名 `loadBalancingMode` 使用
+    #[allow(dead_code)]
+    pub fn register_strategy(&self, strategy: Arc<dyn SelectionStrategy>) {
+        self.strategies.write().register(strategy);
+    }
+
+    /// 校验负载均衡模式是否已注册
+    pub fn validate_load_balancing_mode(&self, mode: &str) -> anyhow::Result<()> {
+        let strategies = self.strategies.read();
+        if !strategies.contains(mode) {
+            anyhow::bail!(
+                "无效的负载均衡模式: {}（可选: {}）",
+                mode,
+                strategies.modes().join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// 在限定凭据范围的作用域内执行 `fut`（租户绑定凭据时使用）
+    ///
+    /// 作用域内按负载均衡选择凭据时只考虑 `ids` 中的凭据，`ids` 中的凭据均不可用时按 `fallback`
+    /// 返回错误或改用全部凭据；固定凭据（请求头、模型路由）不在 `ids` 中时总是返回错误
+    pub async fn with_credential_scope<F: Future>(
+        ids: &[u64],
+        fallback: CredentialFallback,
+        fut: F,
+    ) -> F::Output {
+        if ids.is_empty() {
+            return fut.await;
+        }
+        let scope = CredentialScope {
+            ids: Arc::from(ids),
+            fallback,
+        };
+        CREDENTIAL_SCOPE.scope(scope, fut).await
     }
 
     /// 获取 API 调用上下文
@@ -717,6 +1214,21 @@ impl MultiTokenManager {
     /// # 参数
     /// - `model`: 可选的模型名称，用于过滤支持该模型的凭据（如 opus 模型需要付费订阅）
     pub async fn acquire_context(&self, model: Option<&str>) -> anyhow::Result<CallContext> {
+        self.acquire_context_excluding(model, None, &[]).await
+    }
+
+    /// 获取 API 调用上下文，尽量避开 `exclude` 中的凭据（用于瞬态错误后换凭据重试）
+    ///
+    /// `affinity` 为会话亲和键，用于 sticky 负载均衡模式
+    ///
+    /// 所有可用凭据都在 `exclude` 中时仍会从中选择，以便在重试次数内继续尝试
+    pub async fn acquire_context_excluding(
+        &self,
+        model: Option<&str>,
+        affinity: Option<&str>,
+        exclude: &[u64],
+    ) -> anyhow::Result<CallContext> {
+        self.recover_exhausted_credentials();
         let total = self.total_count();
         let mut tried_count = 0;
 
@@ -730,18 +1242,23 @@ impl MultiTokenManager {
             }
 
             let (id, credentials) = {
-                let is_balanced = self.load_balancing_mode.lock().as_str() == "balanced";
+                let select_per_request = self.strategy().select_per_request();
 
-                // balanced 模式：每次请求都轮询选择，不固定 current_id
+                // balanced / sticky 等模式：每次请求都重新选择，不固定 current_id
                 // priority 模式：优先使用 current_id 指向的凭据
-                let current_hit = if is_balanced {
+                let current_hit = if select_per_request {
                     None
                 } else {
                     let entries = self.entries.lock();
                     let current_id = *self.current_id.lock();
                     entries
                         .iter()
-                        .find(|e| e.id == current_id && !e.disabled)
+                        .find(|e| {
+                            e.id == current_id
+                                && !e.disabled
+                                && !exclude.contains(&e.id)
+                                && in_credential_scope(e.id)
+                        })
                         .map(|e| (e.id, e.credentials.clone()))
                 };
 
@@ -749,7 +1266,7 @@ impl MultiTokenManager {
                     hit
                 } else {
                     // 当前凭据不可用或 balanced 模式，根据负载均衡策略选择
-                    let mut best = self.select_next_credential(model);
+                    let mut best = self.select_next_credential(model, affinity, exclude);
 
                     // 没有可用凭据：如果是"自动禁用导致全灭"，做一次类似重启的自愈
                     if best.is_none() {
@@ -762,13 +1279,16 @@ impl MultiTokenManager {
                             );
                             for e in entries.iter_mut() {
                                 if e.disabled_reason == Some(DisabledReason::TooManyFailures) {
+                                    events::publish(AppEvent::CredentialEnabled {
+                                        credential_id: e.id,
+                                    });
                                     e.disabled = false;
                                     e.disabled_reason = None;
                                     e.failure_count = 0;
                                 }
                             }
                             drop(entries);
-                            best = self.select_next_credential(model);
+                            best = self.select_next_credential(model, affinity, exclude);
                         }
                     }
 
@@ -783,6 +1303,9 @@ impl MultiTokenManager {
                         // 因为 available_count() 会尝试获取 entries 锁，
                         // 而此时我们已经持有该锁，会导致死锁
                         let available = entries.iter().filter(|e| !e.disabled).count();
+                        if let Ok(ids) = CREDENTIAL_SCOPE.try_with(|scope| scope.ids.clone()) {
+                            anyhow::bail!("API Key 绑定的凭据 {:?} 均不可用", ids);
+                        }
                         anyhow::bail!("所有凭据均已禁用（{}/{}）", available, total);
                     }
                 }
@@ -804,6 +1327,29 @@ impl MultiTokenManager {
         }
     }
 
+    /// 获取指定凭据的 API 调用上下文（模型路由固定凭据时使用）
+    ///
+    /// 不经过负载均衡，也不做故障转移：凭据不存在、已禁用、不在 `with_credential_scope` 的范围内
+    /// 或 Token 刷新失败时直接返回错误
+    pub async fn acquire_context_for(&self, id: u64) -> anyhow::Result<CallContext> {
+        if !in_credential_scope(id) {
+            anyhow::bail!("凭据 #{} 不在 API Key 绑定的凭据范围内", id);
+        }
+        self.recover_exhausted_credentials();
+        let credentials = {
+            let entries = self.entries.lock();
+            let entry = entries
+                .iter()
+                .find(|e| e.id == id)
+                .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
+            if entry.disabled {
+                anyhow::bail!("凭据 #{} 已禁用", id);
+            }
+            entry.credentials.clone()
+        };
+        self.try_ensure_token(id, &credentials).await
+    }
+
     /// 切换到下一个优先级最高的可用凭据（内部方法）
     fn switch_to_next_by_priority(&self) {
         let entries = self.entries.lock();
@@ -881,9 +1427,7 @@ impl MultiTokenManager {
 
             if is_token_expired(&current_creds) || is_token_expiring_soon(&current_creds) {
                 // 确实需要刷新
-                let effective_proxy = current_creds.effective_proxy(self.proxy.as_ref());
-                let new_creds =
-                    refresh_token(&current_creds, &self.config, effective_proxy.as_ref()).await?;
+                let new_creds = self.refresh_token_with_failover(&current_creds).await?;
 
                 if is_token_expired(&new_creds) {
                     anyhow::bail!("刷新后的 Token 仍然无效或已过期");
@@ -948,23 +1492,18 @@ impl MultiTokenManager {
         };
 
         // 收集所有凭据
-        let credentials: Vec<KiroCredentials> = {
-            let entries = self.entries.lock();
-            entries
-                .iter()
-                .map(|e| {
-                    let mut cred = e.credentials.clone();
-                    cred.canonicalize_auth_method();
-                    // 同步 disabled 状态到凭据对象
-                    cred.disabled = e.disabled;
-                    cred
-                })
-                .collect()
+        let credentials = self.export_credentials();
+
+        // 序列化为 pretty JSON（配置了加密密钥时写入加密信封）
+        let json = match &self.encryption_key {
+            Some(key) => {
+                let plaintext = serde_json::to_vec(&credentials).context("序列化凭据失败")?;
+                let envelope = key.encrypt(&plaintext)?;
+                serde_json::to_string_pretty(&envelope).context("序列化加密凭据失败")?
+            }
+            None => serde_json::to_string_pretty(&credentials).context("序列化凭据失败")?,
         };
 
-        // 序列化为 pretty JSON
-        let json = serde_json::to_string_pretty(&credentials).context("序列化凭据失败")?;
-
         // 写入文件（在 Tokio runtime 内使用 block_in_place 避免阻塞 worker）
         if tokio::runtime::Handle::try_current().is_ok() {
             tokio::task::block_in_place(|| std::fs::write(path, &json))
@@ -977,8 +1516,26 @@ impl MultiTokenManager {
         Ok(true)
     }
 
-    /// 获取缓存目录（凭据文件所在目录）
+    /// 导出所有凭据（与凭据文件的多凭据格式一致）
+    pub fn export_credentials(&self) -> Vec<KiroCredentials> {
+        let entries = self.entries.lock();
+        entries
+            .iter()
+            .map(|e| {
+                let mut cred = e.credentials.clone();
+                cred.canonicalize_auth_method();
+                // 同步 disabled 状态到凭据对象
+                cred.disabled = e.disabled;
+                cred
+            })
+            .collect()
+    }
+
+    /// 获取数据目录（配置的 `dataDir`，未配置时为凭据文件所在目录）
     pub fn cache_dir(&self) -> Option<PathBuf> {
+        if let Some(dir) = &self.data_dir {
+            return Some(dir.clone());
+        }
         self.credentials_path
             .as_ref()
             .and_then(|p| p.parent().map(|d| d.to_path_buf()))
@@ -1005,6 +1562,7 @@ impl MultiTokenManager {
             Ok(s) => s,
             Err(e) => {
                 tracing::warn!("解析统计缓存失败，将忽略: {}", e);
+                crate::recovery::quarantine(&path, e);
                 return;
             }
         };
@@ -1014,6 +1572,7 @@ impl MultiTokenManager {
             if let Some(s) = stats.get(&entry.id.to_string()) {
                 entry.success_count = s.success_count;
                 entry.last_used_at = s.last_used_at.clone();
+                entry.stats = s.quality.clone();
             }
         }
         *self.last_stats_save_at.lock() = Some(Instant::now());
@@ -1027,6 +1586,10 @@ impl MultiTokenManager {
             Some(p) => p,
             None => return,
         };
+        // 磁盘空间不足时暂不写入，保留 dirty 标记等空间恢复后再写
+        if !crate::disk_monitor::optional_writes_allowed() {
+            return;
+        }
 
         let stats: HashMap<String, StatsEntry> = {
             let entries = self.entries.lock();
@@ -1038,6 +1601,7 @@ impl MultiTokenManager {
                         StatsEntry {
                             success_count: e.success_count,
                             last_used_at: e.last_used_at.clone(),
+                            quality: e.stats.clone(),
                         },
                     )
                 })
@@ -1097,6 +1661,30 @@ impl MultiTokenManager {
         self.save_stats_debounced();
     }
 
+    /// 记录指定凭据一次成功请求的延迟
+    pub fn record_latency(&self, id: u64, latency: StdDuration) {
+        {
+            let mut entries = self.entries.lock();
+            if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+                entry.stats.record_latency(latency);
+            }
+        }
+        self.save_stats_debounced();
+    }
+
+    /// 记录指定凭据的一次失败（按类别计数并保存错误信息）
+    ///
+    /// 仅用于长期统计，不影响禁用判断（禁用由 `report_failure` 等方法负责）
+    pub fn record_error(&self, id: u64, class: FailureClass, message: &str) {
+        {
+            let mut entries = self.entries.lock();
+            if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+                entry.stats.record_error(class, message);
+            }
+        }
+        self.save_stats_debounced();
+    }
+
     /// 报告指定凭据 API 调用失败
     ///
     /// 增加失败计数，达到阈值时禁用凭据并切换到优先级最高的可用凭据
@@ -1129,6 +1717,10 @@ impl MultiTokenManager {
                 entry.disabled = true;
                 entry.disabled_reason = Some(DisabledReason::TooManyFailures);
                 tracing::error!("凭据 #{} 已连续失败 {} 次，已被禁用", id, failure_count);
+                events::publish(AppEvent::CredentialDisabled {
+                    credential_id: id,
+                    reason: DisabledReason::TooManyFailures,
+                });
 
                 // 切换到优先级最高的可用凭据
                 if let Some(next) = entries
@@ -1156,7 +1748,8 @@ impl MultiTokenManager {
     /// 报告指定凭据额度已用尽
     ///
     /// 用于处理 402 Payment Required 且 reason 为 `MONTHLY_REQUEST_COUNT` 的场景：
-    /// - 立即禁用该凭据（不等待连续失败阈值）
+    /// - 立即禁用该凭据（不等待连续失败阈值），直到额度重置时间（默认下个自然月 1 日 UTC，
+    ///   可通过 `set_exhausted_until` 用余额接口返回的重置时间修正）
     /// - 切换到下一个可用凭据继续重试
     /// - 返回是否还有可用凭据
     pub fn report_quota_exhausted(&self, id: u64) -> bool {
@@ -1173,13 +1766,19 @@ impl MultiTokenManager {
                 return entries.iter().any(|e| !e.disabled);
             }
 
+            let now = Utc::now();
             entry.disabled = true;
             entry.disabled_reason = Some(DisabledReason::QuotaExceeded);
-            entry.last_used_at = Some(Utc::now().to_rfc3339());
+            entry.exhausted_until = Some(next_month_start(now));
+            entry.last_used_at = Some(now.to_rfc3339());
             // 设为阈值，便于在管理面板中直观看到该凭据已不可用
             entry.failure_count = MAX_FAILURES_PER_CREDENTIAL;
 
             tracing::error!("凭据 #{} 额度已用尽（MONTHLY_REQUEST_COUNT），已被禁用", id);
+            events::publish(AppEvent::CredentialDisabled {
+                credential_id: id,
+                reason: DisabledReason::QuotaExceeded,
+            });
 
             // 切换到优先级最高的可用凭据
             if let Some(next) = entries
@@ -1232,10 +1831,10 @@ impl MultiTokenManager {
     /// 获取使用额度信息
     pub async fn get_usage_limits(&self) -> anyhow::Result<UsageLimitsResponse> {
         let ctx = self.acquire_context(None).await?;
-        let effective_proxy = ctx.credentials.effective_proxy(self.proxy.as_ref());
+        let effective_proxy = self.proxy_for(&ctx.credentials);
         get_usage_limits(
             &ctx.credentials,
-            &self.config,
+            &self.config(),
             &ctx.token,
             effective_proxy.as_ref(),
         )
@@ -1275,6 +1874,12 @@ impl MultiTokenManager {
                     last_used_at: e.last_used_at.clone(),
                     has_proxy: e.credentials.proxy_url.is_some(),
                     proxy_url: e.credentials.proxy_url.clone(),
+                    proxy_fallback_urls: e.credentials.proxy_fallback_urls.clone(),
+                    exhausted_until: e.exhausted_until.map(|t| t.to_rfc3339()),
+                    stats: e.stats.clone(),
+                    notes: e.credentials.notes.clone(),
+                    owner_contact: e.credentials.owner_contact.clone(),
+                    source: e.credentials.source.clone(),
                 })
                 .collect(),
             current_id,
@@ -1296,8 +1901,14 @@ impl MultiTokenManager {
                 // 启用时重置失败计数
                 entry.failure_count = 0;
                 entry.disabled_reason = None;
+                entry.exhausted_until = None;
+                events::publish(AppEvent::CredentialEnabled { credential_id: id });
             } else {
                 entry.disabled_reason = Some(DisabledReason::Manual);
+                events::publish(AppEvent::CredentialDisabled {
+                    credential_id: id,
+                    reason: DisabledReason::Manual,
+                });
             }
         }
         // 持久化更改
@@ -1325,26 +1936,61 @@ impl MultiTokenManager {
         Ok(())
     }
 
-    /// 重置凭据失败计数并重新启用（Admin API）
-    pub fn reset_and_enable(&self, id: u64) -> anyhow::Result<()> {
+    /// 更新凭据的备注、负责人联系方式与来源（Admin API）
+    ///
+    /// 为 None 的字段保持不变，空字符串表示清除
+    pub fn update_notes(
+        &self,
+        id: u64,
+        notes: Option<String>,
+        owner_contact: Option<String>,
+        source: Option<String>,
+    ) -> anyhow::Result<()> {
         {
             let mut entries = self.entries.lock();
             let entry = entries
                 .iter_mut()
                 .find(|e| e.id == id)
                 .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
-            entry.failure_count = 0;
-            entry.disabled = false;
-            entry.disabled_reason = None;
+            let credentials = &mut entry.credentials;
+            let fields = [
+                (&mut credentials.notes, notes),
+                (&mut credentials.owner_contact, owner_contact),
+                (&mut credentials.source, source),
+            ];
+            for (field, value) in fields {
+                if let Some(value) = value {
+                    let value = value.trim();
+                    *field = (!value.is_empty()).then(|| value.to_string());
+                }
+            }
         }
-        // 持久化更改
         self.persist_credentials()?;
         Ok(())
     }
 
-    /// 获取指定凭据的使用额度（Admin API）
-    pub async fn get_usage_limits_for(&self, id: u64) -> anyhow::Result<UsageLimitsResponse> {
-        let credentials = {
+    /// 重置凭据失败计数并重新启用（Admin API）
+    pub fn reset_and_enable(&self, id: u64) -> anyhow::Result<()> {
+        {
+            let mut entries = self.entries.lock();
+            let entry = entries
+                .iter_mut()
+                .find(|e| e.id == id)
+                .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
+            entry.failure_count = 0;
+            entry.disabled = false;
+            entry.disabled_reason = None;
+            entry.exhausted_until = None;
+        }
+        events::publish(AppEvent::CredentialEnabled { credential_id: id });
+        // 持久化更改
+        self.persist_credentials()?;
+        Ok(())
+    }
+
+    /// 获取指定凭据的使用额度（Admin API）
+    pub async fn get_usage_limits_for(&self, id: u64) -> anyhow::Result<UsageLimitsResponse> {
+        let credentials = {
             let entries = self.entries.lock();
             entries
                 .iter()
@@ -1368,9 +2014,7 @@ impl MultiTokenManager {
             };
 
             if is_token_expired(&current_creds) || is_token_expiring_soon(&current_creds) {
-                let effective_proxy = current_creds.effective_proxy(self.proxy.as_ref());
-                let new_creds =
-                    refresh_token(&current_creds, &self.config, effective_proxy.as_ref()).await?;
+                let new_creds = self.refresh_token_with_failover(&current_creds).await?;
                 {
                     let mut entries = self.entries.lock();
                     if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
@@ -1404,38 +2048,60 @@ impl MultiTokenManager {
                 .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?
         };
 
-        let effective_proxy = credentials.effective_proxy(self.proxy.as_ref());
-        let usage_limits = get_usage_limits(&credentials, &self.config, &token, effective_proxy.as_ref()).await?;
-
-        // 更新订阅等级到凭据（仅在发生变化时持久化）
-        if let Some(subscription_title) = usage_limits.subscription_title() {
-            let changed = {
-                let mut entries = self.entries.lock();
-                if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
-                    let old_title = entry.credentials.subscription_title.clone();
-                    if old_title.as_deref() != Some(subscription_title) {
-                        entry.credentials.subscription_title =
-                            Some(subscription_title.to_string());
+        let effective_proxy = self.proxy_for(&credentials);
+        let usage_limits = get_usage_limits(
+            &credentials,
+            &self.config(),
+            &token,
+            effective_proxy.as_ref(),
+        )
+        .await?;
+
+        // 更新订阅等级、邮箱与账号 ID 到凭据（仅在发生变化时持久化）
+        let changed = {
+            let mut entries = self.entries.lock();
+            match entries.iter_mut().find(|e| e.id == id) {
+                Some(entry) => {
+                    let mut changed = false;
+                    if let Some(subscription_title) = usage_limits.subscription_title()
+                        && entry.credentials.subscription_title.as_deref()
+                            != Some(subscription_title)
+                    {
                         tracing::info!(
                             "凭据 #{} 订阅等级已更新: {:?} -> {}",
                             id,
-                            old_title,
+                            entry.credentials.subscription_title,
                             subscription_title
                         );
-                        true
-                    } else {
-                        false
+                        entry.credentials.subscription_title = Some(subscription_title.to_string());
+                        changed = true;
                     }
-                } else {
-                    false
-                }
-            };
-
-            if changed {
-                if let Err(e) = self.persist_credentials() {
-                    tracing::warn!("订阅等级更新后持久化失败（不影响本次请求）: {}", e);
+                    if let Some(email) = usage_limits.email()
+                        && entry.credentials.email.as_deref() != Some(email)
+                    {
+                        tracing::info!(
+                            "凭据 #{} 邮箱已更新: {:?} -> {}",
+                            id,
+                            entry.credentials.email,
+                            email
+                        );
+                        entry.credentials.email = Some(email.to_string());
+                        changed = true;
+                    }
+                    if let Some(account_id) = usage_limits.account_id()
+                        && entry.credentials.account_id.as_deref() != Some(account_id)
+                    {
+                        entry.credentials.account_id = Some(account_id.to_string());
+                        changed = true;
+                    }
+                    changed
                 }
+                None => false,
             }
+        };
+
+        if changed && let Err(e) = self.persist_credentials() {
+            tracing::warn!("账号信息更新后持久化失败（不影响本次请求）: {}", e);
         }
 
         Ok(usage_limits)
@@ -1481,9 +2147,9 @@ impl MultiTokenManager {
         }
 
         // 3. 尝试刷新 Token 验证凭据有效性
-        let effective_proxy = new_cred.effective_proxy(self.proxy.as_ref());
+        let effective_proxy = self.proxy_for(&new_cred);
         let mut validated_cred =
-            refresh_token(&new_cred, &self.config, effective_proxy.as_ref()).await?;
+            refresh_token(&new_cred, &self.config(), effective_proxy.as_ref()).await?;
 
         // 4. 分配新 ID
         let new_id = {
@@ -1508,9 +2174,12 @@ impl MultiTokenManager {
         validated_cred.api_region = new_cred.api_region;
         validated_cred.machine_id = new_cred.machine_id;
         validated_cred.email = new_cred.email;
+        validated_cred.account_id = new_cred.account_id;
+        validated_cred.weight = new_cred.weight;
         validated_cred.proxy_url = new_cred.proxy_url;
         validated_cred.proxy_username = new_cred.proxy_username;
         validated_cred.proxy_password = new_cred.proxy_password;
+        validated_cred.proxy_fallback_urls = new_cred.proxy_fallback_urls;
 
         {
             let mut entries = self.entries.lock();
@@ -1522,6 +2191,8 @@ impl MultiTokenManager {
                 disabled_reason: None,
                 success_count: 0,
                 last_used_at: None,
+                exhausted_until: None,
+                stats: CredentialStats::default(),
             });
         }
 
@@ -1529,9 +2200,128 @@ impl MultiTokenManager {
         self.persist_credentials()?;
 
         tracing::info!("成功添加凭据 #{}", new_id);
+        events::publish(AppEvent::CredentialAdded {
+            credential_id: new_id,
+        });
         Ok(new_id)
     }
 
+    /// 刷新凭据的账号信息（Admin API / 后台任务）
+    ///
+    /// 重新查询账号信息并更新邮箱、订阅等级；检测到账号被暂停时自动禁用该凭据，
+    /// 避免继续被调度到 API 请求中
+    pub async fn refresh_account(&self, id: u64) -> anyhow::Result<AccountStatus> {
+        match self.get_usage_limits_for(id).await {
+            Ok(usage_limits) => Ok(AccountStatus {
+                email: usage_limits.email().map(String::from),
+                user_id: usage_limits.account_id().map(String::from),
+                suspended: false,
+            }),
+            Err(e) if e.to_string().starts_with(ACCOUNT_SUSPENDED_MESSAGE) => {
+                let email = {
+                    let mut entries = self.entries.lock();
+                    let entry = entries
+                        .iter_mut()
+                        .find(|e| e.id == id)
+                        .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
+                    if entry.disabled_reason != Some(DisabledReason::Suspended) {
+                        tracing::error!("凭据 #{} 账号已被暂停，已自动禁用: {}", id, e);
+                        events::publish(AppEvent::CredentialDisabled {
+                            credential_id: id,
+                            reason: DisabledReason::Suspended,
+                        });
+                    }
+                    entry.disabled = true;
+                    entry.disabled_reason = Some(DisabledReason::Suspended);
+                    entry.credentials.email.clone()
+                };
+                self.select_highest_priority();
+                if let Err(e) = self.persist_credentials() {
+                    tracing::warn!("禁用已暂停账号后持久化失败: {}", e);
+                }
+                Ok(AccountStatus {
+                    email,
+                    user_id: None,
+                    suspended: true,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 刷新所有未禁用凭据的账号信息（后台任务）
+    pub async fn refresh_all_accounts(&self) {
+        let ids: Vec<u64> = {
+            let entries = self.entries.lock();
+            entries
+                .iter()
+                .filter(|e| !e.disabled)
+                .map(|e| e.id)
+                .collect()
+        };
+        for id in ids {
+            if let Err(e) = self.refresh_account(id).await {
+                tracing::warn!("凭据 #{} 账号信息刷新失败: {}", id, e);
+            }
+        }
+    }
+
+    /// 列出疑似重复的凭据分组（Admin API）
+    pub fn duplicate_groups(&self) -> Vec<DuplicateGroup> {
+        let entries = self.entries.lock();
+        let credentials: Vec<&KiroCredentials> = entries.iter().map(|e| &e.credentials).collect();
+        group_duplicates(&credentials)
+    }
+
+    /// 查找与指定凭据疑似重复的其他凭据 ID
+    pub fn find_duplicates_of(&self, id: u64) -> Vec<u64> {
+        let mut ids: Vec<u64> = self
+            .duplicate_groups()
+            .into_iter()
+            .filter(|g| g.credential_ids.contains(&id))
+            .flat_map(|g| g.credential_ids)
+            .filter(|&other| other != id)
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// 启动后台账号信息刷新任务
+    ///
+    /// 每轮按配置的 `accountRefreshIntervalSecs` 等待后刷新所有未禁用凭据；
+    /// 间隔在每轮重新读取，热重载配置后无需重启即可启用、停用或调整
+    pub fn spawn_account_refresh(self: &Arc<Self>) {
+        /// 未启用时重新检查配置的间隔
+        const DISABLED_RECHECK: StdDuration = StdDuration::from_secs(60);
+
+        let manager = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                let interval = match manager.upgrade() {
+                    Some(manager) => manager.config().account_refresh_interval_secs,
+                    None => return,
+                };
+                let Some(secs) = interval.filter(|&secs| secs > 0) else {
+                    tokio::time::sleep(DISABLED_RECHECK).await;
+                    continue;
+                };
+                tokio::time::sleep(StdDuration::from_secs(secs)).await;
+
+                let Some(manager) = manager.upgrade() else {
+                    return;
+                };
+                // 集群模式下只由 leader 实例刷新，结果通过凭据状态同步给其他实例
+                if manager.config().account_refresh_interval_secs.is_some()
+                    && crate::cluster::is_leader()
+                {
+                    tracing::debug!("开始后台刷新账号信息");
+                    manager.refresh_all_accounts().await;
+                }
+            }
+        });
+    }
+
     /// 删除凭据（Admin API）
     ///
     /// # 前置条件
@@ -1595,6 +2385,7 @@ impl MultiTokenManager {
         self.save_stats();
 
         tracing::info!("已删除凭据 #{}", id);
+        events::publish(AppEvent::CredentialDeleted { credential_id: id });
         Ok(())
     }
 
@@ -1606,7 +2397,7 @@ impl MultiTokenManager {
     fn persist_load_balancing_mode(&self, mode: &str) -> anyhow::Result<()> {
         use anyhow::Context;
 
-        let config_path = match self.config.config_path() {
+        let config_path = match self.config().config_path() {
             Some(path) => path.to_path_buf(),
             None => {
                 tracing::warn!("配置文件路径未知，负载均衡模式仅在当前进程生效: {}", mode);
@@ -1614,7 +2405,7 @@ impl MultiTokenManager {
             }
         };
 
-        let mut config = Config::load(&config_path)
+        let mut config = Config::load_file(&config_path)
             .with_context(|| format!("重新加载配置失败: {}", config_path.display()))?;
         config.load_balancing_mode = mode.to_string();
         config
@@ -1624,12 +2415,35 @@ impl MultiTokenManager {
         Ok(())
     }
 
+    /// 热更新配置（Admin API）
+    ///
+    /// 替换当前配置与全局代理，并应用新的负载均衡模式；之后的请求即使用新的
+    /// region、代理等设置。返回只能重启后生效的已变更配置项
+    pub fn reload_config(&self, mut config: Config) -> anyhow::Result<Vec<&'static str>> {
+        // 命令行参数不在配置文件中，沿用启动时的值
+        config.data_dir_override = self.config().data_dir_override.clone();
+        let mode = config.load_balancing_mode.clone();
+        self.validate_load_balancing_mode(&mode)?;
+
+        let restart_required = self.config().restart_required_changes(&config);
+        *self.proxy.write() = ProxyConfig::from_config(&config);
+        *self.config.write() = Arc::new(config);
+        *self.load_balancing_mode.lock() = mode;
+
+        tracing::info!("配置已重新加载");
+        if !restart_required.is_empty() {
+            tracing::warn!("以下配置项需重启后生效: {}", restart_required.join(", "));
+        }
+        events::publish(AppEvent::ConfigReloaded {
+            requires_restart: restart_required.iter().map(|s| s.to_string()).collect(),
+        });
+        Ok(restart_required)
+    }
+
     /// 设置负载均衡模式（Admin API）
     pub fn set_load_balancing_mode(&self, mode: String) -> anyhow::Result<()> {
         // 验证模式值
-        if mode != "priority" && mode != "balanced" {
-            anyhow::bail!("无效的负载均衡模式: {}", mode);
-        }
+        self.validate_load_balancing_mode(&mode)?;
 
         let previous_mode = self.get_load_balancing_mode();
         if previous_mode == mode {
@@ -1646,14 +2460,183 @@ impl MultiTokenManager {
         tracing::info!("负载均衡模式已设置为: {}", mode);
         Ok(())
     }
+
+    /// 获取模型路由表（Admin API）
+    pub fn model_routes(&self) -> BTreeMap<String, ModelRoute> {
+        self.config().model_aliases.clone()
+    }
+
+    /// 新增或替换模型路由（Admin API）
+    pub fn set_model_route(&self, alias: String, route: ModelRoute) -> anyhow::Result<()> {
+        self.validate_model_route(&alias, &route)?;
+
+        let mut routes = self.model_routes();
+        routes.insert(alias.clone(), route);
+        self.update_model_routes(routes)?;
+        tracing::info!("模型路由已更新: {}", alias);
+        Ok(())
+    }
+
+    /// 校验模型路由（不修改路由表）
+    pub fn validate_model_route(&self, alias: &str, route: &ModelRoute) -> anyhow::Result<()> {
+        if alias.trim().is_empty() {
+            anyhow::bail!("模型别名不能为空");
+        }
+        if route.model.trim().is_empty() {
+            anyhow::bail!("目标模型不能为空");
+        }
+        if route.max_tokens.is_some_and(|n| n <= 0) {
+            anyhow::bail!("maxTokens 必须为正数");
+        }
+        if let Some(id) = route.credential_id
+            && !self.entries.lock().iter().any(|e| e.id == id)
+        {
+            anyhow::bail!("凭据不存在: {}", id);
+        }
+        Ok(())
+    }
+
+    /// 删除模型路由（Admin API），返回路由是否存在
+    pub fn remove_model_route(&self, alias: &str) -> anyhow::Result<bool> {
+        let mut routes = self.model_routes();
+        if routes.remove(alias).is_none() {
+            return Ok(false);
+        }
+        self.update_model_routes(routes)?;
+        tracing::info!("模型路由已删除: {}", alias);
+        Ok(true)
+    }
+
+    /// 持久化并应用新的模型路由表
+    fn update_model_routes(&self, routes: BTreeMap<String, ModelRoute>) -> anyhow::Result<()> {
+        use anyhow::Context;
+
+        let current = self.config();
+        if let Some(config_path) = current.config_path() {
+            let mut config = Config::load_file(config_path)
+                .with_context(|| format!("重新加载配置失败: {}", config_path.display()))?;
+            config.model_aliases = routes.clone();
+            config
+                .save()
+                .with_context(|| format!("持久化模型路由失败: {}", config_path.display()))?;
+        } else {
+            tracing::warn!("配置文件路径未知，模型路由仅在当前进程生效");
+        }
+
+        let mut config = (*current).clone();
+        config.model_aliases = routes;
+        *self.config.write() = Arc::new(config);
+        Ok(())
+    }
+
+    /// 获取租户表（Admin API）
+    pub fn tenants(&self) -> BTreeMap<String, TenantConfig> {
+        self.config().tenants.clone()
+    }
+
+    /// 新增或替换租户（Admin API）
+    pub fn set_tenant(&self, name: String, tenant: TenantConfig) -> anyhow::Result<()> {
+        self.validate_tenant(&name, &tenant)?;
+
+        let mut tenants = self.tenants();
+        tenants.insert(name.clone(), tenant);
+        self.update_tenants(tenants)?;
+        tracing::info!("租户已更新: {}", name);
+        Ok(())
+    }
+
+    /// 校验租户配置（不修改租户表）：凭据需存在，API Key 不能与 `apiKey` 或其他租户重复
+    pub fn validate_tenant(&self, name: &str, tenant: &TenantConfig) -> anyhow::Result<()> {
+        use crate::common::auth::configured_api_key_id;
+
+        if name.trim().is_empty() {
+            anyhow::bail!("租户名不能为空");
+        }
+        {
+            let entries = self.entries.lock();
+            if let Some(id) = tenant
+                .credential_ids
+                .iter()
+                .find(|id| !entries.iter().any(|e| e.id == **id))
+            {
+                anyhow::bail!("凭据不存在: {}", id);
+            }
+        }
+
+        let config = self.config();
+        let mut taken: Vec<String> = config
+            .tenants
+            .iter()
+            .filter(|(other, _)| other.as_str() != name)
+            .flat_map(|(_, other)| other.api_keys.iter())
+            .chain(config.api_key.iter())
+            .map(|key| configured_api_key_id(key))
+            .collect();
+        for key in &tenant.api_keys {
+            if key.trim().is_empty() {
+                anyhow::bail!("API Key 不能为空");
+            }
+            let id = configured_api_key_id(key);
+            if taken.contains(&id) {
+                anyhow::bail!("API Key {} 已被使用", id);
+            }
+            taken.push(id);
+        }
+        Ok(())
+    }
+
+    /// 删除租户（Admin API），返回租户是否存在
+    pub fn remove_tenant(&self, name: &str) -> anyhow::Result<bool> {
+        let mut tenants = self.tenants();
+        if tenants.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.update_tenants(tenants)?;
+        tracing::info!("租户已删除: {}", name);
+        Ok(true)
+    }
+
+    /// 持久化并应用新的租户表
+    fn update_tenants(&self, tenants: BTreeMap<String, TenantConfig>) -> anyhow::Result<()> {
+        use anyhow::Context;
+
+        let current = self.config();
+        if let Some(config_path) = current.config_path() {
+            let mut config = Config::load_file(config_path)
+                .with_context(|| format!("重新加载配置失败: {}", config_path.display()))?;
+            config.tenants = tenants.clone();
+            config
+                .save()
+                .with_context(|| format!("持久化租户失败: {}", config_path.display()))?;
+        } else {
+            tracing::warn!("配置文件路径未知，租户仅在当前进程生效");
+        }
+
+        let mut config = (*current).clone();
+        config.tenants = tenants;
+        *self.config.write() = Arc::new(config);
+        Ok(())
+    }
 }
 
-impl Drop for MultiTokenManager {
-    fn drop(&mut self) {
+impl MultiTokenManager {
+    /// 立即将未落盘的统计数据写入磁盘（用于退出前）
+    pub fn flush_stats(&self) {
         if self.stats_dirty.load(Ordering::Relaxed) {
             self.save_stats();
         }
     }
+
+    /// 是否有未落盘的统计数据
+    pub fn stats_dirty(&self) -> bool {
+        self.stats_dirty.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for MultiTokenManager {
+    fn drop(&mut self) {
+        self.flush_stats();
+    }
 }
 
 #[cfg(test)]
@@ -1872,21 +2855,14 @@ mod tests {
 
     #[test]
     fn test_set_load_balancing_mode_persists_to_config_file() {
-        let config_path = std::env::temp_dir().join(format!(
-            "kiro-load-balancing-{}.json",
-            uuid::Uuid::new_v4()
-        ));
+        let config_path =
+            std::env::temp_dir().join(format!("kiro-load-balancing-{}.json", uuid::Uuid::new_v4()));
         std::fs::write(&config_path, r#"{"loadBalancingMode":"priority"}"#).unwrap();
 
         let config = Config::load(&config_path).unwrap();
-        let manager = MultiTokenManager::new(
-            config,
-            vec![KiroCredentials::default()],
-            None,
-            None,
-            false,
-        )
-        .unwrap();
+        let manager =
+            MultiTokenManager::new(config, vec![KiroCredentials::default()], None, None, false)
+                .unwrap();
 
         manager
             .set_load_balancing_mode("balanced".to_string())
@@ -1960,7 +2936,12 @@ mod tests {
         manager.report_quota_exhausted(2);
         assert_eq!(manager.available_count(), 0);
 
-        let err = manager.acquire_context(None).await.err().unwrap().to_string();
+        let err = manager
+            .acquire_context(None)
+            .await
+            .err()
+            .unwrap()
+            .to_string();
         assert!(
             err.contains("所有凭据均已禁用"),
             "错误应提示所有凭据禁用，实际: {}",
@@ -1969,6 +2950,340 @@ mod tests {
         assert_eq!(manager.available_count(), 0);
     }
 
+    #[tokio::test]
+    async fn test_multi_token_manager_quota_exhausted_recovers_after_reset() {
+        let config = Config::default();
+        let cred1 = KiroCredentials {
+            access_token: Some("t1".to_string()),
+            expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+
+        let manager = MultiTokenManager::new(config, vec![cred1], None, None, false).unwrap();
+
+        manager.report_quota_exhausted(1);
+        let until = manager.snapshot().entries[0].exhausted_until.clone();
+        assert!(until.is_some(), "额度用尽后应记录恢复时间");
+
+        // 恢复时间已过：下次获取上下文时自动重新启用
+        manager.set_exhausted_until(1, Utc::now() - Duration::seconds(1));
+        let ctx = manager.acquire_context(None).await.unwrap();
+        assert_eq!(ctx.id, 1);
+        assert_eq!(manager.available_count(), 1);
+        assert!(manager.snapshot().entries[0].exhausted_until.is_none());
+    }
+
+    #[test]
+    fn test_apply_disabled_state() {
+        let config = Config::default();
+        let manager = MultiTokenManager::new(
+            config,
+            vec![KiroCredentials::default(), KiroCredentials::default()],
+            None,
+            None,
+            false,
+        )
+        .unwrap();
+
+        let until = Utc::now() + Duration::days(1);
+        let state = DisabledState {
+            disabled: true,
+            reason: Some(DisabledReason::QuotaExceeded),
+            exhausted_until: Some(until),
+        };
+        assert!(manager.apply_disabled_state(1, &state));
+        assert_eq!(manager.disabled_state(1), Some(state.clone()));
+        assert_eq!(manager.available_count(), 1);
+        // 当前凭据被禁用时切换到其他可用凭据
+        assert_eq!(manager.snapshot().current_id, 2);
+        // 重复应用相同状态没有变化
+        assert!(!manager.apply_disabled_state(1, &state));
+        assert!(!manager.apply_disabled_state(99, &state));
+
+        let enabled = DisabledState {
+            disabled: false,
+            reason: None,
+            exhausted_until: None,
+        };
+        assert!(manager.apply_disabled_state(1, &enabled));
+        assert_eq!(manager.available_count(), 2);
+        assert_eq!(manager.snapshot().entries[0].failure_count, 0);
+    }
+
+    #[test]
+    fn test_update_notes_keeps_unspecified_fields() {
+        let cred = KiroCredentials {
+            source: Some("批次 A".to_string()),
+            ..Default::default()
+        };
+        let manager =
+            MultiTokenManager::new(Config::default(), vec![cred], None, None, false).unwrap();
+
+        let contact = Some("ops@example.com".to_string());
+        manager
+            .update_notes(1, Some(" 测试号 ".to_string()), contact, None)
+            .unwrap();
+        let entry = &manager.snapshot().entries[0];
+        assert_eq!(entry.notes.as_deref(), Some("测试号"));
+        assert_eq!(entry.owner_contact.as_deref(), Some("ops@example.com"));
+        assert_eq!(entry.source.as_deref(), Some("批次 A"));
+
+        // 空字符串清除字段
+        manager
+            .update_notes(1, Some(String::new()), None, None)
+            .unwrap();
+        assert!(manager.snapshot().entries[0].notes.is_none());
+
+        assert!(manager.update_notes(2, None, None, None).is_err());
+    }
+
+    #[test]
+    fn test_next_month_start() {
+        use chrono::TimeZone;
+
+        let dec = Utc.with_ymd_and_hms(2025, 12, 15, 8, 0, 0).unwrap();
+        assert_eq!(
+            next_month_start(dec),
+            Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+        );
+        let mar = Utc.with_ymd_and_hms(2026, 3, 31, 23, 59, 59).unwrap();
+        assert_eq!(
+            next_month_start(mar),
+            Utc.with_ymd_and_hms(2026, 4, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_multi_token_manager_sticky_mode_keeps_conversation_affinity() {
+        let mut config = Config::default();
+        config.load_balancing_mode = "sticky".to_string();
+        let creds: Vec<KiroCredentials> = (1..=3)
+            .map(|i| KiroCredentials {
+                access_token: Some(format!("t{}", i)),
+                expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+                ..Default::default()
+            })
+            .collect();
+
+        let manager = MultiTokenManager::new(config, creds, None, None, false).unwrap();
+
+        let first = manager
+            .acquire_context_excluding(None, Some("conv-a"), &[])
+            .await
+            .unwrap();
+        for _ in 0..5 {
+            let ctx = manager
+                .acquire_context_excluding(None, Some("conv-a"), &[])
+                .await
+                .unwrap();
+            assert_eq!(ctx.id, first.id, "同一会话应始终使用同一凭据");
+        }
+
+        // 绑定的凭据被禁用后，会话迁移到其他凭据
+        manager.set_disabled(first.id, true).ok();
+        let moved = manager
+            .acquire_context_excluding(None, Some("conv-a"), &[])
+            .await
+            .unwrap();
+        assert_ne!(moved.id, first.id);
+    }
+
+    #[tokio::test]
+    async fn test_preview_credential_matches_next_selection() {
+        let mut config = Config::default();
+        config.load_balancing_mode = "sticky".to_string();
+        let creds: Vec<KiroCredentials> = (1..=3)
+            .map(|i| KiroCredentials {
+                access_token: Some(format!("t{}", i)),
+                expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+                ..Default::default()
+            })
+            .collect();
+        let manager = MultiTokenManager::new(config, creds, None, None, false).unwrap();
+
+        let previewed = manager.preview_credential(None, Some("conv-b")).unwrap();
+        let ctx = manager
+            .acquire_context_excluding(None, Some("conv-b"), &[])
+            .await
+            .unwrap();
+        assert_eq!(previewed, ctx.id);
+
+        for id in 1..=3 {
+            manager.set_disabled(id, true).ok();
+        }
+        assert_eq!(manager.preview_credential(None, Some("conv-b")), None);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_context_excluding_prefers_other_credentials() {
+        let creds: Vec<KiroCredentials> = (1..=2)
+            .map(|i| KiroCredentials {
+                access_token: Some(format!("t{}", i)),
+                expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+                priority: i,
+                ..Default::default()
+            })
+            .collect();
+        let manager = MultiTokenManager::new(Config::default(), creds, None, None, false).unwrap();
+
+        assert_eq!(manager.acquire_context(None).await.unwrap().id, 1);
+        let ctx = manager
+            .acquire_context_excluding(None, None, &[1])
+            .await
+            .unwrap();
+        assert_eq!(ctx.id, 2);
+
+        // 所有凭据都被排除时仍可选择，以便继续重试
+        let ctx = manager
+            .acquire_context_excluding(None, None, &[1, 2])
+            .await
+            .unwrap();
+        assert!(ctx.id == 1 || ctx.id == 2);
+        assert_eq!(manager.snapshot().entries[0].failure_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_credential_scope_limits_selection() {
+        let creds: Vec<KiroCredentials> = (1..=3)
+            .map(|i| KiroCredentials {
+                access_token: Some(format!("t{}", i)),
+                expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+                priority: i,
+                ..Default::default()
+            })
+            .collect();
+        let manager = MultiTokenManager::new(Config::default(), creds, None, None, false).unwrap();
+        assert_eq!(manager.acquire_context(None).await.unwrap().id, 1);
+
+        // 当前凭据不在范围内时改选范围内的凭据
+        let scoped =
+            MultiTokenManager::with_credential_scope(&[2, 3], CredentialFallback::Fail, async {
+                let ctx = manager.acquire_context(None).await.unwrap();
+                (ctx.id, manager.preview_credential(None, None))
+            })
+            .await;
+        assert_eq!(scoped, (2, Some(2)));
+
+        // 固定的凭据也必须在范围内
+        let pinned =
+            MultiTokenManager::with_credential_scope(&[2, 3], CredentialFallback::Pool, async {
+                manager.acquire_context_for(1).await.map(|ctx| ctx.id)
+            })
+            .await;
+        assert!(pinned.is_err());
+
+        // 范围内的凭据都已禁用时：fail 不会退回到其他凭据，pool 改用全部凭据
+        manager.set_disabled(2, true).unwrap();
+        manager.set_disabled(3, true).unwrap();
+        let result =
+            MultiTokenManager::with_credential_scope(&[2, 3], CredentialFallback::Fail, async {
+                manager.acquire_context(None).await.map(|ctx| ctx.id)
+            })
+            .await;
+        assert!(result.is_err());
+        let result =
+            MultiTokenManager::with_credential_scope(&[2, 3], CredentialFallback::Pool, async {
+                manager.acquire_context(None).await.map(|ctx| ctx.id)
+            })
+            .await;
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(manager.acquire_context(None).await.unwrap().id, 1);
+    }
+
+    #[test]
+    fn test_credential_stats_record_and_persist() {
+        let mut stats = CredentialStats::default();
+        stats.record_latency(StdDuration::from_millis(100));
+        stats.record_latency(StdDuration::from_millis(200));
+        assert!((stats.avg_latency_ms.unwrap() - 120.0).abs() < 1e-6);
+
+        stats.record_error(FailureClass::Transient, "503 Service Unavailable");
+        stats.record_error(FailureClass::Auth, &"x".repeat(1000));
+        assert_eq!(stats.failures.transient, 1);
+        assert_eq!(stats.failures.auth, 1);
+        assert_eq!(
+            stats.last_error.as_ref().unwrap().chars().count(),
+            LAST_ERROR_MAX_CHARS
+        );
+
+        // 旧版本的统计缓存没有质量统计字段
+        let old: StatsEntry =
+            serde_json::from_str(r#"{"success_count":3,"last_used_at":null}"#).unwrap();
+        assert_eq!(old.success_count, 3);
+        assert!(old.quality.avg_latency_ms.is_none());
+
+        let entry = StatsEntry {
+            success_count: 1,
+            last_used_at: None,
+            quality: stats,
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        let restored: StatsEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.quality.failures.auth, 1);
+        assert_eq!(
+            restored.quality.avg_latency_ms,
+            entry.quality.avg_latency_ms
+        );
+    }
+
+    #[tokio::test]
+    async fn test_model_route_pins_credential() {
+        let creds: Vec<KiroCredentials> = (1..=2)
+            .map(|i| KiroCredentials {
+                access_token: Some(format!("t{}", i)),
+                expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+                priority: i,
+                ..Default::default()
+            })
+            .collect();
+        let manager = MultiTokenManager::new(Config::default(), creds, None, None, false).unwrap();
+
+        let route = ModelRoute {
+            model: "claude-sonnet-4.6".to_string(),
+            max_tokens: Some(8192),
+            credential_id: Some(2),
+        };
+        manager
+            .set_model_route("gpt-4o".to_string(), route.clone())
+            .unwrap();
+        assert_eq!(manager.model_routes()["gpt-4o"], route);
+
+        // 不存在的凭据不能被固定
+        let mut invalid = route.clone();
+        invalid.credential_id = Some(9);
+        assert!(manager.set_model_route("bad".to_string(), invalid).is_err());
+
+        // 固定凭据绕过优先级选择，被禁用时直接失败而不是切换
+        assert_eq!(manager.acquire_context_for(2).await.unwrap().id, 2);
+        manager.set_disabled(2, true).unwrap();
+        assert!(manager.acquire_context_for(2).await.is_err());
+
+        assert!(manager.remove_model_route("gpt-4o").unwrap());
+        assert!(!manager.remove_model_route("gpt-4o").unwrap());
+    }
+
+    #[test]
+    fn test_reload_config_applies_runtime_settings() {
+        let manager = MultiTokenManager::new(Config::default(), vec![], None, None, false).unwrap();
+
+        let mut config = Config::default();
+        config.region = "eu-west-1".to_string();
+        config.load_balancing_mode = "balanced".to_string();
+        config.proxy_url = Some("http://127.0.0.1:7890".to_string());
+        config.port = 9999;
+
+        let restart_required = manager.reload_config(config).unwrap();
+        assert_eq!(restart_required, vec!["port"]);
+        assert_eq!(manager.config().region, "eu-west-1");
+        assert_eq!(manager.get_load_balancing_mode(), "balanced");
+        assert_eq!(manager.proxy().unwrap().url, "http://127.0.0.1:7890");
+
+        let mut invalid = Config::default();
+        invalid.load_balancing_mode = "random".to_string();
+        assert!(manager.reload_config(invalid).is_err());
+        assert_eq!(manager.config().region, "eu-west-1");
+    }
+
     // ============ 凭据级 Region 优先级测试 ============
 
     #[test]
@@ -2121,4 +3436,46 @@ mod tests {
         assert_eq!(credentials.effective_auth_region(&config), "auth-only");
         assert_eq!(credentials.effective_api_region(&config), "api-only");
     }
+
+    #[test]
+    fn test_account_info_parsing_and_suspension() {
+        let usage: UsageLimitsResponse = serde_json::from_str(
+            r#"{"userInfo": {"email": "dev@example.com", "userId": "u-1"}, "usageBreakdownList": []}"#,
+        )
+        .unwrap();
+        assert_eq!(usage.email(), Some("dev@example.com"));
+
+        assert!(is_account_suspended(
+            r#"{"__type": "AccessDeniedException", "reason": "TEMPORARILY_SUSPENDED"}"#
+        ));
+        assert!(!is_account_suspended(
+            r#"{"__type": "AccessDeniedException"}"#
+        ));
+    }
+
+    #[test]
+    fn test_group_duplicates_by_account_id_and_email() {
+        let cred = |id: u64, account_id: Option<&str>, email: Option<&str>| KiroCredentials {
+            id: Some(id),
+            account_id: account_id.map(String::from),
+            email: email.map(String::from),
+            ..Default::default()
+        };
+        let creds = [
+            cred(1, Some("acct-a"), Some("dev@example.com")),
+            cred(2, Some("acct-a"), Some("Dev@Example.com")),
+            cred(3, None, Some(" dev@example.com")),
+            cred(4, Some("acct-b"), Some("other@example.com")),
+        ];
+        let refs: Vec<&KiroCredentials> = creds.iter().collect();
+
+        let groups = group_duplicates(&refs);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].matched_by, "accountId");
+        assert_eq!(groups[0].credential_ids, vec![1, 2]);
+        // 邮箱分组包含账号 ID 分组之外的凭据 #3，需要单独报告
+        assert_eq!(groups[1].matched_by, "email");
+        assert_eq!(groups[1].value, "dev@example.com");
+        assert_eq!(groups[1].credential_ids, vec![1, 2, 3]);
+    }
 }
@@ -0,0 +1,237 @@
+//! 内存日志缓冲
+//!
+//! 作为 tracing Layer 挂在全局订阅器上，保存最近的日志事件并广播给实时订阅者，
+//! 供 Admin API 查询（`/api/admin/logs`）和 WebSocket 实时推送（`/api/admin/logs/stream`）。
+//! 只记录经过全局 EnvFilter 的事件，与终端输出保持一致；
+//! 事件所在 span 的字段（如 `request_id`）会合并到日志的结构化字段中。
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Debug;
+use std::sync::{Arc, OnceLock};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+/// 保留的最近日志条数
+const LOG_BUFFER_CAPACITY: usize = 1000;
+
+/// 实时订阅通道容量（订阅者处理过慢时丢弃最旧的事件）
+const LOG_CHANNEL_CAPACITY: usize = 256;
+
+/// 单条日志
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// 时间（RFC3339 格式）
+    pub timestamp: String,
+    #[serde(serialize_with = "serialize_level")]
+    #[schema(value_type = String, example = "INFO")]
+    pub level: Level,
+    /// 日志来源模块
+    pub target: String,
+    pub message: String,
+    /// 除 message 之外的结构化字段
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+fn serialize_level<S: serde::Serializer>(level: &Level, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(level.as_str())
+}
+
+/// 日志过滤条件
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// 最低级别（如 WARN 表示只保留 WARN 与 ERROR）
+    pub level: Option<Level>,
+    /// 来源模块前缀（如 `kiro_rs::kiro`）
+    pub target: Option<String>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        // tracing 中越详细的级别越大：TRACE > DEBUG > INFO > WARN > ERROR
+        self.level.is_none_or(|level| entry.level <= level)
+            && self
+                .target
+                .as_deref()
+                .is_none_or(|target| entry.target.starts_with(target))
+    }
+}
+
+/// 日志环形缓冲
+pub struct LogBuffer {
+    entries: Mutex<VecDeque<LogEntry>>,
+    capacity: usize,
+    sender: broadcast::Sender<LogEntry>,
+}
+
+impl LogBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            sender: broadcast::channel(LOG_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    fn push(&self, entry: LogEntry) {
+        // 没有订阅者时发送失败是正常情况
+        let _ = self.sender.send(entry.clone());
+        let mut entries = self.entries.lock();
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// 获取最近的日志（按时间正序，最多 limit 条）
+    pub fn recent(&self, filter: &LogFilter, limit: usize) -> Vec<LogEntry> {
+        let entries = self.entries.lock();
+        let mut matched: Vec<LogEntry> = entries
+            .iter()
+            .rev()
+            .filter(|e| filter.matches(e))
+            .take(limit)
+            .cloned()
+            .collect();
+        matched.reverse();
+        matched
+    }
+
+    /// 订阅之后产生的新日志
+    pub fn subscribe(&self) -> broadcast::Receiver<LogEntry> {
+        self.sender.subscribe()
+    }
+}
+
+/// 全局日志缓冲
+static LOG_BUFFER: OnceLock<Arc<LogBuffer>> = OnceLock::new();
+
+/// 获取全局日志缓冲（未安装 Layer 时返回 None）
+pub fn buffer() -> Option<&'static Arc<LogBuffer>> {
+    LOG_BUFFER.get()
+}
+
+/// 创建写入全局日志缓冲的 Layer，应在初始化 tracing 订阅器时调用一次
+pub fn layer() -> LogBufferLayer {
+    let buffer = LOG_BUFFER
+        .get_or_init(|| Arc::new(LogBuffer::new(LOG_BUFFER_CAPACITY)))
+        .clone();
+    LogBufferLayer { buffer }
+}
+
+/// 写入日志缓冲的 tracing Layer
+pub struct LogBufferLayer {
+    buffer: Arc<LogBuffer>,
+}
+
+/// 保存在 span 扩展中的字段
+struct SpanFields(BTreeMap<String, String>);
+
+impl<S> Layer<S> for LogBufferLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().insert(SpanFields(visitor.fields));
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut visitor = FieldVisitor::default();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    visitor.fields.extend(fields.0.clone());
+                }
+            }
+        }
+        event.record(&mut visitor);
+
+        self.buffer.push(LogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message: visitor.message,
+            fields: visitor.fields,
+        });
+    }
+}
+
+/// 收集事件字段
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields
+                .insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_buffer_records_and_filters_events() {
+        let buffer = Arc::new(LogBuffer::new(2));
+        let subscriber = tracing_subscriber::registry().with(LogBufferLayer {
+            buffer: buffer.clone(),
+        });
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("first");
+            tracing::warn!(credential_id = 3, "second");
+            let _span = tracing::info_span!("request", request_id = "req-1").entered();
+            tracing::error!("third");
+        });
+
+        // 容量为 2，最早的事件被淘汰
+        let all = buffer.recent(&LogFilter::default(), 10);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].message, "second");
+        assert_eq!(all[0].fields["credential_id"], "3");
+
+        let errors = buffer.recent(
+            &LogFilter {
+                level: Some(Level::ERROR),
+                target: None,
+            },
+            10,
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "third");
+        assert_eq!(errors[0].fields["request_id"], "req-1");
+    }
+}
@@ -1,47 +1,150 @@
 mod admin;
 mod admin_ui;
+mod alerts;
 mod anthropic;
+mod cluster;
 mod common;
+mod daemon;
+mod disk_monitor;
+mod dns;
+mod events;
 mod http_client;
 mod kiro;
+mod logging;
 mod model;
+mod moderation;
+mod openai;
+mod openapi;
+mod proxy_health;
+mod recovery;
+mod server_tls;
+mod tenants;
+mod tls;
 pub mod token;
+mod unix_socket;
 
+use std::future::IntoFuture;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
+use axum::serve::ListenerExt;
 use clap::Parser;
 use kiro::model::credentials::{CredentialsConfig, KiroCredentials};
 use kiro::provider::KiroProvider;
 use kiro::token_manager::MultiTokenManager;
-use model::arg::Args;
-use model::config::Config;
+use model::arg::{Args, Command, ConfigCommand};
+use model::config::{Config, LogFormat};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 
-#[tokio::main]
-async fn main() {
+fn main() {
     // 解析命令行参数
     let args = Args::parse();
 
-    // 初始化日志
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    // 转入后台必须在启动 tokio 运行时（创建线程）之前完成
+    if args.daemon && args.command.is_none() && args.hash_api_key.is_none() {
+        let log_file = args.log_file.as_deref().map(std::path::Path::new);
+        if let Err(e) = daemon::daemonize(log_file) {
+            eprintln!("转入后台运行失败: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("创建 tokio 运行时失败")
+        .block_on(run(args));
+}
+
+async fn run(args: Args) {
+    if let Some(key) = &args.hash_api_key {
+        println!("{}", common::auth::hash_api_key(key));
+        return;
+    }
+
+    // 加载配置：未指定时使用平台配置目录中的 config.json，都不存在时回退到 ./config.json
+    let config_from_platform_dir = args.config.is_none();
+    let config_path = args
+        .config
+        .map(PathBuf::from)
+        .unwrap_or_else(Config::resolve_default_path);
+
+    if let Some(Command::Config {
+        action: ConfigCommand::Path,
+    }) = &args.command
+    {
+        println!("{}", config_path.display());
+        if !config_path.exists() {
+            eprintln!("（文件不存在，将使用默认配置）");
+        }
+        return;
+    }
+
+    let config = Config::load(&config_path);
+
+    // 初始化日志（终端输出 + 供 Admin API 查询的内存缓冲），输出格式取自配置
+    let json_logs = config
+        .as_ref()
+        .is_ok_and(|c| c.log_format == LogFormat::Json);
+    tracing_subscriber::registry()
+        .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
         )
+        // 守护进程模式输出到日志文件，不使用终端颜色
+        .with((!json_logs).then(|| tracing_subscriber::fmt::layer().with_ansi(!args.daemon)))
+        .with(json_logs.then(|| {
+            tracing_subscriber::fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_span_list(false)
+        }))
+        .with(logging::layer())
         .init();
 
-    // 加载配置
-    let config_path = args
-        .config
-        .unwrap_or_else(|| Config::default_config_path().to_string());
-    let config = Config::load(&config_path).unwrap_or_else(|e| {
+    let mut config = config.unwrap_or_else(|e| {
         tracing::error!("加载配置失败: {}", e);
         std::process::exit(1);
     });
+    config.data_dir_override = args.data_dir.map(std::path::PathBuf::from);
+    if !config.env_overrides.is_empty() {
+        tracing::info!("已应用环境变量配置: {}", config.env_overrides.join(", "));
+    }
+    if !config.unknown_env_vars.is_empty() {
+        tracing::warn!(
+            "以下环境变量不对应任何配置项，已忽略: {}",
+            config.unknown_env_vars.join(", ")
+        );
+    }
+    if config.demo_mode {
+        tracing::warn!("演示模式已启用：不会访问上游 API，所有对话请求返回模拟响应");
+    }
+    if let Some(dir) = config.resolved_data_dir() {
+        tracing::info!("数据目录: {}", dir.display());
+    }
+
+    // 初始化 DNS 解析配置（需在创建任何 HTTP Client 之前）
+    dns::init_config(dns::DnsConfig::from_config(&config));
+    // 加载出站连接的自定义 CA 与客户端证书（同样需在创建任何 HTTP Client 之前）
+    let tls_settings = tls::TlsSettings::from_config(&config).unwrap_or_else(|e| {
+        tracing::error!("加载 TLS 证书失败: {:#}", e);
+        std::process::exit(1);
+    });
+    tls::init_config(tls_settings);
 
     // 加载凭证（支持单对象或数组格式）
-    let credentials_path = args
-        .credentials
-        .unwrap_or_else(|| KiroCredentials::default_credentials_path().to_string());
+    // 配置来自平台配置目录时，默认凭证文件也放在同一目录
+    let credentials_path = args.credentials.unwrap_or_else(|| {
+        let default = KiroCredentials::default_credentials_path();
+        match config_path.parent() {
+            Some(dir) if config_from_platform_dir && !dir.as_os_str().is_empty() => {
+                dir.join(default).to_string_lossy().into_owned()
+            }
+            _ => default.to_string(),
+        }
+    });
     let credentials_config = CredentialsConfig::load(&credentials_path).unwrap_or_else(|e| {
         tracing::error!("加载凭证失败: {}", e);
         std::process::exit(1);
@@ -58,6 +161,11 @@ async fn main() {
     let first_credentials = credentials_list.first().cloned().unwrap_or_default();
     tracing::debug!("主凭证: {:?}", first_credentials);
 
+    // 校验配置与凭据中的 Region（未知 Region 仅告警，仍按默认规则拼接端点）
+    for warning in kiro::regions::validate_all(&config, &credentials_list) {
+        tracing::warn!("{}", warning);
+    }
+
     // 获取 API Key
     let api_key = config.api_key.clone().unwrap_or_else(|| {
         tracing::error!("配置文件中未设置 apiKey");
@@ -65,13 +173,7 @@ async fn main() {
     });
 
     // 构建代理配置
-    let proxy_config = config.proxy_url.as_ref().map(|url| {
-        let mut proxy = http_client::ProxyConfig::new(url);
-        if let (Some(username), Some(password)) = (&config.proxy_username, &config.proxy_password) {
-            proxy = proxy.with_auth(username, password);
-        }
-        proxy
-    });
+    let proxy_config = http_client::ProxyConfig::from_config(&config);
 
     if proxy_config.is_some() {
         tracing::info!("已配置 HTTP 代理: {}", config.proxy_url.as_ref().unwrap());
@@ -90,13 +192,68 @@ async fn main() {
         std::process::exit(1);
     });
     let token_manager = Arc::new(token_manager);
-    let kiro_provider = KiroProvider::with_proxy(token_manager.clone(), proxy_config.clone());
+
+    // PID 文件默认放在数据目录（未配置时为凭据文件所在目录）
+    let pid_file = args.pid_file.map(PathBuf::from).or_else(|| {
+        args.daemon
+            .then(|| token_manager.cache_dir().map(|dir| dir.join("kiro-rs.pid")))
+            .flatten()
+    });
+    if let Some(path) = pid_file
+        && let Err(e) = daemon::write_pid_file(path)
+    {
+        tracing::error!("写入 PID 文件失败: {}", e);
+        std::process::exit(1);
+    }
+    if let Some(cluster_config) = &config.cluster
+        && let Err(e) = cluster::init(cluster_config, token_manager.clone()).await
+    {
+        tracing::error!("启动集群模式失败: {:#}", e);
+        daemon::remove_pid_file();
+        std::process::exit(1);
+    }
+    token_manager.spawn_account_refresh();
+    events::spawn_audit_logger();
+    alerts::spawn(token_manager.clone());
+    let shared_tenant_usage = config
+        .cluster
+        .as_ref()
+        .is_some_and(|cluster| cluster.share_tenant_usage)
+        .then(cluster::tenant_usage_store)
+        .flatten();
+    tenants::spawn(token_manager.clone(), shared_tenant_usage);
+    proxy_health::spawn(token_manager.clone());
+    if let Some(dir) = token_manager.cache_dir() {
+        disk_monitor::spawn(dir.clone(), config.min_free_disk_mb * 1024 * 1024);
+        kiro::schema_drift::init_sampling(dir.join("unknown-events"), config.unknown_event_samples);
+        recovery::spawn(dir, token_manager.clone());
+    }
+    let kiro_provider = KiroProvider::new(token_manager.clone());
 
     // 初始化 count_tokens 配置
     token::init_config(token::CountTokensConfig {
         api_url: config.count_tokens_api_url.clone(),
         api_key: config.count_tokens_api_key.clone(),
         auth_type: config.count_tokens_auth_type.clone(),
+        proxy: proxy_config.clone(),
+        tls_backend: config.tls_backend,
+    });
+
+    // 初始化内容审核配置
+    moderation::init_config(moderation::ModerationConfig {
+        api_url: config.moderation_api_url.clone(),
+        api_key: config.moderation_api_key.clone(),
+        keywords: config.moderation_keywords.clone(),
+        proxy: proxy_config.clone(),
+        tls_backend: config.tls_backend,
+    });
+
+    // 初始化图片下载配置
+    if config.fetch_image_urls && proxy_config.is_some() {
+        tracing::warn!("已配置 proxyUrl，fetchImageUrls 不生效：URL 来源的图片将返回 400");
+    }
+    anthropic::images::init_config(anthropic::images::ImageFetchConfig {
+        enabled: config.fetch_image_urls,
         proxy: proxy_config,
         tls_backend: config.tls_backend,
     });
@@ -108,44 +265,82 @@ async fn main() {
         first_credentials.profile_arn.clone(),
     );
 
-    // 构建 Admin API 路由（如果配置了非空的 admin_api_key）
-    // 安全检查：空字符串被视为未配置，防止空 key 绕过认证
-    let admin_key_valid = config
-        .admin_api_key
-        .as_ref()
-        .map(|k| !k.trim().is_empty())
-        .unwrap_or(false);
-
-    let app = if let Some(admin_key) = &config.admin_api_key {
-        if admin_key.trim().is_empty() {
-            tracing::warn!("admin_api_key 配置为空，Admin API 未启用");
-            anthropic_app
-        } else {
-            let admin_service = admin::AdminService::new(token_manager.clone());
-            let admin_state = admin::AdminState::new(admin_key, admin_service);
-            let admin_app = admin::create_admin_router(admin_state);
-
-            // 创建 Admin UI 路由
-            let admin_ui_app = admin_ui::create_admin_ui_router();
-
-            tracing::info!("Admin API 已启用");
-            tracing::info!("Admin UI 已启用: /admin");
-            anthropic_app
-                .nest("/api/admin", admin_app)
-                .nest("/admin", admin_ui_app)
+    // 构建 Admin API 路由（如果配置了至少一个 Admin 账号）
+    // 安全检查：空 Key 被视为未配置，防止空 key 绕过认证
+    let admin_key_valid = !config.all_admin_accounts().is_empty();
+
+    let app = if admin_key_valid {
+        let admin_service = admin::AdminService::new(token_manager.clone());
+        let admin_state = admin::AdminState::new(admin_service);
+        admin_state.service.spawn_event_consumer();
+        #[cfg(feature = "grpc")]
+        if let Some(grpc_port) = config.grpc_port {
+            match format!("{}:{}", config.host, grpc_port).parse() {
+                Ok(grpc_addr) => {
+                    admin::spawn_grpc_server(admin_state.clone(), grpc_addr);
+                    tracing::info!("gRPC 管理接口已启用: {}", grpc_addr);
+                }
+                Err(e) => tracing::error!("gRPC 管理接口监听地址无效: {}", e),
+            }
+        }
+        #[cfg(not(feature = "grpc"))]
+        if config.grpc_port.is_some() {
+            tracing::warn!("当前构建未启用 grpc feature，忽略 grpcPort 配置");
         }
+        let admin_app = admin::create_admin_router(admin_state.clone());
+
+        // 创建 Admin UI 路由
+        let dev_assets_dir = args.dev.then(|| {
+            std::path::PathBuf::from(
+                args.dev_assets_dir
+                    .as_deref()
+                    .unwrap_or(admin_ui::DEV_ASSET_DIR),
+            )
+        });
+        if let Some(dir) = &dev_assets_dir {
+            tracing::info!("开发模式: Admin UI 静态文件从 {} 读取", dir.display());
+        }
+        let admin_ui_app = admin_ui::create_admin_ui_router(dev_assets_dir)
+            .layer(axum::middleware::from_fn_with_state(
+                admin_state,
+                admin::ip_access_middleware,
+            ))
+            .layer(axum::middleware::from_fn(admin::locale_middleware));
+
+        tracing::info!("Admin API 已启用");
+        tracing::info!("Admin UI 已启用: /admin");
+        anthropic_app
+            .nest("/api/admin", admin_app)
+            .nest("/admin", admin_ui_app)
     } else {
+        if config.admin_api_key.is_some() || !config.admin_accounts.is_empty() {
+            tracing::warn!("Admin API Key 配置为空，Admin API 未启用");
+        }
         anthropic_app
     };
+    // OpenAPI 规范与 Swagger UI（不需要认证）
+    let app = app
+        .merge(openapi::router())
+        .layer(axum::middleware::from_fn(
+            common::request_id::request_id_middleware,
+        ));
 
     // 启动服务器
     let addr = format!("{}:{}", config.host, config.port);
-    tracing::info!("启动 Anthropic API 端点: {}", addr);
+    let scheme = if config.server_tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    tracing::info!("启动 Anthropic API 端点: {}://{}", scheme, addr);
     tracing::info!("API Key: {}***", &api_key[..(api_key.len() / 2)]);
     tracing::info!("可用 API:");
     tracing::info!("  GET  /v1/models");
     tracing::info!("  POST /v1/messages");
     tracing::info!("  POST /v1/messages/count_tokens");
+    tracing::info!("  POST /v1/chat/completions");
+    tracing::info!("  POST /v1/embeddings");
+    tracing::info!("  GET  {} ({})", openapi::SPEC_PATH, openapi::DOCS_PATH);
     if admin_key_valid {
         tracing::info!("Admin API:");
         tracing::info!("  GET  /api/admin/credentials");
@@ -158,5 +353,118 @@ async fn main() {
     }
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+
+    // 收到退出信号后停止接受新连接，等待进行中的请求完成
+    let (shutdown, shutdown_rx) = tokio::sync::watch::channel(false);
+    let graceful = |mut rx: tokio::sync::watch::Receiver<bool>| async move {
+        let _ = rx.wait_for(|stop| *stop).await;
+    };
+    let mut unix_server = config.unix_socket.as_ref().map(|unix| {
+        let server = unix_socket::spawn(unix, app.clone(), graceful(shutdown_rx.clone()))
+            .unwrap_or_else(|e| {
+                tracing::error!("启用 Unix 套接字失败: {:#}", e);
+                std::process::exit(1);
+            });
+        tracing::info!("同时监听 Unix 套接字: {}", unix.path);
+        server
+    });
+    // IP 访问控制需要对端地址
+    let app = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+    let mut server = match &config.server_tls {
+        Some(tls) => {
+            let listener = server_tls::TlsListener::new(listener, tls).unwrap_or_else(|e| {
+                tracing::error!("启用 HTTPS 失败: {:#}", e);
+                std::process::exit(1);
+            });
+            // tap_io 让自定义监听器也能提供 ConnectInfo<SocketAddr>
+            tokio::spawn(
+                axum::serve(listener.tap_io(|_| {}), app)
+                    .with_graceful_shutdown(graceful(shutdown_rx))
+                    .into_future(),
+            )
+        }
+        None => tokio::spawn(
+            axum::serve(listener, app)
+                .with_graceful_shutdown(graceful(shutdown_rx))
+                .into_future(),
+        ),
+    };
+    daemon::notify_ready(&format!("监听 {}://{}", scheme, addr));
+
+    tokio::select! {
+        result = &mut server => {
+            if let Ok(Err(e)) = result {
+                tracing::error!("服务器异常退出: {}", e);
+            }
+            token_manager.flush_stats();
+            if let Some(unix) = &config.unix_socket {
+                unix_socket::remove(unix);
+            }
+            daemon::remove_pid_file();
+            std::process::exit(1);
+        }
+        _ = shutdown_signal() => {}
+    }
+    daemon::notify_stopping();
+
+    let timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    tracing::info!(
+        "收到退出信号，停止接受新连接，最多等待 {} 秒让进行中的请求完成",
+        timeout.as_secs()
+    );
+    let _ = shutdown.send(true);
+    let drained = tokio::time::timeout(timeout, async {
+        let _ = (&mut server).await;
+        if let Some(unix_server) = &mut unix_server {
+            let _ = unix_server.await;
+        }
+    });
+    if drained.await.is_err() {
+        tracing::warn!("等待进行中的请求超时，强制退出");
+        server.abort();
+        if let Some(unix_server) = &unix_server {
+            unix_server.abort();
+        }
+    }
+    if let Some(unix) = &config.unix_socket {
+        unix_socket::remove(unix);
+    }
+
+    token_manager.flush_stats();
+    tenants::flush();
+    cluster::shutdown().await;
+    recovery::clear();
+    daemon::remove_pid_file();
+    tracing::info!("已退出");
+}
+
+/// 等待 Ctrl+C 或 SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("监听 Ctrl+C 失败: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("监听 SIGTERM 失败: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
 }
@@ -1,14 +1,62 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 /// Anthropic <-> Kiro API 客户端
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// 配置文件路径
-    #[arg(short, long)]
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// 配置文件路径（未指定时依次查找平台配置目录下的 kiro/config.json 与 ./config.json）
+    #[arg(short, long, global = true, env = "KIRO_CONFIG")]
     pub config: Option<String>,
 
     /// 凭证文件路径
-    #[arg(long)]
+    #[arg(long, env = "KIRO_CREDENTIALS")]
     pub credentials: Option<String>,
+
+    /// 数据目录（覆盖配置文件中的 dataDir）
+    #[arg(long, value_name = "DIR")]
+    pub data_dir: Option<String>,
+
+    /// 输出 API Key 的哈希存储形式（用于 config.json 的 apiKey / adminApiKey）后退出
+    #[arg(long, value_name = "KEY")]
+    pub hash_api_key: Option<String>,
+
+    /// 以守护进程方式在后台运行（仅 Unix）；未指定 `--pid-file` 时 PID 写入数据目录的 kiro-rs.pid
+    #[arg(long)]
+    pub daemon: bool,
+
+    /// PID 文件路径（正常退出时删除）；文件对应的进程仍在运行时拒绝启动
+    #[arg(long, value_name = "PATH")]
+    pub pid_file: Option<String>,
+
+    /// 守护进程模式下追加写入日志的文件（未指定时丢弃终端输出）
+    #[arg(long, value_name = "PATH", requires = "daemon")]
+    pub log_file: Option<String>,
+
+    /// 开发模式：Admin UI 静态文件从磁盘读取（默认 admin-ui/dist）并禁用缓存，
+    /// 修改前端后无需重新编译二进制
+    #[arg(long)]
+    pub dev: bool,
+
+    /// 开发模式下 Admin UI 静态文件目录
+    #[arg(long, value_name = "DIR", requires = "dev")]
+    pub dev_assets_dir: Option<String>,
+}
+
+/// 子命令（不指定时启动服务）
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 配置文件相关操作
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// 输出实际使用的配置文件路径后退出
+    Path,
 }
@@ -1,8 +1,13 @@
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::common::cors::CorsPolicy;
+use crate::common::ip_access::IpAccessList;
+use crate::common::usage_signing::UsageSigningKey;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum TlsBackend {
@@ -16,6 +21,500 @@ impl Default for TlsBackend {
     }
 }
 
+/// 单个 Region 的域名覆盖（`regionEndpoints` 的值），未设置的域名使用默认规则
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RegionEndpointOverride {
+    /// Social Token 刷新域名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_host: Option<String>,
+    /// IdC Token 刷新域名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oidc_host: Option<String>,
+    /// Kiro API 域名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_host: Option<String>,
+}
+
+/// 模型路由（`modelAliases` 的值）
+///
+/// 兼容旧格式：值为字符串时等价于只设置 `model`；仅设置 `model` 时也序列化为字符串
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(from = "ModelRouteRepr", into = "ModelRouteRepr")]
+pub struct ModelRoute {
+    /// 实际使用的模型名（Kiro 模型 ID 或可映射到 Kiro 模型的 Anthropic 模型名）
+    pub model: String,
+    /// 客户端未指定 max_tokens 时使用的默认值
+    pub max_tokens: Option<i32>,
+    /// 固定使用的凭据 ID（该凭据不可用时请求失败，不会切换到其他凭据）
+    pub credential_id: Option<u64>,
+}
+
+impl ModelRoute {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            max_tokens: None,
+            credential_id: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ModelRouteRepr {
+    Model(String),
+    #[serde(rename_all = "camelCase")]
+    Detailed {
+        model: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_tokens: Option<i32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        credential_id: Option<u64>,
+    },
+}
+
+impl From<ModelRouteRepr> for ModelRoute {
+    fn from(repr: ModelRouteRepr) -> Self {
+        match repr {
+            ModelRouteRepr::Model(model) => Self::new(model),
+            ModelRouteRepr::Detailed {
+                model,
+                max_tokens,
+                credential_id,
+            } => Self {
+                model,
+                max_tokens,
+                credential_id,
+            },
+        }
+    }
+}
+
+impl From<ModelRoute> for ModelRouteRepr {
+    fn from(route: ModelRoute) -> Self {
+        if route.max_tokens.is_none() && route.credential_id.is_none() {
+            Self::Model(route.model)
+        } else {
+            Self::Detailed {
+                model: route.model,
+                max_tokens: route.max_tokens,
+                credential_id: route.credential_id,
+            }
+        }
+    }
+}
+
+/// 模型单价（`modelPricing` 的值），单位：美元 / 百万 tokens
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPricing {
+    /// 输入 tokens（不含缓存部分）单价
+    pub input: f64,
+    /// 输出 tokens 单价
+    pub output: f64,
+    /// 缓存写入 tokens 单价（未配置时按输入单价计）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_write: Option<f64>,
+    /// 缓存读取 tokens 单价（未配置时按输入单价计）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_read: Option<f64>,
+}
+
+impl ModelPricing {
+    /// 按 usage 各字段计算费用（美元）
+    pub fn cost(
+        &self,
+        input_tokens: i32,
+        output_tokens: i32,
+        cache_creation_input_tokens: i32,
+        cache_read_input_tokens: i32,
+    ) -> f64 {
+        let per_token = |tokens: i32, price: f64| tokens.max(0) as f64 * price / 1_000_000.0;
+        per_token(input_tokens, self.input)
+            + per_token(output_tokens, self.output)
+            + per_token(
+                cache_creation_input_tokens,
+                self.cache_write.unwrap_or(self.input),
+            )
+            + per_token(
+                cache_read_input_tokens,
+                self.cache_read.unwrap_or(self.input),
+            )
+    }
+}
+
+/// 告警通知配置（`alerts`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertsConfig {
+    /// 触发告警的事件类型（事件的 `type`，如 `credentialDisabled`）
+    #[serde(default = "default_alert_events")]
+    pub events: Vec<String>,
+    /// 同一事件类型两次告警之间的最小间隔（分钟），期间的同类事件只计数不发送
+    #[serde(default = "default_alert_min_interval_minutes")]
+    pub min_interval_minutes: u64,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telegram: Option<TelegramAlertConfig>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smtp: Option<SmtpAlertConfig>,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            events: default_alert_events(),
+            min_interval_minutes: default_alert_min_interval_minutes(),
+            telegram: None,
+            smtp: None,
+        }
+    }
+}
+
+/// 集群模式配置（`cluster`）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterConfig {
+    /// Redis 地址，如 `redis://:password@redis:6379/0`
+    pub redis_url: String,
+    /// Redis 键前缀（多个集群共用一个 Redis 时区分）
+    #[serde(default = "default_cluster_key_prefix")]
+    pub key_prefix: String,
+    /// 实例标识（未配置时使用主机名与进程 ID）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+    /// Leader 租约时长（秒）：leader 实例失联超过该时长后由其他实例接替
+    #[serde(default = "default_cluster_leader_lease_secs")]
+    pub leader_lease_secs: u64,
+    /// Prompt caching 前缀记录存入 Redis（重启后保留、实例间共享），关闭时各实例单独记录
+    #[serde(default)]
+    pub share_prompt_cache: bool,
+    /// 租户每月用量存入 Redis（实例间共享，配额检查与计数原子执行），关闭时各实例单独记录
+    #[serde(default)]
+    pub share_tenant_usage: bool,
+}
+
+fn default_cluster_key_prefix() -> String {
+    "kiro-rs".to_string()
+}
+
+fn default_cluster_leader_lease_secs() -> u64 {
+    15
+}
+
+/// Telegram Bot 告警
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelegramAlertConfig {
+    pub bot_token: String,
+    /// 接收告警的聊天 ID（用户、群组或频道）
+    pub chat_id: String,
+}
+
+/// SMTP 连接的加密方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SmtpSecurity {
+    /// 明文连接后通过 STARTTLS 升级（默认端口 587）
+    #[default]
+    Starttls,
+    /// 直接建立 TLS 连接（默认端口 465）
+    Tls,
+    /// 不加密（默认端口 25，仅用于本机或内网中继）
+    None,
+}
+
+/// SMTP 邮件告警
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmtpAlertConfig {
+    pub host: String,
+    /// 端口（未配置时按 `security` 使用默认端口）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// 发件人，如 `kiro-rs <alerts@example.com>`
+    pub from: String,
+    /// 收件人
+    pub to: Vec<String>,
+}
+
+/// Admin 账号的角色
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum AdminRole {
+    /// 完整权限
+    #[default]
+    Admin,
+    /// 只读：仅允许查询类请求（导出凭据除外）
+    Readonly,
+}
+
+/// Admin 账号（`adminAccounts` 的元素）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminAccount {
+    /// 账号名称，记录在审计日志和事件中
+    pub name: String,
+    /// Admin API Key，支持 `sha256:` 哈希形式
+    pub key: String,
+    #[serde(default)]
+    pub role: AdminRole,
+}
+
+/// 租户绑定的凭据均不可用时的处理方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum CredentialFallback {
+    /// 直接返回错误，绝不使用绑定范围之外的凭据
+    #[default]
+    Fail,
+    /// 改为从全部凭据中选择
+    Pool,
+}
+
+/// 租户（`tenants` 的值）：一组 API Key 共享的模型范围、凭据与每月用量上限
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantConfig {
+    /// 租户的 API Key（与 `apiKey` 一样用于 `/v1` 认证），支持 `sha256:` 哈希形式
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<String>,
+    /// 允许请求的模型（客户端请求中的模型名，`*` 结尾表示前缀匹配），为空时不限制
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_models: Vec<String>,
+    /// 只使用这些凭据，为空时使用全部凭据
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub credential_ids: Vec<u64>,
+    /// `credentialIds` 中的凭据均不可用时的处理方式
+    #[serde(default)]
+    pub credential_fallback: CredentialFallback,
+    /// 每月（UTC 自然月）输入与输出 token 合计上限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly_token_quota: Option<u64>,
+    /// 每月（UTC 自然月）请求数上限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly_request_quota: Option<u64>,
+}
+
+impl TenantConfig {
+    /// 是否允许请求该模型
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.allowed_models.is_empty()
+            || self
+                .allowed_models
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => model.starts_with(prefix),
+                    None => pattern == model,
+                })
+    }
+}
+
+/// IP 访问控制（`ipAccess`），Anthropic API 与 Admin API 分别配置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpAccessConfig {
+    /// `/v1`、`/cc/v1` 路由的规则
+    #[serde(default)]
+    pub anthropic: IpAccessList,
+    /// Admin API 与 Admin UI 的规则
+    #[serde(default)]
+    pub admin: IpAccessList,
+    /// 按 `X-Forwarded-For` 的最后一项（反向代理追加的地址）判断客户端地址（仅在反向代理之后部署时开启）
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+}
+
+/// 跨域设置（`cors`），Anthropic API 与 Admin API 分别配置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorsConfig {
+    /// `/v1`、`/cc/v1` 路由的规则；未配置时允许任意来源
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anthropic: Option<CorsPolicy>,
+    /// Admin API 的规则；未配置时不允许跨域访问
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin: Option<CorsPolicy>,
+}
+
+/// 出站 TLS 设置（`kiroTls` / `integrationTls`）：自定义 CA 与客户端证书
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsClientConfig {
+    /// 额外信任的 CA 证书文件（PEM，可包含多个证书），用于企业 MITM 代理或自签名证书的服务器
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_cert_file: Option<String>,
+    /// 不信任内置根证书，只信任 `caCertFile` 中的证书
+    #[serde(default)]
+    pub disable_built_in_roots: bool,
+    /// 客户端证书文件（PEM，可附带中间证书），用于 mTLS，仅支持 rustls 后端
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_cert_file: Option<String>,
+    /// 客户端私钥文件（PEM），私钥已包含在 `clientCertFile` 中时可省略
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_key_file: Option<String>,
+}
+
+/// 本地服务的 HTTPS（`serverTls`）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerTlsConfig {
+    /// 证书文件（PEM，按顺序包含服务器证书与中间证书）
+    pub cert_file: String,
+    /// 私钥文件（PEM）
+    pub key_file: String,
+    /// 检查证书文件变化的间隔（秒），文件变化时自动重新加载；未设置时不检查
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reload_interval_secs: Option<u64>,
+}
+
+/// 额外监听的 Unix 域套接字（`unixSocket`）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnixSocketConfig {
+    /// 套接字文件路径
+    pub path: String,
+    /// 套接字文件权限（八进制，如 `"660"`）；未设置时由 umask 决定
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    /// 经套接字的 `/v1` 请求是否仍需 API Key（默认 false：由文件权限控制访问）
+    #[serde(default)]
+    pub require_api_key: bool,
+}
+
+/// 日志输出格式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    /// 人类可读的文本格式
+    #[default]
+    Text,
+    /// 每行一个 JSON 对象，便于日志系统采集
+    Json,
+}
+
+/// DNS 解析结果的 IP 版本偏好
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IpPreference {
+    /// 保持解析器返回的顺序
+    #[default]
+    Auto,
+    /// IPv4 地址优先
+    Ipv4First,
+    /// IPv6 地址优先
+    Ipv6First,
+    /// 仅使用 IPv4
+    Ipv4Only,
+    /// 仅使用 IPv6
+    Ipv6Only,
+}
+
+/// 不作为配置项覆盖的 `KIRO_*` 环境变量（命令行参数与凭据加密密钥）
+const RESERVED_ENV_VARS: &[&str] = &["KIRO_CONFIG", "KIRO_CREDENTIALS", "KIRO_CREDENTIALS_KEY"];
+
+/// `KIRO_*` 环境变量对应的配置项路径：去掉前缀后以 `__` 分隔嵌套层级，
+/// 每段由大写下划线转为 camelCase（如 `KIRO_ALERTS__SMTP__PASSWORD` → `alerts.smtp.password`）
+fn env_override_path(name: &str) -> Option<Vec<String>> {
+    if RESERVED_ENV_VARS.contains(&name) {
+        return None;
+    }
+    let rest = name.strip_prefix("KIRO_")?;
+    rest.split("__")
+        .map(|segment| {
+            let mut words = segment.split('_');
+            let first = words.next().filter(|w| !w.is_empty())?.to_ascii_lowercase();
+            words.try_fold(first, |mut key, word| {
+                let mut chars = word.chars();
+                key.push(chars.next()?.to_ascii_uppercase());
+                key.push_str(&chars.as_str().to_ascii_lowercase());
+                Some(key)
+            })
+        })
+        .collect()
+}
+
+/// 把环境变量的值写入 `keys` 指定的位置：值为合法 JSON 且能通过校验时按 JSON 解析
+/// （数字、布尔值、数组、对象、`null`），否则按字符串处理
+fn apply_env_override(
+    value: serde_json::Value,
+    keys: &[String],
+    raw: &str,
+) -> anyhow::Result<serde_json::Value> {
+    let with_value = |candidate| {
+        let mut trial = value.clone();
+        set_json_path(&mut trial, keys, candidate);
+        serde_json::from_value::<Config>(trial.clone()).map(|_| trial)
+    };
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(raw)
+        && let Ok(trial) = with_value(json)
+    {
+        return Ok(trial);
+    }
+    Ok(with_value(serde_json::Value::String(raw.to_string()))?)
+}
+
+/// `keys` 指定的位置在 `value` 中是否存在
+fn json_path_exists(value: &serde_json::Value, keys: &[String]) -> bool {
+    keys.iter()
+        .try_fold(value, |current, key| current.get(key))
+        .is_some()
+}
+
+/// 值为 `null`、空数组或空对象时，对应的配置项可能在序列化时被省略，无法据此判断是否存在
+fn is_empty_json(raw: &str) -> bool {
+    match serde_json::from_str::<serde_json::Value>(raw) {
+        Ok(serde_json::Value::Null) => true,
+        Ok(serde_json::Value::Array(items)) => items.is_empty(),
+        Ok(serde_json::Value::Object(map)) => map.is_empty(),
+        _ => false,
+    }
+}
+
+fn set_json_path(target: &mut serde_json::Value, keys: &[String], new_value: serde_json::Value) {
+    let Some((last, parents)) = keys.split_last() else {
+        return;
+    };
+    let mut current = target;
+    for key in parents {
+        if !current.is_object() {
+            *current = serde_json::Value::Object(serde_json::Map::new());
+        }
+        current = current
+            .as_object_mut()
+            .expect("刚确认为对象")
+            .entry(key.clone())
+            .or_insert(serde_json::Value::Null);
+    }
+    if !current.is_object() {
+        *current = serde_json::Value::Object(serde_json::Map::new());
+    }
+    if let Some(object) = current.as_object_mut() {
+        object.insert(last.clone(), new_value);
+    }
+}
+
 /// KNA 应用配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -26,6 +525,16 @@ pub struct Config {
     #[serde(default = "default_port")]
     pub port: u16,
 
+    /// 监听端口使用 HTTPS（未配置时为 HTTP）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_tls: Option<ServerTlsConfig>,
+
+    /// 在 TCP 端口之外额外监听 Unix 域套接字（仅 Unix 平台）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unix_socket: Option<UnixSocketConfig>,
+
     #[serde(default = "default_region")]
     pub region: String,
 
@@ -48,6 +557,11 @@ pub struct Config {
     #[serde(default)]
     pub api_key: Option<String>,
 
+    /// `apiKey` 的过期时间（RFC3339），过期后认证失败；未设置时不过期
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_expires_at: Option<chrono::DateTime<chrono::Utc>>,
+
     #[serde(default = "default_system_version")]
     pub system_version: String,
 
@@ -70,7 +584,7 @@ pub struct Config {
     pub count_tokens_auth_type: String,
 
     /// HTTP 代理地址（可选）
-    /// 支持格式: http://host:port, https://host:port, socks5://host:port
+    /// 支持格式: http://host:port, https://host:port, socks5://host:port, socks5h://host:port（目标域名由代理解析）
     #[serde(default)]
     pub proxy_url: Option<String>,
 
@@ -86,10 +600,239 @@ pub struct Config {
     #[serde(default)]
     pub admin_api_key: Option<String>,
 
-    /// 负载均衡模式（"priority" 或 "balanced"）
+    /// 只读 Admin API 密钥（可选，仅允许查询类请求，供监控系统使用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin_readonly_api_key: Option<String>,
+
+    /// Admin 账号列表（多人共用 Admin API 时为每人分配独立的 Key 和角色）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub admin_accounts: Vec<AdminAccount>,
+
+    /// `POST /api/admin/login` 签发的会话 Token 有效期（分钟）
+    #[serde(default = "default_admin_session_ttl_minutes")]
+    pub admin_session_ttl_minutes: u64,
+
+    /// gRPC 管理接口监听端口（可选，监听地址同 `host`；需要配置 Admin 账号）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_port: Option<u16>,
+
+    /// 负载均衡模式（"priority"、"balanced"、"weighted"、"least-usage" 或 "sticky"）
     #[serde(default = "default_load_balancing_mode")]
     pub load_balancing_mode: String,
 
+    /// 单个 tool_result 文本的最大字符数（可选，超出部分截断并插入标记）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_result_max_chars: Option<usize>,
+
+    /// 非流式请求收到空响应（无文本、无工具调用）时，是否避开该凭据自动重试一次（流式请求不重试）
+    #[serde(default = "default_empty_response_retry")]
+    pub empty_response_retry: bool,
+
+    /// 是否在系统提示词中注入当前日期等运行时上下文
+    #[serde(default)]
+    pub system_prompt_inject_date: bool,
+
+    /// 注入日期时使用的 UTC 偏移（可选，如 "+08:00"，默认 UTC）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt_utc_offset: Option<String>,
+
+    /// 注入到系统提示词中的用户语言区域（可选，如 "zh-CN"）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt_locale: Option<String>,
+
+    /// 内容审核接口地址（可选，POST {"input": "..."}，响应 flagged 为 true 时拒绝请求）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moderation_api_url: Option<String>,
+
+    /// 内容审核接口密钥（可选，以 Bearer 方式发送）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moderation_api_key: Option<String>,
+
+    /// 本地审核关键词，用户消息命中任一关键词（不区分大小写）即拒绝
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub moderation_keywords: Vec<String>,
+
+    /// 是否下载 URL 来源的图片并以 base64 转发（关闭时这类图片返回 400）
+    #[serde(default)]
+    pub fetch_image_urls: bool,
+
+    /// 模型路由表（客户端模型名 → 路由），别名会出现在 /v1/models 中
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub model_aliases: BTreeMap<String, ModelRoute>,
+
+    /// 模型单价表（模型名或模型名前缀 → 单价），用于估算费用
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub model_pricing: BTreeMap<String, ModelPricing>,
+
+    /// /v1/embeddings 转发的上游地址（可选，未配置时该端点返回 501）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embeddings_api_url: Option<String>,
+
+    /// /v1/embeddings 上游密钥（可选，以 Bearer 方式发送）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embeddings_api_key: Option<String>,
+
+    /// 每分钟最大请求数（可选，超出时返回 429）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit_requests_per_minute: Option<u32>,
+
+    /// 每分钟最大输入 tokens（可选，按估算的输入 tokens 累计，超出时返回 429）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit_tokens_per_minute: Option<u64>,
+
+    /// 是否屏蔽生成内容中的代理自身密钥（apiKey、凭据 token 等）与 `sk-` 格式的 API Key
+    #[serde(default)]
+    pub secret_scanning: bool,
+
+    /// 是否允许仅凭 API Key 使用 `x-kiro-credential-id` 指定凭据（默认需同时携带 Admin API Key）
+    #[serde(default)]
+    pub allow_credential_override: bool,
+
+    /// 是否对发往 Kiro API 的大请求体进行 gzip 压缩（上游不支持时自动回退为不压缩）
+    #[serde(default)]
+    pub upstream_request_compression: bool,
+
+    /// DNS 覆盖（域名 → IP，多个 IP 以逗号分隔），优先于解析器
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub dns_overrides: BTreeMap<String, String>,
+
+    /// DNS over HTTPS 解析地址（可选，JSON 格式接口，如 https://cloudflare-dns.com/dns-query）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns_over_https_url: Option<String>,
+
+    /// DNS 解析结果的 IP 版本偏好
+    #[serde(default)]
+    pub ip_preference: IpPreference,
+
+    /// 发往 Kiro 的连接（API、MCP、Token 刷新、额度查询、代理测试）使用的 CA 与客户端证书
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kiro_tls: Option<TlsClientConfig>,
+
+    /// 发往其他服务（告警 Webhook、内容审核、外部 count_tokens）的连接使用的 CA 与客户端证书
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integration_tls: Option<TlsClientConfig>,
+
+    /// 附加到所有 Kiro 上游请求（API、MCP、Token 刷新、额度查询）的请求头，
+    /// 用于满足企业出口代理的身份标识要求；不会覆盖内置请求头
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub upstream_extra_headers: BTreeMap<String, String>,
+
+    /// 按 Region 覆盖 Kiro 端点域名（Region → 覆盖项），详见 `kiro::regions`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub region_endpoints: BTreeMap<String, RegionEndpointOverride>,
+
+    /// 后台刷新账号信息（邮箱、订阅等级、暂停状态）的间隔（秒），未设置时不启用
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_refresh_interval_secs: Option<u64>,
+
+    /// 日志输出格式（text / json）
+    #[serde(default)]
+    pub log_format: LogFormat,
+
+    /// 收到退出信号后等待进行中请求（含流式响应）完成的最长时间（秒）
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+
+    /// 流式响应的 ping 保活间隔（秒）：上游持续这么久没有输出时发送一次 ping
+    #[serde(default = "default_ping_interval_secs")]
+    pub ping_interval_secs: u64,
+
+    /// 是否开启服务端会话记忆（按 `x-kiro-conversation-id` 头保存并拼接历史消息）
+    #[serde(default)]
+    pub conversation_memory: bool,
+
+    /// 会话记忆的过期时间（秒）：会话超过这么久未使用时清除
+    #[serde(default = "default_conversation_memory_ttl_secs")]
+    pub conversation_memory_ttl_secs: u64,
+
+    /// 每个会话最多保存的消息条数，超出时丢弃最早的消息
+    #[serde(default = "default_conversation_memory_max_messages")]
+    pub conversation_memory_max_messages: usize,
+
+    /// 数据目录：统计、余额缓存、批次等运行时状态文件的存放位置
+    ///
+    /// 相对路径相对于配置文件所在目录；未配置时使用凭据文件所在目录
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<String>,
+
+    /// 数据目录所在磁盘的可用空间下限（MB），低于时暂停写入可选数据并在 `/readyz` 报告
+    #[serde(default = "default_min_free_disk_mb")]
+    pub min_free_disk_mb: u64,
+
+    /// Message Batches API 同时执行的请求数上限（所有批次共享）
+    #[serde(default = "default_batch_concurrency")]
+    pub batch_concurrency: usize,
+
+    /// 每种未知事件类型 / 字段保存到数据目录 `unknown-events/` 的原始帧样本数（0 表示不保存）
+    #[serde(default)]
+    pub unknown_event_samples: usize,
+
+    /// 演示模式：不访问上游、不需要凭据，所有对话请求返回模拟响应
+    #[serde(default)]
+    pub demo_mode: bool,
+
+    /// IP 白名单 / 黑名单（未配置时不限制）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_access: Option<IpAccessConfig>,
+
+    /// 跨域（CORS）规则
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfig>,
+
+    /// 请求记录签名私钥（base64 编码的 32 字节 Ed25519 种子），未配置时不签名
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_signing_key: Option<UsageSigningKey>,
+
+    /// 告警通知（Telegram / SMTP），未配置时不发送告警
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<AlertsConfig>,
+
+    /// 集群模式：多个实例通过 Redis 共享凭据状态、用量计数与限流，未配置时单实例运行
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<ClusterConfig>,
+
+    /// 租户（租户名 → 配置）：每个租户有独立的 API Key、可用模型、凭据与每月用量上限
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tenants: BTreeMap<String, TenantConfig>,
+
+    /// 命令行 `--data-dir` 指定的数据目录（运行时元数据，不写入 JSON，优先于 `dataDir`）
+    #[serde(skip)]
+    pub data_dir_override: Option<PathBuf>,
+
+    /// 加载时应用的 `KIRO_*` 环境变量名（运行时元数据，不写入 JSON）
+    #[serde(skip)]
+    pub env_overrides: Vec<String>,
+
+    /// 不对应任何配置项、已忽略的 `KIRO_*` 环境变量名（运行时元数据，不写入 JSON）
+    #[serde(skip)]
+    pub unknown_env_vars: Vec<String>,
+
     /// 配置文件路径（运行时元数据，不写入 JSON）
     #[serde(skip)]
     config_path: Option<PathBuf>,
@@ -124,6 +867,45 @@ fn default_count_tokens_auth_type() -> String {
     "x-api-key".to_string()
 }
 
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+fn default_ping_interval_secs() -> u64 {
+    25
+}
+
+fn default_conversation_memory_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_conversation_memory_max_messages() -> usize {
+    200
+}
+
+fn default_min_free_disk_mb() -> u64 {
+    100
+}
+
+fn default_admin_session_ttl_minutes() -> u64 {
+    30
+}
+
+fn default_batch_concurrency() -> usize {
+    2
+}
+
+fn default_alert_events() -> Vec<String> {
+    vec![
+        "credentialDisabled".to_string(),
+        "diskSpaceChanged".to_string(),
+    ]
+}
+
+fn default_alert_min_interval_minutes() -> u64 {
+    10
+}
+
 fn default_tls_backend() -> TlsBackend {
     TlsBackend::Rustls
 }
@@ -132,17 +914,24 @@ fn default_load_balancing_mode() -> String {
     "priority".to_string()
 }
 
+fn default_empty_response_retry() -> bool {
+    true
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             host: default_host(),
             port: default_port(),
+            server_tls: None,
+            unix_socket: None,
             region: default_region(),
             auth_region: None,
             api_region: None,
             kiro_version: default_kiro_version(),
             machine_id: None,
             api_key: None,
+            api_key_expires_at: None,
             system_version: default_system_version(),
             node_version: default_node_version(),
             tls_backend: default_tls_backend(),
@@ -153,7 +942,57 @@ impl Default for Config {
             proxy_username: None,
             proxy_password: None,
             admin_api_key: None,
+            admin_readonly_api_key: None,
+            admin_accounts: Vec::new(),
+            admin_session_ttl_minutes: default_admin_session_ttl_minutes(),
+            grpc_port: None,
             load_balancing_mode: default_load_balancing_mode(),
+            tool_result_max_chars: None,
+            empty_response_retry: default_empty_response_retry(),
+            system_prompt_inject_date: false,
+            system_prompt_utc_offset: None,
+            system_prompt_locale: None,
+            moderation_api_url: None,
+            moderation_api_key: None,
+            moderation_keywords: Vec::new(),
+            fetch_image_urls: false,
+            model_aliases: BTreeMap::new(),
+            model_pricing: BTreeMap::new(),
+            embeddings_api_url: None,
+            embeddings_api_key: None,
+            rate_limit_requests_per_minute: None,
+            rate_limit_tokens_per_minute: None,
+            secret_scanning: false,
+            allow_credential_override: false,
+            upstream_request_compression: false,
+            dns_overrides: BTreeMap::new(),
+            dns_over_https_url: None,
+            ip_preference: IpPreference::default(),
+            kiro_tls: None,
+            integration_tls: None,
+            upstream_extra_headers: BTreeMap::new(),
+            region_endpoints: BTreeMap::new(),
+            account_refresh_interval_secs: None,
+            log_format: LogFormat::default(),
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+            ping_interval_secs: default_ping_interval_secs(),
+            conversation_memory: false,
+            conversation_memory_ttl_secs: default_conversation_memory_ttl_secs(),
+            conversation_memory_max_messages: default_conversation_memory_max_messages(),
+            data_dir: None,
+            min_free_disk_mb: default_min_free_disk_mb(),
+            batch_concurrency: default_batch_concurrency(),
+            unknown_event_samples: 0,
+            demo_mode: false,
+            ip_access: None,
+            cors: None,
+            usage_signing_key: None,
+            alerts: None,
+            cluster: None,
+            tenants: BTreeMap::new(),
+            data_dir_override: None,
+            env_overrides: Vec::new(),
+            unknown_env_vars: Vec::new(),
             config_path: None,
         }
     }
@@ -165,6 +1004,45 @@ impl Config {
         "config.json"
     }
 
+    /// 平台相关的配置文件位置（按查找顺序，不含当前目录）
+    ///
+    /// - `$XDG_CONFIG_HOME/kiro/config.json`（Unix 上未设置时为 `~/.config/kiro/config.json`）
+    /// - macOS：`~/Library/Application Support/kiro/config.json`
+    /// - Windows：`%APPDATA%\kiro\config.json`
+    pub fn platform_config_paths() -> Vec<PathBuf> {
+        let env_dir = |name: &str| {
+            std::env::var_os(name)
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+        };
+        let home = env_dir("HOME");
+        let mut dirs = Vec::new();
+
+        match env_dir("XDG_CONFIG_HOME") {
+            Some(dir) => dirs.push(dir),
+            None if cfg!(unix) => dirs.extend(home.as_ref().map(|h| h.join(".config"))),
+            None => {}
+        }
+        if cfg!(target_os = "macos") {
+            dirs.extend(home.map(|h| h.join("Library").join("Application Support")));
+        }
+        if cfg!(windows) {
+            dirs.extend(env_dir("APPDATA"));
+        }
+
+        dirs.into_iter()
+            .map(|dir| dir.join("kiro").join(Self::default_config_path()))
+            .collect()
+    }
+
+    /// 未指定 `--config` 时使用的配置文件：第一个存在的平台配置位置，都不存在时为 `./config.json`
+    pub fn resolve_default_path() -> PathBuf {
+        Self::platform_config_paths()
+            .into_iter()
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| PathBuf::from(Self::default_config_path()))
+    }
+
     /// 获取有效的 Auth Region（用于 Token 刷新）
     /// 优先使用 auth_region，未配置时回退到 region
     pub fn effective_auth_region(&self) -> &str {
@@ -179,16 +1057,59 @@ impl Config {
 
     /// 从文件加载配置
     pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
-        let path = path.as_ref();
-        if !path.exists() {
+        Self::load_with_env(path.as_ref(), std::env::vars())
+    }
+
+    /// 只读取配置文件、不应用环境变量（修改后写回文件时使用，避免把环境变量的值写入文件）
+    pub fn load_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        Self::load_with_env(path.as_ref(), std::iter::empty())
+    }
+
+    /// 读取配置文件（不存在时使用默认配置），再按 `KIRO_*` 环境变量覆盖
+    fn load_with_env(
+        path: &Path,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> anyhow::Result<Self> {
+        let mut overrides: Vec<(String, Vec<String>, String)> = vars
+            .into_iter()
+            .filter_map(|(name, raw)| Some((name.clone(), env_override_path(&name)?, raw)))
+            .collect();
+        // 按名称排序：父级（如 KIRO_ALERTS）先于嵌套字段（KIRO_ALERTS__SMTP__PASSWORD）应用
+        overrides.sort();
+
+        let mut value = if path.exists() {
+            let content = fs::read_to_string(path)?;
+            let value: serde_json::Value = serde_json::from_str(&content)?;
+            // 先校验文件本身，错误信息指向配置文件而不是环境变量
+            serde_json::from_value::<Config>(value.clone())?;
+            value
+        } else if overrides.is_empty() {
             // 配置文件不存在，返回默认配置
             let mut config = Self::default();
             config.config_path = Some(path.to_path_buf());
             return Ok(config);
+        } else {
+            serde_json::to_value(Self::default())?
+        };
+
+        let mut applied = Vec::new();
+        let mut unknown = Vec::new();
+        for (name, keys, raw) in overrides {
+            let trial = apply_env_override(value.clone(), &keys, &raw)
+                .map_err(|e| anyhow::anyhow!("环境变量 {} 的值无效: {}", name, e))?;
+            // 不对应任何配置项的变量在反序列化时被忽略，重新序列化后找不到它的路径
+            let known = serde_json::to_value(serde_json::from_value::<Config>(trial.clone())?)?;
+            if json_path_exists(&known, &keys) || is_empty_json(&raw) {
+                value = trial;
+                applied.push(name);
+            } else {
+                unknown.push(name);
+            }
         }
 
-        let content = fs::read_to_string(path)?;
-        let mut config: Config = serde_json::from_str(&content)?;
+        let mut config: Config = serde_json::from_value(value)?;
+        config.env_overrides = applied;
+        config.unknown_env_vars = unknown;
         config.config_path = Some(path.to_path_buf());
         Ok(config)
     }
@@ -198,6 +1119,84 @@ impl Config {
         self.config_path.as_deref()
     }
 
+    /// 配置的数据目录（相对路径按配置文件所在目录解析）
+    pub fn resolved_data_dir(&self) -> Option<PathBuf> {
+        if let Some(dir) = &self.data_dir_override {
+            return Some(dir.clone());
+        }
+        let dir = Path::new(self.data_dir.as_deref().filter(|d| !d.trim().is_empty())?);
+        let base = self.config_path.as_deref().and_then(Path::parent);
+        Some(match base {
+            Some(base) if dir.is_relative() => base.join(dir),
+            _ => dir.to_path_buf(),
+        })
+    }
+
+    /// 查找模型单价：优先精确匹配，否则使用最长的前缀匹配
+    pub fn pricing_for(&self, model: &str) -> Option<&ModelPricing> {
+        self.model_pricing.get(model).or_else(|| {
+            self.model_pricing
+                .iter()
+                .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+                .max_by_key(|(prefix, _)| prefix.len())
+                .map(|(_, pricing)| pricing)
+        })
+    }
+
+    /// 所有 Admin 账号：`adminAccounts` 加上 `adminApiKey`（名为 `admin`）和
+    /// `adminReadonlyApiKey`（名为 `readonly`），忽略空 Key
+    pub fn all_admin_accounts(&self) -> Vec<AdminAccount> {
+        let legacy = [
+            (&self.admin_api_key, "admin", AdminRole::Admin),
+            (
+                &self.admin_readonly_api_key,
+                "readonly",
+                AdminRole::Readonly,
+            ),
+        ];
+        legacy
+            .into_iter()
+            .filter_map(|(key, name, role)| {
+                key.as_ref().map(|key| AdminAccount {
+                    name: name.to_string(),
+                    key: key.clone(),
+                    role,
+                })
+            })
+            .chain(self.admin_accounts.iter().cloned())
+            .filter(|account| !account.key.trim().is_empty())
+            .collect()
+    }
+
+    /// 按 Admin API Key 查找账号
+    pub fn find_admin_account(&self, presented: &str) -> Option<AdminAccount> {
+        self.all_admin_accounts()
+            .into_iter()
+            .find(|account| crate::common::auth::verify_api_key(presented, &account.key))
+    }
+
+    /// 按客户端提供的 API Key 查找租户
+    pub fn find_tenant(&self, presented: &str) -> Option<(&str, &TenantConfig)> {
+        self.tenants.iter().find_map(|(name, tenant)| {
+            tenant
+                .api_keys
+                .iter()
+                .any(|key| crate::common::auth::verify_api_key(presented, key))
+                .then_some((name.as_str(), tenant))
+        })
+    }
+
+    /// 按 API Key 标识（见 `common::auth::api_key_id`）查找所属租户名
+    pub fn tenant_for_key_id(&self, key_id: &str) -> Option<&str> {
+        self.tenants.iter().find_map(|(name, tenant)| {
+            tenant
+                .api_keys
+                .iter()
+                .any(|key| crate::common::auth::configured_api_key_id(key) == key_id)
+                .then_some(name.as_str())
+        })
+    }
+
     /// 将当前配置写回原始配置文件
     pub fn save(&self) -> anyhow::Result<()> {
         let path = self
@@ -209,4 +1208,51 @@ impl Config {
         fs::write(path, content).with_context(|| format!("写入配置文件失败: {}", path.display()))?;
         Ok(())
     }
+
+    /// 列出相对 `other` 发生变化、但只能在重启后生效的配置项（camelCase 名称）
+    ///
+    /// 这些配置在启动时用于构建监听地址、路由认证、限流器或全局客户端，热重载无法应用
+    pub fn restart_required_changes(&self, other: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        macro_rules! check {
+            ($($field:ident => $name:literal),* $(,)?) => {
+                $(if self.$field != other.$field { changed.push($name); })*
+            };
+        }
+        check! {
+            host => "host",
+            port => "port",
+            server_tls => "serverTls",
+            unix_socket => "unixSocket",
+            cors => "cors",
+            grpc_port => "grpcPort",
+            api_key => "apiKey",
+            tls_backend => "tlsBackend",
+            count_tokens_api_url => "countTokensApiUrl",
+            count_tokens_api_key => "countTokensApiKey",
+            count_tokens_auth_type => "countTokensAuthType",
+            moderation_api_url => "moderationApiUrl",
+            moderation_api_key => "moderationApiKey",
+            moderation_keywords => "moderationKeywords",
+            fetch_image_urls => "fetchImageUrls",
+            rate_limit_requests_per_minute => "rateLimitRequestsPerMinute",
+            rate_limit_tokens_per_minute => "rateLimitTokensPerMinute",
+            dns_overrides => "dnsOverrides",
+            dns_over_https_url => "dnsOverHttpsUrl",
+            ip_preference => "ipPreference",
+            kiro_tls => "kiroTls",
+            integration_tls => "integrationTls",
+            log_format => "logFormat",
+            batch_concurrency => "batchConcurrency",
+            unknown_event_samples => "unknownEventSamples",
+            min_free_disk_mb => "minFreeDiskMb",
+            data_dir => "dataDir",
+            cluster => "cluster",
+        }
+        // Admin 账号可热重载，但是否挂载 Admin API 在启动时决定
+        if self.all_admin_accounts().is_empty() != other.all_admin_accounts().is_empty() {
+            changed.push("adminAccounts");
+        }
+        changed
+    }
 }
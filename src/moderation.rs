@@ -0,0 +1,273 @@
+//! 内容审核前置过滤模块
+//!
+//! 在请求发往上游之前审核用户最新一条消息：
+//! - 本地关键词：命中任一关键词（不区分大小写）即拒绝
+//! - 远程审核接口：POST `{"input": "..."}`，响应中 `flagged`（或 OpenAI 格式的 `results[0].flagged`）为 true 时拒绝
+//!
+//! 被拒绝的请求不会调用上游，而是返回 `stop_reason = "refusal"` 的 Anthropic 风格响应。
+//! 远程接口调用失败时放行（仅记录警告）。
+
+use std::sync::OnceLock;
+
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::anthropic::stream::SseEvent;
+use crate::anthropic::types::Message;
+use crate::http_client::{ProxyConfig, build_client};
+use crate::model::config::TlsBackend;
+use crate::tls::Destination;
+
+/// 拒绝时返回给客户端的文本
+pub const REFUSAL_TEXT: &str =
+    "I can't help with that request: it was blocked by the content policy of this service.";
+
+/// 远程审核接口超时（秒）
+const MODERATION_TIMEOUT_SECS: u64 = 10;
+
+/// 内容审核配置
+#[derive(Clone, Default)]
+pub struct ModerationConfig {
+    /// 远程审核接口地址
+    pub api_url: Option<String>,
+    /// 远程审核接口密钥（以 Bearer 方式发送）
+    pub api_key: Option<String>,
+    /// 本地拒绝关键词
+    pub keywords: Vec<String>,
+    /// 代理配置
+    pub proxy: Option<ProxyConfig>,
+
+    pub tls_backend: TlsBackend,
+}
+
+impl ModerationConfig {
+    fn is_enabled(&self) -> bool {
+        self.api_url.is_some() || !self.keywords.is_empty()
+    }
+}
+
+/// 审核结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModerationVerdict {
+    Allow,
+    /// 拒绝，附带原因（仅用于日志）
+    Deny(String),
+}
+
+/// 全局配置存储
+static MODERATION_CONFIG: OnceLock<ModerationConfig> = OnceLock::new();
+
+/// 初始化内容审核配置
+///
+/// 应在应用启动时调用一次
+pub fn init_config(config: ModerationConfig) {
+    let _ = MODERATION_CONFIG.set(config);
+}
+
+/// 审核请求中最新的用户消息
+pub(crate) async fn check_messages(messages: &[Message]) -> ModerationVerdict {
+    let Some(config) = MODERATION_CONFIG.get().filter(|c| c.is_enabled()) else {
+        return ModerationVerdict::Allow;
+    };
+
+    let Some(text) = last_user_text(messages) else {
+        return ModerationVerdict::Allow;
+    };
+
+    if let Some(keyword) = match_keyword(&config.keywords, &text) {
+        return ModerationVerdict::Deny(format!("命中关键词: {}", keyword));
+    }
+
+    if let Some(api_url) = &config.api_url {
+        match call_remote_moderation(api_url, config, &text).await {
+            Ok(true) => return ModerationVerdict::Deny("远程审核接口判定违规".to_string()),
+            Ok(false) => {}
+            Err(e) => tracing::warn!("远程审核接口调用失败，放行请求: {}", e),
+        }
+    }
+
+    ModerationVerdict::Allow
+}
+
+/// 提取最后一条 user 消息中的文本（tool_result 等非文本块不参与审核）
+fn last_user_text(messages: &[Message]) -> Option<String> {
+    let message = messages.iter().rev().find(|m| m.role == "user")?;
+    let text = match &message.content {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(blocks) => blocks
+            .iter()
+            .filter(|b| b.get("type").and_then(|v| v.as_str()) == Some("text"))
+            .filter_map(|b| b.get("text").and_then(|v| v.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+
+    if text.trim().is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// 返回命中的第一个关键词
+fn match_keyword<'a>(keywords: &'a [String], text: &str) -> Option<&'a str> {
+    let text_lower = text.to_lowercase();
+    keywords
+        .iter()
+        .filter(|k| !k.is_empty())
+        .find(|k| text_lower.contains(&k.to_lowercase()))
+        .map(|k| k.as_str())
+}
+
+/// 调用远程审核接口，返回是否违规
+async fn call_remote_moderation(
+    api_url: &str,
+    config: &ModerationConfig,
+    text: &str,
+) -> anyhow::Result<bool> {
+    let client = build_client(
+        config.proxy.as_ref(),
+        MODERATION_TIMEOUT_SECS,
+        config.tls_backend,
+        Destination::Integration,
+    )?;
+
+    let mut req_builder = client.post(api_url).json(&json!({ "input": text }));
+    if let Some(api_key) = &config.api_key {
+        req_builder = req_builder.header("Authorization", format!("Bearer {}", api_key));
+    }
+
+    let response = req_builder.send().await?;
+    if !response.status().is_success() {
+        anyhow::bail!("审核接口返回错误状态: {}", response.status());
+    }
+
+    let body: serde_json::Value = response.json().await?;
+    parse_flagged(&body).ok_or_else(|| anyhow::anyhow!("无法识别的审核接口响应: {}", body))
+}
+
+/// 解析审核接口响应中的 flagged 字段
+fn parse_flagged(body: &serde_json::Value) -> Option<bool> {
+    body.get("flagged")
+        .or_else(|| body.pointer("/results/0/flagged"))
+        .and_then(|v| v.as_bool())
+}
+
+/// 构建拒绝响应的 SSE 事件序列（流式）
+pub(crate) fn refusal_events(model: &str, input_tokens: i32) -> Vec<SseEvent> {
+    let message = refusal_message(model, input_tokens);
+    vec![
+        SseEvent::new(
+            "message_start",
+            json!({
+                "type": "message_start",
+                "message": {
+                    "id": message["id"],
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [],
+                    "model": model,
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": {"input_tokens": input_tokens, "output_tokens": 0}
+                }
+            }),
+        ),
+        SseEvent::new(
+            "content_block_start",
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        ),
+        SseEvent::new(
+            "content_block_delta",
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": REFUSAL_TEXT}}),
+        ),
+        SseEvent::new(
+            "content_block_stop",
+            json!({"type": "content_block_stop", "index": 0}),
+        ),
+        SseEvent::new(
+            "message_delta",
+            json!({
+                "type": "message_delta",
+                "delta": {"stop_reason": "refusal", "stop_sequence": null},
+                "usage": message["usage"]
+            }),
+        ),
+        SseEvent::new("message_stop", json!({"type": "message_stop"})),
+    ]
+}
+
+/// 构建拒绝响应的消息体（非流式）
+pub(crate) fn refusal_message(model: &str, input_tokens: i32) -> serde_json::Value {
+    json!({
+        "id": format!("msg_{}", Uuid::new_v4().to_string().replace('-', "")),
+        "type": "message",
+        "role": "assistant",
+        "content": [{"type": "text", "text": REFUSAL_TEXT}],
+        "model": model,
+        "stop_reason": "refusal",
+        "stop_sequence": null,
+        "usage": {
+            "input_tokens": input_tokens,
+            "output_tokens": crate::token::count_tokens(REFUSAL_TEXT) as i32
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(content: serde_json::Value) -> Message {
+        Message {
+            role: "user".to_string(),
+            content,
+        }
+    }
+
+    #[test]
+    fn test_last_user_text_ignores_non_text_blocks() {
+        let messages = vec![
+            user(json!("first")),
+            Message {
+                role: "assistant".to_string(),
+                content: json!("ok"),
+            },
+            user(json!([
+                {"type": "tool_result", "tool_use_id": "t1", "content": "secret"},
+                {"type": "text", "text": "second"}
+            ])),
+        ];
+        assert_eq!(last_user_text(&messages).as_deref(), Some("second"));
+        assert_eq!(last_user_text(&[user(json!([]))]), None);
+    }
+
+    #[test]
+    fn test_match_keyword_case_insensitive() {
+        let keywords = vec!["".to_string(), "Forbidden".to_string()];
+        assert_eq!(
+            match_keyword(&keywords, "this is FORBIDDEN text"),
+            Some("Forbidden")
+        );
+        assert_eq!(match_keyword(&keywords, "harmless"), None);
+    }
+
+    #[test]
+    fn test_parse_flagged_formats() {
+        assert_eq!(parse_flagged(&json!({"flagged": true})), Some(true));
+        assert_eq!(
+            parse_flagged(&json!({"results": [{"flagged": false}]})),
+            Some(false)
+        );
+        assert_eq!(parse_flagged(&json!({"ok": 1})), None);
+    }
+
+    #[test]
+    fn test_refusal_events_shape() {
+        let events = refusal_events("claude-sonnet-4-5", 10);
+        assert_eq!(events.first().unwrap().event, "message_start");
+        assert_eq!(events.last().unwrap().event, "message_stop");
+        assert_eq!(events[4].data["delta"]["stop_reason"], "refusal");
+    }
+}
@@ -0,0 +1,616 @@
+//! OpenAI ↔ Anthropic 协议转换
+//!
+//! 请求方向：将 OpenAI Chat Completions 请求转换为 Anthropic Messages 请求，
+//! 之后复用 Anthropic → Kiro 的转换逻辑。
+//! 响应方向：将 Anthropic SSE 事件翻译为 OpenAI `chat.completion.chunk`，
+//! 非流式响应由同一组 chunk 聚合而成。
+
+use std::collections::HashMap;
+
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::anthropic::stream::SseEvent;
+use crate::anthropic::types::{Message, MessagesRequest, Metadata, SystemMessage, Thinking, Tool};
+
+use super::types::{ChatCompletionRequest, ChatMessage, ChatTool};
+
+/// 未指定 max_tokens 时的默认值
+const DEFAULT_MAX_TOKENS: i32 = 4096;
+
+/// 将 OpenAI 请求转换为 Anthropic 请求
+pub fn to_messages_request(req: &ChatCompletionRequest) -> Result<MessagesRequest, String> {
+    let mut system = Vec::new();
+    let mut messages: Vec<Message> = Vec::new();
+
+    for msg in &req.messages {
+        match msg.role.as_str() {
+            "system" | "developer" => {
+                let text = content_to_text(msg.content.as_ref());
+                if !text.is_empty() {
+                    system.push(SystemMessage {
+                        text,
+                        cache_control: None,
+                    });
+                }
+            }
+            "user" => push_message(&mut messages, "user", user_content(msg)),
+            "assistant" => {
+                let content = assistant_content(msg);
+                if !content.is_empty() {
+                    push_message(&mut messages, "assistant", content);
+                }
+            }
+            "tool" => {
+                let tool_call_id = msg
+                    .tool_call_id
+                    .clone()
+                    .ok_or_else(|| "tool 消息缺少 tool_call_id".to_string())?;
+                push_message(
+                    &mut messages,
+                    "user",
+                    vec![json!({
+                        "type": "tool_result",
+                        "tool_use_id": tool_call_id,
+                        "content": content_to_text(msg.content.as_ref())
+                    })],
+                );
+            }
+            other => return Err(format!("不支持的消息角色: {}", other)),
+        }
+    }
+
+    let tools = req.tools.as_ref().map(|tools| {
+        tools
+            .iter()
+            .filter(|t| t.tool_type == "function")
+            .map(convert_tool)
+            .collect()
+    });
+
+    let thinking = req.reasoning_effort.as_deref().map(|effort| Thinking {
+        thinking_type: "enabled".to_string(),
+        budget_tokens: match effort {
+            "low" => 4096,
+            "medium" => 10000,
+            _ => 24576,
+        },
+    });
+
+    Ok(MessagesRequest {
+        model: req.model.clone(),
+        max_tokens: req
+            .max_completion_tokens
+            .or(req.max_tokens)
+            .unwrap_or(DEFAULT_MAX_TOKENS),
+        messages,
+        stream: req.stream,
+        system: if system.is_empty() {
+            None
+        } else {
+            Some(system)
+        },
+        tools,
+        tool_choice: req.tool_choice.as_ref().map(convert_tool_choice),
+        thinking,
+        output_config: None,
+        metadata: req.user.as_ref().map(|user| Metadata {
+            user_id: Some(user.clone()),
+        }),
+    })
+}
+
+/// 追加消息，与上一条同角色消息合并（tool 结果与后续 user 消息需要合并为一条）
+fn push_message(messages: &mut Vec<Message>, role: &str, blocks: Vec<serde_json::Value>) {
+    if let Some(last) = messages.last_mut()
+        && last.role == role
+        && let serde_json::Value::Array(existing) = &mut last.content
+    {
+        existing.extend(blocks);
+        return;
+    }
+
+    messages.push(Message {
+        role: role.to_string(),
+        content: serde_json::Value::Array(blocks),
+    });
+}
+
+/// 提取纯文本内容（字符串或 text 片段数组）
+fn content_to_text(content: Option<&serde_json::Value>) -> String {
+    match content {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(|v| v.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// 转换 user 消息内容为 Anthropic 内容块
+fn user_content(msg: &ChatMessage) -> Vec<serde_json::Value> {
+    match &msg.content {
+        Some(serde_json::Value::String(s)) => vec![json!({"type": "text", "text": s})],
+        Some(serde_json::Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| match part.get("type").and_then(|v| v.as_str()) {
+                Some("text") => part
+                    .get("text")
+                    .and_then(|v| v.as_str())
+                    .map(|text| json!({"type": "text", "text": text})),
+                Some("image_url") => {
+                    let url = part
+                        .get("image_url")
+                        .and_then(|v| v.get("url").or(Some(v)))
+                        .and_then(|v| v.as_str())?;
+                    Some(convert_image_url(url))
+                }
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// 转换图片 URL：data URL 转为 base64 图片块，其他 URL 保留为 url 图片块
+fn convert_image_url(url: &str) -> serde_json::Value {
+    if let Some(rest) = url.strip_prefix("data:")
+        && let Some((media_type, data)) = rest.split_once(";base64,")
+    {
+        return json!({
+            "type": "image",
+            "source": {"type": "base64", "media_type": media_type, "data": data}
+        });
+    }
+
+    json!({
+        "type": "image",
+        "source": {"type": "url", "url": url}
+    })
+}
+
+/// 转换 assistant 消息内容（文本 + 工具调用）为 Anthropic 内容块
+fn assistant_content(msg: &ChatMessage) -> Vec<serde_json::Value> {
+    let mut blocks = Vec::new();
+
+    let text = content_to_text(msg.content.as_ref());
+    if !text.is_empty() {
+        blocks.push(json!({"type": "text", "text": text}));
+    }
+
+    for call in msg.tool_calls.iter().flatten() {
+        let input: serde_json::Value = if call.function.arguments.trim().is_empty() {
+            json!({})
+        } else {
+            serde_json::from_str(&call.function.arguments).unwrap_or_else(|e| {
+                tracing::warn!("工具调用参数 JSON 解析失败: {}, id: {}", e, call.id);
+                json!({})
+            })
+        };
+        blocks.push(json!({
+            "type": "tool_use",
+            "id": call.id,
+            "name": call.function.name,
+            "input": input
+        }));
+    }
+
+    blocks
+}
+
+/// 转换工具定义
+fn convert_tool(tool: &ChatTool) -> Tool {
+    let input_schema = match &tool.function.parameters {
+        Some(serde_json::Value::Object(map)) => map.clone().into_iter().collect(),
+        _ => HashMap::new(),
+    };
+
+    Tool {
+        tool_type: None,
+        name: tool.function.name.clone(),
+        description: tool.function.description.clone(),
+        input_schema,
+        max_uses: None,
+        cache_control: None,
+    }
+}
+
+/// 转换 tool_choice 为 Anthropic 格式
+fn convert_tool_choice(choice: &serde_json::Value) -> serde_json::Value {
+    match choice {
+        serde_json::Value::String(s) => match s.as_str() {
+            "required" => json!({"type": "any"}),
+            "none" => json!({"type": "none"}),
+            _ => json!({"type": "auto"}),
+        },
+        serde_json::Value::Object(_) => {
+            let name = choice.pointer("/function/name").and_then(|v| v.as_str());
+            json!({"type": "tool", "name": name})
+        }
+        _ => json!({"type": "auto"}),
+    }
+}
+
+/// 将 Anthropic stop_reason 映射为 OpenAI finish_reason
+fn map_finish_reason(stop_reason: &str) -> &'static str {
+    match stop_reason {
+        "tool_use" => "tool_calls",
+        "max_tokens" | "model_context_window_exceeded" => "length",
+        "refusal" => "content_filter",
+        _ => "stop",
+    }
+}
+
+/// Anthropic SSE 事件 → OpenAI chunk 翻译器
+pub struct ChunkTranslator {
+    id: String,
+    created: i64,
+    model: String,
+    /// Anthropic 内容块索引 → OpenAI tool_calls 索引
+    tool_call_indices: HashMap<i64, usize>,
+    prompt_tokens: i64,
+    completion_tokens: i64,
+}
+
+impl ChunkTranslator {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", Uuid::new_v4().simple()),
+            created: chrono::Utc::now().timestamp(),
+            model: model.into(),
+            tool_call_indices: HashMap::new(),
+            prompt_tokens: 0,
+            completion_tokens: 0,
+        }
+    }
+
+    fn chunk(&self, delta: serde_json::Value, finish_reason: Option<&str>) -> serde_json::Value {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": finish_reason
+            }]
+        })
+    }
+
+    /// 翻译单个 Anthropic SSE 事件，返回零或多个 OpenAI chunk
+    pub fn translate(&mut self, event: &SseEvent) -> Vec<serde_json::Value> {
+        let data = &event.data;
+        match event.event.as_str() {
+            "message_start" => {
+                if let Some(tokens) = data
+                    .pointer("/message/usage/input_tokens")
+                    .and_then(|v| v.as_i64())
+                {
+                    self.prompt_tokens = tokens;
+                }
+                vec![self.chunk(json!({"role": "assistant", "content": ""}), None)]
+            }
+            "content_block_start" => {
+                if data.pointer("/content_block/type").and_then(|v| v.as_str()) != Some("tool_use")
+                {
+                    return Vec::new();
+                }
+                let block_index = data["index"].as_i64().unwrap_or_default();
+                let call_index = self.tool_call_indices.len();
+                self.tool_call_indices.insert(block_index, call_index);
+                vec![self.chunk(
+                    json!({"tool_calls": [{
+                        "index": call_index,
+                        "id": data["content_block"]["id"],
+                        "type": "function",
+                        "function": {"name": data["content_block"]["name"], "arguments": ""}
+                    }]}),
+                    None,
+                )]
+            }
+            "content_block_delta" => {
+                let delta = &data["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => match delta["text"].as_str() {
+                        Some(text) if !text.is_empty() => {
+                            vec![self.chunk(json!({"content": text}), None)]
+                        }
+                        _ => Vec::new(),
+                    },
+                    Some("thinking_delta") => match delta["thinking"].as_str() {
+                        Some(thinking) if !thinking.is_empty() => {
+                            vec![self.chunk(json!({"reasoning_content": thinking}), None)]
+                        }
+                        _ => Vec::new(),
+                    },
+                    Some("input_json_delta") => {
+                        let block_index = data["index"].as_i64().unwrap_or_default();
+                        let Some(&call_index) = self.tool_call_indices.get(&block_index) else {
+                            return Vec::new();
+                        };
+                        vec![self.chunk(
+                            json!({"tool_calls": [{
+                                "index": call_index,
+                                "function": {"arguments": delta["partial_json"]}
+                            }]}),
+                            None,
+                        )]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            "message_delta" => {
+                if let Some(tokens) = data.pointer("/usage/input_tokens").and_then(|v| v.as_i64()) {
+                    self.prompt_tokens = tokens;
+                }
+                if let Some(tokens) = data
+                    .pointer("/usage/output_tokens")
+                    .and_then(|v| v.as_i64())
+                {
+                    self.completion_tokens = tokens;
+                }
+                let stop_reason = data.pointer("/delta/stop_reason").and_then(|v| v.as_str());
+                vec![self.chunk(
+                    json!({}),
+                    Some(map_finish_reason(stop_reason.unwrap_or("end_turn"))),
+                )]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// usage 对象
+    pub fn usage(&self) -> serde_json::Value {
+        json!({
+            "prompt_tokens": self.prompt_tokens,
+            "completion_tokens": self.completion_tokens,
+            "total_tokens": self.prompt_tokens + self.completion_tokens
+        })
+    }
+
+    /// `stream_options.include_usage` 时在末尾发送的 usage chunk
+    pub fn usage_chunk(&self) -> serde_json::Value {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [],
+            "usage": self.usage()
+        })
+    }
+
+    /// 将全部 chunk 聚合为非流式 `chat.completion` 响应
+    pub fn aggregate(&self, chunks: &[serde_json::Value]) -> serde_json::Value {
+        let mut content = String::new();
+        let mut reasoning = String::new();
+        let mut tool_calls: Vec<serde_json::Value> = Vec::new();
+        let mut finish_reason = "stop".to_string();
+
+        for chunk in chunks {
+            let Some(choice) = chunk.pointer("/choices/0") else {
+                continue;
+            };
+            if let Some(reason) = choice["finish_reason"].as_str() {
+                finish_reason = reason.to_string();
+            }
+            let delta = &choice["delta"];
+            if let Some(text) = delta["content"].as_str() {
+                content.push_str(text);
+            }
+            if let Some(text) = delta["reasoning_content"].as_str() {
+                reasoning.push_str(text);
+            }
+            for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                let index = call["index"].as_u64().unwrap_or_default() as usize;
+                if index >= tool_calls.len() {
+                    tool_calls.push(json!({
+                        "id": call["id"],
+                        "type": "function",
+                        "function": {"name": call["function"]["name"], "arguments": ""}
+                    }));
+                }
+                if let Some(args) = call["function"]["arguments"].as_str()
+                    && let Some(existing) = tool_calls[index]["function"]["arguments"].as_str()
+                {
+                    tool_calls[index]["function"]["arguments"] =
+                        json!(format!("{}{}", existing, args));
+                }
+            }
+        }
+
+        let mut message = json!({
+            "role": "assistant",
+            "content": if content.is_empty() && !tool_calls.is_empty() { serde_json::Value::Null } else { json!(content) }
+        });
+        if !reasoning.is_empty() {
+            message["reasoning_content"] = json!(reasoning);
+        }
+        if !tool_calls.is_empty() {
+            message["tool_calls"] = json!(tool_calls);
+        }
+
+        json!({
+            "id": self.id,
+            "object": "chat.completion",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "message": message,
+                "finish_reason": finish_reason
+            }],
+            "usage": self.usage()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_request(value: serde_json::Value) -> ChatCompletionRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_to_messages_request_basic() {
+        let req = parse_request(json!({
+            "model": "claude-sonnet-4-5",
+            "max_completion_tokens": 100,
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "Hi"}
+            ]
+        }));
+
+        let converted = to_messages_request(&req).unwrap();
+        assert_eq!(converted.max_tokens, 100);
+        assert_eq!(converted.system.unwrap()[0].text, "Be brief.");
+        assert_eq!(converted.messages.len(), 1);
+        assert_eq!(converted.messages[0].content[0]["text"], "Hi");
+        assert!(converted.thinking.is_none());
+    }
+
+    #[test]
+    fn test_to_messages_request_tool_round_trip() {
+        let req = parse_request(json!({
+            "model": "claude-sonnet-4-5",
+            "tool_choice": "required",
+            "tools": [{"type": "function", "function": {
+                "name": "get_weather",
+                "description": "Get weather",
+                "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+            }}],
+            "messages": [
+                {"role": "user", "content": "Weather in Paris and Rome?"},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}},
+                    {"id": "call_2", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Rome\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "sunny"},
+                {"role": "tool", "tool_call_id": "call_2", "content": "rainy"}
+            ]
+        }));
+
+        let converted = to_messages_request(&req).unwrap();
+        assert_eq!(converted.tool_choice.unwrap(), json!({"type": "any"}));
+        assert_eq!(converted.tools.as_ref().unwrap()[0].name, "get_weather");
+
+        // 连续的 tool 消息应合并为一条 user 消息
+        assert_eq!(converted.messages.len(), 3);
+        let assistant = &converted.messages[1];
+        assert_eq!(assistant.content[0]["type"], "tool_use");
+        assert_eq!(assistant.content[0]["input"]["city"], "Paris");
+        let results = converted.messages[2].content.as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1]["tool_use_id"], "call_2");
+        assert_eq!(results[1]["content"], "rainy");
+    }
+
+    #[test]
+    fn test_to_messages_request_image_and_reasoning() {
+        let req = parse_request(json!({
+            "model": "claude-sonnet-4-5",
+            "reasoning_effort": "low",
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "What is this?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+            ]}]
+        }));
+
+        let converted = to_messages_request(&req).unwrap();
+        let image = &converted.messages[0].content[1];
+        assert_eq!(image["source"]["media_type"], "image/png");
+        assert_eq!(image["source"]["data"], "AAAA");
+        assert_eq!(converted.thinking.unwrap().budget_tokens, 4096);
+    }
+
+    #[test]
+    fn test_to_messages_request_rejects_tool_without_id() {
+        let req = parse_request(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [{"role": "tool", "content": "x"}]
+        }));
+        assert!(to_messages_request(&req).is_err());
+    }
+
+    fn sse(event: &str, data: serde_json::Value) -> SseEvent {
+        SseEvent::new(event, data)
+    }
+
+    #[test]
+    fn test_chunk_translator_and_aggregate() {
+        let mut translator = ChunkTranslator::new("claude-sonnet-4-5");
+        let events = vec![
+            sse(
+                "message_start",
+                json!({"type": "message_start", "message": {"usage": {"input_tokens": 12}}}),
+            ),
+            sse(
+                "content_block_start",
+                json!({"index": 0, "content_block": {"type": "text", "text": ""}}),
+            ),
+            sse(
+                "content_block_delta",
+                json!({"index": 0, "delta": {"type": "text_delta", "text": "Let me check."}}),
+            ),
+            sse("content_block_stop", json!({"index": 0})),
+            sse(
+                "content_block_start",
+                json!({"index": 1, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "get_weather"}}),
+            ),
+            sse(
+                "content_block_delta",
+                json!({"index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"city\":"}}),
+            ),
+            sse(
+                "content_block_delta",
+                json!({"index": 1, "delta": {"type": "input_json_delta", "partial_json": "\"Paris\"}"}}),
+            ),
+            sse("content_block_stop", json!({"index": 1})),
+            sse(
+                "message_delta",
+                json!({"delta": {"stop_reason": "tool_use"}, "usage": {"input_tokens": 20, "output_tokens": 7}}),
+            ),
+            sse("message_stop", json!({})),
+        ];
+
+        let chunks: Vec<_> = events
+            .iter()
+            .flat_map(|e| translator.translate(e))
+            .collect();
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        assert!(
+            chunks
+                .iter()
+                .all(|c| c["object"] == "chat.completion.chunk")
+        );
+        assert_eq!(
+            chunks.last().unwrap()["choices"][0]["finish_reason"],
+            "tool_calls"
+        );
+
+        let completion = translator.aggregate(&chunks);
+        let message = &completion["choices"][0]["message"];
+        assert_eq!(message["content"], "Let me check.");
+        assert_eq!(message["tool_calls"][0]["id"], "toolu_1");
+        assert_eq!(
+            message["tool_calls"][0]["function"]["arguments"],
+            "{\"city\":\"Paris\"}"
+        );
+        assert_eq!(completion["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(completion["usage"]["total_tokens"], 27);
+    }
+
+    #[test]
+    fn test_map_finish_reason() {
+        assert_eq!(map_finish_reason("end_turn"), "stop");
+        assert_eq!(map_finish_reason("max_tokens"), "length");
+        assert_eq!(map_finish_reason("tool_use"), "tool_calls");
+        assert_eq!(map_finish_reason("refusal"), "content_filter");
+    }
+}
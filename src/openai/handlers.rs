@@ -0,0 +1,520 @@
+//! OpenAI API Handler 函数
+
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::{
+    Extension, Json as JsonExtractor,
+    body::Body,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use futures::{Stream, StreamExt, stream};
+use tokio::time::interval;
+
+use crate::anthropic::converter::{
+    ConversionError, ConversionOptions, convert_request_with_options,
+};
+use crate::anthropic::handlers::{
+    apply_model_route, attach_warnings_header, invalid_image_response, map_provider_error,
+    override_thinking_from_model_name, ping_interval,
+};
+use crate::anthropic::images;
+use crate::anthropic::middleware::{ApiKeyId, AppState};
+use crate::anthropic::secret_scan::SecretScanner;
+use crate::anthropic::stream::{SseEvent, StreamContext};
+use crate::anthropic::types::ErrorResponse;
+use crate::events::{self, AppEvent, StreamEndGuard, StreamOutcome};
+use crate::kiro::model::events::Event;
+use crate::kiro::model::requests::kiro::KiroRequest;
+use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::kiro::provider::KiroProvider;
+use crate::moderation::{self, ModerationVerdict};
+use crate::token;
+
+use super::converter::{ChunkTranslator, to_messages_request};
+use super::types::ChatCompletionRequest;
+
+/// POST /v1/chat/completions
+///
+/// OpenAI Chat Completions 兼容端点，内部转换为 Anthropic 请求后复用同一条 Kiro 管线
+#[utoipa::path(
+    post,
+    path = "/v1/chat/completions",
+    tag = "openai",
+    request_body = ChatCompletionRequest,
+    responses((status = 200, description = "Chat Completions 响应；`stream: true` 时为 SSE 事件流", body = serde_json::Value), (status = 400, body = ErrorResponse), (status = 401, body = ErrorResponse))
+)]
+pub async fn chat_completions(
+    State(state): State<AppState>,
+    api_key_id: Option<Extension<ApiKeyId>>,
+    JsonExtractor(payload): JsonExtractor<ChatCompletionRequest>,
+) -> Response {
+    tracing::info!(
+        model = %payload.model,
+        stream = %payload.stream,
+        message_count = %payload.messages.len(),
+        "Received POST /v1/chat/completions request"
+    );
+    let api_key_id = api_key_id.map(|Extension(ApiKeyId(id))| id);
+
+    let provider = match &state.kiro_provider {
+        Some(p) => p.clone(),
+        None => {
+            tracing::error!("KiroProvider 未配置");
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse::new(
+                    "service_unavailable",
+                    "Kiro API provider not configured",
+                )),
+            )
+                .into_response();
+        }
+    };
+
+    let mut request = match to_messages_request(&payload) {
+        Ok(request) => request,
+        Err(message) => {
+            tracing::warn!("OpenAI 请求转换失败: {}", message);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new("invalid_request_error", message)),
+            )
+                .into_response();
+        }
+    };
+
+    let route = apply_model_route(&mut request.model, &provider.token_manager().config());
+    let pinned_credential = route.as_ref().and_then(|r| r.credential_id);
+    // 客户端未指定 max_tokens 时使用路由的默认值
+    if let Some(max_tokens) = route.and_then(|r| r.max_tokens)
+        && payload
+            .max_completion_tokens
+            .or(payload.max_tokens)
+            .is_none()
+    {
+        request.max_tokens = max_tokens;
+    }
+
+    // 检测模型名是否包含 "thinking" 后缀，若包含则覆写 thinking 配置
+    override_thinking_from_model_name(&mut request);
+
+    // 内容审核前置过滤
+    if let ModerationVerdict::Deny(reason) = moderation::check_messages(&request.messages).await {
+        tracing::warn!(model = %payload.model, "请求被内容审核拒绝: {}", reason);
+        return refusal_response(&payload);
+    }
+
+    // 下载 URL 来源的图片
+    if let Err(message) = images::resolve_image_urls(&mut request.messages).await {
+        return invalid_image_response(&message);
+    }
+
+    let options = ConversionOptions::from_config(&provider.token_manager().config());
+    let conversion_result = match convert_request_with_options(&request, &options) {
+        Ok(result) => result,
+        Err(e) => {
+            let message = match &e {
+                ConversionError::UnsupportedModel(model) => format!("模型不支持: {}", model),
+                ConversionError::EmptyMessages => "消息列表为空".to_string(),
+                ConversionError::InvalidImage(message) => format!("图片无效: {}", message),
+            };
+            tracing::warn!("请求转换失败: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new("invalid_request_error", message)),
+            )
+                .into_response();
+        }
+    };
+
+    let warnings = conversion_result.warnings;
+    for warning in &warnings {
+        tracing::debug!(code = warning.code, "请求转换告警: {}", warning.message);
+    }
+
+    let kiro_request = KiroRequest {
+        conversation_state: conversion_result.conversation_state,
+        profile_arn: state.profile_arn.clone(),
+    };
+
+    let request_body = match serde_json::to_string(&kiro_request) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("序列化请求失败: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse::new(
+                    "internal_error",
+                    format!("序列化请求失败: {}", e),
+                )),
+            )
+                .into_response();
+        }
+    };
+
+    tracing::debug!("Kiro request body: {}", request_body);
+
+    let input_tokens = token::count_all_tokens(
+        request.model.clone(),
+        request.system,
+        request.messages,
+        request.tools,
+    ) as i32;
+    state.record_tokens(input_tokens);
+
+    let thinking_enabled = request
+        .thinking
+        .as_ref()
+        .map(|t| t.is_enabled())
+        .unwrap_or(false);
+
+    let ctx = StreamContext::new_with_thinking(&request.model, input_tokens, thinking_enabled)
+        .with_secret_scanner(SecretScanner::from_provider(&provider));
+    let translator = ChunkTranslator::new(&payload.model);
+
+    let response = KiroProvider::with_pinned_credential(pinned_credential, async {
+        if payload.stream {
+            let include_usage = include_usage(&payload);
+            let response = match provider.call_api_stream(&request_body).await {
+                Ok(resp) => resp,
+                Err(e) => return map_provider_error(e),
+            };
+
+            Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "text/event-stream")
+                .header(header::CACHE_CONTROL, "no-cache")
+                .header(header::CONNECTION, "keep-alive")
+                .body(Body::from_stream(create_chunk_stream(
+                    response,
+                    ctx,
+                    translator,
+                    include_usage,
+                    ping_interval(&provider),
+                    StreamEndGuard::new(api_key_id, &request.model)
+                        .with_credential_id(KiroProvider::served_credential()),
+                )))
+                .unwrap()
+        } else {
+            handle_non_stream(provider, &request_body, ctx, translator, api_key_id).await
+        }
+    })
+    .await;
+
+    attach_warnings_header(response, &warnings)
+}
+
+/// 嵌入接口上游超时（秒）
+const EMBEDDINGS_TIMEOUT_SECS: u64 = 60;
+
+/// POST /v1/embeddings
+///
+/// Kiro 上游不提供嵌入接口：配置 `embeddingsApiUrl` 时原样转发请求体，否则返回 501
+#[utoipa::path(
+    post,
+    path = "/v1/embeddings",
+    tag = "openai",
+    request_body = serde_json::Value,
+    responses((status = 200, description = "上游 Embeddings 接口的原始响应", body = serde_json::Value), (status = 501, description = "未配置 embeddingsApiUrl", body = ErrorResponse))
+)]
+pub async fn embeddings(
+    State(state): State<AppState>,
+    JsonExtractor(payload): JsonExtractor<serde_json::Value>,
+) -> Response {
+    tracing::info!("Received POST /v1/embeddings request");
+
+    let provider = match &state.kiro_provider {
+        Some(p) => p.clone(),
+        None => {
+            tracing::error!("KiroProvider 未配置");
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse::new(
+                    "service_unavailable",
+                    "Kiro API provider not configured",
+                )),
+            )
+                .into_response();
+        }
+    };
+
+    let config = provider.token_manager().config();
+    let Some(api_url) = &config.embeddings_api_url else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(ErrorResponse::new(
+                "invalid_request_error",
+                "Embeddings are not supported by the upstream. Configure embeddingsApiUrl to proxy them.",
+            )),
+        )
+            .into_response();
+    };
+
+    let client = match provider.default_client() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("创建 HTTP 客户端失败: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse::new("internal_error", e.to_string())),
+            )
+                .into_response();
+        }
+    };
+
+    let mut req_builder = client
+        .post(api_url)
+        .timeout(Duration::from_secs(EMBEDDINGS_TIMEOUT_SECS))
+        .json(&payload);
+    if let Some(api_key) = &config.embeddings_api_key {
+        req_builder = req_builder.header(header::AUTHORIZATION, format!("Bearer {}", api_key));
+    }
+
+    let response = match req_builder.send().await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::error!("嵌入接口调用失败: {}", e);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(ErrorResponse::new(
+                    "api_error",
+                    format!("上游 API 调用失败: {}", e),
+                )),
+            )
+                .into_response();
+        }
+    };
+
+    let status = response.status();
+    match response.bytes().await {
+        Ok(body) => Response::builder()
+            .status(status.as_u16())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap(),
+        Err(e) => {
+            tracing::error!("读取响应体失败: {}", e);
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(ErrorResponse::new(
+                    "api_error",
+                    format!("读取响应失败: {}", e),
+                )),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 是否在流末尾发送 usage chunk
+fn include_usage(payload: &ChatCompletionRequest) -> bool {
+    payload
+        .stream_options
+        .as_ref()
+        .map(|o| o.include_usage)
+        .unwrap_or(false)
+}
+
+/// 内容审核拒绝时返回 finish_reason 为 content_filter 的响应（不调用上游）
+fn refusal_response(payload: &ChatCompletionRequest) -> Response {
+    let mut translator = ChunkTranslator::new(&payload.model);
+    let events = moderation::refusal_events(&payload.model, 0);
+
+    if payload.stream {
+        let bytes = encode_stream_end(&mut translator, &events, include_usage(payload));
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(Body::from_stream(stream::iter(bytes)))
+            .unwrap();
+    }
+
+    let chunks: Vec<_> = events
+        .iter()
+        .flat_map(|event| translator.translate(event))
+        .collect();
+    (StatusCode::OK, Json(translator.aggregate(&chunks))).into_response()
+}
+
+/// 将一组 Anthropic 事件翻译为 `data: ...` 字节块
+fn encode_chunks(
+    translator: &mut ChunkTranslator,
+    events: &[SseEvent],
+) -> Vec<Result<Bytes, Infallible>> {
+    events
+        .iter()
+        .flat_map(|event| translator.translate(event))
+        .map(|chunk| Ok(Bytes::from(format!("data: {}\n\n", chunk))))
+        .collect()
+}
+
+/// 结束流：追加可选的 usage chunk 与 `[DONE]` 标记
+fn encode_stream_end(
+    translator: &mut ChunkTranslator,
+    final_events: &[SseEvent],
+    include_usage: bool,
+) -> Vec<Result<Bytes, Infallible>> {
+    let mut bytes = encode_chunks(translator, final_events);
+    if include_usage {
+        bytes.push(Ok(Bytes::from(format!(
+            "data: {}\n\n",
+            translator.usage_chunk()
+        ))));
+    }
+    bytes.push(Ok(Bytes::from_static(b"data: [DONE]\n\n")));
+    bytes
+}
+
+/// 创建 OpenAI chunk 事件流
+fn create_chunk_stream(
+    response: reqwest::Response,
+    mut ctx: StreamContext,
+    mut translator: ChunkTranslator,
+    include_usage: bool,
+    ping_period: Duration,
+    guard: StreamEndGuard,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    let initial_events = ctx.generate_initial_events();
+    let initial_stream = stream::iter(encode_chunks(&mut translator, &initial_events));
+
+    let body_stream = response.bytes_stream();
+
+    let processing_stream = stream::unfold(
+        (body_stream, ctx, translator, EventStreamDecoder::new(), false, interval(ping_period), guard),
+        move |(mut body_stream, mut ctx, mut translator, mut decoder, finished, mut ping_interval, mut guard)| async move {
+            if finished {
+                return None;
+            }
+
+            tokio::select! {
+                chunk_result = body_stream.next() => {
+                    match chunk_result {
+                        Some(Ok(chunk)) => {
+                            if let Err(e) = decoder.feed(&chunk) {
+                                tracing::warn!("缓冲区溢出: {}", e);
+                            }
+
+                            let mut events = Vec::new();
+                            for result in decoder.decode_iter() {
+                                match result {
+                                    Ok(frame) => {
+                                        if let Ok(event) = Event::from_frame(frame) {
+                                            events.extend(ctx.process_kiro_event(&event));
+                                        }
+                                    }
+                                    Err(e) => {
+                                        tracing::warn!("解码事件失败: {}", e);
+                                    }
+                                }
+                            }
+
+                            // 有输出时重新计时，保活注释只在静默期发送
+                            if !events.is_empty() {
+                                ping_interval.reset();
+                            }
+                            guard.record_usage(ctx.usage());
+
+                            let bytes = encode_chunks(&mut translator, &events);
+                            Some((stream::iter(bytes), (body_stream, ctx, translator, decoder, false, ping_interval, guard)))
+                        }
+                        Some(Err(e)) => {
+                            tracing::error!(
+                                output_tokens = ctx.output_tokens,
+                                "读取响应流失败，以 error 结束并保留已生成的部分输出: {}",
+                                e
+                            );
+                            guard.finish(StreamOutcome::UpstreamError);
+                            let final_events = ctx.generate_abort_events(&e.to_string());
+                            guard.record_usage(ctx.usage());
+                            let bytes = encode_stream_end(&mut translator, &final_events, include_usage);
+                            Some((stream::iter(bytes), (body_stream, ctx, translator, decoder, true, ping_interval, guard)))
+                        }
+                        None => {
+                            guard.finish(StreamOutcome::Completed);
+                            let final_events = ctx.generate_final_events();
+                            guard.record_usage(ctx.usage());
+                            let bytes = encode_stream_end(&mut translator, &final_events, include_usage);
+                            Some((stream::iter(bytes), (body_stream, ctx, translator, decoder, true, ping_interval, guard)))
+                        }
+                    }
+                }
+                // SSE 注释行保活，OpenAI 客户端会忽略
+                _ = ping_interval.tick() => {
+                    tracing::trace!("发送 ping 保活注释");
+                    let bytes: Vec<Result<Bytes, Infallible>> = vec![Ok(Bytes::from_static(b": ping\n\n"))];
+                    Some((stream::iter(bytes), (body_stream, ctx, translator, decoder, false, ping_interval, guard)))
+                }
+            }
+        },
+    )
+    .flatten();
+
+    initial_stream.chain(processing_stream)
+}
+
+/// 处理非流式请求：读取完整响应后聚合为 `chat.completion`
+async fn handle_non_stream(
+    provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
+    request_body: &str,
+    mut ctx: StreamContext,
+    mut translator: ChunkTranslator,
+    api_key_id: Option<String>,
+) -> Response {
+    let response = match provider.call_api(request_body).await {
+        Ok(resp) => resp,
+        Err(e) => return map_provider_error(e),
+    };
+
+    let body_bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("读取响应体失败: {}", e);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(ErrorResponse::new(
+                    "api_error",
+                    format!("读取响应失败: {}", e),
+                )),
+            )
+                .into_response();
+        }
+    };
+
+    let mut decoder = EventStreamDecoder::new();
+    if let Err(e) = decoder.feed(&body_bytes) {
+        tracing::warn!("缓冲区溢出: {}", e);
+    }
+
+    let mut events = ctx.generate_initial_events();
+    for result in decoder.decode_iter() {
+        match result {
+            Ok(frame) => {
+                if let Ok(event) = Event::from_frame(frame) {
+                    events.extend(ctx.process_kiro_event(&event));
+                }
+            }
+            Err(e) => {
+                tracing::warn!("解码事件失败: {}", e);
+            }
+        }
+    }
+    events.extend(ctx.generate_final_events());
+    events::publish(AppEvent::UsageRecorded {
+        api_key_id,
+        credential_id: KiroProvider::served_credential(),
+        model: ctx.model.clone(),
+        usage: ctx.usage(),
+    });
+
+    let chunks: Vec<_> = events
+        .iter()
+        .flat_map(|event| translator.translate(event))
+        .collect();
+
+    (StatusCode::OK, Json(translator.aggregate(&chunks))).into_response()
+}
@@ -0,0 +1,16 @@
+//! OpenAI API 兼容服务模块
+//!
+//! - `POST /v1/chat/completions`：流式与非流式对话，请求先转换为 Anthropic Messages 格式，
+//!   再复用 Anthropic → Kiro 的转换与调用逻辑
+//! - `POST /v1/embeddings`：转发到配置的嵌入接口（Kiro 上游不提供嵌入能力）
+//!
+//! `GET /v1/models` 由 Anthropic 模块提供，返回格式同时兼容 OpenAI，并包含配置的模型别名。
+//! 路由与认证挂载在 Anthropic 的 `/v1` 路由下。
+
+mod converter;
+mod handlers;
+mod openapi;
+mod types;
+
+pub use handlers::{chat_completions, embeddings};
+pub use openapi::OpenAiApiDoc;
@@ -0,0 +1,10 @@
+//! OpenAI 兼容 API 的 OpenAPI 描述
+
+use utoipa::OpenApi;
+
+use super::handlers;
+
+/// OpenAI 兼容 API（`/v1/chat/completions`、`/v1/embeddings`）
+#[derive(OpenApi)]
+#[openapi(paths(handlers::chat_completions, handlers::embeddings))]
+pub struct OpenAiApiDoc;
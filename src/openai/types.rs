@@ -0,0 +1,86 @@
+//! OpenAI Chat Completions API 类型定义
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Chat Completions 请求体
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub stream: bool,
+    pub stream_options: Option<StreamOptions>,
+    /// 旧版最大输出 tokens 字段
+    pub max_tokens: Option<i32>,
+    /// 新版最大输出 tokens 字段（优先于 max_tokens）
+    pub max_completion_tokens: Option<i32>,
+    pub tools: Option<Vec<ChatTool>>,
+    /// "auto" / "none" / "required" 或 {"type": "function", "function": {"name": ...}}
+    pub tool_choice: Option<serde_json::Value>,
+    /// 推理强度（"low" / "medium" / "high"），设置后启用 thinking
+    pub reasoning_effort: Option<String>,
+    /// 终端用户标识
+    pub user: Option<String>,
+}
+
+/// 流式选项
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StreamOptions {
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+/// 对话消息
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ChatMessage {
+    /// "system" / "developer" / "user" / "assistant" / "tool"
+    pub role: String,
+    /// 字符串或内容片段数组，assistant 仅有 tool_calls 时可为 null
+    #[serde(default)]
+    pub content: Option<serde_json::Value>,
+    /// assistant 消息中的工具调用
+    pub tool_calls: Option<Vec<ChatToolCall>>,
+    /// tool 消息对应的工具调用 ID
+    pub tool_call_id: Option<String>,
+}
+
+/// 工具调用
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ChatToolCall {
+    pub id: String,
+    #[serde(rename = "type", default = "default_function_type")]
+    pub call_type: String,
+    pub function: ChatFunctionCall,
+}
+
+/// 工具调用的函数名和参数
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ChatFunctionCall {
+    pub name: String,
+    /// JSON 字符串形式的参数
+    #[serde(default)]
+    pub arguments: String,
+}
+
+/// 工具定义
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ChatTool {
+    #[serde(rename = "type", default = "default_function_type")]
+    pub tool_type: String,
+    pub function: ChatFunction,
+}
+
+/// 函数定义
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ChatFunction {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub parameters: Option<serde_json::Value>,
+}
+
+fn default_function_type() -> String {
+    "function".to_string()
+}
@@ -0,0 +1,97 @@
+//! OpenAPI 规范与 Swagger UI
+//!
+//! 合并 Anthropic 兼容、OpenAI 兼容与 Admin API 的描述，在 `/api/openapi.json` 提供
+//! OpenAPI 3 规范（可用于生成客户端），在 `/api/docs` 提供内嵌的 Swagger UI。
+//! 两者都不需要认证；规范只描述接口，不包含任何配置或凭据。
+
+use axum::Router;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::admin::AdminApiDoc;
+use crate::anthropic::AnthropicApiDoc;
+use crate::openai::OpenAiApiDoc;
+
+/// 规范路径
+pub const SPEC_PATH: &str = "/api/openapi.json";
+
+/// Swagger UI 路径
+pub const DOCS_PATH: &str = "/api/docs";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "kiro-rs",
+        description = "Anthropic Claude API 兼容的 Kiro 代理服务"
+    ),
+    modifiers(&SecuritySchemes),
+    security(("api_key" = []), ("bearer" = [])),
+    tags(
+        (name = "anthropic", description = "Anthropic Messages 兼容 API，使用 apiKey 认证"),
+        (name = "batches", description = "Message Batches API，使用 apiKey 认证"),
+        (name = "openai", description = "OpenAI 兼容 API，使用 apiKey 认证"),
+        (name = "admin", description = "Admin API，使用 Admin 账号的 Key 或会话 Token 认证"),
+    )
+)]
+struct ApiDoc;
+
+/// 认证方式：`x-api-key` 头或 `Authorization: Bearer`
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))),
+        );
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// 完整的 OpenAPI 规范
+pub fn spec() -> utoipa::openapi::OpenApi {
+    let mut spec = ApiDoc::openapi();
+    // Cargo.toml 未声明许可证，不输出空的 license 对象
+    spec.info.license = None;
+    spec.merge(AnthropicApiDoc::openapi());
+    spec.merge(OpenAiApiDoc::openapi());
+    spec.merge(AdminApiDoc::openapi());
+    spec
+}
+
+/// `/api/openapi.json` 与 `/api/docs` 路由
+pub fn router() -> Router {
+    SwaggerUi::new(DOCS_PATH).url(SPEC_PATH, spec()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_covers_all_surfaces() {
+        let spec = spec();
+        for path in [
+            "/v1/messages",
+            "/v1/messages/batches/{id}",
+            "/v1/chat/completions",
+            "/api/admin/credentials/{id}",
+            "/api/admin/login",
+        ] {
+            assert!(spec.paths.paths.contains_key(path), "缺少 {}", path);
+        }
+        let schemas = &spec.components.as_ref().unwrap().schemas;
+        assert!(schemas.contains_key("MessagesRequest"));
+        assert!(schemas.contains_key("CredentialsStatusResponse"));
+
+        // 登录与就绪检查不需要认证
+        let login = spec.paths.paths["/api/admin/login"].post.as_ref().unwrap();
+        assert_eq!(login.security.as_ref().map(Vec::len), Some(1));
+        assert!(serde_json::to_string(&spec).is_ok());
+    }
+}
@@ -0,0 +1,266 @@
+//! 非正常退出后的启动恢复报告
+//!
+//! 运行期间在数据目录维护哨兵文件 `kiro_running.json`，定期写入心跳（进行中的流式响应数、
+//! 是否有未落盘的统计数据），正常退出时删除。启动时若哨兵文件仍存在，说明上次进程没有
+//! 正常退出（崩溃、被 kill -9、断电等），据此生成恢复报告：
+//! - 未落盘的统计窗口：最后一次心跳时统计数据仍有未保存的更新，窗口为上次保存到最后一次心跳
+//! - 中断的流式响应：最后一次心跳时仍在进行的流式响应数
+//! - 被隔离的损坏文件：启动时解析失败的状态文件会重命名为 `<文件名>.corrupt-<时间>` 保留
+//!
+//! 报告在启动时写入日志，并可通过 `GET /api/admin/recovery` 查询。
+//! 心跳有间隔，最后一次心跳之后的变化无法得知，报告中的数字是估计值。
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::kiro::token_manager::MultiTokenManager;
+
+/// 心跳间隔
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 哨兵文件名
+const SENTINEL_FILE: &str = "kiro_running.json";
+
+/// 哨兵文件内容（心跳）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Heartbeat {
+    pid: u32,
+    started_at: DateTime<Utc>,
+    last_heartbeat_at: DateTime<Utc>,
+    /// 进行中的流式响应数
+    active_streams: u64,
+    /// 是否有未落盘的统计数据
+    stats_dirty: bool,
+}
+
+/// 被隔离的损坏文件
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedFile {
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+    /// 重命名后的路径（重命名失败时为 None）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub quarantined_path: Option<PathBuf>,
+    pub error: String,
+    pub quarantined_at: DateTime<Utc>,
+}
+
+/// 恢复报告
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryReport {
+    /// 上次进程是否没有正常退出
+    pub unclean_shutdown: bool,
+    /// 上次进程的 PID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_pid: Option<u32>,
+    /// 上次进程的启动时间
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_started_at: Option<DateTime<Utc>>,
+    /// 上次进程最后一次心跳时间
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_heartbeat_at: Option<DateTime<Utc>>,
+    /// 估计未落盘的统计数据时间窗口（秒），没有未保存的统计时为 None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unsaved_usage_window_secs: Option<i64>,
+    /// 估计被中断的流式响应数
+    pub interrupted_streams: u64,
+    /// 本次启动中被隔离的损坏文件
+    pub quarantined_files: Vec<QuarantinedFile>,
+}
+
+static REPORT: OnceLock<RecoveryReport> = OnceLock::new();
+static SENTINEL: OnceLock<PathBuf> = OnceLock::new();
+static QUARANTINED: Mutex<Vec<QuarantinedFile>> = Mutex::new(Vec::new());
+
+/// 根据上次遗留的心跳生成报告（`stats_saved_at` 为统计文件最后写入时间）
+fn build_report(previous: &Heartbeat, stats_saved_at: Option<DateTime<Utc>>) -> RecoveryReport {
+    let unsaved_usage_window_secs = previous.stats_dirty.then(|| {
+        let since = stats_saved_at.unwrap_or(previous.started_at);
+        (previous.last_heartbeat_at - since).num_seconds().max(0)
+    });
+    RecoveryReport {
+        unclean_shutdown: true,
+        previous_pid: Some(previous.pid),
+        previous_started_at: Some(previous.started_at),
+        last_heartbeat_at: Some(previous.last_heartbeat_at),
+        unsaved_usage_window_secs,
+        interrupted_streams: previous.active_streams,
+        quarantined_files: Vec::new(),
+    }
+}
+
+fn file_modified_at(path: &Path) -> Option<DateTime<Utc>> {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .map(DateTime::<Utc>::from)
+}
+
+fn write_heartbeat(path: &Path, heartbeat: &Heartbeat) {
+    match serde_json::to_string_pretty(heartbeat) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(path, json) {
+                tracing::warn!("写入运行哨兵文件失败: {}", e);
+            }
+        }
+        Err(e) => tracing::warn!("序列化运行哨兵文件失败: {}", e),
+    }
+}
+
+/// 检查上次是否正常退出并开始写入心跳（只能启动一次）
+pub fn spawn(dir: PathBuf, token_manager: Arc<MultiTokenManager>) {
+    let path = dir.join(SENTINEL_FILE);
+    if SENTINEL.set(path.clone()).is_err() {
+        return;
+    }
+
+    let previous = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Heartbeat>(&s).ok());
+    let report = match &previous {
+        Some(previous) => build_report(previous, file_modified_at(&dir.join("kiro_stats.json"))),
+        None if path.exists() => RecoveryReport {
+            unclean_shutdown: true,
+            ..Default::default()
+        },
+        None => RecoveryReport::default(),
+    };
+    let quarantined = QUARANTINED.lock().len();
+    if report.unclean_shutdown {
+        tracing::warn!(
+            "检测到上次未正常退出（PID {}，最后心跳 {}）：估计未落盘统计窗口 {} 秒，中断的流式响应 {} 个，隔离的损坏文件 {} 个",
+            report
+                .previous_pid
+                .map(|p| p.to_string())
+                .unwrap_or_else(|| "未知".to_string()),
+            report
+                .last_heartbeat_at
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_else(|| "未知".to_string()),
+            report.unsaved_usage_window_secs.unwrap_or(0),
+            report.interrupted_streams,
+            quarantined
+        );
+    } else if quarantined > 0 {
+        tracing::warn!("启动时隔离了 {} 个损坏的状态文件", quarantined);
+    }
+    let _ = REPORT.set(report);
+
+    let started_at = Utc::now();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let heartbeat = Heartbeat {
+                pid: std::process::id(),
+                started_at,
+                last_heartbeat_at: Utc::now(),
+                active_streams: crate::events::active_streams(),
+                stats_dirty: token_manager.stats_dirty(),
+            };
+            write_heartbeat(&path, &heartbeat);
+        }
+    });
+}
+
+/// 正常退出：删除哨兵文件（应在统计数据落盘之后调用）
+pub fn clear() {
+    if let Some(path) = SENTINEL.get()
+        && let Err(e) = std::fs::remove_file(path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        tracing::warn!("删除运行哨兵文件失败: {}", e);
+    }
+}
+
+/// 隔离解析失败的状态文件：重命名为 `<文件名>.corrupt-<时间>` 保留原内容，避免下次写入时覆盖
+pub fn quarantine(path: &Path, error: impl std::fmt::Display) {
+    let now = Utc::now();
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".corrupt-{}", now.format("%Y%m%d%H%M%S")));
+    let target = path.with_file_name(name);
+    let quarantined_path = match std::fs::rename(path, &target) {
+        Ok(()) => {
+            tracing::warn!(
+                "已隔离损坏的文件 {} -> {}",
+                path.display(),
+                target.display()
+            );
+            Some(target)
+        }
+        Err(e) => {
+            tracing::warn!("隔离损坏的文件 {} 失败: {}", path.display(), e);
+            None
+        }
+    };
+    QUARANTINED.lock().push(QuarantinedFile {
+        path: path.to_path_buf(),
+        quarantined_path,
+        error: error.to_string(),
+        quarantined_at: now,
+    });
+}
+
+/// 当前的恢复报告（包含启动之后隔离的文件）
+pub fn report() -> RecoveryReport {
+    let mut report = REPORT.get().cloned().unwrap_or_default();
+    report.quarantined_files = QUARANTINED.lock().clone();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat(stats_dirty: bool) -> Heartbeat {
+        Heartbeat {
+            pid: 42,
+            started_at: "2026-01-01T00:00:00Z".parse().unwrap(),
+            last_heartbeat_at: "2026-01-01T01:00:00Z".parse().unwrap(),
+            active_streams: 3,
+            stats_dirty,
+        }
+    }
+
+    #[test]
+    fn test_build_report() {
+        let saved_at = "2026-01-01T00:59:00Z".parse().ok();
+        let report = build_report(&heartbeat(true), saved_at);
+        assert!(report.unclean_shutdown);
+        assert_eq!(report.unsaved_usage_window_secs, Some(60));
+        assert_eq!(report.interrupted_streams, 3);
+        // 没有统计文件时从启动时间算起
+        let report = build_report(&heartbeat(true), None);
+        assert_eq!(report.unsaved_usage_window_secs, Some(3600));
+        let report = build_report(&heartbeat(false), saved_at);
+        assert_eq!(report.unsaved_usage_window_secs, None);
+    }
+
+    #[test]
+    fn test_quarantine_renames_file() {
+        let dir = std::env::temp_dir().join(format!("kiro-recovery-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+        std::fs::write(&path, "{broken").unwrap();
+
+        quarantine(&path, "expected value");
+        assert!(!path.exists());
+        let files = QUARANTINED.lock().clone();
+        let entry = files.iter().find(|f| f.path == path).unwrap();
+        let target = entry.quarantined_path.as_ref().unwrap();
+        assert_eq!(std::fs::read_to_string(target).unwrap(), "{broken");
+        assert!(report().quarantined_files.iter().any(|f| f.path == path));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
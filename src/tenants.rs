@@ -0,0 +1,536 @@
+//! 租户用量与每月配额
+//!
+//! `/v1` 请求在转发前检查租户的 `monthlyRequestQuota` / `monthlyTokenQuota`，已用完时拒绝；
+//! 生成请求（`POST /messages`、`/chat/completions`、`/messages/batches`）放行时计入一次请求，
+//! 检查与计数原子执行，并发请求不会超出配额，上游返回错误时撤销。token 数（输入含缓存部分，
+//! 加上输出）订阅 `UsageRecorded` 事件，按事件中的 API Key 标识找到所属租户（配置 `tenants`）累加。
+//! 用量按 UTC 自然月统计，进入新的月份时清零。
+//!
+//! 用量通过 `TenantUsageStore` 存储：默认保存在数据目录的 `tenant_usage.json` 中，重启后保留；
+//! 集群模式开启 `cluster.shareTenantUsage` 时存入 Redis（见 `cluster`），在实例间共享，
+//! Redis 出错时退回本实例记录。
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use chrono::Utc;
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+use crate::events::{self, AppEvent, TokenUsage};
+use crate::kiro::token_manager::MultiTokenManager;
+use crate::model::config::TenantConfig;
+
+/// 用量文件名（位于数据目录）
+const USAGE_FILE: &str = "tenant_usage.json";
+
+/// 用量落盘间隔
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+static TENANTS: OnceLock<Tenants> = OnceLock::new();
+
+/// 租户本月用量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantUsage {
+    pub requests: u64,
+    /// 输入 tokens（含缓存写入与缓存命中部分）
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TenantUsage {
+    /// 计入 `monthlyTokenQuota` 的 token 数
+    pub fn tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+/// 超出的配额
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaExceeded {
+    Requests(u64),
+    Tokens(u64),
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Requests(limit) => write!(f, "Monthly request quota of {} exceeded", limit),
+            Self::Tokens(limit) => write!(f, "Monthly token quota of {} exceeded", limit),
+        }
+    }
+}
+
+/// 租户用量的存储后端
+pub trait TenantUsageStore: Send + Sync {
+    /// 租户在 `month`（UTC，YYYY-MM）的用量
+    fn usage<'a>(
+        &'a self,
+        month: &'a str,
+        tenant: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<TenantUsage>>;
+
+    /// 检查配额，未超出时计入一次请求
+    ///
+    /// 检查与计数需为原子操作，多个实例并发请求时不会超出配额
+    fn acquire<'a>(
+        &'a self,
+        month: &'a str,
+        tenant: &'a str,
+        quota: &'a TenantConfig,
+    ) -> BoxFuture<'a, anyhow::Result<Result<(), QuotaExceeded>>>;
+
+    /// 撤销 `acquire` 计入的一次请求
+    fn release<'a>(&'a self, month: &'a str, tenant: &'a str) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// 累加 token 用量
+    fn add_tokens<'a>(
+        &'a self,
+        month: &'a str,
+        tenant: &'a str,
+        input_tokens: u64,
+        output_tokens: u64,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// 当月各租户的用量（即 `tenant_usage.json` 的内容）
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageBook {
+    /// 月份（UTC，YYYY-MM）
+    month: String,
+    tenants: BTreeMap<String, TenantUsage>,
+}
+
+impl UsageBook {
+    /// 切换到 `month`：月份变化时清空上月用量
+    fn roll(&mut self, month: &str) {
+        if self.month != month {
+            self.month = month.to_string();
+            self.tenants.clear();
+        }
+    }
+
+    fn entry(&mut self, month: &str, tenant: &str) -> &mut TenantUsage {
+        self.roll(month);
+        self.tenants.entry(tenant.to_string()).or_default()
+    }
+
+    fn acquire(
+        &mut self,
+        month: &str,
+        tenant: &str,
+        quota: &TenantConfig,
+    ) -> Result<(), QuotaExceeded> {
+        let entry = self.entry(month, tenant);
+        quota_status(entry, quota)?;
+        entry.requests += 1;
+        Ok(())
+    }
+
+    fn release(&mut self, month: &str, tenant: &str) {
+        // 请求跨月时上月的计数已清空，不再撤销
+        if self.month == month
+            && let Some(entry) = self.tenants.get_mut(tenant)
+        {
+            entry.requests = entry.requests.saturating_sub(1);
+        }
+    }
+
+    fn add_tokens(&mut self, month: &str, tenant: &str, input_tokens: u64, output_tokens: u64) {
+        let entry = self.entry(month, tenant);
+        entry.input_tokens += input_tokens;
+        entry.output_tokens += output_tokens;
+    }
+
+    fn get(&self, month: &str, tenant: &str) -> TenantUsage {
+        if self.month != month {
+            return TenantUsage::default();
+        }
+        self.tenants.get(tenant).copied().unwrap_or_default()
+    }
+}
+
+/// 保存在 `tenant_usage.json` 中的用量（默认）
+struct FileUsageStore {
+    path: Option<PathBuf>,
+    book: Mutex<UsageBook>,
+    dirty: AtomicBool,
+}
+
+impl FileUsageStore {
+    fn load(path: Option<PathBuf>) -> Self {
+        let book = path
+            .as_ref()
+            .and_then(|path| {
+                let content = std::fs::read_to_string(path).ok()?;
+                match serde_json::from_str(&content) {
+                    Ok(book) => Some(book),
+                    Err(e) => {
+                        tracing::warn!("解析租户用量失败，将忽略: {}", e);
+                        crate::recovery::quarantine(path, e);
+                        None
+                    }
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            path,
+            book: Mutex::new(book),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut UsageBook) -> T) -> T {
+        let result = f(&mut self.book.lock());
+        self.dirty.store(true, Ordering::Relaxed);
+        result
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        // 磁盘空间不足时暂不写入，等空间恢复后再写
+        if !crate::disk_monitor::optional_writes_allowed() {
+            self.dirty.store(true, Ordering::Relaxed);
+            return;
+        }
+        let json = serde_json::to_string_pretty(&*self.book.lock());
+        let result = json
+            .map_err(anyhow::Error::from)
+            .and_then(|json| std::fs::write(path, json).map_err(Into::into));
+        if let Err(e) = result {
+            tracing::warn!("保存租户用量失败: {}", e);
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl TenantUsageStore for FileUsageStore {
+    fn usage<'a>(
+        &'a self,
+        month: &'a str,
+        tenant: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<TenantUsage>> {
+        Box::pin(std::future::ready(Ok(self.book.lock().get(month, tenant))))
+    }
+
+    fn acquire<'a>(
+        &'a self,
+        month: &'a str,
+        tenant: &'a str,
+        quota: &'a TenantConfig,
+    ) -> BoxFuture<'a, anyhow::Result<Result<(), QuotaExceeded>>> {
+        let result = self.update(|book| book.acquire(month, tenant, quota));
+        Box::pin(std::future::ready(Ok(result)))
+    }
+
+    fn release<'a>(&'a self, month: &'a str, tenant: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        self.update(|book| book.release(month, tenant));
+        Box::pin(std::future::ready(Ok(())))
+    }
+
+    fn add_tokens<'a>(
+        &'a self,
+        month: &'a str,
+        tenant: &'a str,
+        input_tokens: u64,
+        output_tokens: u64,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        self.update(|book| book.add_tokens(month, tenant, input_tokens, output_tokens));
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
+struct Tenants {
+    local: FileUsageStore,
+    /// 集群共享的用量（开启 `cluster.shareTenantUsage` 时）
+    shared: Option<Arc<dyn TenantUsageStore>>,
+}
+
+impl Tenants {
+    /// 优先使用共享存储，出错时退回本实例记录
+    async fn with_store<'a, T>(
+        &'a self,
+        op: impl Fn(&'a dyn TenantUsageStore) -> BoxFuture<'a, anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        if let Some(shared) = &self.shared {
+            match op(shared.as_ref()).await {
+                Ok(value) => return Ok(value),
+                Err(e) => tracing::warn!("读写共享的租户用量失败，使用本实例记录: {}", e),
+            }
+        }
+        op(&self.local).await
+    }
+}
+
+/// 放行时计入的一次请求，上游返回错误时通过 `release` 撤销
+#[must_use]
+pub struct Reservation {
+    month: String,
+    tenant: String,
+}
+
+impl Reservation {
+    pub async fn release(self) {
+        let Some(tenants) = TENANTS.get() else {
+            return;
+        };
+        let result = tenants
+            .with_store(|store| store.release(&self.month, &self.tenant))
+            .await;
+        if let Err(e) = result {
+            tracing::warn!("撤销租户请求计数失败: {}", e);
+        }
+    }
+}
+
+fn current_month() -> String {
+    Utc::now().format("%Y-%m").to_string()
+}
+
+/// 加载已保存的用量，并开始按事件累计租户用量
+///
+/// `shared` 为集群共享的存储（见 `cluster::tenant_usage_store`）
+pub fn spawn(token_manager: Arc<MultiTokenManager>, shared: Option<Arc<dyn TenantUsageStore>>) {
+    let path = token_manager.cache_dir().map(|dir| dir.join(USAGE_FILE));
+    let tenants = Tenants {
+        local: FileUsageStore::load(path),
+        shared,
+    };
+    if TENANTS.set(tenants).is_err() {
+        return;
+    }
+    let Some(tenants) = TENANTS.get() else {
+        return;
+    };
+
+    let mut receiver = events::subscribe();
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(envelope) => {
+                    let AppEvent::UsageRecorded {
+                        api_key_id: Some(key_id),
+                        usage,
+                        ..
+                    } = envelope.event
+                    else {
+                        continue;
+                    };
+                    let config = token_manager.config();
+                    let Some(tenant) = config.tenant_for_key_id(&key_id) else {
+                        continue;
+                    };
+                    let (input, output) = usage_tokens(&usage);
+                    let month = current_month();
+                    let result = tenants
+                        .with_store(|store| store.add_tokens(&month, tenant, input, output))
+                        .await;
+                    if let Err(e) = result {
+                        tracing::warn!("累计租户用量失败: {}", e);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("租户用量统计处理过慢，跳过了 {} 个事件", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SAVE_INTERVAL);
+        loop {
+            interval.tick().await;
+            tenants.local.save();
+        }
+    });
+}
+
+/// 一次响应计入租户用量的输入与输出 tokens
+fn usage_tokens(usage: &TokenUsage) -> (u64, u64) {
+    let input =
+        usage.input_tokens + usage.cache_creation_input_tokens + usage.cache_read_input_tokens;
+    (input.max(0) as u64, usage.output_tokens.max(0) as u64)
+}
+
+/// 租户本月用量
+pub async fn usage(tenant: &str) -> TenantUsage {
+    let Some(tenants) = TENANTS.get() else {
+        return TenantUsage::default();
+    };
+    let month = current_month();
+    tenants
+        .with_store(|store| store.usage(&month, tenant))
+        .await
+        .unwrap_or_default()
+}
+
+/// 检查租户本月用量是否已达到配额（不计入请求）
+pub async fn check_quota(name: &str, tenant: &TenantConfig) -> Result<(), QuotaExceeded> {
+    quota_status(&usage(name).await, tenant)
+}
+
+/// 检查租户本月用量是否已达到配额，未达到时计入一次请求
+pub async fn acquire(name: &str, tenant: &TenantConfig) -> Result<Reservation, QuotaExceeded> {
+    let reservation = Reservation {
+        month: current_month(),
+        tenant: name.to_string(),
+    };
+    let Some(tenants) = TENANTS.get() else {
+        return Ok(reservation);
+    };
+    let result = tenants
+        .with_store(|store| store.acquire(&reservation.month, name, tenant))
+        .await;
+    match result {
+        Ok(status) => status.map(|()| reservation),
+        Err(e) => {
+            tracing::warn!("检查租户配额失败: {}", e);
+            Ok(reservation)
+        }
+    }
+}
+
+fn quota_status(usage: &TenantUsage, tenant: &TenantConfig) -> Result<(), QuotaExceeded> {
+    if let Some(limit) = tenant.monthly_request_quota
+        && usage.requests >= limit
+    {
+        return Err(QuotaExceeded::Requests(limit));
+    }
+    if let Some(limit) = tenant.monthly_token_quota
+        && usage.tokens() >= limit
+    {
+        return Err(QuotaExceeded::Tokens(limit));
+    }
+    Ok(())
+}
+
+/// 立即保存未落盘的用量（用于退出前）
+pub fn flush() {
+    if let Some(tenants) = TENANTS.get() {
+        tenants.local.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input: i32, output: i32, cache_read: i32) -> TokenUsage {
+        TokenUsage {
+            input_tokens: input,
+            output_tokens: output,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: cache_read,
+        }
+    }
+
+    /// 放行一次请求并累加其用量
+    fn record(book: &mut UsageBook, month: &str, tenant: &str, usage: &TokenUsage) {
+        let (input, output) = usage_tokens(usage);
+        assert_eq!(
+            book.acquire(month, tenant, &TenantConfig::default()),
+            Ok(())
+        );
+        book.add_tokens(month, tenant, input, output);
+    }
+
+    #[test]
+    fn test_usage_book_rolls_over_monthly() {
+        let mut book = UsageBook::default();
+        record(&mut book, "2026-01", "team-a", &usage(100, 20, 50));
+        record(&mut book, "2026-01", "team-a", &usage(10, 5, 0));
+        record(&mut book, "2026-01", "team-b", &usage(1, 1, 0));
+
+        let a = book.get("2026-01", "team-a");
+        assert_eq!(a.requests, 2);
+        assert_eq!(a.input_tokens, 160);
+        assert_eq!(a.tokens(), 185);
+        assert_eq!(book.get("2026-02", "team-a"), TenantUsage::default());
+
+        // 新月份的第一条用量清空上月记录
+        record(&mut book, "2026-02", "team-b", &usage(3, 0, 0));
+        assert_eq!(book.get("2026-02", "team-b").requests, 1);
+        assert_eq!(book.tenants.len(), 1);
+    }
+
+    #[test]
+    fn test_usage_book_acquire_and_release() {
+        let tenant = TenantConfig {
+            monthly_request_quota: Some(2),
+            ..Default::default()
+        };
+        let mut book = UsageBook::default();
+        assert_eq!(book.acquire("2026-01", "team-a", &tenant), Ok(()));
+        assert_eq!(book.acquire("2026-01", "team-a", &tenant), Ok(()));
+        assert_eq!(
+            book.acquire("2026-01", "team-a", &tenant),
+            Err(QuotaExceeded::Requests(2))
+        );
+        assert_eq!(book.get("2026-01", "team-a").requests, 2);
+
+        // 撤销后可以再放行一次；跨月的撤销不影响新月份
+        book.release("2026-01", "team-a");
+        assert_eq!(book.acquire("2026-01", "team-a", &tenant), Ok(()));
+        assert_eq!(book.acquire("2026-02", "team-a", &tenant), Ok(()));
+        book.release("2026-01", "team-a");
+        assert_eq!(book.get("2026-02", "team-a").requests, 1);
+    }
+
+    #[test]
+    fn test_quota_status() {
+        let tenant = TenantConfig {
+            monthly_request_quota: Some(10),
+            monthly_token_quota: Some(1000),
+            ..Default::default()
+        };
+        let mut used = TenantUsage {
+            requests: 9,
+            input_tokens: 900,
+            output_tokens: 99,
+        };
+        assert_eq!(quota_status(&used, &tenant), Ok(()));
+        used.output_tokens = 100;
+        assert_eq!(
+            quota_status(&used, &tenant),
+            Err(QuotaExceeded::Tokens(1000))
+        );
+        used.requests = 10;
+        assert_eq!(
+            quota_status(&used, &tenant),
+            Err(QuotaExceeded::Requests(10))
+        );
+        assert_eq!(quota_status(&used, &TenantConfig::default()), Ok(()));
+    }
+
+    #[test]
+    fn test_allows_model() {
+        let tenant = TenantConfig {
+            allowed_models: vec![
+                "claude-sonnet-4-5".to_string(),
+                "claude-haiku-*".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert!(tenant.allows_model("claude-sonnet-4-5"));
+        assert!(tenant.allows_model("claude-haiku-4-5-20251001"));
+        assert!(!tenant.allows_model("claude-sonnet-4-5-20250929"));
+        assert!(!tenant.allows_model("claude-opus-4-1"));
+        assert!(TenantConfig::default().allows_model("claude-opus-4-1"));
+    }
+}
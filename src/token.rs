@@ -12,6 +12,7 @@ use crate::anthropic::types::{
 };
 use crate::http_client::{ProxyConfig, build_client};
 use crate::model::config::TlsBackend;
+use crate::tls::Destination;
 use std::sync::OnceLock;
 
 /// Count Tokens API 配置
@@ -146,7 +147,12 @@ async fn call_remote_count_tokens(
     messages: &Vec<Message>,
     tools: &Option<Vec<Tool>>,
 ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
-    let client = build_client(config.proxy.as_ref(), 300, config.tls_backend)?;
+    let client = build_client(
+        config.proxy.as_ref(),
+        300,
+        config.tls_backend,
+        Destination::Integration,
+    )?;
 
     // 构建请求体
     let request = CountTokensRequest {
@@ -204,9 +210,7 @@ fn count_all_tokens_local(
             total += count_tokens(s);
         } else if let serde_json::Value::Array(arr) = &msg.content {
             for item in arr {
-                if let Some(text) = item.get("text").and_then(|v| v.as_str()) {
-                    total += count_tokens(text);
-                }
+                total += count_content_block_tokens(item);
             }
         }
     }
@@ -224,6 +228,33 @@ fn count_all_tokens_local(
     total.max(1)
 }
 
+/// 估算单个消息内容块的 tokens
+///
+/// 计入文本、thinking、工具调用（名称与参数）和工具结果（字符串或嵌套文本块）；
+/// 图片等二进制内容不计入
+fn count_content_block_tokens(block: &serde_json::Value) -> u64 {
+    let field = |name: &str| block.get(name).and_then(|v| v.as_str()).unwrap_or_default();
+
+    match block.get("type").and_then(|v| v.as_str()) {
+        Some("thinking") => count_tokens(field("thinking")),
+        Some("tool_use") => {
+            let input = block
+                .get("input")
+                .map(|v| serde_json::to_string(v).unwrap_or_default())
+                .unwrap_or_default();
+            count_tokens(field("name")) + count_tokens(&input)
+        }
+        Some("tool_result") => match block.get("content") {
+            Some(serde_json::Value::String(s)) => count_tokens(s),
+            Some(serde_json::Value::Array(items)) => {
+                items.iter().map(count_content_block_tokens).sum()
+            }
+            _ => 0,
+        },
+        _ => count_tokens(field("text")),
+    }
+}
+
 /// 估算输出 tokens
 pub(crate) fn estimate_output_tokens(content: &[serde_json::Value]) -> i32 {
     let mut total = 0;
@@ -232,6 +263,9 @@ pub(crate) fn estimate_output_tokens(content: &[serde_json::Value]) -> i32 {
         if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
             total += count_tokens(text) as i32;
         }
+        if let Some(thinking) = block.get("thinking").and_then(|v| v.as_str()) {
+            total += count_tokens(thinking) as i32;
+        }
         if block.get("type").and_then(|v| v.as_str()) == Some("tool_use") {
             // 工具调用开销
             if let Some(input) = block.get("input") {
@@ -243,3 +277,39 @@ pub(crate) fn estimate_output_tokens(content: &[serde_json::Value]) -> i32 {
 
     total.max(1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_content_block_tokens() {
+        let text = serde_json::json!({"type": "text", "text": "hello world"});
+        assert_eq!(
+            count_content_block_tokens(&text),
+            count_tokens("hello world")
+        );
+
+        let tool_use = serde_json::json!({
+            "type": "tool_use",
+            "id": "toolu_1",
+            "name": "read_file",
+            "input": {"path": "/tmp/a.txt"}
+        });
+        assert!(count_content_block_tokens(&tool_use) > count_tokens("read_file"));
+
+        let tool_result = serde_json::json!({
+            "type": "tool_result",
+            "tool_use_id": "toolu_1",
+            "content": [{"type": "text", "text": "file contents here"}]
+        });
+        assert_eq!(
+            count_content_block_tokens(&tool_result),
+            count_tokens("file contents here")
+        );
+
+        let image =
+            serde_json::json!({"type": "image", "source": {"type": "base64", "data": "AAAA"}});
+        assert_eq!(count_content_block_tokens(&image), 0);
+    }
+}
@@ -0,0 +1,244 @@
+//! 额外在 Unix 域套接字上提供服务（配置 `unixSocket`）
+//!
+//! 与 TCP 端口共用同一套路由，用于只在本机访问的部署：能否连接由套接字文件的权限（`mode`）决定。
+//! - `requireApiKey` 为 false（默认）时，经套接字且未携带 Key 的 `/v1` 请求直接放行；
+//!   携带的 Key 仍会校验（用于识别租户）。Admin API 仍需 Admin Key
+//! - IP 访问控制中，套接字连接视为来自 `127.0.0.1`
+//! - 启动时清理残留的套接字文件（已被其他进程监听时拒绝启动），退出时删除
+//!
+//! 仅支持 Unix 平台，其他平台（如 Windows 命名管道）配置后拒绝启动。
+
+use std::future::Future;
+#[cfg(unix)]
+use std::net::{Ipv4Addr, SocketAddr};
+
+use axum::Router;
+#[cfg(unix)]
+use axum::extract::ConnectInfo;
+use tokio::task::JoinHandle;
+
+use crate::model::config::UnixSocketConfig;
+
+/// 经套接字且免 API Key 的请求（由套接字服务写入请求扩展，见 `auth_middleware`）
+#[derive(Debug, Clone, Copy)]
+pub struct TrustedLocalPeer;
+
+/// 解析八进制权限（如 `"660"`、`"0600"`）
+#[cfg(unix)]
+fn parse_mode(mode: &str) -> anyhow::Result<u32> {
+    let value = u32::from_str_radix(mode.trim(), 8)
+        .map_err(|_| anyhow::anyhow!("unixSocket.mode: 无效的八进制权限 {}", mode))?;
+    if value > 0o777 {
+        anyhow::bail!("unixSocket.mode: 无效的八进制权限 {}", mode);
+    }
+    Ok(value)
+}
+
+/// 为套接字连接写入请求扩展：本机地址（供 IP 访问控制使用），以及免 Key 标记
+#[cfg(unix)]
+fn local_router(app: Router, config: &UnixSocketConfig) -> Router {
+    let app = app.layer(axum::Extension(ConnectInfo(SocketAddr::from((
+        Ipv4Addr::LOCALHOST,
+        0,
+    )))));
+    if config.require_api_key {
+        app
+    } else {
+        app.layer(axum::Extension(TrustedLocalPeer))
+    }
+}
+
+/// 绑定套接字文件并设置权限
+#[cfg(unix)]
+fn bind(config: &UnixSocketConfig) -> anyhow::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    use anyhow::Context;
+
+    let path = std::path::Path::new(&config.path);
+    let mode = config.mode.as_deref().map(parse_mode).transpose()?;
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            anyhow::bail!("unixSocket.path: {} 已存在且不是套接字文件", config.path);
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            anyhow::bail!("unixSocket.path: {} 正被其他进程监听", config.path);
+        }
+        // 上次退出时未清理的套接字文件
+        std::fs::remove_file(path)
+            .with_context(|| format!("unixSocket.path: 删除残留的 {} 失败", config.path))?;
+    }
+
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("unixSocket.path: 监听 {} 失败", config.path))?;
+    if let Some(mode) = mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+            .with_context(|| format!("unixSocket.mode: 设置 {} 的权限失败", config.path))?;
+    }
+    Ok(listener)
+}
+
+/// 在套接字上启动服务，`shutdown` 完成后停止接受新连接并等待进行中的请求完成
+#[cfg(unix)]
+pub fn spawn<F>(
+    config: &UnixSocketConfig,
+    app: Router,
+    shutdown: F,
+) -> anyhow::Result<JoinHandle<()>>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let listener = bind(config)?;
+    let app = local_router(app, config);
+    Ok(tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown)
+            .await
+        {
+            tracing::error!("Unix 套接字服务异常退出: {}", e);
+        }
+    }))
+}
+
+/// 非 Unix 平台不支持
+#[cfg(not(unix))]
+pub fn spawn<F>(
+    _config: &UnixSocketConfig,
+    _app: Router,
+    _shutdown: F,
+) -> anyhow::Result<JoinHandle<()>>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    anyhow::bail!("unixSocket: 当前平台不支持 Unix 域套接字")
+}
+
+/// 删除套接字文件（用于退出前）
+pub fn remove(config: &UnixSocketConfig) {
+    if let Err(e) = std::fs::remove_file(&config.path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        tracing::warn!("删除 Unix 套接字文件 {} 失败: {}", config.path, e);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use axum::extract::Request;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("kiro-uds-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    async fn get_over_socket(path: &std::path::Path) -> String {
+        let mut stream = tokio::net::UnixStream::connect(path).await.unwrap();
+        stream
+            .write_all(b"GET /peer HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    fn config(path: &std::path::Path) -> UnixSocketConfig {
+        UnixSocketConfig {
+            path: path.to_string_lossy().into_owned(),
+            mode: Some("600".to_string()),
+            require_api_key: false,
+        }
+    }
+
+    fn peer_app() -> Router {
+        Router::new().route(
+            "/peer",
+            get(|request: Request| async move {
+                let ip = request
+                    .extensions()
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(addr)| addr.ip().to_string());
+                let trusted = request.extensions().get::<TrustedLocalPeer>().is_some();
+                format!("ip={} trusted={}", ip.unwrap_or_default(), trusted)
+            }),
+        )
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("660").unwrap(), 0o660);
+        assert_eq!(parse_mode("0600").unwrap(), 0o600);
+        assert!(parse_mode("888").is_err());
+        assert!(parse_mode("1777").is_err());
+        assert!(parse_mode("rw").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_serves_over_socket() {
+        let dir = test_dir("serve");
+        let path = dir.join("kiro.sock");
+        // 上次运行残留的套接字文件
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let config = config(&path);
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let server = spawn(&config, peer_app(), async move {
+            let _ = rx.await;
+        })
+        .unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let response = get_over_socket(&path).await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(
+            response.ends_with("ip=127.0.0.1 trusted=true"),
+            "{}",
+            response
+        );
+
+        // 正在监听的套接字不能被另一个实例接管
+        assert!(bind(&config).is_err());
+
+        tx.send(()).unwrap();
+        server.await.unwrap();
+        remove(&config);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_require_api_key() {
+        let dir = test_dir("require-key");
+        let path = dir.join("kiro.sock");
+        let config = UnixSocketConfig {
+            require_api_key: true,
+            ..config(&path)
+        };
+        let server = spawn(&config, peer_app(), std::future::pending()).unwrap();
+        let response = get_over_socket(&path).await;
+        assert!(
+            response.ends_with("ip=127.0.0.1 trusted=false"),
+            "{}",
+            response
+        );
+        server.abort();
+
+        // 不是套接字的文件不会被删除
+        std::fs::write(dir.join("plain"), "").unwrap();
+        let plain = UnixSocketConfig {
+            path: dir.join("plain").to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        assert!(bind(&plain).is_err());
+        assert!(dir.join("plain").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}